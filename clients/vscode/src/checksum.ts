@@ -0,0 +1,41 @@
+import { TextDocumentChangeEvent } from "vscode";
+
+import { LanguageClient, Middleware } from "vscode-languageclient/node";
+
+const DOCUMENT_CHECKSUM = "flb/documentChecksum";
+
+// 32-bit FNV-1a hash of the UTF-8 content, as the server computes it.
+function contentHash(text: string): number {
+  let hash = 0x811c9dc5;
+  for (const byte of new TextEncoder().encode(text)) {
+    hash = Math.imul(hash ^ byte, 0x01000193) >>> 0;
+  }
+  return hash;
+}
+
+// Sends `flb/documentChecksum` after the changes of each version of a
+// document, for servers handling it to detect that they applied them to other
+// content than the editor's and request a resync.
+export function checksumMiddleware(client: () => LanguageClient): Middleware {
+  return {
+    didChange: async (event: TextDocumentChangeEvent, next) => {
+      await next(event);
+
+      const experimental = client().initializeResult?.capabilities.experimental as any;
+      if (!experimental?.fluentBit?.notifications?.[DOCUMENT_CHECKSUM]) {
+        return;
+      }
+      const { document } = event;
+      const text = document.getText();
+      await client().sendNotification(DOCUMENT_CHECKSUM, {
+        textDocument: {
+          uri: client().code2ProtocolConverter.asUri(document.uri),
+          version: document.version,
+        },
+        // positions are negotiated in UTF-16, as strings are
+        length: text.length,
+        hash: contentHash(text),
+      });
+    },
+  };
+}
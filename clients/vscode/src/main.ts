@@ -10,6 +10,8 @@ import {
   ServerOptions,
 } from "vscode-languageclient/node";
 import { bootstrap } from "./bootstrap";
+import { checksumMiddleware } from "./checksum";
+import { ResyncDocumentFeature } from "./resync";
 
 let client: LanguageClient;
 
@@ -33,6 +35,7 @@ export async function activate(context: ExtensionContext) {
     // Register the server for plain text documents
     documentSelector: [
       { scheme: "file", language: "fluent-bit" },
+      { scheme: "file", language: "yaml", pattern: "**/fluent-bit*.{yaml,yml}" },
       // { scheme: "file", pattern: "**/*.conf" },
       // { scheme: "file", language: "plaintext" },
     ],
@@ -40,6 +43,8 @@ export async function activate(context: ExtensionContext) {
       fileEvents: workspace.createFileSystemWatcher("**/.clientrc"),
     },
     // traceOutputChannel,
+    initializationOptions: initializationOptions(),
+    middleware: checksumMiddleware(() => client),
   };
 
   // Create the language client and start the client.
@@ -49,11 +54,20 @@ export async function activate(context: ExtensionContext) {
     serverOptions,
     clientOptions
   );
+  client.registerFeature(new ResyncDocumentFeature(client));
 
   console.log("Running fluent-bit extension");
   await client.start();
 }
 
+// Settings of the server, i.e. the `fluentbitLanguageServer.*` settings but
+// the client's `trace.server`.
+function initializationOptions() {
+  const config = workspace.getConfiguration("fluentbitLanguageServer");
+  const { trace, ...options } = JSON.parse(JSON.stringify(config));
+  return options;
+}
+
 export function deactivate(): Thenable<void> | undefined {
   if (!client) {
     return undefined;
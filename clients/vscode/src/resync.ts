@@ -0,0 +1,60 @@
+import { workspace } from "vscode";
+
+import {
+  ClientCapabilities,
+  DidCloseTextDocumentNotification,
+  DidOpenTextDocumentNotification,
+  FeatureState,
+  LanguageClient,
+  StaticFeature,
+} from "vscode-languageclient/node";
+
+const RESYNC_DOCUMENT = "flb/resyncDocument";
+
+interface ResyncDocumentParams {
+  textDocument: { uri: string };
+}
+
+// Handles `flb/resyncDocument`, sent by the server when it lost track of the
+// content of a document, by closing and reopening the document on the server.
+export class ResyncDocumentFeature implements StaticFeature {
+  constructor(private readonly client: LanguageClient) {}
+
+  getState(): FeatureState {
+    return { kind: "static" };
+  }
+
+  fillClientCapabilities(capabilities: ClientCapabilities): void {
+    const experimental = (capabilities.experimental ?? {}) as any;
+    const fluentBit = experimental.fluentBit ?? {};
+    capabilities.experimental = {
+      ...experimental,
+      fluentBit: {
+        ...fluentBit,
+        requests: [...(fluentBit.requests ?? []), RESYNC_DOCUMENT],
+      },
+    };
+  }
+
+  initialize(): void {
+    this.client.onRequest(RESYNC_DOCUMENT, async (params: ResyncDocumentParams) => {
+      const converter = this.client.code2ProtocolConverter;
+      const document = workspace.textDocuments.find(
+        (document) => converter.asUri(document.uri) === params.textDocument.uri
+      );
+      if (!document) {
+        return null;
+      }
+
+      await this.client.sendNotification(DidCloseTextDocumentNotification.type, {
+        textDocument: { uri: params.textDocument.uri },
+      });
+      await this.client.sendNotification(DidOpenTextDocumentNotification.type, {
+        textDocument: converter.asTextDocumentItem(document),
+      });
+      return null;
+    });
+  }
+
+  dispose(): void {}
+}
@@ -130,7 +130,7 @@ pub struct FlbProperty {
     pub default: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FlbPropertyType {
     String,
     Integer,
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
 pub enum FlbSectionType {
+    Service,
     Input,
     Parser,
     MultilineParser,
@@ -19,6 +20,7 @@ impl FromStr for FlbSectionType {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s.to_uppercase().as_str() {
+            "SERVICE" => FlbSectionType::Service,
             "INPUT" => FlbSectionType::Input,
             "PARSER" => FlbSectionType::Parser,
             "MULTILINE_PARSER" => FlbSectionType::MultilineParser,
@@ -43,6 +45,7 @@ impl<'de> Deserialize<'de> for FlbSectionType {
 impl Display for FlbSectionType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
+            FlbSectionType::Service => "service".to_string(),
             FlbSectionType::Input => "input".to_string(),
             FlbSectionType::Parser => "parser".to_string(),
             FlbSectionType::MultilineParser => "multiline_parser".to_string(),
@@ -63,6 +66,9 @@ mod tests {
     fn parse_section_name_to_enum() {
         let section: FlbSectionType = str::parse("input").unwrap();
         assert_eq!(section, FlbSectionType::Input);
+
+        let section: FlbSectionType = str::parse("SERVICE").unwrap();
+        assert_eq!(section, FlbSectionType::Service);
     }
 
     // #[test]
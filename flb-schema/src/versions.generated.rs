@@ -0,0 +1,5 @@
+/// Generated by `cargo xtask generate --bundle`
+/// Don't modify this file manually.
+
+const BUNDLED_SCHEMAS: &[(&str, &str)] = &[
+];
@@ -0,0 +1,84 @@
+//! Schemas of several fluent-bit minor versions, e.g. 3.0, 3.1 and 3.2,
+//! bundled by `cargo xtask generate --bundle`, so that the data set can be
+//! switched at runtime instead of rebuilding for another version.
+//!
+//! Schemas are kept as their JSON and parsed when they're looked up.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::config::FlbConfigSchema;
+
+include!("versions.generated.rs");
+
+/// `major.minor.patch` version of fluent-bit, the missing parts being `0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FlbVersion(pub u32, pub u32, pub u32);
+
+impl FlbVersion {
+    /// Minor version, e.g. `3.1` for `3.1.5`.
+    pub fn minor(self) -> String {
+        format!("{}.{}", self.0, self.1)
+    }
+}
+
+impl FromStr for FlbVersion {
+    type Err = anyhow::Error;
+
+    /// Parses e.g. `2.1`, `3.1.5` or `v2.0.9`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix('v').unwrap_or(s);
+        let parts = s
+            .split('.')
+            .map(str::parse)
+            .collect::<Result<Vec<u32>, _>>()?;
+        match parts[..] {
+            [major] => Ok(FlbVersion(major, 0, 0)),
+            [major, minor] => Ok(FlbVersion(major, minor, 0)),
+            [major, minor, patch] => Ok(FlbVersion(major, minor, patch)),
+            _ => anyhow::bail!("invalid fluent-bit version `{s}`"),
+        }
+    }
+}
+
+impl Display for FlbVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// Minor versions with a bundled schema, oldest first.
+pub fn bundled_versions() -> impl Iterator<Item = &'static str> {
+    BUNDLED_SCHEMAS.iter().map(|(version, _)| *version)
+}
+
+/// Bundled schema of the minor version of `version`, `None` if there's none.
+pub fn bundled_schema(version: &str) -> Option<anyhow::Result<FlbConfigSchema>> {
+    let minor = version.parse::<FlbVersion>().ok()?.minor();
+    let (_, json) = BUNDLED_SCHEMAS
+        .iter()
+        .find(|(version, _)| *version == minor)?;
+
+    Some(serde_json::from_str(json).map_err(Into::into))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minor_versions() {
+        let version = |version: &str| version.parse::<FlbVersion>().ok();
+        assert_eq!(version("2.1"), Some(FlbVersion(2, 1, 0)));
+        assert_eq!(version("v3.1.5"), Some(FlbVersion(3, 1, 5)));
+        assert_eq!(version("2.x"), None);
+        assert_eq!(version("nightly"), None);
+        assert!(FlbVersion(2, 0, 9) < FlbVersion(2, 1, 0));
+        assert_eq!(FlbVersion(3, 1, 5).minor(), "3.1");
+
+        for version in bundled_versions() {
+            assert!(bundled_schema(version).unwrap().is_ok(), "{version}");
+        }
+        assert!(bundled_schema("0.1").is_none());
+    }
+}
@@ -0,0 +1,174 @@
+//! Compresses the bundled plugin docs so they don't bloat the binary.
+//!
+//! Every `src/assets/docs/<section>/<name>.md` is written to
+//! `$OUT_DIR/docs/<section>/<name>.md.z` (raw deflate), which `read_flb_docs!`
+//! embeds instead of the plain markdown.
+//!
+//! The example configurations of the docs, fenced code blocks starting with a
+//! section header or a directive (YAML ones with a top-level `pipeline`,
+//! `service` or `parsers`), are listed in `$OUT_DIR/doc_examples.rs`, for a
+//! test to check that they parse and pass the diagnostics.
+//!
+//! Message catalogs, `src/assets/locales/<locale>.json`, are listed in
+//! `$OUT_DIR/locales.rs`, so that adding one doesn't need code changes.
+//!
+//! The version of tree-sitter-fluentbit locked by the workspace is exposed as
+//! `TREE_SITTER_FLUENTBIT_VERSION`.
+
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::DeflateEncoder, Compression};
+
+const DOCS_DIR: &str = "src/assets/docs";
+const LOCALES_DIR: &str = "src/assets/locales";
+const LOCK_FILE: &str = "../Cargo.lock";
+
+const CODE_FENCE: &str = "```";
+/// Languages of the code blocks of classic configurations, e.g. `python` for
+/// its highlighting.
+const CLASSIC_LANGUAGES: &[&str] = &["", "text", "python", "ini"];
+
+fn main() {
+    println!("cargo:rerun-if-changed={DOCS_DIR}");
+    println!("cargo:rerun-if-changed={LOCALES_DIR}");
+    println!("cargo:rerun-if-changed={LOCK_FILE}");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    compress_dir(Path::new(DOCS_DIR), &out_dir.join("docs"));
+    list_examples(Path::new(DOCS_DIR), &out_dir.join("doc_examples.rs"));
+    list_locales(Path::new(LOCALES_DIR), &out_dir.join("locales.rs"));
+    println!(
+        "cargo:rustc-env=TREE_SITTER_FLUENTBIT_VERSION={}",
+        locked_version(Path::new(LOCK_FILE), "tree-sitter-fluentbit")
+            .unwrap_or_else(|| "unknown".to_string())
+    );
+}
+
+/// Version of the package in the lock file, which lists `name` then `version`.
+fn locked_version(lock_file: &Path, package: &str) -> Option<String> {
+    let lock = fs::read_to_string(lock_file).ok()?;
+    let mut lines = lock.lines();
+    lines.find(|line| *line == format!("name = \"{package}\""))?;
+    let version = lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')?;
+
+    Some(version.to_string())
+}
+
+fn list_locales(src: &Path, dst: &Path) {
+    let mut paths: Vec<PathBuf> = fs::read_dir(src)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut code = String::from("static LOCALES: &[(&str, &str)] = &[\n");
+    for path in paths {
+        let locale = path.file_stem().unwrap().to_str().unwrap().to_lowercase();
+        let path = fs::canonicalize(&path).unwrap();
+        code.push_str(&format!("    ({locale:?}, include_str!({path:?})),\n"));
+    }
+    code.push_str("];\n");
+
+    fs::write(dst, code).unwrap();
+}
+
+fn compress_dir(src: &Path, dst: &Path) {
+    fs::create_dir_all(dst).unwrap();
+
+    for entry in fs::read_dir(src).unwrap() {
+        let path = entry.unwrap().path();
+        let file_name = path.file_name().unwrap();
+
+        if path.is_dir() {
+            compress_dir(&path, &dst.join(file_name));
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(&fs::read(&path).unwrap()).unwrap();
+
+            let mut dst_name = file_name.to_owned();
+            dst_name.push(".z");
+            fs::write(dst.join(dst_name), encoder.finish().unwrap()).unwrap();
+        }
+    }
+}
+
+/// Example configurations of the markdown: the line of their opening fence,
+/// whether they're YAML, and their text.
+fn examples(markdown: &str) -> Vec<(usize, bool, String)> {
+    let mut ret = Vec::new();
+    let mut lines = markdown.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let Some(language) = line.trim().strip_prefix(CODE_FENCE) else {
+            continue;
+        };
+        let body: Vec<&str> = lines
+            .by_ref()
+            .map(|(_, line)| line)
+            .take_while(|line| !line.trim_start().starts_with(CODE_FENCE))
+            .collect();
+
+        let first = body
+            .iter()
+            .map(|line| line.trim())
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .unwrap_or_default();
+        let is_header = first
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .is_some_and(|name| {
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c == '_')
+            });
+        let is_classic = CLASSIC_LANGUAGES.contains(&language.trim())
+            && (is_header || first.starts_with("@SET") || first.starts_with("@INCLUDE"));
+        let is_yaml = language.trim() == "yaml"
+            && body
+                .iter()
+                .any(|line| matches!(line.trim_end(), "pipeline:" | "service:" | "parsers:"));
+
+        if is_classic || is_yaml {
+            ret.push((i + 1, is_yaml, body.join("\n") + "\n"));
+        }
+    }
+
+    ret
+}
+
+fn collect_examples(docs_dir: &Path, dir: &Path, code: &mut String) {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            collect_examples(docs_dir, &path, code);
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            // e.g. `input/tail.md`
+            let doc_path = path.strip_prefix(docs_dir).unwrap();
+            let doc_path = doc_path.to_string_lossy().replace('\\', "/");
+            for (line, is_yaml, text) in examples(&fs::read_to_string(&path).unwrap()) {
+                let location = format!("{doc_path}:{line}");
+                code.push_str(&format!("    ({location:?}, {is_yaml}, {text:?}),\n"));
+            }
+        }
+    }
+}
+
+/// Lists the example configurations of the docs, by location, e.g.
+/// `input/tail.md:120`.
+fn list_examples(src: &Path, dst: &Path) {
+    let mut code = String::from("static DOC_EXAMPLES: &[(&str, bool, &str)] = &[\n");
+    collect_examples(src, src, &mut code);
+    code.push_str("];\n");
+
+    fs::write(dst, code).unwrap();
+}
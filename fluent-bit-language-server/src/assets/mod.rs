@@ -1 +1,182 @@
+use std::{io::Read, sync::Arc};
 
+use flate2::read::DeflateDecoder;
+use once_cell::sync::OnceCell;
+
+/// Plugin documentation embedded in the binary as deflate-compressed markdown
+/// (see `build.rs`).
+///
+/// The markdown is only decompressed the first time it is requested, so docs
+/// that are never shown in a hover or completion don't take up memory.
+pub(crate) struct EmbeddedDoc {
+    /// e.g. `input/tail`
+    path: &'static str,
+    compressed: &'static [u8],
+    markdown: OnceCell<String>,
+}
+
+impl EmbeddedDoc {
+    pub(crate) const fn new(path: &'static str, compressed: &'static [u8]) -> Self {
+        Self {
+            path,
+            compressed,
+            markdown: OnceCell::new(),
+        }
+    }
+
+    /// Page of the doc on <https://docs.fluentbit.io>.
+    pub(crate) fn url(&self) -> Option<String> {
+        let (section, name) = self.path.split_once('/')?;
+        let pipeline = match section {
+            "input" => "inputs",
+            "filter" => "filters",
+            "output" => "outputs",
+            _ => return None,
+        };
+
+        Some(format!(
+            "https://docs.fluentbit.io/manual/pipeline/{pipeline}/{name}"
+        ))
+    }
+
+    pub(crate) fn markdown(&self) -> &str {
+        self.markdown.get_or_init(|| {
+            let mut markdown = String::new();
+            DeflateDecoder::new(self.compressed)
+                .read_to_string(&mut markdown)
+                .expect("embedded docs should always be valid deflate-compressed UTF-8");
+            markdown
+        })
+    }
+}
+
+/// Documentation shown for a plugin in completions and hovers.
+#[derive(Clone)]
+pub(crate) enum PluginDoc {
+    /// Bundled docs, shared with the builtin data set.
+    Embedded(Arc<EmbeddedDoc>),
+    /// Plain markdown, e.g. the plugin description of a runtime schema.
+    Markdown(String),
+}
+
+impl PluginDoc {
+    pub(crate) fn markdown(&self) -> &str {
+        match self {
+            PluginDoc::Embedded(doc) => doc.markdown(),
+            PluginDoc::Markdown(markdown) => markdown,
+        }
+    }
+
+    pub(crate) fn url(&self) -> Option<String> {
+        match self {
+            PluginDoc::Embedded(doc) => doc.url(),
+            PluginDoc::Markdown(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{DiagnosticSeverity, NumberOrString};
+
+    use super::*;
+    use crate::{
+        completion::FLB_DATA, document::TextDocument, language_server::Backend,
+        lsp_ext::ConfigFormat, plugins::UNKNOWN_PLUGIN, positions::PositionEncodingKind,
+    };
+
+    include!(concat!(env!("OUT_DIR"), "/doc_examples.rs"));
+
+    /// Code of the examples tree-sitter-fluentbit doesn't parse.
+    const SYNTAX_ERROR: &str = "syntax-error";
+
+    /// Errors of the examples which are known, by location of the example.
+    const KNOWN_ERRORS: &[(&str, &str)] = &[
+        // gaps of the grammar: indentation of other than 4 spaces, a tab or a
+        // trailing space, braces in values, and comments at the start of the
+        // lines of a section
+        ("filter/multiline-stacktrace.md:264", SYNTAX_ERROR),
+        ("output/azure_logs_ingestion.md:56", SYNTAX_ERROR),
+        ("output/file.md:72", SYNTAX_ERROR),
+        ("output/s3.md:275", SYNTAX_ERROR),
+        ("output/syslog.md:119", SYNTAX_ERROR),
+        ("output/websocket.md:60", SYNTAX_ERROR),
+        // plugins the schema doesn't have: Windows-only ones, and ones it's
+        // built without
+        ("filter/tensorflow.md:53", UNKNOWN_PLUGIN),
+        ("input/exec.md:59", UNKNOWN_PLUGIN),
+        ("input/exec.md:80", UNKNOWN_PLUGIN),
+        ("input/udp.md:44", UNKNOWN_PLUGIN),
+        ("input/windows-event-log-winevtlog.md:30", UNKNOWN_PLUGIN),
+        ("input/windows-event-log.md:23", UNKNOWN_PLUGIN),
+        ("input/windows-exporter-metrics.md:72", UNKNOWN_PLUGIN),
+        // mistakes of the docs: an indented section header, and placeholders
+        // for plugin names
+        ("output/loki.md:226", SYNTAX_ERROR),
+        ("output/kafka.md:127", UNKNOWN_PLUGIN),
+        ("output/opentelemetry.md:43", UNKNOWN_PLUGIN),
+    ];
+
+    #[test]
+    fn embedded_doc_roundtrip() {
+        let doc = EmbeddedDoc::new(
+            "input/tail",
+            include_bytes!(concat!(env!("OUT_DIR"), "/docs/input/tail.md.z")),
+        );
+
+        assert_eq!(doc.markdown(), include_str!("docs/input/tail.md"));
+        assert_eq!(
+            doc.url().as_deref(),
+            Some("https://docs.fluentbit.io/manual/pipeline/inputs/tail")
+        );
+    }
+
+    #[test]
+    fn doc_examples_pass_diagnostics() {
+        assert!(DOC_EXAMPLES.len() > 100, "examples should be extracted");
+
+        let mut errors = Vec::new();
+        let mut known = Vec::new();
+        for (location, is_yaml, text) in DOC_EXAMPLES {
+            let format = match is_yaml {
+                true => ConfigFormat::Yaml,
+                false => ConfigFormat::Classic,
+            };
+            let document = TextDocument::with_format(text, format);
+            // the diagnostics of an example which doesn't parse follow from it
+            let found: Vec<(String, String)> =
+                if document.tree.as_ref().unwrap().root_node().has_error() {
+                    vec![(SYNTAX_ERROR.to_string(), "syntax error".to_string())]
+                } else {
+                    Backend::analyze(&FLB_DATA, &document, PositionEncodingKind::UTF16)
+                        .into_iter()
+                        .filter(|diagnostic| diagnostic.severity == Some(DiagnosticSeverity::ERROR))
+                        .map(|diagnostic| {
+                            let code = match diagnostic.code {
+                                Some(NumberOrString::String(code)) => code,
+                                _ => String::new(),
+                            };
+                            let line = diagnostic.range.start.line + 1;
+                            (code, format!("line {line}: {}", diagnostic.message))
+                        })
+                        .collect()
+                };
+
+            for (code, message) in found {
+                match KNOWN_ERRORS
+                    .iter()
+                    .position(|e| *e == (*location, code.as_str()))
+                {
+                    Some(i) => known.push(i),
+                    None => errors.push(format!("{location}: {message} [{code}]")),
+                }
+            }
+        }
+
+        assert!(errors.is_empty(), "{}", errors.join("\n"));
+        // the fixed ones are removed from the list
+        for (i, error) in KNOWN_ERRORS.iter().enumerate() {
+            assert!(known.contains(&i), "{error:?} is fixed");
+        }
+    }
+}
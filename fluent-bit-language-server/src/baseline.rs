@@ -0,0 +1,222 @@
+//! Baselines of known diagnostics, for configurations adopting the diagnostics
+//! gradually: the diagnostics a baseline records aren't reported, only new
+//! ones are.
+//!
+//! Diagnostics are recorded by file, code and text of their line, trimmed, so
+//! that the baseline survives edits of the other lines and is independent of
+//! the locale of the messages. A diagnostic recorded `count` times suppresses
+//! as many of the diagnostics with the same fingerprint in the file.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "diagnostics": [
+//!     { "file": "conf/inputs.conf", "code": "duplicate-key", "line": "Name  mem", "count": 1 }
+//!   ]
+//! }
+//! ```
+//!
+//! Files are relative to the directory of the baseline, with `/` separators.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::bail;
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{Diagnostic, NumberOrString};
+
+const BASELINE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BaselineEntry {
+    file: String,
+    code: String,
+    line: String,
+    count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BaselineFile {
+    version: u32,
+    diagnostics: Vec<BaselineEntry>,
+}
+
+/// File, code and line of a diagnostic.
+type Fingerprint = (String, String, String);
+
+#[derive(Debug, Default)]
+pub(crate) struct Baseline {
+    /// Directory of the baseline file.
+    root: PathBuf,
+    counts: HashMap<Fingerprint, usize>,
+}
+
+fn line_of(rope: &Rope, diagnostic: &Diagnostic) -> String {
+    rope.get_line(diagnostic.range.start.line as usize)
+        .map(|line| line.to_string().trim().to_string())
+        .unwrap_or_default()
+}
+
+fn code_of(diagnostic: &Diagnostic) -> Option<&str> {
+    match &diagnostic.code {
+        Some(NumberOrString::String(code)) => Some(code),
+        _ => None,
+    }
+}
+
+impl Baseline {
+    /// Empty baseline of the file at `path`, which diagnostics can be recorded to.
+    pub(crate) fn new(path: &Path) -> Self {
+        Self {
+            root: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            counts: HashMap::new(),
+        }
+    }
+
+    pub(crate) async fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = tokio::fs::read_to_string(path).await?;
+        Self::from_json(path, &json)
+    }
+
+    fn from_json(path: &Path, json: &str) -> anyhow::Result<Self> {
+        let file: BaselineFile = serde_json::from_str(json)?;
+        if file.version != BASELINE_VERSION {
+            bail!("unsupported baseline version {}", file.version);
+        }
+
+        let mut baseline = Self::new(path);
+        for entry in file.diagnostics {
+            *baseline
+                .counts
+                .entry((entry.file, entry.code, entry.line))
+                .or_default() += entry.count;
+        }
+
+        Ok(baseline)
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        let mut diagnostics: Vec<BaselineEntry> = self
+            .counts
+            .iter()
+            .map(|((file, code, line), count)| BaselineEntry {
+                file: file.clone(),
+                code: code.clone(),
+                line: line.clone(),
+                count: *count,
+            })
+            .collect();
+        // sorted, for the baseline to diff well
+        diagnostics.sort_by(|a, b| (&a.file, &a.code, &a.line).cmp(&(&b.file, &b.code, &b.line)));
+        let file = BaselineFile {
+            version: BASELINE_VERSION,
+            diagnostics,
+        };
+
+        serde_json::to_string_pretty(&file).unwrap_or_default() + "\n"
+    }
+
+    /// Path of the file relative to the directory of the baseline, `None` if
+    /// the file is outside of it.
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let path = path.strip_prefix(&self.root).ok()?;
+        let components: Vec<_> = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+
+        Some(components.join("/"))
+    }
+
+    /// Records the diagnostics of the file at `path`, whose content is `rope`.
+    pub(crate) fn record(&mut self, path: &Path, rope: &Rope, diagnostics: &[Diagnostic]) {
+        let Some(file) = self.relative_path(path) else {
+            return;
+        };
+        for diagnostic in diagnostics {
+            let Some(code) = code_of(diagnostic) else {
+                continue;
+            };
+            let fingerprint = (file.clone(), code.to_string(), line_of(rope, diagnostic));
+            *self.counts.entry(fingerprint).or_default() += 1;
+        }
+    }
+
+    /// Drops the diagnostics of the file at `path` the baseline records.
+    pub(crate) fn suppress(&self, path: &Path, rope: &Rope, diagnostics: &mut Vec<Diagnostic>) {
+        if self.counts.is_empty() {
+            return;
+        }
+        let Some(file) = self.relative_path(path) else {
+            return;
+        };
+
+        let mut remaining = HashMap::new();
+        diagnostics.retain(|diagnostic| {
+            let Some(code) = code_of(diagnostic) else {
+                return true;
+            };
+            let fingerprint = (file.clone(), code.to_string(), line_of(rope, diagnostic));
+            let Some(count) = self.counts.get(&fingerprint) else {
+                return true;
+            };
+            let remaining = remaining.entry(fingerprint).or_insert(*count);
+            if *remaining == 0 {
+                return true;
+            }
+            *remaining -= 1;
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+
+    fn diagnostic(line: u32, code: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(line, 4), Position::new(line, 8)),
+            code: Some(NumberOrString::String(code.to_string())),
+            ..Diagnostic::default()
+        }
+    }
+
+    #[test]
+    fn known_diagnostics_are_suppressed() {
+        let baseline_path = Path::new("/repo/.fluent-bit-baseline.json");
+        let path = Path::new("/repo/conf/inputs.conf");
+        let rope = Rope::from_str("[INPUT]\n    Name  cpu\n    Name  mem\n");
+        let mut baseline = Baseline::new(baseline_path);
+        baseline.record(path, &rope, &[diagnostic(2, "duplicate-key")]);
+        let json = baseline.to_json();
+        assert!(json.contains(r#""file": "conf/inputs.conf""#), "{json}");
+        assert!(json.contains(r#""line": "Name  mem""#), "{json}");
+
+        // lines were inserted before, and the same problem was added after
+        let baseline = Baseline::from_json(baseline_path, &json).unwrap();
+        let rope = Rope::from_str("\n\n[INPUT]\n    Name  cpu\n    Name  mem\n    Name  mem\n");
+        let mut diagnostics = vec![
+            diagnostic(4, "duplicate-key"),
+            diagnostic(5, "duplicate-key"),
+            diagnostic(5, "unknown-key"),
+        ];
+        baseline.suppress(path, &rope, &mut diagnostics);
+        assert_eq!(diagnostics, [
+            diagnostic(5, "duplicate-key"),
+            diagnostic(5, "unknown-key"),
+        ]);
+
+        // other files aren't suppressed
+        let mut diagnostics = vec![diagnostic(4, "duplicate-key")];
+        baseline.suppress(Path::new("/other/inputs.conf"), &rope, &mut diagnostics);
+        assert_eq!(diagnostics.len(), 1);
+
+        assert!(Baseline::from_json(baseline_path, r#"{"version":2,"diagnostics":[]}"#).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Lint mode of the command line, reporting the diagnostics of configuration
+//! files without an editor, e.g. in CI or pre-commit hooks:
+//!
+//! ```sh
+//! fluent-bit-language-server lint fluent-bit.conf pipeline.yaml
+//! # unsaved buffers, named to resolve their relative paths and format
+//! fluent-bit-language-server lint --stdin --stdin-filename fluent-bit.conf < buffer
+//! # known diagnostics of legacy configurations, which aren't reported anymore
+//! fluent-bit-language-server lint --write-baseline .fluent-bit-baseline.json conf/*.conf
+//! fluent-bit-language-server lint --baseline .fluent-bit-baseline.json conf/*.conf
+//! ```
+//!
+//! Diagnostics are printed as `file:line:column: severity: message [code]`,
+//! lines and columns (in characters) starting at 1, or as a SARIF report with
+//! `--format sarif`, which includes their fixes. They're the ones the server
+//! reports, of the document and of the configurations it embeds. The exit
+//! status is 1 when some were reported, and 2 when the arguments or a file
+//! couldn't be read.
+
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use ropey::Rope;
+use tokio::io::AsyncReadExt;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use crate::{
+    baseline::Baseline,
+    completion::FLB_DATA,
+    diagnostics::Diagnosis,
+    document::TextDocument,
+    encoding::{decode, TextEncoding},
+    lsp_ext::ConfigFormat,
+    path_checks::PathCache,
+    positions::PositionEncodingKind,
+    sarif,
+    settings::Settings,
+};
+
+/// Name of the content read from stdin without `--stdin-filename`.
+const STDIN_NAME: &str = "<stdin>";
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Sarif,
+}
+
+#[derive(Debug, PartialEq, clap::Args)]
+pub(crate) struct LintArgs {
+    /// Files to lint
+    #[arg(required_unless_present = "stdin", conflicts_with = "stdin")]
+    files: Vec<PathBuf>,
+
+    /// Lint the content read from stdin, e.g. an unsaved buffer
+    #[arg(long)]
+    stdin: bool,
+
+    /// Name of the content read from stdin, resolving its relative paths and
+    /// telling its format
+    #[arg(long, value_name = "NAME", requires = "stdin")]
+    stdin_filename: Option<PathBuf>,
+
+    /// Format of the diagnostics printed
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Don't report the diagnostics of this baseline
+    #[arg(long, value_name = "PATH")]
+    baseline: Option<PathBuf>,
+
+    /// Write the diagnostics to this baseline instead of reporting them
+    #[arg(long, value_name = "PATH")]
+    write_baseline: Option<PathBuf>,
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Diagnostics of the content of the file at `path`, which may not exist when
+/// the content is an unsaved buffer, decoded from `encoding` if it isn't UTF-8.
+async fn diagnose(
+    path: &Path,
+    text: &str,
+    encoding: Option<TextEncoding>,
+    paths: &PathCache,
+) -> Vec<Diagnostic> {
+    let position_encoding = PositionEncodingKind::UTF32;
+    let uri = std::path::absolute(path)
+        .ok()
+        .and_then(|path| Url::from_file_path(path).ok());
+    let format = uri
+        .as_ref()
+        .map_or(ConfigFormat::Classic, ConfigFormat::from_uri);
+    let mut document = TextDocument::with_format(text, format);
+    document.decoded_from = encoding;
+    if document.tree.is_none() {
+        return Vec::new();
+    }
+
+    let settings = Settings::default();
+    let mut diagnostics = Diagnosis::new(
+        &FLB_DATA,
+        &document,
+        uri.as_ref(),
+        &settings,
+        position_encoding,
+    )
+    .finish(paths)
+    .await;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+
+    diagnostics
+}
+
+fn format_diagnostic(name: &str, diagnostic: &Diagnostic) -> String {
+    let severity = match diagnostic.severity {
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "error",
+    };
+    let start = diagnostic.range.start;
+    let mut line = format!(
+        "{name}:{}:{}: {severity}: {}",
+        start.line + 1,
+        start.character + 1,
+        diagnostic.message
+    );
+    if let Some(NumberOrString::String(code)) = &diagnostic.code {
+        line.push_str(&format!(" [{code}]"));
+    }
+
+    line
+}
+
+/// Runs `lint`, and returns the exit status.
+pub(crate) async fn lint(args: LintArgs) -> i32 {
+    let mut sources = Vec::new();
+    if args.stdin {
+        let mut bytes = Vec::new();
+        if let Err(e) = tokio::io::stdin().read_to_end(&mut bytes).await {
+            eprintln!("error: failed to read stdin: {e}");
+            return 2;
+        }
+        let name = args
+            .stdin_filename
+            .unwrap_or_else(|| PathBuf::from(STDIN_NAME));
+        sources.push((name, decode(bytes)));
+    }
+    for file in args.files {
+        match tokio::fs::read(&file).await {
+            Ok(bytes) => sources.push((file, decode(bytes))),
+            Err(e) => {
+                eprintln!("error: failed to read {}: {e}", file.display());
+                return 2;
+            }
+        }
+    }
+
+    let baseline = match &args.baseline {
+        Some(path) => match Baseline::load(&absolute(path)).await {
+            Ok(baseline) => Some(baseline),
+            Err(e) => {
+                eprintln!("error: failed to load baseline {}: {e}", path.display());
+                return 2;
+            }
+        },
+        None => None,
+    };
+    let mut recorded = args
+        .write_baseline
+        .as_deref()
+        .map(|path| Baseline::new(&absolute(path)));
+
+    let paths = PathCache::default();
+    let mut files = Vec::with_capacity(sources.len());
+    for (path, (text, encoding)) in sources {
+        let mut diagnostics = diagnose(&path, &text, encoding, &paths).await;
+        let rope = Rope::from_str(&text);
+        if let Some(baseline) = &baseline {
+            baseline.suppress(&absolute(&path), &rope, &mut diagnostics);
+        }
+        if let Some(recorded) = &mut recorded {
+            recorded.record(&absolute(&path), &rope, &diagnostics);
+        }
+        // URIs of SARIF reports use `/`, e.g. on Windows
+        files.push((path.to_string_lossy().replace('\\', "/"), diagnostics));
+    }
+
+    if let (Some(recorded), Some(path)) = (recorded, &args.write_baseline) {
+        if let Err(e) = tokio::fs::write(path, recorded.to_json()).await {
+            eprintln!("error: failed to write baseline {}: {e}", path.display());
+            return 2;
+        }
+        return 0;
+    }
+
+    match args.format {
+        OutputFormat::Text => {
+            for (name, diagnostics) in &files {
+                for diagnostic in diagnostics {
+                    println!("{}", format_diagnostic(name, diagnostic));
+                }
+            }
+        }
+        OutputFormat::Sarif => println!("{:#}", sarif::report(&files)),
+    }
+
+    i32::from(files.iter().any(|(_, diagnostics)| !diagnostics.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Lint {
+        #[command(flatten)]
+        args: LintArgs,
+    }
+
+    #[tokio::test]
+    async fn lint_stdin_content() {
+        let args = |args: &[&str]| {
+            let args = ["lint"].iter().chain(args);
+            Lint::try_parse_from(args).map(|lint| lint.args).ok()
+        };
+        assert_eq!(
+            args(&["--stdin", "--stdin-filename", "conf/pipeline.yaml"]),
+            Some(LintArgs {
+                files: vec![],
+                stdin: true,
+                stdin_filename: Some(PathBuf::from("conf/pipeline.yaml")),
+                format: OutputFormat::Text,
+                baseline: None,
+                write_baseline: None,
+            })
+        );
+        assert_eq!(
+            args(&["--format", "sarif", "fluent-bit.conf"]).map(|args| args.format),
+            Some(OutputFormat::Sarif)
+        );
+        assert_eq!(args(&["--format", "json", "fluent-bit.conf"]), None);
+        assert_eq!(args(&["--stdin", "fluent-bit.conf"]), None);
+        assert_eq!(args(&["--stdin-filename", "fluent-bit.conf"]), None);
+        assert_eq!(args(&[]), None);
+
+        let text = "[INPUT]\n    Name  cpu\n    Name  mem\n";
+        let lines: Vec<_> = diagnose(Path::new("unsaved.conf"), text, None, &PathCache::default())
+            .await
+            .iter()
+            .map(|diagnostic| format_diagnostic("unsaved.conf", diagnostic))
+            .collect();
+        assert_eq!(lines, [
+            "unsaved.conf:3:5: warning: `Name` is already set in this section. [duplicate-key]"
+        ]);
+
+        // configurations embedded in other files are linted
+        let text = "kind: ConfigMap\ndata:\n  fluent-bit.conf: |\n    [INPUT]\n        Name  cpu\n        Name  mem\n";
+        let lines: Vec<_> = diagnose(
+            Path::new("configmap.yaml"),
+            text,
+            None,
+            &PathCache::default(),
+        )
+        .await
+        .iter()
+        .map(|diagnostic| format_diagnostic("configmap.yaml", diagnostic))
+        .collect();
+        assert_eq!(lines, [
+            "configmap.yaml:6:9: warning: `Name` is already set in this section. [duplicate-key]"
+        ]);
+
+        // with the fixes of the diagnostics, for the tools applying them
+        let text = "[INPUT]\n    Name  cpu  \n";
+        let diagnostics =
+            diagnose(Path::new("unsaved.conf"), text, None, &PathCache::default()).await;
+        let report = sarif::report(&[("unsaved.conf".to_string(), diagnostics)]);
+        let fix = &report["runs"][0]["results"][0]["fixes"][0];
+        assert_eq!(fix["description"]["text"], "Remove trailing whitespace");
+        assert_eq!(
+            fix["artifactChanges"][0]["replacements"][0],
+            serde_json::json!({
+                "deletedRegion": { "startLine": 2, "startColumn": 14, "endLine": 2, "endColumn": 16 },
+                "insertedContent": { "text": "" },
+            })
+        );
+    }
+}
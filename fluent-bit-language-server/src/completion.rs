@@ -1,32 +1,74 @@
-use std::{collections::HashMap, string::ToString};
+use std::{collections::HashMap, string::ToString, sync::Arc};
 
 use convert_case::{Case, Casing};
-use flb_schema::section::FlbSectionType;
+use flb_schema::{
+    config::{FlbConfigSchema, FlbPlugin, FlbPropertyType},
+    section::FlbSectionType,
+};
 /// TODO: sort out generated code
 #[allow(unused_imports)]
 use once_cell::sync::Lazy;
 use tower_lsp::lsp_types::{
-    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, Documentation,
-    InsertTextFormat, InsertTextMode, MarkupContent, MarkupKind,
+    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, CompletionList,
+    CompletionResponse, Documentation, InsertTextFormat, InsertTextMode, MarkupContent,
+};
+
+use crate::{
+    assets::{EmbeddedDoc, PluginDoc},
+    i18n::t,
+    lsp_ext::{ConfigFormat, PluginInfo},
+    markdown::{self, MarkupOptions},
+    overlay::{is_repeatable, is_yaml_only},
+    section_index::IndexedSection,
+    service::SERVICE_PARAMETERS,
+    versions::{is_available, is_plugin_available, FlbVersion},
+    yaml,
 };
 
+pub(crate) const KEY_WIDTH: usize = 15; // TODO: dynamic?
+
+/// Number of completion items returned at most, more items being left for the
+/// client to ask for as the user types.
+const MAX_COMPLETION_ITEMS: usize = 50;
+
+/// Number of parameters listed in the table of plugin hovers.
+const HOVER_MAX_PARAMETERS: usize = 10;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct FlbConfigParameterInfo {
     pub(crate) default_value: Option<String>,
     pub(crate) description: String,
+    /// fluent-bit version which introduced the parameter
+    pub(crate) since: Option<String>,
+    /// Unknown for data generated before types were part of it.
+    pub(crate) type_: Option<FlbPropertyType>,
 }
 
-impl From<FlbConfigParameterInfo> for MarkupContent {
-    fn from(info: FlbConfigParameterInfo) -> Self {
-        let mut value = info.description.clone();
-        if let Some(default_value) = info.default_value {
-            value.push_str(format!("\n\n(Default: `{}`)", default_value).as_str());
-        }
+impl FlbConfigParameterInfo {
+    pub(crate) fn to_markup_content(&self, options: &MarkupOptions) -> MarkupContent {
+        self.to_markup_content_with_notes(options, &[])
+    }
 
-        MarkupContent {
-            kind: MarkupKind::Markdown,
-            value,
+    /// Like [`Self::to_markup_content`], with paragraphs about the parameter
+    /// which aren't part of the schema appended.
+    pub(crate) fn to_markup_content_with_notes(
+        &self,
+        options: &MarkupOptions,
+        notes: &[String],
+    ) -> MarkupContent {
+        let mut value = self.description.clone();
+        if let Some(default_value) = &self.default_value {
+            value.push_str(&format!("\n\n{}", t!("doc-default", value = default_value)));
+        }
+        if let Some(since) = &self.since {
+            value.push_str(&format!("\n\n{}", t!("doc-since", version = since)));
         }
+        for note in notes {
+            value.push_str("\n\n");
+            value.push_str(note);
+        }
+
+        options.render(&value, None)
     }
 }
 
@@ -34,6 +76,9 @@ impl From<FlbConfigParameterInfo> for MarkupContent {
 pub(crate) struct FlbConfigParameter {
     pub(crate) key: String,
     pub(crate) info: FlbConfigParameterInfo,
+    /// Whether the plugin can't do without it, the snippet inserting only
+    /// such parameters.
+    pub(crate) required: bool,
 }
 
 impl FlbConfigParameter {
@@ -43,23 +88,65 @@ impl FlbConfigParameter {
             info: FlbConfigParameterInfo {
                 default_value: default_value.map(|s| s.to_string()),
                 description: description.to_string(),
+                since: None,
+                type_: None,
             },
+            required: false,
         }
     }
 
-    fn to_insert_text(&self, tab_stop: usize, key_width: usize) -> String {
+    fn with_type(mut self, type_: FlbPropertyType) -> Self {
+        self.info.type_ = Some(type_);
+        self
+    }
+
+    fn to_insert_text(&self, tab_stop: usize, format: ConfigFormat) -> String {
         assert!(tab_stop > 0);
 
-        let value_str = match &self.info.default_value {
-            Some(val) => format!("${{{tab_stop}:{}}}", val),
-            None => format!("${tab_stop}"),
+        let value_str = match (&self.info.default_value, format) {
+            (Some(val), ConfigFormat::Classic) => format!("${{{tab_stop}:{}}}", val),
+            (Some(val), ConfigFormat::Yaml) => format!("${{{tab_stop}:{}}}", yaml::quote(val)),
+            (None, _) => format!("${tab_stop}"),
         };
 
-        format!("{:key_width$} {}", self.key, value_str)
+        match format {
+            ConfigFormat::Classic => format!("{:KEY_WIDTH$} {}", self.key, value_str),
+            ConfigFormat::Yaml => format!("{}: {}", self.key.to_lowercase(), value_str),
+        }
+    }
+
+    /// Completion of the key, a parameter of `plugin_name` if it's given.
+    fn to_completion(
+        &self,
+        section_type: &FlbSectionType,
+        plugin_name: Option<&str>,
+        markup_options: &MarkupOptions,
+        format: ConfigFormat,
+    ) -> CompletionItem {
+        let description = match plugin_name {
+            Some(plugin_name) => format!("{plugin_name} {section_type} key"),
+            None => format!("{section_type} key"),
+        };
+
+        CompletionItem {
+            kind: Some(CompletionItemKind::PROPERTY),
+            label: self.key.clone(),
+            label_details: Some(CompletionItemLabelDetails {
+                detail: (format == ConfigFormat::Classic
+                    && is_yaml_only(section_type, plugin_name, &self.key))
+                .then(|| format!(" {}", t!("completion-yaml-only"))),
+                description: Some(description),
+            }),
+            documentation: Some(Documentation::MarkupContent(
+                self.info.to_markup_content(markup_options),
+            )),
+            insert_text_format: Some(InsertTextFormat::SNIPPET),
+            insert_text: Some(self.to_insert_text(1, format)),
+            ..CompletionItem::default()
+        }
     }
 }
 
-#[derive(Clone)]
 pub(crate) struct FlbCompletionSnippet {
     /// Completion Label which will be printed in the completion list
     ///
@@ -70,7 +157,7 @@ pub(crate) struct FlbCompletionSnippet {
     ///
     /// e.g. `netif`
     plugin_name: String,
-    documentation_markdown: String,
+    documentation: PluginDoc,
     config_params: Vec<FlbConfigParameter>,
     // XXX: maybe no need
     // detail: Option<String>,
@@ -82,49 +169,149 @@ impl FlbCompletionSnippet {
     pub fn new(
         label: &str,
         plugin_name: Option<&str>,
-        documentation_markdown: &str,
+        documentation: PluginDoc,
         config_params: Vec<FlbConfigParameter>,
     ) -> Self {
         FlbCompletionSnippet {
             label: label.to_string(),
             plugin_name: plugin_name.map_or_else(|| label.to_case(Case::Snake), |s| s.to_string()),
-            documentation_markdown: documentation_markdown.to_string(),
+            documentation,
             config_params,
         }
     }
 
-    pub fn props_to_insert_text(&self) -> String {
-        const KEY_WIDTH: usize = 15; // TODO: dynamic?
+    pub fn plugin_name(&self) -> &str {
+        &self.plugin_name
+    }
+
+    pub(crate) fn config_params(&self) -> &[FlbConfigParameter] {
+        &self.config_params
+    }
+
+    /// Marks the parameters of the keys as required (case insensitive).
+    fn with_required(mut self, keys: &[&str]) -> Self {
+        for param in &mut self.config_params {
+            param.required = keys.iter().any(|key| key.eq_ignore_ascii_case(&param.key));
+        }
+        self
+    }
 
-        let mut ret = format!("{:KEY_WIDTH$} {}\n", "Name", self.plugin_name);
+    /// Insert text of the snippet, without the parameters `skip` returns true
+    /// for.
+    pub fn props_to_insert_text(
+        &self,
+        format: ConfigFormat,
+        skip: impl Fn(&FlbConfigParameter) -> bool,
+    ) -> String {
+        let mut ret = match format {
+            ConfigFormat::Classic => format!("{:KEY_WIDTH$} {}\n", "Name", self.plugin_name),
+            ConfigFormat::Yaml => format!("name: {}\n", self.plugin_name),
+        };
 
-        for (index, param) in self.config_params.iter().enumerate() {
+        let params = self.config_params.iter().filter(|param| !skip(param));
+        for (index, param) in params.enumerate() {
             let tab_stop = index + 1;
-            let line = param.to_insert_text(tab_stop, KEY_WIDTH);
+            let line = param.to_insert_text(tab_stop, format);
             ret.push_str(format!("{}\n", line).as_str());
         }
 
         ret
     }
+
+    /// Markdown table of the first parameters, with their type (when known)
+    /// and default.
+    fn parameters_table(&self) -> String {
+        let cell = |s: &str| s.replace('|', "\\|");
+        let params = &self.config_params[..self.config_params.len().min(HOVER_MAX_PARAMETERS)];
+        let has_types = params.iter().any(|param| param.info.type_.is_some());
+
+        let mut ret = match has_types {
+            true => String::from("| Key | Type | Default |\n| :-- | :-- | :-- |\n"),
+            false => String::from("| Key | Default |\n| :-- | :-- |\n"),
+        };
+        for param in params {
+            let default_value = match param.info.default_value.as_deref() {
+                Some(default_value) if !default_value.is_empty() => {
+                    format!("`{}`", cell(default_value))
+                }
+                _ => String::new(),
+            };
+            let type_ = match (has_types, &param.info.type_) {
+                (true, Some(type_)) => format!(" {} |", cell(&type_.to_string())),
+                (true, None) => " |".to_string(),
+                (false, _) => String::new(),
+            };
+            ret.push_str(&format!(
+                "| `{}` |{type_} {default_value} |\n",
+                cell(&param.key)
+            ));
+        }
+
+        let more = self.config_params.len() - params.len();
+        if more > 0 {
+            ret.push_str(&format!("\n…and {more} more parameters.\n"));
+        }
+
+        ret
+    }
+
+    pub(crate) fn to_plugin_info(&self, available: bool) -> PluginInfo {
+        PluginInfo {
+            name: self.plugin_name.clone(),
+            label: self.label.clone(),
+            description: markdown::summary(self.documentation.markdown()),
+            documentation_url: self.documentation.url(),
+            available,
+        }
+    }
+}
+
+/// Completions of the snippet: inserting its required parameters only, and
+/// every parameter, unless it has no other parameters.
+pub fn snippet_to_completions(
+    snippet: &FlbCompletionSnippet,
+    section_type: &FlbSectionType,
+    section: Option<&IndexedSection>,
+    markup_options: &MarkupOptions,
+    format: ConfigFormat,
+) -> Vec<CompletionItem> {
+    let has_optional = snippet.config_params.iter().any(|param| !param.required);
+    [false, true]
+        .into_iter()
+        .filter(|full| !full || has_optional)
+        .map(|full| {
+            snippet_to_completion(snippet, section_type, section, markup_options, format, full)
+        })
+        .collect()
 }
 
-pub fn snippet_to_completion(
-    snippet: FlbCompletionSnippet,
+fn snippet_to_completion(
+    snippet: &FlbCompletionSnippet,
     section_type: &FlbSectionType,
+    section: Option<&IndexedSection>,
+    markup_options: &MarkupOptions,
+    format: ConfigFormat,
+    full: bool,
 ) -> CompletionItem {
-    let insert_text = snippet.props_to_insert_text();
+    let insert_text = snippet.props_to_insert_text(format, |param| {
+        let is_set = section.is_some_and(|section| {
+            section.contains_key(&param.key)
+                && !is_repeatable(section_type, Some(snippet.plugin_name()), &param.key)
+        });
+        is_set || !(full || param.required)
+    });
 
     CompletionItem {
         kind: Some(CompletionItemKind::SNIPPET),
-        label: snippet.label,
+        label: snippet.label.clone(),
         label_details: Some(CompletionItemLabelDetails {
-            detail: None,
+            detail: full.then(|| format!(" {}", t!("completion-all-parameters"))),
             description: Some(format!("{} plugin", section_type)),
         }),
-        documentation: Some(Documentation::MarkupContent(MarkupContent {
-            kind: MarkupKind::Markdown,
-            value: snippet.documentation_markdown,
-        })),
+        documentation: Some(Documentation::MarkupContent(markup_options.render(
+            snippet.documentation.markdown(),
+            snippet.documentation.url().as_deref(),
+        ))),
         insert_text_mode: Some(InsertTextMode::ADJUST_INDENTATION),
         insert_text_format: Some(InsertTextFormat::SNIPPET),
         insert_text: Some(insert_text),
@@ -132,26 +319,103 @@ pub fn snippet_to_completion(
     }
 }
 
+/// Indents the lines following the first one of the insert texts by `indent`
+/// more columns than the line being completed, e.g. when it starts a YAML
+/// sequence item (`- `).
+pub(crate) fn indent_continuation_lines(items: &mut [CompletionItem], indent: usize) {
+    let indent = " ".repeat(indent);
+    for insert_text in items
+        .iter_mut()
+        .filter_map(|item| item.insert_text.as_mut())
+    {
+        let mut lines = insert_text.split('\n');
+        let first = lines.next().unwrap_or_default().to_string();
+        *insert_text = lines.fold(first, |text, line| match line.is_empty() {
+            true => format!("{text}\n"),
+            false => format!("{text}\n{indent}{line}"),
+        });
+    }
+}
+
+/// Whether the characters of `word` appear in `label` in order, like clients
+/// match completion items (case insensitive).
+fn fuzzy_matches(label: &str, word: &str) -> bool {
+    let mut label = label.chars().map(|c| c.to_ascii_lowercase());
+    word.chars()
+        .all(|c| label.any(|l| l == c.to_ascii_lowercase()))
+}
+
+/// Keeps the items matching the word typed before the cursor, those starting
+/// with it first. When too many match, the list is trimmed and marked
+/// incomplete so that the client asks again as the user types.
+pub(crate) fn paginate_completion(items: Vec<CompletionItem>, word: &str) -> CompletionResponse {
+    let starts_with = |label: &str| {
+        label
+            .get(..word.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(word))
+    };
+
+    let mut items: Vec<_> = items
+        .into_iter()
+        .filter(|item| fuzzy_matches(item.filter_text.as_ref().unwrap_or(&item.label), word))
+        .collect();
+    if items.len() <= MAX_COMPLETION_ITEMS {
+        return CompletionResponse::Array(items);
+    }
+
+    items.sort_by_key(|item| !starts_with(item.filter_text.as_ref().unwrap_or(&item.label)));
+    items.truncate(MAX_COMPLETION_ITEMS);
+    CompletionResponse::List(CompletionList {
+        is_incomplete: true,
+        items,
+    })
+}
+
 // static datas for completion, hover, etc
 pub struct FlbData {
+    /// fluent-bit version the data was built for.
+    pub(crate) flb_version: String,
     pub(crate) snippets: HashMap<FlbSectionType, Vec<FlbCompletionSnippet>>,
+    /// Parameters of each plugin, by lowercase plugin name and key.
+    pub(crate) plugin_params:
+        HashMap<(FlbSectionType, String), HashMap<String, FlbConfigParameterInfo>>,
     pub(crate) params: HashMap<(FlbSectionType, String), FlbConfigParameterInfo>,
+    pub(crate) service_params: Vec<FlbConfigParameter>,
 }
 
 impl FlbData {
-    pub fn new() -> Self {
+    /// Creates data with the `[SERVICE]` keys, which don't come from plugins.
+    pub fn new(flb_version: &str) -> Self {
+        let service_params: Vec<FlbConfigParameter> = SERVICE_PARAMETERS
+            .iter()
+            .map(|param| {
+                let mut config_param =
+                    FlbConfigParameter::new(param.key, param.default_value, param.description);
+                config_param.info.since = param.since.map(|s| s.to_string());
+                config_param
+            })
+            .collect();
+
+        let params = service_params
+            .iter()
+            .map(|param| {
+                (
+                    (FlbSectionType::Service, param.key.to_lowercase()),
+                    param.info.clone(),
+                )
+            })
+            .collect();
+
         FlbData {
+            flb_version: flb_version.to_string(),
             snippets: HashMap::new(),
-            params: HashMap::new(),
+            plugin_params: HashMap::new(),
+            params,
+            service_params,
         }
     }
 
     pub fn add_snippet(&mut self, section_type: FlbSectionType, snippet: FlbCompletionSnippet) {
-        self.snippets
-            .entry(section_type.clone())
-            .or_default()
-            .push(snippet.clone());
-
         // insert params
         snippet.config_params.iter().for_each(|param| {
             self.params.insert(
@@ -159,6 +423,75 @@ impl FlbData {
                 param.info.clone(),
             );
         });
+
+        let plugin_params = snippet
+            .config_params
+            .iter()
+            .map(|param| (param.key.to_lowercase(), param.info.clone()))
+            .collect();
+        self.plugin_params.insert(
+            (section_type.clone(), snippet.plugin_name.to_lowercase()),
+            plugin_params,
+        );
+        self.snippets
+            .entry(section_type.clone())
+            .or_default()
+            .push(snippet);
+    }
+
+    /// Builds data from a fluent-bit schema loaded at runtime.
+    ///
+    /// Bundled docs and labels are reused for plugins known to the builtin
+    /// data set, otherwise the plugin description from the schema is used.
+    pub fn from_schema(schema: &FlbConfigSchema) -> Self {
+        let mut data = FlbData::new(&schema.fluent_bit.version);
+
+        let plugins = schema
+            .customs
+            .iter()
+            .chain(schema.inputs.iter())
+            .chain(schema.filters.iter())
+            .chain(schema.outputs.iter());
+        for plugin in plugins {
+            data.add_snippet(plugin.type_.clone(), Self::snippet_from_plugin(plugin));
+        }
+
+        data
+    }
+
+    fn snippet_from_plugin(plugin: &FlbPlugin) -> FlbCompletionSnippet {
+        let config_params = plugin
+            .properties
+            .iter()
+            .map(|prop| {
+                FlbConfigParameter::new(&prop.name, prop.default.as_deref(), &prop.description)
+                    .with_type(prop.type_.clone())
+            })
+            .collect();
+
+        match FLB_DATA.get_snippet(&plugin.type_, &plugin.name) {
+            Some(builtin) => {
+                let required: Vec<&str> = builtin
+                    .config_params
+                    .iter()
+                    .filter(|param| param.required)
+                    .map(|param| param.key.as_str())
+                    .collect();
+                FlbCompletionSnippet::new(
+                    &builtin.label,
+                    Some(&plugin.name),
+                    builtin.documentation.clone(),
+                    config_params,
+                )
+                .with_required(&required)
+            }
+            None => FlbCompletionSnippet::new(
+                &plugin.name,
+                Some(&plugin.name),
+                PluginDoc::Markdown(plugin.description.clone()),
+                config_params,
+            ),
+        }
     }
 
     pub fn get_snippets(
@@ -168,6 +501,103 @@ impl FlbData {
         self.snippets.get(section_type)
     }
 
+    /// Whether `plugin_name` is a plugin of `section_type` (case insensitive).
+    pub fn has_plugin(&self, section_type: &FlbSectionType, plugin_name: &str) -> bool {
+        self.plugin_params
+            .contains_key(&(section_type.clone(), plugin_name.to_lowercase()))
+    }
+
+    /// Lowercase names of the plugins of `section_type`.
+    pub fn plugin_names(&self, section_type: &FlbSectionType) -> impl Iterator<Item = &str> {
+        let section_type = section_type.clone();
+        self.plugin_params
+            .keys()
+            .filter(move |(type_, _)| *type_ == section_type)
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// Parameters of the plugin (case insensitive), by lowercase key. Keys
+    /// common to all plugins of the section type, e.g. `Tag`, aren't among them.
+    pub fn get_plugin_params(
+        &self,
+        section_type: &FlbSectionType,
+        plugin_name: &str,
+    ) -> Option<&HashMap<String, FlbConfigParameterInfo>> {
+        self.plugin_params
+            .get(&(section_type.clone(), plugin_name.to_lowercase()))
+    }
+
+    pub fn get_snippet(
+        &self,
+        section_type: &FlbSectionType,
+        plugin_name: &str,
+    ) -> Option<&FlbCompletionSnippet> {
+        self.get_snippets(section_type)?
+            .iter()
+            .find(|snippet| snippet.plugin_name.eq_ignore_ascii_case(plugin_name))
+    }
+
+    /// Plugins of `section_type`, together with the ones only known to
+    /// `builtin` which are reported as unavailable.
+    pub fn get_plugin_infos(
+        &self,
+        section_type: &FlbSectionType,
+        builtin: &FlbData,
+    ) -> Vec<PluginInfo> {
+        let snippets = self
+            .get_snippets(section_type)
+            .map_or(&[][..], Vec::as_slice);
+        let mut infos: Vec<PluginInfo> = snippets
+            .iter()
+            .map(|snippet| snippet.to_plugin_info(true))
+            .collect();
+
+        let unavailable = builtin
+            .get_snippets(section_type)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .filter(|snippet| {
+                self.get_snippet(section_type, &snippet.plugin_name)
+                    .is_none()
+            })
+            .map(|snippet| snippet.to_plugin_info(false));
+        infos.extend(unavailable);
+
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        infos
+    }
+
+    /// Hover shown on the name of a plugin: its summary, a table of its
+    /// parameters and the rest of its documentation, clamped by
+    /// `markup_options`.
+    pub fn get_plugin_hover(
+        &self,
+        section_type: &FlbSectionType,
+        plugin_name: &str,
+        markup_options: &MarkupOptions,
+    ) -> Option<MarkupContent> {
+        let snippet = self.get_snippet(section_type, plugin_name)?;
+
+        let mut value = format!(
+            "**{}** (`{}` {section_type} plugin)\n\n",
+            snippet.label, snippet.plugin_name
+        );
+        let (summary, rest) = markdown::split_summary(snippet.documentation.markdown());
+        if !summary.is_empty() {
+            value.push_str(&summary);
+            value.push_str("\n\n");
+        }
+        if !snippet.config_params.is_empty() {
+            value.push_str(&snippet.parameters_table());
+        }
+        if !rest.is_empty() {
+            value.push_str("\n---\n\n");
+            value.push_str(rest);
+        }
+
+        Some(markup_options.render(&value, snippet.documentation.url().as_deref()))
+    }
+
     pub fn get_parameter_info(
         &self,
         section_type: &FlbSectionType,
@@ -175,15 +605,90 @@ impl FlbData {
     ) -> Option<&FlbConfigParameterInfo> {
         self.params.get(&(section_type.clone(), key.to_string()))
     }
+
+    /// Completions for `section_type`, in the syntax of `format`. Keys already
+    /// set in `section`, the section being completed, are left out unless
+    /// they're repeatable, and so are the plugins and keys `version` doesn't
+    /// have.
+    pub fn get_completion(
+        &self,
+        section_type: &FlbSectionType,
+        section: Option<&IndexedSection>,
+        markup_options: &MarkupOptions,
+        format: ConfigFormat,
+        version: Option<FlbVersion>,
+    ) -> Vec<CompletionItem> {
+        let is_set = |plugin_name: Option<&str>, key: &str| {
+            section.is_some_and(|section| {
+                section.contains_key(key) && !is_repeatable(section_type, plugin_name, key)
+            })
+        };
+
+        if *section_type == FlbSectionType::Service {
+            return self
+                .service_params
+                .iter()
+                .filter(|param| !is_set(None, &param.key))
+                .filter(|param| is_available(param.info.since.as_deref(), version))
+                .map(|param| param.to_completion(section_type, None, markup_options, format))
+                .collect();
+        }
+
+        // inside the section of a known plugin, its parameters are completed
+        let snippet = section
+            .and_then(|section| section.get_value("Name"))
+            .and_then(|name| self.get_snippet(section_type, name));
+        if let Some(snippet) = snippet {
+            let plugin_name = Some(snippet.plugin_name());
+            return snippet
+                .config_params
+                .iter()
+                .filter(|param| !is_set(plugin_name, &param.key))
+                .filter(|param| is_available(param.info.since.as_deref(), version))
+                .map(|param| param.to_completion(section_type, plugin_name, markup_options, format))
+                .collect();
+        }
+
+        self.get_snippets(section_type)
+            .unwrap_or(&vec![])
+            .iter()
+            .filter(|snippet| is_plugin_available(section_type, snippet.plugin_name(), version))
+            .flat_map(|snippet| {
+                snippet_to_completions(snippet, section_type, section, markup_options, format)
+            })
+            .collect()
+    }
+
+    pub fn get_hover_info(
+        &self,
+        section_type: &FlbSectionType,
+        key: &str,
+    ) -> Option<FlbConfigParameterInfo> {
+        self.get_parameter_info(section_type, key.to_lowercase().as_str())
+            .cloned()
+    }
 }
 
 macro_rules! read_flb_docs {
     ($section:literal, $name:literal) => {
-        include_str!(concat!("assets/docs/", $section, "/", $name, ".md"))
+        PluginDoc::Embedded(Arc::new(EmbeddedDoc::new(
+            concat!($section, "/", $name),
+            include_bytes!(concat!(
+                env!("OUT_DIR"),
+                "/docs/",
+                $section,
+                "/",
+                $name,
+                ".md.z"
+            )),
+        )))
     };
 
     ($path:literal) => {
-        include_str!(concat!("assets/docs/", $path, ".md"))
+        PluginDoc::Embedded(Arc::new(EmbeddedDoc::new(
+            $path,
+            include_bytes!(concat!(env!("OUT_DIR"), "/docs/", $path, ".md.z")),
+        )))
     };
 }
 
@@ -195,14 +700,14 @@ macro_rules! add_snippet {
         $doc_path:expr,
         [
             $(
-                ($key:expr, $default:expr, $desc:expr)
+                ($key:expr, $default:expr, $desc:expr $(, $type:expr)?)
             ),*
             $(,)?
         ]
     ) => {
         let config_params = vec![
             $(
-                FlbConfigParameter::new($key, $default, $desc),
+                FlbConfigParameter::new($key, $default, $desc)$(.with_type($type))?,
             )*
         ];
         let snippet = FlbCompletionSnippet::new($label, None, read_flb_docs!($doc_path), config_params);
@@ -217,34 +722,264 @@ macro_rules! add_snippet {
         $doc_path:expr,
         [
             $(
-                ($key:expr, $default:expr, $desc:expr)
+                ($key:expr, $default:expr, $desc:expr $(, $type:expr)?)
             ),*
             $(,)?
         ]
+        $(, required: [$($required:expr),* $(,)?])?
     ) => {
         let config_params = vec![
             $(
-                FlbConfigParameter::new($key, $default, $desc),
+                FlbConfigParameter::new($key, $default, $desc)$(.with_type($type))?,
             )*
         ];
-        let snippet = FlbCompletionSnippet::new($label, Some($plugin_name), read_flb_docs!($doc_path), config_params);
+        let snippet = FlbCompletionSnippet::new($label, Some($plugin_name), read_flb_docs!($doc_path), config_params)
+            .with_required(&[$($($required),*)?]);
         $flb_data.add_snippet($section, snippet);
     };
 }
 
 include!("schema.generated.rs");
 
-pub fn get_completion(section_type: &FlbSectionType) -> Vec<CompletionItem> {
-    FLB_DATA
-        .get_snippets(section_type)
-        .unwrap_or(&vec![])
-        .iter()
-        .map(|snippet| snippet_to_completion(snippet.clone(), section_type))
-        .collect()
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flb_data_from_schema() {
+        let schema: FlbConfigSchema = serde_json::from_str(
+            r#"{
+                "fluent-bit": { "version": "3.1.5", "schema_version": "1", "os": "linux" },
+                "customs": [],
+                "inputs": [
+                    {
+                        "type": "input",
+                        "name": "tail",
+                        "description": "Tail files",
+                        "properties": {
+                            "options": [
+                                { "name": "path", "description": "Path", "default": null, "type": "string" }
+                            ]
+                        }
+                    },
+                    {
+                        "type": "input",
+                        "name": "brand_new",
+                        "description": "Not in the builtin data",
+                        "properties": {}
+                    }
+                ],
+                "filters": [],
+                "outputs": []
+            }"#,
+        )
+        .unwrap();
+        let data = FlbData::from_schema(&schema);
+
+        let tail = data.get_snippet(&FlbSectionType::Input, "tail").unwrap();
+        let builtin_tail = FLB_DATA
+            .get_snippet(&FlbSectionType::Input, "tail")
+            .unwrap();
+        assert_eq!(tail.label, builtin_tail.label);
+        assert_eq!(
+            tail.documentation.markdown(),
+            builtin_tail.documentation.markdown()
+        );
+        assert!(data
+            .get_hover_info(&FlbSectionType::Input, "Path")
+            .is_some());
+        // the builtin data tells which parameters are required
+        assert!(tail.config_params[0].required);
+
+        let brand_new = data
+            .get_snippet(&FlbSectionType::Input, "brand_new")
+            .unwrap();
+        assert_eq!(
+            brand_new.documentation.markdown(),
+            "Not in the builtin data"
+        );
+        assert!(data.get_snippet(&FlbSectionType::Input, "cpu").is_none());
+
+        assert_eq!(data.flb_version, "3.1.5");
+        let infos = data.get_plugin_infos(&FlbSectionType::Input, &FLB_DATA);
+        let info = |name: &str| infos.iter().find(|info| info.name == name).unwrap();
+        assert!(info("tail").available);
+        assert_eq!(info("brand_new").description, "Not in the builtin data");
+        assert!(!info("cpu").available);
+
+        assert_eq!(
+            data.get_hover_info(&FlbSectionType::Input, "path")
+                .and_then(|info| info.type_),
+            Some(FlbPropertyType::String)
+        );
+        let hover = data
+            .get_plugin_hover(
+                &FlbSectionType::Input,
+                "tail",
+                &MarkupOptions::from_client_formats(None, None),
+            )
+            .unwrap();
+        assert!(hover.value.starts_with("**Tail** (`tail` input plugin)"));
+        assert!(hover.value.contains("| `path` | string |  |"));
+        // followed by the documentation
+        assert!(hover
+            .value
+            .contains("\n---\n\nThe plugin reads every matched file"));
+        let hover = data
+            .get_plugin_hover(
+                &FlbSectionType::Input,
+                "tail",
+                &MarkupOptions::from_client_formats(None, Some(1000)),
+            )
+            .unwrap();
+        assert!(hover
+            .value
+            .ends_with("[Read more](https://docs.fluentbit.io/manual/pipeline/inputs/tail)"));
 
-pub fn get_hover_info(section_type: &FlbSectionType, key: &str) -> Option<FlbConfigParameterInfo> {
-    FLB_DATA
-        .get_parameter_info(section_type, key.to_lowercase().as_str())
-        .cloned()
+        // SERVICE keys don't come from the schema
+        assert!(data
+            .get_hover_info(&FlbSectionType::Service, "Hot_Reload")
+            .is_some_and(|info| info.since.is_some()));
+    }
+
+    #[test]
+    fn completion_skips_present_keys() {
+        let document = crate::document::TextDocument::new(
+            "[SERVICE]\n    Flush        1\n    Parsers_File parsers.conf\n",
+        );
+        let section = &document.index.sections()[0];
+        let options = MarkupOptions::from_client_formats(None, None);
+
+        let labels: Vec<_> = FLB_DATA
+            .get_completion(
+                &FlbSectionType::Service,
+                Some(section),
+                &options,
+                ConfigFormat::Classic,
+                None,
+            )
+            .into_iter()
+            .map(|item| item.label)
+            .collect();
+        assert!(!labels.iter().any(|label| label == "flush"));
+        assert!(labels.iter().any(|label| label == "parsers_file"));
+        assert!(labels.iter().any(|label| label == "grace"));
+
+        // keys introduced after the targeted version aren't completed
+        let completion = |version: &str| {
+            FLB_DATA.get_completion(
+                &FlbSectionType::Service,
+                None,
+                &options,
+                ConfigFormat::Classic,
+                version.parse().ok(),
+            )
+        };
+        assert!(!completion("2.0")
+            .iter()
+            .any(|item| item.label == "hot_reload"));
+        assert!(completion("2.1")
+            .iter()
+            .any(|item| item.label == "hot_reload"));
+    }
+
+    #[test]
+    fn completion_of_the_parameters_of_the_plugin() {
+        let document = crate::document::TextDocument::new(
+            "[INPUT]\n    Name tail\n    Path /var/log/*.log\n\n[INPUT]\n    Tag app\n",
+        );
+        let options = MarkupOptions::from_client_formats(None, None);
+        let completion = |section: &IndexedSection| {
+            FLB_DATA.get_completion(
+                &FlbSectionType::Input,
+                Some(section),
+                &options,
+                ConfigFormat::Classic,
+                None,
+            )
+        };
+
+        let items = completion(&document.index.sections()[0]);
+        assert!(items
+            .iter()
+            .all(|item| item.kind == Some(CompletionItemKind::PROPERTY)));
+        assert!(!items.iter().any(|item| item.label == "path"));
+        let refresh_interval = items
+            .iter()
+            .find(|item| item.label == "refresh_interval")
+            .unwrap();
+        assert_eq!(
+            refresh_interval.insert_text.as_deref(),
+            Some("refresh_interval ${1:60}")
+        );
+        assert_eq!(
+            refresh_interval
+                .label_details
+                .as_ref()
+                .and_then(|details| details.description.as_deref()),
+            Some("tail input key")
+        );
+
+        // without `Name`, plugins are completed, with their required
+        // parameters only, or with every parameter
+        let items = completion(&document.index.sections()[1]);
+        let tail: Vec<_> = items.iter().filter(|item| item.label == "Tail").collect();
+        assert_eq!(tail.len(), 2);
+        assert_eq!(
+            tail[0].insert_text.as_deref(),
+            Some("Name            tail\npath            $1\n")
+        );
+        assert_eq!(
+            tail[1]
+                .label_details
+                .as_ref()
+                .and_then(|details| details.detail.as_deref()),
+            Some(" (all parameters)")
+        );
+        assert!(tail[1]
+            .insert_text
+            .as_deref()
+            .is_some_and(|text| text.contains("refresh_interval")));
+    }
+
+    #[test]
+    fn large_completion_lists_are_trimmed() {
+        let items = |labels: &[&str]| {
+            labels
+                .iter()
+                .map(|label| CompletionItem::new_simple(label.to_string(), String::new()))
+                .collect::<Vec<_>>()
+        };
+        let labels = |response: CompletionResponse| match response {
+            CompletionResponse::Array(items) => (false, items),
+            CompletionResponse::List(list) => (list.is_incomplete, list.items),
+        };
+
+        let (incomplete, matched) = labels(paginate_completion(
+            items(&["tail", "Kubernetes", "stdout"]),
+            "t",
+        ));
+        assert!(!incomplete);
+        assert_eq!(matched.len(), 3);
+        let (_, matched) = labels(paginate_completion(
+            items(&["tail", "Kubernetes", "stdout"]),
+            "KUB",
+        ));
+        assert_eq!(matched[0].label, "Kubernetes");
+
+        let mut many: Vec<String> = (0..MAX_COMPLETION_ITEMS).map(|i| format!("a{i}")).collect();
+        many.push("ktail".to_string());
+        many.push("tail".to_string());
+        let many: Vec<&str> = many.iter().map(String::as_str).collect();
+        let (incomplete, matched) = labels(paginate_completion(items(&many), ""));
+        assert!(incomplete);
+        assert_eq!(matched.len(), MAX_COMPLETION_ITEMS);
+
+        let (incomplete, matched) = labels(paginate_completion(items(&many), "ta"));
+        assert!(!incomplete);
+        assert_eq!(
+            matched.iter().map(|item| &*item.label).collect::<Vec<_>>(),
+            ["ktail", "tail"]
+        );
+    }
 }
@@ -0,0 +1,114 @@
+//! Debounce of the diagnostics published while typing: a document is analyzed
+//! once its changes stopped for the `diagnosticsDebounceMs` setting, not after
+//! each keystroke.
+//!
+//! The first change of a burst spawns a task waiting for the changes to
+//! settle, the following ones only pushing its deadline back, which keeps a
+//! single task waiting per document and the handlers of the changes returning
+//! right away.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+use tower_lsp::lsp_types::Url;
+
+pub(crate) const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Default)]
+pub(crate) struct Debouncer {
+    /// Last change of the documents waiting to be analyzed.
+    pending: Mutex<HashMap<Url, Instant>>,
+}
+
+impl Debouncer {
+    /// Runs `analyze` in a task once the document didn't change for `delay`,
+    /// unless the task of an earlier change already waits, which runs its own
+    /// instead, or the document is closed meanwhile.
+    pub(crate) fn schedule<F>(self: &Arc<Self>, url: &Url, delay: Duration, analyze: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let already_waiting = self
+            .pending
+            .lock()
+            .unwrap()
+            .insert(url.clone(), Instant::now())
+            .is_some();
+        if already_waiting {
+            return;
+        }
+
+        let (debouncer, url) = (self.clone(), url.clone());
+        tokio::spawn(async move {
+            if debouncer.settle(&url, delay).await {
+                analyze.await;
+            }
+        });
+    }
+
+    /// Waits until the document didn't change for `delay`, returning whether
+    /// it's still open then.
+    async fn settle(&self, url: &Url, delay: Duration) -> bool {
+        loop {
+            let deadline = {
+                let mut pending = self.pending.lock().unwrap();
+                let Some(last_change) = pending.get(url) else {
+                    return false;
+                };
+                let deadline = *last_change + delay;
+                if Instant::now() >= deadline {
+                    pending.remove(url);
+                    return true;
+                }
+                deadline
+            };
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+
+    /// Forgets the changes of a closed document, which aren't analyzed then.
+    pub(crate) fn cancel(&self, url: &Url) {
+        self.pending.lock().unwrap().remove(url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn changes_are_analyzed_once_settled() {
+        let debouncer = Arc::new(Debouncer::default());
+        let url = Url::parse("file:///fluent-bit.conf").unwrap();
+        let delay = Duration::from_millis(50);
+        let (analyzed, mut analyses) = mpsc::unbounded_channel();
+        let analyze = |change: u32| {
+            let analyzed = analyzed.clone();
+            async move { analyzed.send((change, Instant::now())).unwrap() }
+        };
+        let start = Instant::now();
+
+        // scheduling returns right away
+        debouncer.schedule(&url, delay, analyze(1));
+        assert!(start.elapsed() < Duration::from_millis(20));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // the first change analyzes this one too, once both settled
+        debouncer.schedule(&url, delay, analyze(2));
+        let (change, analyzed_at) = analyses.recv().await.unwrap();
+        assert_eq!(change, 1);
+        assert!(analyzed_at - start >= Duration::from_millis(70));
+
+        debouncer.schedule(&url, delay, analyze(3));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        debouncer.cancel(&url);
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert!(analyses.try_recv().is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! Table of the rules diagnostics are reported by, their `code`, e.g. for the
+//! rule metadata of SARIF reports.
+
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+use crate::{
+    encoding::INVALID_ENCODING,
+    file_role::MISPLACED_SECTION,
+    include_graph::{DUPLICATE_PARSER, LARGE_FILE, UNMATCHED_RULE, UNROUTED_INPUT},
+    language_server::UNKNOWN_SERVICE_KEY,
+    lints::{MISPLACED_COMMENT, TRAILING_WHITESPACE, UTF8_BOM},
+    migration::OUTDATED_KEY,
+    overrides::OVERRIDDEN_SERVICE_KEY,
+    path_checks::{MISSING_FILE, UNMATCHED_GLOB},
+    plugins::{MISSING_NAME, UNKNOWN_KEY, UNKNOWN_PLUGIN},
+    routing::{FILTER_AFTER_OUTPUT, SERVICE_NOT_FIRST},
+    rules::{
+        DUPLICATE_KEY, INCOMPATIBLE_VALUES, MEM_BUF_LIMIT_FILESYSTEM, MISSING_REQUIRED_KEY,
+        YAML_ONLY_KEY,
+    },
+    section_names::UNKNOWN_SECTION,
+    service::MISPLACED_SERVICE_KEY,
+    validation::{INVALID_TAG, INVALID_VALUE},
+    variables::UNDEFINED_VARIABLE,
+    versions::UNSUPPORTED_BY_VERSION,
+    yaml::YAML_SYNTAX,
+    yaml_env::UNUSED_VARIABLE,
+};
+
+pub(crate) struct DiagnosticRule {
+    pub(crate) code: &'static str,
+    /// Severity the rule reports its diagnostics with.
+    pub(crate) severity: DiagnosticSeverity,
+    pub(crate) description: &'static str,
+}
+
+const fn rule(
+    code: &'static str,
+    severity: DiagnosticSeverity,
+    description: &'static str,
+) -> DiagnosticRule {
+    DiagnosticRule {
+        code,
+        severity,
+        description,
+    }
+}
+
+pub(crate) const DIAGNOSTIC_RULES: &[DiagnosticRule] = &[
+    rule(
+        YAML_SYNTAX,
+        DiagnosticSeverity::ERROR,
+        "Invalid YAML syntax.",
+    ),
+    rule(
+        MISPLACED_COMMENT,
+        DiagnosticSeverity::ERROR,
+        "Comment where the classic format doesn't allow one, e.g. after a header.",
+    ),
+    rule(
+        UTF8_BOM,
+        DiagnosticSeverity::ERROR,
+        "Byte order mark at the start of the file, which fluent-bit doesn't skip.",
+    ),
+    rule(
+        INVALID_ENCODING,
+        DiagnosticSeverity::ERROR,
+        "File which isn't UTF-8, e.g. UTF-16 or Latin-1.",
+    ),
+    rule(
+        TRAILING_WHITESPACE,
+        DiagnosticSeverity::WARNING,
+        "Whitespace at the end of a value, which fluent-bit keeps.",
+    ),
+    rule(
+        UNKNOWN_SECTION,
+        DiagnosticSeverity::WARNING,
+        "Section header which is a typo of a section name, e.g. `[INPUTS]`.",
+    ),
+    rule(
+        MISPLACED_SECTION,
+        DiagnosticSeverity::WARNING,
+        "Section the role of its file doesn't allow: other than a parser in a parsers file, or a parser in a configuration setting `Parsers_File`.",
+    ),
+    rule(
+        SERVICE_NOT_FIRST,
+        DiagnosticSeverity::WARNING,
+        "`[SERVICE]` section after other sections of a classic file.",
+    ),
+    rule(
+        UNKNOWN_SERVICE_KEY,
+        DiagnosticSeverity::WARNING,
+        "Key of a [SERVICE] section fluent-bit doesn't know.",
+    ),
+    rule(
+        OVERRIDDEN_SERVICE_KEY,
+        DiagnosticSeverity::HINT,
+        "[SERVICE] key set again later in the configuration, e.g. by an included file.",
+    ),
+    rule(
+        MISPLACED_SERVICE_KEY,
+        DiagnosticSeverity::WARNING,
+        "[SERVICE] key set in a plugin section.",
+    ),
+    rule(
+        MISSING_NAME,
+        DiagnosticSeverity::ERROR,
+        "Plugin section without `Name`.",
+    ),
+    rule(
+        UNKNOWN_PLUGIN,
+        DiagnosticSeverity::ERROR,
+        "`Name` which isn't a plugin of the section type.",
+    ),
+    rule(
+        UNKNOWN_KEY,
+        DiagnosticSeverity::WARNING,
+        "Key which isn't a parameter of the plugin of the section.",
+    ),
+    rule(
+        UNSUPPORTED_BY_VERSION,
+        DiagnosticSeverity::WARNING,
+        "Plugin or key introduced after the fluent-bit version of the `fluentBitVersion` setting.",
+    ),
+    rule(
+        OUTDATED_KEY,
+        DiagnosticSeverity::WARNING,
+        "Key the fluent-bit version of the `fluentBitVersion` setting renamed or removed.",
+    ),
+    rule(
+        INVALID_VALUE,
+        DiagnosticSeverity::ERROR,
+        "Value fluent-bit doesn't accept for its key.",
+    ),
+    rule(
+        INVALID_TAG,
+        DiagnosticSeverity::WARNING,
+        "`Tag` of an input with characters tags can't contain.",
+    ),
+    rule(
+        MISSING_REQUIRED_KEY,
+        DiagnosticSeverity::ERROR,
+        "Key required by the value of another key is missing.",
+    ),
+    rule(
+        DUPLICATE_KEY,
+        DiagnosticSeverity::WARNING,
+        "Key set more than once in a section.",
+    ),
+    rule(
+        YAML_ONLY_KEY,
+        DiagnosticSeverity::ERROR,
+        "Key only the YAML format supports.",
+    ),
+    rule(
+        INCOMPATIBLE_VALUES,
+        DiagnosticSeverity::ERROR,
+        "Values of two keys which can't be combined.",
+    ),
+    rule(
+        MEM_BUF_LIMIT_FILESYSTEM,
+        DiagnosticSeverity::WARNING,
+        "`Mem_Buf_Limit` of an input buffered in the filesystem, which doesn't pause it.",
+    ),
+    rule(
+        MISSING_FILE,
+        DiagnosticSeverity::WARNING,
+        "Referenced file which doesn't exist.",
+    ),
+    rule(
+        UNMATCHED_GLOB,
+        DiagnosticSeverity::INFORMATION,
+        "Wildcard pattern matching no file.",
+    ),
+    rule(
+        DUPLICATE_PARSER,
+        DiagnosticSeverity::WARNING,
+        "Parser defined more than once in a configuration.",
+    ),
+    rule(
+        UNMATCHED_RULE,
+        DiagnosticSeverity::WARNING,
+        "`Match` or `Match_Regex` no input of the configuration produces records for.",
+    ),
+    rule(
+        FILTER_AFTER_OUTPUT,
+        DiagnosticSeverity::WARNING,
+        "`[FILTER]` after an `[OUTPUT]` matching the same records in a classic file.",
+    ),
+    rule(
+        UNROUTED_INPUT,
+        DiagnosticSeverity::WARNING,
+        "Input whose records no output matches.",
+    ),
+    rule(
+        UNDEFINED_VARIABLE,
+        DiagnosticSeverity::INFORMATION,
+        "Reference to a variable no file of the configuration sets, read from the environment.",
+    ),
+    rule(
+        UNUSED_VARIABLE,
+        DiagnosticSeverity::HINT,
+        "Variable of a YAML `env` no value of the configuration references.",
+    ),
+    rule(
+        LARGE_FILE,
+        DiagnosticSeverity::INFORMATION,
+        "Included file too large to be analyzed, of which only the section headers are indexed.",
+    ),
+];
@@ -0,0 +1,99 @@
+//! Diagnostics of a document, as the server reports them and `lint` prints
+//! them: the analysis of its content and of the configurations it embeds, the
+//! rules depending on the settings, e.g. the role of the file or the targeted
+//! fluent-bit version, and the checks of the paths it references.
+
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, Url};
+
+use crate::{
+    completion::FlbData,
+    document::TextDocument,
+    embedded,
+    file_role::{check_section_roles, FileRole},
+    fixes,
+    language_server::Backend,
+    lsp_ext::ConfigFormat,
+    migration::check_migrations,
+    path_checks::{check_paths, collect_paths, document_dir, PathCache, PathCheck},
+    positions::PositionEncodingKind,
+    scope::tolerate_template,
+    settings::Settings,
+    versions::check_versions,
+};
+
+/// Diagnostics of the content of a document, whose paths are checked later
+/// without holding the document.
+pub(crate) struct Diagnosis {
+    diagnostics: Vec<Diagnostic>,
+    checks: Vec<PathCheck>,
+    /// Content of the document, when it's analyzed as a template.
+    template: Option<Rope>,
+}
+
+impl Diagnosis {
+    /// Diagnoses the document at `uri`, which is unknown for unsaved buffers.
+    pub(crate) fn new(
+        data: &FlbData,
+        document: &TextDocument,
+        uri: Option<&Url>,
+        settings: &Settings,
+        position_encoding: PositionEncodingKind,
+    ) -> Self {
+        let TextDocument { rope, index, .. } = document;
+
+        let mut diagnostics = Backend::analyze(data, document, position_encoding);
+        if let Some(uri) = uri.filter(|_| document.format == ConfigFormat::Classic) {
+            let role = FileRole::of(uri, &settings.parsers_files);
+            diagnostics.extend(check_section_roles(role, index, rope, position_encoding));
+        }
+        if let Some(version) = settings.target_version() {
+            diagnostics.extend(check_versions(
+                data,
+                index,
+                rope,
+                position_encoding,
+                version,
+            ));
+            diagnostics.extend(check_migrations(index, rope, position_encoding, version));
+        }
+        fixes::attach_data(data, document, &mut diagnostics, position_encoding);
+        // diagnostics of embedded configurations are reported in the document
+        for embedded in embedded::extract(document, position_encoding) {
+            let mut embedded_diagnostics =
+                Backend::analyze(data, &embedded.document, position_encoding);
+            fixes::attach_rules(&mut embedded_diagnostics);
+            embedded.diagnostics_to_host(&mut embedded_diagnostics);
+            diagnostics.extend(embedded_diagnostics);
+        }
+
+        let base_dir = uri.and_then(document_dir);
+        let checks = collect_paths(index, rope, position_encoding, base_dir.as_deref());
+        // ropes are cheap to clone
+        let template = settings.templates.is_template(rope).then(|| rope.clone());
+
+        Self {
+            diagnostics,
+            checks,
+            template,
+        }
+    }
+
+    /// Checks the paths the document references.
+    pub(crate) async fn finish(self, paths: &PathCache) -> Vec<Diagnostic> {
+        let Self {
+            mut diagnostics,
+            checks,
+            template,
+        } = self;
+
+        let mut path_diagnostics = check_paths(paths, checks).await;
+        fixes::attach_rules(&mut path_diagnostics);
+        diagnostics.extend(path_diagnostics);
+        if let Some(rope) = &template {
+            tolerate_template(&mut diagnostics, rope);
+        }
+
+        diagnostics
+    }
+}
@@ -0,0 +1,167 @@
+//! Documents read from the filesystem, e.g. the included files which aren't
+//! open, cached by URI with the modification time and size of their file, so
+//! that they're only read and parsed again once the file changed.
+//!
+//! Documents which were open are remembered once closed, and stay roots of the
+//! analyses spanning the workspace, read from their file: closing the file
+//! including the others doesn't lose their context, e.g. the variables it
+//! sets, nor its parsers for go to definition.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use tower_lsp::lsp_types::Url;
+
+use crate::{document::TextDocument, document_map::normalize_uri, include_graph::read_document};
+
+/// Cached documents, beyond which the cache is cleared, not to keep every
+/// file ever included in memory.
+const MAX_CACHE_ENTRIES: usize = 1024;
+
+/// What a file is known by, to tell it changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// Document read from a file.
+#[derive(Clone)]
+pub(crate) struct DiskDocument {
+    pub(crate) document: Arc<TextDocument>,
+    /// Size of the file if it's too large to be analyzed, only its section
+    /// headers being indexed then.
+    pub(crate) partial_size: Option<u64>,
+}
+
+#[derive(Default)]
+pub(crate) struct DiskCache {
+    entries: Mutex<HashMap<Url, (FileStamp, DiskDocument)>>,
+    /// Normalized URIs of the documents closed by the client.
+    closed: Mutex<HashSet<Url>>,
+}
+
+impl DiskCache {
+    /// Document of the file, read again only if it changed since it was
+    /// cached. `None` if it can't be read.
+    pub(crate) async fn read(&self, path: &Path, uri: &Url) -> Option<DiskDocument> {
+        let uri = normalize_uri(uri);
+        let metadata = tokio::fs::metadata(path).await.ok()?;
+        let stamp = FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        };
+        if let Some((cached_stamp, document)) = self.entries.lock().unwrap().get(&uri) {
+            // without a modification time, it can't be told unchanged
+            if *cached_stamp == stamp && stamp.modified.is_some() {
+                return Some(document.clone());
+            }
+        }
+
+        let Some((document, partial_size)) = read_document(path, &uri).await else {
+            self.entries.lock().unwrap().remove(&uri);
+            return None;
+        };
+        let document = DiskDocument {
+            document: Arc::new(document),
+            partial_size,
+        };
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_CACHE_ENTRIES {
+            entries.clear();
+        }
+        entries.insert(uri, (stamp, document.clone()));
+
+        Some(document)
+    }
+
+    /// Remembers the document closed by the client, whose file is analyzed
+    /// from then on.
+    pub(crate) fn close(&self, uri: &Url) {
+        if uri.scheme() == "file" {
+            self.closed.lock().unwrap().insert(normalize_uri(uri));
+        }
+    }
+
+    /// Forgets the document opened again by the client.
+    pub(crate) fn open(&self, uri: &Url) {
+        self.closed.lock().unwrap().remove(&normalize_uri(uri));
+    }
+
+    /// Normalized URIs of the documents closed by the client, in no particular
+    /// order.
+    pub(crate) fn closed(&self) -> Vec<Url> {
+        self.closed.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tower_lsp::lsp_types::DiagnosticSeverity;
+
+    use super::*;
+    use crate::{
+        include_graph::{DocumentLinks, IncludeGraph},
+        positions::PositionEncodingKind,
+    };
+
+    #[tokio::test]
+    async fn documents_are_read_again_once_changed() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("outputs.conf");
+        fs::write(&path, "[OUTPUT]\n    Name stdout\n").unwrap();
+        let uri = Url::from_file_path(&path).unwrap();
+
+        let cache = DiskCache::default();
+        let first = cache.read(&path, &uri).await.unwrap();
+        let second = cache.read(&path, &uri).await.unwrap();
+        assert!(Arc::ptr_eq(&first.document, &second.document));
+
+        // a different size, the modification time may be the same
+        fs::write(&path, "[OUTPUT]\n    Name  stdout\n    Match *\n").unwrap();
+        let changed = cache.read(&path, &uri).await.unwrap();
+        assert!(!Arc::ptr_eq(&first.document, &changed.document));
+        assert_eq!(changed.document.index.sections()[0].entries.len(), 2);
+
+        fs::remove_file(&path).unwrap();
+        assert!(cache.read(&path, &uri).await.is_none());
+
+        cache.close(&uri);
+        cache.close(&Url::parse("untitled:Untitled-1").unwrap());
+        assert_eq!(cache.closed(), [uri.clone()]);
+        cache.open(&uri);
+        assert!(cache.closed().is_empty());
+
+        // a closed configuration still sets the variables of the files it
+        // includes
+        let root = dir.join("fluent-bit.conf");
+        fs::write(&root, "@SET LOG_DIR=/var/log\n@INCLUDE inputs.conf\n").unwrap();
+        let text = "[INPUT]\n    Name tail\n    Path ${LOG_DIR}/*.log\n";
+        fs::write(dir.join("inputs.conf"), text).unwrap();
+        let encoding = PositionEncodingKind::UTF16;
+        let inputs = Url::from_file_path(dir.join("inputs.conf")).unwrap();
+        let links = DocumentLinks::new(&inputs, &TextDocument::new(text), encoding);
+        cache.close(&Url::from_file_path(&root).unwrap());
+        let graph = IncludeGraph::load(
+            vec![(inputs, links)],
+            Vec::new(),
+            &cache.closed(),
+            &cache,
+            &[],
+            encoding,
+        )
+        .await;
+        assert_eq!(graph.loaded.len(), 1);
+        assert!(graph
+            .undefined_variables(&[], DiagnosticSeverity::INFORMATION)
+            .is_empty());
+    }
+}
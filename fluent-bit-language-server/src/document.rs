@@ -1,41 +1,141 @@
 //! https://gist.github.com/rojas-diego/04d9c4e3fff5f8374f29b9b738d541ef
 
-use ropey::{Rope, RopeSlice};
+use ropey::Rope;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tower_lsp::lsp_types::{Position, TextDocumentContentChangeEvent};
-use tree_sitter::{InputEdit, Parser, Point, Tree};
+use tower_lsp::lsp_types::{Range, TextDocumentContentChangeEvent};
+use tree_sitter::{InputEdit, Parser, Tree};
+
+use crate::{
+    encoding::TextEncoding,
+    lsp_ext::ConfigFormat,
+    positions::{byte_to_point, len_in, resolve_position, PositionEncodingKind},
+    section_index::SectionIndex,
+    yaml,
+};
 
 pub struct TextDocument {
     pub rope: Rope,
     pub tree: Option<Tree>,
+    pub index: SectionIndex,
+    pub format: ConfigFormat,
+    /// Encoding the server decoded the file from, `None` for UTF-8 and for
+    /// documents the client sent.
+    pub decoded_from: Option<TextEncoding>,
     parser: Parser,
+    /// Version of the last change, unknown until the client sends one.
+    version: Option<i32>,
 }
 
 #[derive(Error, Debug)]
 pub enum DocumentError {
     #[error("position {0}:{1} is out of bounds")]
     PositionOutOfBounds(u32, u32),
+    #[error("range start {}:{} is after its end {}:{}", .0.start.line, .0.start.character, .0.end.line, .0.end.character)]
+    ReversedRange(Range),
+    #[error("version {0} isn't after version {1}")]
+    StaleVersion(i32, i32),
+    #[error("content length {0} isn't the client's {1}")]
+    LengthMismatch(usize, usize),
+    #[error("content hash {0:08x} isn't the client's {1:08x}")]
+    HashMismatch(u32, u32),
+}
+
+/// Line ending of a document.
+///
+/// Text inserted by the server is written with `\n` and converted to the
+/// document's line ending, so that CRLF files don't end up with mixed endings.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+
+    /// The line ending of most lines of the document, LF on ties.
+    pub fn detect(rope: &Rope) -> Self {
+        let (lf, crlf) =
+            line_endings(rope).fold((0, 0), |(lf, crlf), (_, line_ending)| match line_ending {
+                LineEnding::Lf => (lf + 1, crlf),
+                LineEnding::Crlf => (lf, crlf + 1),
+            });
+
+        if crlf > lf {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// Converts the `\n` line endings of `text` to this one.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_string(),
+            LineEnding::Crlf => text.replace('\n', "\r\n"),
+        }
+    }
 }
 
-#[derive(Clone, Debug, Copy)]
-/// We redeclare this enum here because the `lsp_types` crate exports a Cow
-/// type that is unconvenient to deal with.
-pub enum PositionEncodingKind {
-    #[allow(dead_code)]
-    UTF8,
-    UTF16,
-    #[allow(dead_code)]
-    UTF32,
+/// Byte ranges of the line endings of the document.
+pub fn line_endings(
+    rope: &Rope,
+) -> impl Iterator<Item = (std::ops::Range<usize>, LineEnding)> + '_ {
+    let mut previous = None;
+    rope.bytes().enumerate().filter_map(move |(i, byte)| {
+        let after_cr = previous == Some(b'\r');
+        previous = Some(byte);
+        match byte {
+            b'\n' if after_cr => Some((i - 1..i + 1, LineEnding::Crlf)),
+            b'\n' => Some((i..i + 1, LineEnding::Lf)),
+            _ => None,
+        }
+    })
+}
+
+/// 32-bit FNV-1a hash of the UTF-8 content, which clients can compute
+/// without a dependency.
+pub fn content_hash(rope: &Rope) -> u32 {
+    rope.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn index(format: ConfigFormat, tree: &Tree, rope: &Rope) -> SectionIndex {
+    match format {
+        ConfigFormat::Classic => SectionIndex::new(tree, rope),
+        ConfigFormat::Yaml => SectionIndex::from_parts(
+            yaml::index_sections(tree, rope),
+            yaml::index_includes(tree, rope),
+        ),
+    }
 }
 
 impl TextDocument {
-    /// Creates a new document from the given text and language id. It creates
-    /// a rope, parser and syntax tree from the text.
+    /// Creates a new classic document from the given text.
     pub fn new(text: &str) -> Self {
+        Self::with_format(text, ConfigFormat::Classic)
+    }
+
+    /// Creates a new document from the given text and format. It creates a
+    /// rope, parser and syntax tree from the text.
+    pub fn with_format(text: &str, format: ConfigFormat) -> Self {
         let rope = Rope::from_str(text);
         let mut parser = Parser::new();
 
-        let language = tree_sitter_fluentbit::LANGUAGE;
+        let language = match format {
+            ConfigFormat::Classic => tree_sitter_fluentbit::LANGUAGE,
+            ConfigFormat::Yaml => tree_sitter_yaml::LANGUAGE,
+        };
 
         parser
             .set_language(&language.into())
@@ -46,10 +146,62 @@ impl TextDocument {
             .expect("parse should always return a tree when the language was set and no timeout was specified");
 
         Self {
+            index: index(format, &tree, &rope),
             rope,
             tree: Some(tree),
+            format,
+            decoded_from: None,
             parser,
+            version: None,
+        }
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        LineEnding::detect(&self.rope)
+    }
+
+    /// Version of the last change, `None` until the client sent one.
+    pub fn version(&self) -> Option<i32> {
+        self.version
+    }
+
+    /// Records the version of the document after a change, which has to come
+    /// after the current one: a change to an older version was applied to
+    /// other content than the client's.
+    pub fn set_version(&mut self, version: i32) -> Result<(), DocumentError> {
+        match self.version {
+            Some(current) if version <= current => {
+                Err(DocumentError::StaleVersion(version, current))
+            }
+            _ => {
+                self.version = Some(version);
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks that the content is the client's, of `length` code units of
+    /// `position_encoding` and, if it's given, of `hash` (see
+    /// [`content_hash`]): changes applied to other content than the client's
+    /// may stay in bounds but edit the wrong text.
+    pub fn check_content(
+        &self,
+        length: usize,
+        hash: Option<u32>,
+        position_encoding: PositionEncodingKind,
+    ) -> Result<(), DocumentError> {
+        let actual = len_in(&self.rope, position_encoding);
+        if actual != length {
+            return Err(DocumentError::LengthMismatch(actual, length));
         }
+        if let Some(hash) = hash {
+            let actual = content_hash(&self.rope);
+            if actual != hash {
+                return Err(DocumentError::HashMismatch(actual, hash));
+            }
+        }
+
+        Ok(())
     }
 
     /// Apply a change to the document.
@@ -60,154 +212,75 @@ impl TextDocument {
     ) -> Result<(), DocumentError> {
         match change.range {
             Some(range) => {
-                assert!(
-                    range.start.line < range.end.line
-                        || (range.start.line == range.end.line
-                            && range.start.character <= range.end.character)
-                );
-
-                let same_line = range.start.line == range.end.line;
-                let same_character = range.start.character == range.end.character;
-
-                let change_start_line_cu_idx = range.start.character as usize;
-                let change_end_line_cu_idx = range.end.character as usize;
-
-                // 1. Get the line at which the change starts.
-                let change_start_line_idx = range.start.line as usize;
-                let change_start_line = match self.rope.get_line(change_start_line_idx) {
-                    Some(line) => line,
-                    None => {
-                        return Err(DocumentError::PositionOutOfBounds(
-                            range.start.line,
-                            range.start.character,
-                        ))
-                    }
-                };
-
-                // 2. Get the line at which the change ends. (Small optimization
-                // where we first check whether start and end line are the
-                // same O(log N) lookup. We repeat this all throughout this
-                // function).
-                let change_end_line_idx = range.end.line as usize;
-                let change_end_line = match same_line {
-                    true => change_start_line,
-                    false => match self.rope.get_line(change_end_line_idx) {
-                        Some(line) => line,
-                        None => {
-                            return Err(DocumentError::PositionOutOfBounds(
-                                range.end.line,
-                                range.end.character,
-                            ));
-                        }
-                    },
-                };
-
-                fn compute_char_idx(
-                    position_encoding: PositionEncodingKind,
-                    position: &Position,
-                    slice: &RopeSlice,
-                ) -> Result<usize, DocumentError> {
-                    match position_encoding {
-                        PositionEncodingKind::UTF8 => {
-                            slice.try_byte_to_char(position.character as usize)
-                        }
-                        PositionEncodingKind::UTF16 => {
-                            slice.try_utf16_cu_to_char(position.character as usize)
-                        }
-                        PositionEncodingKind::UTF32 => Ok(position.character as usize),
-                    }
-                    .map_err(|_| {
-                        DocumentError::PositionOutOfBounds(position.line, position.character)
-                    })
+                if (range.start.line, range.start.character) > (range.end.line, range.end.character)
+                {
+                    return Err(DocumentError::ReversedRange(range));
                 }
 
-                // 3. Compute the character offset into the start/end line where
-                // the change starts/ends.
-                let change_start_line_char_idx =
-                    compute_char_idx(position_encoding, &range.start, &change_start_line)?;
-                let change_end_line_char_idx = match same_line && same_character {
-                    true => change_start_line_char_idx,
-                    false => compute_char_idx(position_encoding, &range.end, &change_end_line)?,
+                // 1. Resolve where the change starts and ends in the document.
+                let start = resolve_position(&self.rope, &range.start, position_encoding)?;
+                let end = match range.start == range.end {
+                    true => start,
+                    false => resolve_position(&self.rope, &range.end, position_encoding)?,
                 };
+                // `rangeLength` is deprecated, and clients count it in other
+                // units, e.g. characters, so only the range is trusted
 
-                // 4. Compute the character and byte offset into the document
-                // where the change starts/ends.
-                let change_start_doc_char_idx =
-                    self.rope.line_to_char(change_start_line_idx) + change_start_line_char_idx;
-                let change_end_doc_char_idx = match same_line && same_character {
-                    true => change_start_doc_char_idx,
-                    false => self.rope.line_to_char(change_end_line_idx) + change_end_line_char_idx,
-                };
-                let change_start_doc_byte_idx = self.rope.char_to_byte(change_start_doc_char_idx);
-                let change_end_doc_byte_idx = match same_line && same_character {
-                    true => change_start_doc_byte_idx,
-                    false => self.rope.char_to_byte(change_end_doc_char_idx),
-                };
-
-                // 5. Compute the byte offset into the start/end line where the
-                // change starts/ends. Required for tree-sitter.
-                let change_start_line_byte_idx = match position_encoding {
-                    PositionEncodingKind::UTF8 => change_start_line_cu_idx,
-                    PositionEncodingKind::UTF16 => {
-                        change_start_line.char_to_utf16_cu(change_start_line_char_idx)
-                    }
-                    PositionEncodingKind::UTF32 => change_start_line_char_idx,
-                };
-                let change_end_line_byte_idx = match same_line && same_character {
-                    true => change_start_line_byte_idx,
-                    false => match position_encoding {
-                        PositionEncodingKind::UTF8 => change_end_line_cu_idx,
-                        PositionEncodingKind::UTF16 => {
-                            change_end_line.char_to_utf16_cu(change_end_line_char_idx)
-                        }
-                        PositionEncodingKind::UTF32 => change_end_line_char_idx,
-                    },
-                };
-
-                self.rope
-                    .remove(change_start_doc_char_idx..change_end_doc_char_idx);
-                self.rope.insert(change_start_doc_char_idx, &change.text);
+                self.rope.remove(start.char_idx..end.char_idx);
+                self.rope.insert(start.char_idx, &change.text);
 
                 if let Some(tree) = &mut self.tree {
-                    // 6. Compute the byte index into the new end line where the
-                    // change ends. Required for tree-sitter.
-                    let change_new_end_line_idx = self
-                        .rope
-                        .byte_to_line(change_start_doc_byte_idx + change.text.len());
-                    let change_new_end_line_byte_idx =
-                        change_start_doc_byte_idx + change.text.len();
-
-                    // 7. Construct the tree-sitter edit. We stay mindful that
-                    // tree-sitter Point::column is a byte offset.
+                    // 2. Construct the tree-sitter edit, whose new end is where
+                    // the inserted text ends.
+                    let new_end_byte_idx = start.byte_idx + change.text.len();
                     let edit = InputEdit {
-                        start_byte: change_start_doc_byte_idx,
-                        old_end_byte: change_end_doc_byte_idx,
-                        new_end_byte: change_start_doc_byte_idx + change.text.len(),
-                        start_position: Point {
-                            row: change_start_line_idx,
-                            column: change_start_line_byte_idx,
-                        },
-                        old_end_position: Point {
-                            row: change_end_line_idx,
-                            column: change_end_line_byte_idx,
-                        },
-                        new_end_position: Point {
-                            row: change_new_end_line_idx,
-                            column: change_new_end_line_byte_idx,
-                        },
+                        start_byte: start.byte_idx,
+                        old_end_byte: end.byte_idx,
+                        new_end_byte: new_end_byte_idx,
+                        start_position: start.point,
+                        old_end_position: end.point,
+                        new_end_position: byte_to_point(&self.rope, new_end_byte_idx),
                     };
 
                     tree.edit(&edit);
 
-                    self.tree = Some(self
+                    let rope = &self.rope;
+                    let new_tree = self
                         .parser
-                        .parse(self.rope.to_string(), Some(tree))
-                        .expect("parse should always return a tree when the language was set and no timeout was specified"));
+                        .parse_with(
+                            &mut |byte, _| match rope.get_chunk_at_byte(byte) {
+                                Some((chunk, chunk_byte_idx, _, _)) => {
+                                    &chunk.as_bytes()[byte - chunk_byte_idx..]
+                                }
+                                None => &[],
+                            },
+                            Some(tree),
+                        )
+                        .expect("parse should always return a tree when the language was set and no timeout was specified");
+
+                    // 3. Only sections around the edit need to be indexed again,
+                    // YAML sections being nested, their document is indexed
+                    // again as a whole.
+                    match self.format {
+                        ConfigFormat::Classic => self.index.update(
+                            &edit,
+                            tree.changed_ranges(&new_tree),
+                            &new_tree,
+                            rope,
+                        ),
+                        ConfigFormat::Yaml => self.index = index(self.format, &new_tree, rope),
+                    }
+                    self.tree = Some(new_tree);
                 }
             }
             None => {
                 self.rope = Rope::from_str(&change.text);
                 self.tree = self.parser.parse(&change.text, None);
+                self.index = self
+                    .tree
+                    .as_ref()
+                    .map(|tree| index(self.format, tree, &self.rope))
+                    .unwrap_or_default();
             }
         }
 
@@ -217,6 +290,7 @@ impl TextDocument {
 
 #[cfg(test)]
 mod test {
+    use tower_lsp::lsp_types::Position;
     use tree_sitter::Node;
 
     use super::*;
@@ -478,6 +552,144 @@ mod test {
     //     assert_eq!(second_line.chars().nth(13), Some('😊'));
     // }
 
+    #[test]
+    fn reversed_range_is_rejected() {
+        let mut doc = TextDocument::new("[INPUT]\n    Name cpu\n");
+
+        let result = doc.apply_content_change(
+            &TextDocumentContentChangeEvent {
+                range: Some(Range::new(Position::new(1, 4), Position::new(0, 2))),
+                range_length: None,
+                text: "".to_string(),
+            },
+            PositionEncodingKind::UTF16,
+        );
+
+        assert!(matches!(result, Err(DocumentError::ReversedRange(_))));
+        assert_eq!(doc.rope.to_string(), "[INPUT]\n    Name cpu\n");
+    }
+
+    #[test]
+    fn desyncs_are_detected() {
+        let mut doc = TextDocument::new("[INPUT]\n    Name cpu\n");
+
+        // a change past the end of the line the client believes it edits
+        let change = |end| TextDocumentContentChangeEvent {
+            range: Some(Range::new(Position::new(1, 4), Position::new(1, end))),
+            range_length: None,
+            text: "Tag".to_string(),
+        };
+        let result = doc.apply_content_change(&change(40), PositionEncodingKind::UTF8);
+        assert!(matches!(
+            result,
+            Err(DocumentError::PositionOutOfBounds(1, 40))
+        ));
+        assert_eq!(doc.rope.to_string(), "[INPUT]\n    Name cpu\n");
+        doc.apply_content_change(&change(8), PositionEncodingKind::UTF8)
+            .unwrap();
+        assert_eq!(doc.rope.to_string(), "[INPUT]\n    Tag cpu\n");
+
+        doc.set_version(2).unwrap();
+        assert!(matches!(
+            doc.set_version(2),
+            Err(DocumentError::StaleVersion(2, 2))
+        ));
+        doc.set_version(4).unwrap();
+
+        // changes in bounds, but to other content than the client's
+        assert_eq!(content_hash(&Rope::from_str("")), 0x811c_9dc5);
+        assert_eq!(content_hash(&Rope::from_str("a")), 0xe40c_292c);
+        let hash = content_hash(&doc.rope);
+        doc.check_content(20, Some(hash), PositionEncodingKind::UTF16)
+            .unwrap();
+        assert!(matches!(
+            doc.check_content(21, None, PositionEncodingKind::UTF16),
+            Err(DocumentError::LengthMismatch(20, 21))
+        ));
+        assert!(matches!(
+            doc.check_content(20, Some(hash ^ 1), PositionEncodingKind::UTF8),
+            Err(DocumentError::HashMismatch(..))
+        ));
+    }
+
+    #[test]
+    fn incremental_change_with_multibyte_content() {
+        let mut doc = TextDocument::new("[INPUT]\n    Tag 🤗\n    Name cpu\n");
+
+        // replace "🤗" with "é.log" (UTF-16 positions), the length being
+        // counted in characters as some clients do
+        doc.apply_content_change(
+            &TextDocumentContentChangeEvent {
+                range: Some(Range::new(Position::new(1, 8), Position::new(1, 10))),
+                range_length: Some(1),
+                text: "é.log".to_string(),
+            },
+            PositionEncodingKind::UTF16,
+        )
+        .unwrap();
+
+        let text = "[INPUT]\n    Tag é.log\n    Name cpu\n";
+        assert_eq!(doc.rope.to_string(), text);
+
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_fluentbit::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        assert!(nodes_are_equal_recursive(
+            &doc.tree.as_ref().unwrap().root_node(),
+            &tree.root_node()
+        ));
+    }
+
+    #[test]
+    fn section_index_is_updated_incrementally() {
+        let mut doc = TextDocument::new(
+            "[SERVICE]\n    Flush 1\n\n[INPUT]\n    Name cpu\n\n[OUTPUT]\n    Name stdout\n    Match *\n",
+        );
+
+        for (start, end, text) in [
+            // rename a key, which doesn't change the tree's structure
+            (Position::new(1, 4), Position::new(1, 9), "Grace"),
+            // add an entry
+            (
+                Position::new(4, 12),
+                Position::new(4, 12),
+                "\n    Tag  cpu.0",
+            ),
+            // remove the blank line between two sections
+            (Position::new(5, 15), Position::new(7, 0), "\n"),
+            // add a section in the middle
+            (
+                Position::new(2, 0),
+                Position::new(2, 0),
+                "\n[FILTER]\n    Name grep\n",
+            ),
+        ] {
+            doc.apply_content_change(
+                &TextDocumentContentChangeEvent {
+                    range: Some(Range::new(start, end)),
+                    range_length: None,
+                    text: text.to_string(),
+                },
+                PositionEncodingKind::UTF16,
+            )
+            .unwrap();
+
+            let rebuilt = SectionIndex::new(doc.tree.as_ref().unwrap(), &doc.rope);
+            assert_eq!(doc.index, rebuilt, "after inserting {text:?}");
+        }
+
+        let names: Vec<_> = doc
+            .index
+            .sections()
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(names, ["SERVICE", "FILTER", "INPUT", "OUTPUT"]);
+        assert_eq!(doc.index.sections()[2].get_value("tag"), Some("cpu.0"));
+    }
+
     #[test]
     /// I wrote this test to better understand ropey's handling of ranges.
     fn test_rope_remove_and_insert() {
@@ -503,7 +715,6 @@ mod test {
         assert_eq!(rope.lines().next(), Some(rope.slice(0..0)));
     }
 
-    #[allow(dead_code)]
     fn nodes_are_equal_recursive(node1: &Node, node2: &Node) -> bool {
         if node1.kind() != node2.kind() {
             return false;
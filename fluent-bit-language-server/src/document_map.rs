@@ -0,0 +1,157 @@
+//! Open documents, keyed by normalized URIs.
+//!
+//! Clients don't always spell the URI of a document the same way, e.g. on
+//! Windows `file:///C:/fluent-bit.conf` and `file:///c%3A/fluent-bit.conf` are
+//! the same file. Documents are looked up by a normalized form of their URI so
+//! that they don't end up with duplicate, diverging entries.
+//!
+//! Documents out of sync with the client are kept out of the analyses until it
+//! sends their whole content again, rather than serving content known to be
+//! wrong.
+
+use std::collections::{HashMap, HashSet};
+
+use percent_encoding::percent_decode_str;
+use tower_lsp::lsp_types::Url;
+
+use crate::document::TextDocument;
+
+/// Normalizes the URI of a file: percent-encoding is made canonical and the
+/// drive letter of Windows paths lowercase. Other URIs are left as is.
+pub(crate) fn normalize_uri(uri: &Url) -> Url {
+    if uri.scheme() != "file" {
+        return uri.clone();
+    }
+
+    let mut path = percent_decode_str(uri.path())
+        .decode_utf8_lossy()
+        .into_owned();
+    // `/C:/...`
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        path[1..2].make_ascii_lowercase();
+    }
+
+    let mut normalized = uri.clone();
+    normalized.set_path(&path);
+    normalized
+}
+
+#[derive(Default)]
+pub(crate) struct DocumentMap {
+    /// Documents with the URI the client opened them with, by normalized URI.
+    documents: HashMap<Url, (Url, TextDocument)>,
+    /// Normalized URIs of the documents out of sync with the client.
+    desynced: HashSet<Url>,
+}
+
+impl DocumentMap {
+    /// The document, `None` if it isn't open or is out of sync.
+    pub(crate) fn get(&self, uri: &Url) -> Option<&TextDocument> {
+        let uri = normalize_uri(uri);
+        if self.desynced.contains(&uri) {
+            return None;
+        }
+        self.documents.get(&uri).map(|(_, document)| document)
+    }
+
+    pub(crate) fn get_mut(&mut self, uri: &Url) -> Option<&mut TextDocument> {
+        self.documents
+            .get_mut(&normalize_uri(uri))
+            .map(|(_, document)| document)
+    }
+
+    /// Inserts the document, replacing the one with the same normalized URI.
+    /// Returns the URI the replaced document was opened with.
+    pub(crate) fn insert(&mut self, uri: Url, document: TextDocument) -> Option<Url> {
+        let normalized = normalize_uri(&uri);
+        self.desynced.remove(&normalized);
+        self.documents
+            .insert(normalized, (uri, document))
+            .map(|(uri, _)| uri)
+    }
+
+    pub(crate) fn remove(&mut self, uri: &Url) -> Option<TextDocument> {
+        let uri = normalize_uri(uri);
+        self.desynced.remove(&uri);
+        self.documents.remove(&uri).map(|(_, document)| document)
+    }
+
+    /// Marks the open document as out of sync, until [`Self::mark_synced`].
+    /// Returns whether it wasn't already.
+    pub(crate) fn mark_desynced(&mut self, uri: &Url) -> bool {
+        let uri = normalize_uri(uri);
+        self.documents.contains_key(&uri) && self.desynced.insert(uri)
+    }
+
+    /// The client sent the whole content of the document.
+    pub(crate) fn mark_synced(&mut self, uri: &Url) {
+        self.desynced.remove(&normalize_uri(uri));
+    }
+
+    pub(crate) fn is_desynced(&self, uri: &Url) -> bool {
+        self.desynced.contains(&normalize_uri(uri))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// URIs of the documents in sync with the client, as it opened them.
+    pub(crate) fn uris(&self) -> impl Iterator<Item = &Url> {
+        self.documents
+            .iter()
+            .filter(|(uri, _)| !self.desynced.contains(*uri))
+            .map(|(_, (uri, _))| uri)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uris_are_normalized() {
+        let uri = |s: &str| Url::parse(s).unwrap();
+
+        assert_eq!(
+            normalize_uri(&uri("file:///C:/Program%20Files/fluent-bit.conf")),
+            uri("file:///c:/Program%20Files/fluent-bit.conf")
+        );
+        assert_eq!(
+            normalize_uri(&uri("file:///c%3A/Program Files/fluent%2Dbit.conf")),
+            uri("file:///c:/Program%20Files/fluent-bit.conf")
+        );
+        assert_eq!(
+            normalize_uri(&uri("file:///etc/Fluent-Bit/fluent-bit.conf")),
+            uri("file:///etc/Fluent-Bit/fluent-bit.conf")
+        );
+        assert_eq!(
+            normalize_uri(&uri("untitled:Untitled-1")),
+            uri("untitled:Untitled-1")
+        );
+
+        let mut map = DocumentMap::default();
+        map.insert(uri("file:///C:/a.conf"), TextDocument::new("[INPUT]\n"));
+        let replaced = map.insert(uri("file:///c%3A/a.conf"), TextDocument::new("[OUTPUT]\n"));
+        assert_eq!(replaced, Some(uri("file:///C:/a.conf")));
+        assert_eq!(map.len(), 1);
+        assert_eq!(
+            map.get(&uri("file:///C:/a.conf")).unwrap().rope.to_string(),
+            "[OUTPUT]\n"
+        );
+        assert_eq!(map.uris().collect::<Vec<_>>(), [&uri(
+            "file:///c%3A/a.conf"
+        )]);
+
+        // out of sync documents are hidden until they're sent again
+        assert!(map.mark_desynced(&uri("file:///C:/a.conf")));
+        assert!(!map.mark_desynced(&uri("file:///c%3A/a.conf")));
+        assert!(!map.mark_desynced(&uri("file:///C:/b.conf")));
+        assert!(map.get(&uri("file:///C:/a.conf")).is_none());
+        assert!(map.get_mut(&uri("file:///C:/a.conf")).is_some());
+        assert_eq!(map.uris().count(), 0);
+        map.mark_synced(&uri("file:///C:/a.conf"));
+        assert!(map.get(&uri("file:///C:/a.conf")).is_some());
+    }
+}
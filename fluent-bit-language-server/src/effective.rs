@@ -0,0 +1,220 @@
+//! Effective configuration of a section, i.e. what fluent-bit uses for its
+//! plugin: the values the section sets, with the variables the configuration
+//! sets (`@SET`, or `env` in YAML) substituted, and the defaults of the
+//! parameters it doesn't set.
+//!
+//! References to other variables are left as written: fluent-bit reads them
+//! from its environment at runtime.
+
+use std::collections::HashMap;
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+
+use crate::{
+    completion::FlbData,
+    document::TextDocument,
+    lsp_ext::{ConfigFormat, EffectiveParameter, EffectiveSectionResult, ValueSource},
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    section_index::{index_variables, IndexedSection},
+    service::SERVICE_PARAMETERS,
+    yaml,
+};
+
+/// Variables set by the document, by name. The last one set wins.
+pub(crate) fn variables(document: &TextDocument) -> HashMap<String, String> {
+    let Some(tree) = &document.tree else {
+        return HashMap::new();
+    };
+
+    let variables = match document.format {
+        ConfigFormat::Classic => index_variables(tree, &document.rope),
+        ConfigFormat::Yaml => yaml::index_variables(tree, &document.rope),
+    };
+    variables.into_iter().collect()
+}
+
+struct Interpolated {
+    value: String,
+    /// Whether a variable was substituted.
+    substituted: bool,
+    /// Names of the variables which weren't.
+    unresolved: Vec<String>,
+}
+
+/// Substitutes the `${NAME}` references to `variables` of the value.
+fn interpolate(value: &str, variables: &HashMap<String, String>) -> Interpolated {
+    let mut ret = Interpolated {
+        value: String::new(),
+        substituted: false,
+        unresolved: Vec::new(),
+    };
+
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        ret.value.push_str(&rest[..start]);
+        match variables.get(name) {
+            Some(variable) => {
+                ret.value.push_str(variable);
+                ret.substituted = true;
+            }
+            None => {
+                ret.value.push_str(&rest[start..start + 3 + len]);
+                ret.unresolved.push(name.to_string());
+            }
+        }
+        rest = &rest[start + 3 + len..];
+    }
+    ret.value.push_str(rest);
+
+    ret
+}
+
+/// Keys and defaults of the parameters of the section's plugin, or of
+/// `[SERVICE]`.
+pub(crate) fn defaults<'a>(
+    data: &'a FlbData,
+    section: &IndexedSection,
+    plugin_name: Option<&str>,
+) -> Vec<(&'a str, Option<&'a str>)> {
+    if section.section_type == FlbSectionType::Service {
+        return SERVICE_PARAMETERS
+            .iter()
+            .map(|param| (param.key, param.default_value))
+            .collect();
+    }
+
+    plugin_name
+        .and_then(|name| data.get_snippet(&section.section_type, name))
+        .map_or_else(Vec::new, |snippet| {
+            snippet
+                .config_params()
+                .iter()
+                .map(|param| (param.key.as_str(), param.info.default_value.as_deref()))
+                .collect()
+        })
+}
+
+pub(crate) fn effective_section(
+    data: &FlbData,
+    section: &IndexedSection,
+    variables: &HashMap<String, String>,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> EffectiveSectionResult {
+    let mut parameters: Vec<EffectiveParameter> = section
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let raw = entry.value.clone()?;
+            let Interpolated {
+                value,
+                substituted,
+                unresolved,
+            } = interpolate(&raw, variables);
+            let source = if !unresolved.is_empty() {
+                ValueSource::Environment
+            } else if substituted {
+                ValueSource::Variable
+            } else {
+                ValueSource::Explicit
+            };
+            let end = entry
+                .value_range
+                .as_ref()
+                .map_or(entry.key_range.end, |range| range.end);
+
+            Some(EffectiveParameter {
+                key: entry.key.clone(),
+                value,
+                source,
+                raw: Some(raw),
+                range: Some(byte_range_to_lsp_range(
+                    rope,
+                    entry.key_range.start..end,
+                    position_encoding,
+                )),
+                unresolved,
+            })
+        })
+        .collect();
+
+    // e.g. `Name ${OUTPUT}`
+    let plugin_name = section
+        .get_value("Name")
+        .map(|name| interpolate(name, variables).value);
+    for (key, default) in defaults(data, section, plugin_name.as_deref()) {
+        let Some(default) = default.filter(|_| !section.contains_key(key)) else {
+            continue;
+        };
+        parameters.push(EffectiveParameter {
+            key: key.to_string(),
+            value: default.to_string(),
+            source: ValueSource::Default,
+            raw: None,
+            range: None,
+            unresolved: Vec::new(),
+        });
+    }
+
+    EffectiveSectionResult {
+        section_type: section.section_type.clone(),
+        plugin_name,
+        range: byte_range_to_lsp_range(rope, section.byte_range.clone(), position_encoding),
+        parameters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::FLB_DATA;
+
+    #[test]
+    fn values_defaults_and_variables() {
+        let document = TextDocument::new(
+            "@SET output=stdout\n@SET fmt=json_lines\n\n\
+             [OUTPUT]\n    Name   ${output}\n    Match  *\n    Format ${fmt}\n    Tag    ${HOSTNAME}.log\n",
+        );
+        let document_variables = variables(&document);
+        assert_eq!(document_variables["fmt"], "json_lines");
+
+        let section = &document.index.sections()[0];
+        let result = effective_section(
+            &FLB_DATA,
+            section,
+            &document_variables,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        assert_eq!(result.plugin_name.as_deref(), Some("stdout"));
+        let parameters: Vec<_> = result
+            .parameters
+            .iter()
+            .map(|p| (p.key.as_str(), p.value.as_str(), p.source))
+            .collect();
+        assert_eq!(&parameters[..4], [
+            ("Name", "stdout", ValueSource::Variable),
+            ("Match", "*", ValueSource::Explicit),
+            ("Format", "json_lines", ValueSource::Variable),
+            ("Tag", "${HOSTNAME}.log", ValueSource::Environment),
+        ]);
+        assert_eq!(result.parameters[3].unresolved, ["HOSTNAME"]);
+        // the defaults of the parameters which aren't set
+        assert_eq!(&parameters[4..], [(
+            "json_date_key",
+            "date",
+            ValueSource::Default
+        )]);
+
+        let yaml = TextDocument::with_format(
+            "env:\n  flush: 2\npipeline:\n  inputs:\n    - name: cpu\n",
+            ConfigFormat::Yaml,
+        );
+        assert_eq!(variables(&yaml)["flush"], "2");
+    }
+}
@@ -0,0 +1,203 @@
+//! fluent-bit configurations embedded in other YAML files, e.g. the
+//! `fluent-bit.conf: |` of a Helm ConfigMap or the `write_files` of cloud-init,
+//! extracted as virtual documents which are analyzed like files.
+//!
+//! The lines of a virtual document are the lines of the literal block scalar
+//! without their indentation, so that its positions map to the host document
+//! by an offset of lines and columns.
+
+use flb_schema::section::FlbSectionType;
+use tower_lsp::lsp_types::{Diagnostic, Position, Range};
+
+use crate::{
+    document::TextDocument,
+    lsp_ext::{ConfigFormat, EmbeddedDocumentInfo},
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    yaml,
+};
+
+pub(crate) struct EmbeddedDocument {
+    /// Name of the file, e.g. `fluent-bit.conf`.
+    pub(crate) name: String,
+    pub(crate) document: TextDocument,
+    /// Range of the content in the host document.
+    pub(crate) range: Range,
+    /// Line of the host document of the first line.
+    first_line: u32,
+    /// Columns of indentation removed from the lines.
+    indent: u32,
+}
+
+impl EmbeddedDocument {
+    fn to_host(&self, position: Position) -> Position {
+        Position::new(
+            position.line + self.first_line,
+            position.character + self.indent,
+        )
+    }
+
+    /// Moves diagnostics of the virtual document to the host document.
+    pub(crate) fn diagnostics_to_host(&self, diagnostics: &mut [Diagnostic]) {
+        for diagnostic in diagnostics {
+            diagnostic.range = Range::new(
+                self.to_host(diagnostic.range.start),
+                self.to_host(diagnostic.range.end),
+            );
+        }
+    }
+
+    pub(crate) fn to_info(&self) -> EmbeddedDocumentInfo {
+        EmbeddedDocumentInfo {
+            name: self.name.clone(),
+            format: self.document.format,
+            range: self.range,
+            content: self.document.rope.to_string(),
+        }
+    }
+}
+
+fn format_of(name: &str) -> Option<ConfigFormat> {
+    let name = name.to_ascii_lowercase();
+    if name.ends_with(".conf") {
+        Some(ConfigFormat::Classic)
+    } else if name.ends_with(".yaml") || name.ends_with(".yml") {
+        Some(ConfigFormat::Yaml)
+    } else {
+        None
+    }
+}
+
+/// Whether the document is a fluent-bit configuration, rather than another
+/// file of the same format, e.g. `nginx.conf`.
+fn is_configuration(document: &TextDocument) -> bool {
+    document
+        .index
+        .sections()
+        .iter()
+        .any(|section| !matches!(section.section_type, FlbSectionType::Other(_)))
+}
+
+/// Lines of a literal block scalar after its header (`|`, `|-`, ...) without
+/// their common indentation, and the indentation.
+fn unindent(block_scalar: &str) -> Option<(String, usize)> {
+    let mut lines = block_scalar.split_inclusive('\n');
+    // folded scalars (`>`) aren't the text of the file
+    if !lines.next()?.starts_with('|') {
+        return None;
+    }
+
+    let lines: Vec<&str> = lines.collect();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .min()?;
+    let text = lines
+        .iter()
+        .map(|line| match line.get(indent..) {
+            Some(rest) if !line.trim().is_empty() => rest,
+            _ => line.trim_start_matches([' ', '\t']),
+        })
+        .collect();
+
+    Some((text, indent))
+}
+
+/// fluent-bit configurations embedded in a YAML document.
+pub(crate) fn extract(
+    host: &TextDocument,
+    position_encoding: PositionEncodingKind,
+) -> Vec<EmbeddedDocument> {
+    let Some(tree) = host
+        .tree
+        .as_ref()
+        .filter(|_| host.format == ConfigFormat::Yaml)
+    else {
+        return Vec::new();
+    };
+
+    yaml::file_contents(tree, &host.rope)
+        .into_iter()
+        .filter_map(|(name, range)| {
+            let format = format_of(&name)?;
+            let block_scalar = host.rope.get_byte_slice(range.clone())?.to_string();
+            let (text, indent) = unindent(&block_scalar)?;
+            let document = TextDocument::with_format(&text, format);
+            if !is_configuration(&document) {
+                return None;
+            }
+
+            let first_line = host.rope.byte_to_line(range.start) + 1;
+            let start = host.rope.line_to_byte(first_line);
+            Some(EmbeddedDocument {
+                name,
+                document,
+                range: byte_range_to_lsp_range(
+                    &host.rope,
+                    start..range.end.max(start),
+                    position_encoding,
+                ),
+                first_line: first_line as u32,
+                indent: indent as u32,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configurations_of_configmaps_and_cloud_init() {
+        let host = TextDocument::with_format(
+            "\
+apiVersion: v1
+kind: ConfigMap
+data:
+  fluent-bit.conf: |
+    [INPUT]
+        Name tail
+
+    [OUTPUT]
+        Name stdout
+  nginx.conf: |
+    server {}
+---
+write_files:
+  - path: /etc/fluent-bit/fluent-bit.yaml
+    content: |
+      pipeline:
+        inputs:
+          - name: cpu
+",
+            ConfigFormat::Yaml,
+        );
+        let encoding = PositionEncodingKind::UTF16;
+        let embedded = extract(&host, encoding);
+
+        let names: Vec<_> = embedded.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, [
+            "fluent-bit.conf",
+            "/etc/fluent-bit/fluent-bit.yaml"
+        ]);
+        assert_eq!(
+            embedded[0].document.rope.to_string(),
+            "[INPUT]\n    Name tail\n\n[OUTPUT]\n    Name stdout"
+        );
+        assert_eq!(embedded[1].document.format, ConfigFormat::Yaml);
+        assert_eq!(embedded[1].document.index.sections().len(), 1);
+        assert_eq!(embedded[0].range.start, Position::new(4, 0));
+
+        // `Name` of the output
+        let mut diagnostics = [Diagnostic {
+            range: Range::new(Position::new(4, 4), Position::new(4, 8)),
+            ..Diagnostic::default()
+        }];
+        embedded[0].diagnostics_to_host(&mut diagnostics);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(8, 8), Position::new(8, 12))
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Files which aren't UTF-8, which fluent-bit reads its configuration as, e.g.
+//! UTF-16 files saved on Windows or Latin-1 logs pasted into a configuration.
+//!
+//! Files the server reads are decoded from their encoding, and documents the
+//! client decoded lossily, with replacement characters, or byte by byte, with
+//! the NULs of UTF-16, are reported instead of being analyzed as garbage.
+
+use std::fmt::Display;
+
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::{
+    document::TextDocument,
+    i18n::t,
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+};
+
+/// Diagnostic code of a file which isn't UTF-8.
+pub(crate) const INVALID_ENCODING: &str = "invalid-encoding";
+
+/// Bytes looked at to detect UTF-16 without byte order mark.
+const DETECTION_SIZE: usize = 1024;
+
+/// Encoding a file was decoded from, other than UTF-8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TextEncoding {
+    Utf16Le,
+    Utf16Be,
+    /// Bytes of single-byte encodings, e.g. Windows-1252, mapped to the same
+    /// code points.
+    Latin1,
+}
+
+impl Display for TextEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextEncoding::Utf16Le => write!(f, "UTF-16LE"),
+            TextEncoding::Utf16Be => write!(f, "UTF-16BE"),
+            TextEncoding::Latin1 => write!(f, "Latin-1"),
+        }
+    }
+}
+
+/// Encoding of UTF-16 bytes, from their byte order mark or from the NULs of
+/// the high bytes of ASCII characters.
+fn detect_utf16(bytes: &[u8]) -> Option<TextEncoding> {
+    match bytes {
+        [0xff, 0xfe, ..] => return Some(TextEncoding::Utf16Le),
+        [0xfe, 0xff, ..] => return Some(TextEncoding::Utf16Be),
+        _ => {}
+    }
+
+    let pairs = &bytes[..bytes.len().min(DETECTION_SIZE) & !1];
+    let count = |parity| {
+        pairs
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|byte| **byte == 0)
+            .count()
+    };
+    let (even_nuls, odd_nuls) = (count(0), count(1));
+    match pairs.len() / 2 {
+        0 => None,
+        len if odd_nuls * 2 > len && even_nuls == 0 => Some(TextEncoding::Utf16Le),
+        len if even_nuls * 2 > len && odd_nuls == 0 => Some(TextEncoding::Utf16Be),
+        _ => None,
+    }
+}
+
+/// Decodes the content of a file, with the encoding it was decoded from if it
+/// isn't UTF-8.
+pub(crate) fn decode(bytes: Vec<u8>) -> (String, Option<TextEncoding>) {
+    if let Some(encoding) = detect_utf16(&bytes) {
+        let bom = matches!(bytes[..2], [0xff, 0xfe] | [0xfe, 0xff]);
+        let units = bytes[if bom { 2 } else { 0 }..]
+            .chunks_exact(2)
+            .map(|pair| match encoding {
+                TextEncoding::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+                _ => u16::from_le_bytes([pair[0], pair[1]]),
+            });
+        let text = char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+        return (text, Some(encoding));
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(text) => (text, None),
+        Err(e) => {
+            let text = e.into_bytes().into_iter().map(char::from).collect();
+            (text, Some(TextEncoding::Latin1))
+        }
+    }
+}
+
+/// Why a document isn't UTF-8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EncodingProblem {
+    /// Decoded by the server from another encoding.
+    Decoded(TextEncoding),
+    /// Decoded by the client byte by byte although it's UTF-16, the byte index
+    /// of its first NUL.
+    Nul(usize),
+    /// Decoded lossily by the client, the byte index of its first replacement
+    /// character.
+    Replaced(usize),
+}
+
+impl EncodingProblem {
+    /// The problem of the document, if it isn't UTF-8.
+    pub(crate) fn of(document: &TextDocument) -> Option<Self> {
+        if let Some(encoding) = document.decoded_from {
+            return Some(EncodingProblem::Decoded(encoding));
+        }
+
+        let mut byte_idx = 0;
+        for c in document.rope.chars() {
+            match c {
+                '\0' => return Some(EncodingProblem::Nul(byte_idx)),
+                char::REPLACEMENT_CHARACTER => return Some(EncodingProblem::Replaced(byte_idx)),
+                _ => byte_idx += c.len_utf8(),
+            }
+        }
+
+        None
+    }
+
+    /// Whether the document is analyzed despite it, which a document with the
+    /// NULs of UTF-16 isn't, its sections being unreadable.
+    pub(crate) fn is_analyzable(&self) -> bool {
+        !matches!(self, EncodingProblem::Nul(_))
+    }
+
+    pub(crate) fn diagnostic(
+        &self,
+        rope: &Rope,
+        position_encoding: PositionEncodingKind,
+    ) -> Diagnostic {
+        let (range, message) = match *self {
+            EncodingProblem::Decoded(encoding) => {
+                (0..0, t!("encoding-decoded", encoding = encoding))
+            }
+            EncodingProblem::Nul(start) => (start..start + 1, t!("encoding-nul")),
+            EncodingProblem::Replaced(start) => (
+                start..start + char::REPLACEMENT_CHARACTER.len_utf8(),
+                t!("encoding-replaced"),
+            ),
+        };
+
+        Diagnostic {
+            range: byte_range_to_lsp_range(rope, range, position_encoding),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String(INVALID_ENCODING.to_string())),
+            message,
+            ..Diagnostic::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_which_arent_utf8() {
+        let text = "[INPUT]\n    Name cpu\n";
+        assert_eq!(decode(text.as_bytes().to_vec()), (text.to_string(), None));
+        let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(
+            decode(utf16.clone()),
+            (text.to_string(), Some(TextEncoding::Utf16Le))
+        );
+        let utf16_be: Vec<u8> = [0xfe, 0xff]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        assert_eq!(
+            decode(utf16_be),
+            (text.to_string(), Some(TextEncoding::Utf16Be))
+        );
+        assert_eq!(
+            decode(b"# caf\xe9\n".to_vec()),
+            ("# café\n".to_string(), Some(TextEncoding::Latin1))
+        );
+
+        let mut document = TextDocument::new(text);
+        assert_eq!(EncodingProblem::of(&document), None);
+        document.decoded_from = Some(TextEncoding::Latin1);
+        let problem = EncodingProblem::of(&document).unwrap();
+        let diagnostic = problem.diagnostic(&document.rope, PositionEncodingKind::UTF16);
+        assert_eq!(
+            diagnostic.message,
+            "The file was decoded as Latin-1: fluent-bit reads configurations as UTF-8, save it as UTF-8."
+        );
+
+        let misread: String = utf16.iter().copied().map(char::from).collect();
+        let document = TextDocument::new(&misread);
+        assert_eq!(
+            EncodingProblem::of(&document),
+            Some(EncodingProblem::Nul(1))
+        );
+        assert!(!EncodingProblem::Nul(1).is_analyzable());
+        let document = TextDocument::new("[INPUT]\n    Name cpu \u{fffd}\n");
+        assert_eq!(
+            EncodingProblem::of(&document),
+            Some(EncodingProblem::Replaced(21))
+        );
+    }
+}
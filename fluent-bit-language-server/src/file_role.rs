@@ -0,0 +1,155 @@
+//! Role of a classic file in the configuration: the parsers files of
+//! `Parsers_File`, e.g. `parsers.conf`, only define `[PARSER]` and
+//! `[MULTILINE_PARSER]` sections, while the main configuration can't define
+//! parsers once it sets `Parsers_File`. Parsers files are told by their name,
+//! or by the `parsersFiles` setting, and the sections their role doesn't allow
+//! are reported.
+
+use flb_schema::section::FlbSectionType;
+use percent_encoding::percent_decode_str;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use crate::{
+    i18n::t,
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    scope::matches_any,
+    section_index::{IndexedSection, SectionIndex},
+    section_names::name_range,
+};
+
+/// Diagnostic code of a section the role of its file doesn't allow.
+pub(crate) const MISPLACED_SECTION: &str = "misplaced-section";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FileRole {
+    /// File of `Parsers_File`.
+    Parsers,
+    /// Main configuration, or a file it includes.
+    Pipeline,
+}
+
+impl FileRole {
+    /// Role of the document: a parsers file if its name contains `parsers`,
+    /// e.g. `parsers_multiline.conf`, or if it matches one of the
+    /// `parsersFiles` patterns.
+    pub(crate) fn of(uri: &Url, patterns: &[String]) -> Self {
+        let path = percent_decode_str(uri.path()).decode_utf8_lossy();
+        let name = path.rsplit('/').next().unwrap_or_default();
+        match name.to_lowercase().contains("parsers") || matches_any(uri, patterns) {
+            true => FileRole::Parsers,
+            false => FileRole::Pipeline,
+        }
+    }
+}
+
+fn is_parser(section: &IndexedSection) -> bool {
+    matches!(
+        section.section_type,
+        FlbSectionType::Parser | FlbSectionType::MultilineParser
+    )
+}
+
+pub(crate) fn check_section_roles(
+    role: FileRole,
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    let sets_parsers_file = || {
+        index
+            .sections()
+            .iter()
+            .filter(|section| section.section_type == FlbSectionType::Service)
+            .any(|section| section.contains_key("Parsers_File"))
+    };
+    let misplaced: Vec<&IndexedSection> = match role {
+        FileRole::Parsers => index
+            .sections()
+            .iter()
+            .filter(|section| !is_parser(section))
+            .collect(),
+        FileRole::Pipeline if sets_parsers_file() => index
+            .sections()
+            .iter()
+            .filter(|section| is_parser(section))
+            .collect(),
+        FileRole::Pipeline => Vec::new(),
+    };
+
+    misplaced
+        .into_iter()
+        .filter_map(|section| {
+            let message = match role {
+                FileRole::Parsers => t!(MISPLACED_SECTION, name = section.name),
+                FileRole::Pipeline => t!("misplaced-parser", name = section.name),
+            };
+            Some(Diagnostic {
+                range: byte_range_to_lsp_range(rope, name_range(rope, section)?, position_encoding),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(MISPLACED_SECTION.to_string())),
+                message,
+                ..Diagnostic::default()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::TextDocument;
+
+    #[test]
+    fn sections_the_role_of_the_file_does_not_allow() {
+        let uri = |path: &str| Url::from_file_path(path).unwrap();
+        assert_eq!(
+            FileRole::of(&uri("/etc/fluent-bit/parsers_java.conf"), &[]),
+            FileRole::Parsers
+        );
+        assert_eq!(
+            FileRole::of(&uri("/etc/fluent-bit/fluent-bit.conf"), &[]),
+            FileRole::Pipeline
+        );
+        let patterns = ["formats/*.conf".to_string()];
+        assert_eq!(
+            FileRole::of(&uri("/etc/fluent-bit/formats/apache.conf"), &patterns),
+            FileRole::Parsers
+        );
+
+        let encoding = PositionEncodingKind::UTF16;
+        let document = TextDocument::new(
+            "[PARSER]\n    Name   json\n    Format json\n\n\
+             [MULTILINE_PARSER]\n    name java\n\n[INPUT]\n    Name cpu\n",
+        );
+        let diagnostics =
+            check_section_roles(FileRole::Parsers, &document.index, &document.rope, encoding);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "fluent-bit ignores `[INPUT]` in parsers files, which only define `[PARSER]` and `[MULTILINE_PARSER]` sections."
+        ]);
+        assert_eq!(diagnostics[0].range.start.line, 7);
+        // without `Parsers_File`, the configuration may define parsers
+        assert!(check_section_roles(
+            FileRole::Pipeline,
+            &document.index,
+            &document.rope,
+            encoding
+        )
+        .is_empty());
+
+        let document = TextDocument::new(
+            "[SERVICE]\n    Parsers_File parsers.conf\n\n[PARSER]\n    Name   json\n    Format json\n",
+        );
+        let diagnostics = check_section_roles(
+            FileRole::Pipeline,
+            &document.index,
+            &document.rope,
+            encoding,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "`[PARSER]` can't be defined here as the configuration sets `Parsers_File`: move it to one of its parsers files."
+        ]);
+    }
+}
@@ -0,0 +1,333 @@
+//! Fixes of diagnostics.
+//!
+//! Fixes are computed together with the diagnostics and attached to their
+//! `data` as [`DiagnosticData`], so that code actions are built without
+//! analyzing the document again, and tools consuming the diagnostics can apply
+//! them too.
+
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, Diagnostic, NumberOrString, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{
+    completion::FlbData,
+    document::TextDocument,
+    lints::{self, misplaced_comment_fix, TRAILING_WHITESPACE, UTF8_BOM},
+    lsp_ext::{ConfigFormat, DiagnosticData, DiagnosticFix},
+    migration::outdated_key_fix,
+    plugins::{missing_name_fix, unknown_key_fix, unknown_plugin_fix},
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    rules::missing_required_key_fix,
+    section_index::{IndexedEntry, IndexedSection},
+    section_names::unknown_section_fix,
+    service::move_to_service_fix,
+    workspace_edit::WorkspaceEditBuilder,
+};
+
+/// Rules whose fixes can't overlap, and are applied by fix-all.
+const FIX_ALL_RULES: &[&str] = &[UTF8_BOM, TRAILING_WHITESPACE];
+
+fn rule(diagnostic: &Diagnostic) -> Option<&str> {
+    match &diagnostic.code {
+        Some(NumberOrString::String(code)) => Some(code),
+        _ => None,
+    }
+}
+
+fn compute_fix(
+    data: &FlbData,
+    document: &TextDocument,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    lints::fix(diagnostic)
+        .or_else(|| misplaced_comment_fix(document, diagnostic, position_encoding))
+        .or_else(|| move_to_service_fix(document, diagnostic, position_encoding))
+        .or_else(|| unknown_plugin_fix(data, document, diagnostic, position_encoding))
+        .or_else(|| unknown_key_fix(data, document, diagnostic, position_encoding))
+        .or_else(|| missing_name_fix(document, diagnostic, position_encoding))
+        .or_else(|| missing_required_key_fix(document, diagnostic, position_encoding))
+        .or_else(|| unknown_section_fix(document, diagnostic, position_encoding))
+        .or_else(|| outdated_key_fix(document, diagnostic, position_encoding))
+}
+
+/// Inserts entries of the keys without values, each on its own line and
+/// indented like the other entries, after the last entry of the section, or
+/// right after the header of classic sections when `first`.
+pub(crate) fn insert_keys(
+    document: &TextDocument,
+    section: &IndexedSection,
+    keys: &[&str],
+    first: bool,
+    position_encoding: PositionEncodingKind,
+) -> TextEdit {
+    let TextDocument { rope, format, .. } = document;
+    let first = first && *format == ConfigFormat::Classic;
+
+    let entry_end = |entry: &IndexedEntry| {
+        entry
+            .value_range
+            .as_ref()
+            .map_or(entry.key_range.end, |range| range.end)
+    };
+    let (after, indented) = match first {
+        true => (section.byte_range.start, section.entries.first()),
+        false => match section.entries.last() {
+            Some(entry) => (entry_end(entry), Some(entry)),
+            None => (section.byte_range.start, None),
+        },
+    };
+    let indent = match indented {
+        Some(entry) => {
+            let line_start = rope.line_to_byte(rope.byte_to_line(entry.key_range.start));
+            " ".repeat(
+                rope.byte_slice(line_start..entry.key_range.start)
+                    .chars()
+                    .count(),
+            )
+        }
+        None => "    ".to_string(),
+    };
+
+    // at the end of the line, so that a file without a final newline works too
+    let line = rope.byte_to_line(after);
+    let line_end = rope.line_to_byte(line)
+        + rope
+            .line(line)
+            .to_string()
+            .trim_end_matches(['\n', '\r'])
+            .len();
+    let new_text = keys
+        .iter()
+        .map(|key| match format {
+            ConfigFormat::Classic => format!("\n{indent}{key}"),
+            ConfigFormat::Yaml => format!("\n{indent}{}:", key.to_lowercase()),
+        })
+        .collect();
+
+    TextEdit {
+        range: byte_range_to_lsp_range(rope, line_end..line_end, position_encoding),
+        new_text,
+    }
+}
+
+/// Sets the `data` of the diagnostics of the document.
+pub(crate) fn attach_data(
+    data: &FlbData,
+    document: &TextDocument,
+    diagnostics: &mut [Diagnostic],
+    position_encoding: PositionEncodingKind,
+) {
+    for diagnostic in diagnostics {
+        let Some(rule) = rule(diagnostic) else {
+            continue;
+        };
+        let diagnostic_data = DiagnosticData {
+            rule: rule.to_string(),
+            fix: compute_fix(data, document, diagnostic, position_encoding),
+        };
+        diagnostic.data = serde_json::to_value(diagnostic_data).ok();
+    }
+}
+
+/// Sets the `data` of diagnostics computed without the document, which have no
+/// fix.
+pub(crate) fn attach_rules(diagnostics: &mut [Diagnostic]) {
+    for diagnostic in diagnostics {
+        let Some(rule) = rule(diagnostic) else {
+            continue;
+        };
+        let data = DiagnosticData {
+            rule: rule.to_string(),
+            fix: None,
+        };
+        diagnostic.data = serde_json::to_value(data).ok();
+    }
+}
+
+fn attached_data(diagnostic: &Diagnostic) -> Option<DiagnosticData> {
+    serde_json::from_value(diagnostic.data.clone()?).ok()
+}
+
+/// Fix attached to the diagnostic, or computed if the client didn't send its
+/// `data` back.
+fn get_fix(
+    data: &FlbData,
+    document: Option<&TextDocument>,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    match attached_data(diagnostic) {
+        Some(attached) => attached.fix,
+        None => compute_fix(data, document?, diagnostic, position_encoding),
+    }
+}
+
+pub(crate) fn quick_fix(
+    data: &FlbData,
+    uri: &Url,
+    document: Option<&TextDocument>,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<CodeAction> {
+    let fix = get_fix(data, document, diagnostic, position_encoding)?;
+    let mut edit = WorkspaceEditBuilder::new(false);
+    for text_edit in fix.edits {
+        edit.edit(uri, text_edit);
+    }
+
+    Some(CodeAction {
+        title: fix.title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(edit.build()),
+        is_preferred: Some(true),
+        ..CodeAction::default()
+    })
+}
+
+/// Edit applying the attached fixes of the document's diagnostics which can't
+/// overlap, annotated for review. `None` if there's nothing to fix.
+pub(crate) fn fix_all(
+    uri: &Url,
+    diagnostics: &[Diagnostic],
+    change_annotations: bool,
+) -> Option<WorkspaceEdit> {
+    let fixes: Vec<_> = diagnostics
+        .iter()
+        .filter(|diagnostic| rule(diagnostic).is_some_and(|rule| FIX_ALL_RULES.contains(&rule)))
+        .filter_map(|diagnostic| attached_data(diagnostic)?.fix)
+        .collect();
+    if fixes.is_empty() {
+        return None;
+    }
+
+    let mut edit = WorkspaceEditBuilder::new(change_annotations);
+    let annotation = edit.annotation(
+        "Fix all",
+        Some(format!("{} whitespace and encoding fixes", fixes.len())),
+        true,
+    );
+    for text_edit in fixes.into_iter().flat_map(|fix| fix.edits) {
+        edit.annotated_edit(uri, text_edit, &annotation);
+    }
+
+    Some(edit.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+    use crate::{
+        completion::FLB_DATA, lints::MISPLACED_COMMENT, plugins::check_plugin_names,
+        positions::resolve_position, rules::check_conditional_requirements,
+    };
+
+    #[test]
+    fn fixes_are_attached_to_diagnostics() {
+        let document = TextDocument::new("\u{feff}[INPUT]\n    Name  cpu  \n    Flush 5\n");
+        let encoding = PositionEncodingKind::UTF16;
+        let mut diagnostics = [
+            lints::check_bom(&document.rope, encoding)
+                .into_iter()
+                .collect(),
+            lints::check_trailing_whitespace(&document.index, &document.rope, encoding),
+            crate::service::check_misplaced_service_keys(
+                &FLB_DATA,
+                &document.index,
+                &document.rope,
+                encoding,
+            ),
+        ]
+        .concat();
+        attach_data(&FLB_DATA, &document, &mut diagnostics, encoding);
+
+        let data: Vec<DiagnosticData> = diagnostics
+            .iter()
+            .map(|diagnostic| serde_json::from_value(diagnostic.data.clone().unwrap()).unwrap())
+            .collect();
+        let titles: Vec<_> = data
+            .iter()
+            .map(|data| {
+                (
+                    data.rule.as_str(),
+                    data.fix.as_ref().unwrap().title.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(titles, [
+            (UTF8_BOM, "Remove byte order mark"),
+            (TRAILING_WHITESPACE, "Remove trailing whitespace"),
+            ("misplaced-service-key", "Move `Flush` to [SERVICE]"),
+        ]);
+
+        // without the document, the attached fix is used
+        let uri: Url = "file:///fluent-bit.conf".parse().unwrap();
+        let action = quick_fix(&FLB_DATA, &uri, None, &diagnostics[2], encoding).unwrap();
+        assert_eq!(
+            action.edit.unwrap().changes.unwrap()[&uri],
+            data[2].fix.as_ref().unwrap().edits
+        );
+
+        let edit = fix_all(&uri, &diagnostics, false).unwrap();
+        assert_eq!(edit.changes.unwrap()[&uri].len(), 2);
+    }
+
+    #[test]
+    fn fixes_inserting_and_removing_entries() {
+        let encoding = PositionEncodingKind::UTF16;
+        let fixed = |text: &str, diagnostic: &Diagnostic| {
+            let document = TextDocument::new(text);
+            let fix = compute_fix(&FLB_DATA, &document, diagnostic, encoding).unwrap();
+            let mut rope = document.rope.clone();
+            for edit in fix.edits.iter().rev() {
+                let start = resolve_position(&rope, &edit.range.start, encoding).unwrap();
+                let end = resolve_position(&rope, &edit.range.end, encoding).unwrap();
+                rope.remove(start.char_idx..end.char_idx);
+                rope.insert(start.char_idx, &edit.new_text);
+            }
+            (fix.title, rope.to_string())
+        };
+
+        let text = "[INPUT]\n    Tag cpu\n\n[PARSER]\n    Name   apache\n    Format regex";
+        let document = TextDocument::new(text);
+        let missing_name = check_plugin_names(&FLB_DATA, &document.index, &document.rope, encoding);
+        assert_eq!(
+            missing_name[0].message,
+            "[INPUT] sections need a `Name`, the plugin they use."
+        );
+        assert_eq!(
+            fixed(text, &missing_name[0]),
+            (
+                "Insert `Name`".to_string(),
+                "[INPUT]\n    Name\n    Tag cpu\n\n[PARSER]\n    Name   apache\n    Format regex"
+                    .to_string()
+            )
+        );
+        let missing_regex =
+            check_conditional_requirements(&document.index, &document.rope, encoding);
+        assert_eq!(
+            fixed(text, &missing_regex[0]),
+            (
+                "Insert `Regex`".to_string(),
+                "[INPUT]\n    Tag cpu\n\n[PARSER]\n    Name   apache\n    Format regex\n    Regex"
+                    .to_string()
+            )
+        );
+
+        let comment = Diagnostic {
+            range: Range::new(Position::new(0, 8), Position::new(0, 14)),
+            code: Some(NumberOrString::String(MISPLACED_COMMENT.to_string())),
+            ..Diagnostic::default()
+        };
+        assert_eq!(
+            fixed("[INPUT] # tail\n    Name tail\n", &comment),
+            (
+                "Remove comment".to_string(),
+                "[INPUT]\n    Name tail\n".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Folding ranges of a document (`textDocument/foldingRange`): its sections,
+//! and the blocks of consecutive comment lines or `@INCLUDE` directives.
+//!
+//! Ranges are whole lines, as most clients only fold lines.
+
+use std::ops::Range;
+
+use ropey::Rope;
+use tower_lsp::lsp_types::{FoldingRange, FoldingRangeKind};
+use tree_sitter::Tree;
+
+use crate::{document::TextDocument, tree_walk::descendants};
+
+fn folding_range(
+    start_line: usize,
+    end_line: usize,
+    kind: Option<FoldingRangeKind>,
+) -> FoldingRange {
+    FoldingRange {
+        start_line: start_line as u32,
+        end_line: end_line as u32,
+        kind,
+        ..FoldingRange::default()
+    }
+}
+
+/// Lines of the range, without the blank lines it ends with.
+fn lines(rope: &Rope, range: Range<usize>) -> Option<(usize, usize)> {
+    let text = rope.get_byte_slice(range.clone())?.to_string();
+    let end = range.start + text.trim_end().len();
+
+    Some((rope.byte_to_line(range.start), rope.byte_to_line(end)))
+}
+
+/// Folds runs of consecutive lines, e.g. lines starting with a comment.
+fn fold_runs(
+    lines: impl IntoIterator<Item = usize>,
+    kind: FoldingRangeKind,
+) -> impl Iterator<Item = FoldingRange> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for line in lines {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            Some((_, end)) if *end == line => {}
+            _ => runs.push((line, line)),
+        }
+    }
+
+    runs.into_iter()
+        .filter(|(start, end)| start < end)
+        .map(move |(start, end)| folding_range(start, end, Some(kind.clone())))
+}
+
+/// Lines starting with a comment, in the order of the document.
+fn comment_lines(tree: &Tree, rope: &Rope) -> Vec<usize> {
+    descendants(tree.root_node())
+        .filter(|node| node.kind() == "comment")
+        .map(|node| node.start_position())
+        .filter(|point| {
+            rope.get_line(point.row)
+                .and_then(|line| line.get_byte_slice(..point.column))
+                .is_some_and(|before| before.chars().all(char::is_whitespace))
+        })
+        .map(|point| point.row)
+        .collect()
+}
+
+pub(crate) fn folding_ranges(document: &TextDocument) -> Vec<FoldingRange> {
+    let TextDocument {
+        rope, tree, index, ..
+    } = document;
+
+    let mut ranges: Vec<FoldingRange> = index
+        .sections()
+        .iter()
+        .filter_map(|section| lines(rope, section.byte_range.clone()))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| folding_range(start, end, None))
+        .collect();
+    if let Some(tree) = tree {
+        ranges.extend(fold_runs(
+            comment_lines(tree, rope),
+            FoldingRangeKind::Comment,
+        ));
+    }
+    ranges.extend(fold_runs(
+        index
+            .includes()
+            .iter()
+            .map(|include| rope.byte_to_line(include.range.start)),
+        FoldingRangeKind::Imports,
+    ));
+
+    ranges.sort_by_key(|range| (range.start_line, range.end_line));
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp_ext::ConfigFormat;
+
+    #[test]
+    fn sections_comments_and_includes() {
+        let document = TextDocument::new(
+            "@INCLUDE inputs.conf\n@INCLUDE outputs.conf\n\n\
+             # Drop the records\n# of the health checks\n[FILTER]\n    Name    grep\n    # status\n    Exclude status 200\n\n\n\
+             [OUTPUT]\n    Name stdout\n",
+        );
+        let ranges: Vec<_> = folding_ranges(&document)
+            .into_iter()
+            .map(|range| (range.start_line, range.end_line, range.kind))
+            .collect();
+        assert_eq!(ranges, [
+            (0, 1, Some(FoldingRangeKind::Imports)),
+            (3, 4, Some(FoldingRangeKind::Comment)),
+            (5, 8, None),
+            (11, 12, None),
+        ]);
+
+        let yaml = TextDocument::with_format(
+            "pipeline:\n  inputs:\n    # cpu usage\n    # every second\n    - name: cpu\n      tag: cpu\n",
+            ConfigFormat::Yaml,
+        );
+        let ranges: Vec<_> = folding_ranges(&yaml)
+            .into_iter()
+            .map(|range| (range.start_line, range.end_line, range.kind))
+            .collect();
+        assert_eq!(ranges, [
+            (2, 3, Some(FoldingRangeKind::Comment)),
+            (4, 5, None),
+        ]);
+    }
+}
@@ -0,0 +1,241 @@
+//! Document formatting.
+//!
+//! For now, formatting only normalizes line endings: to the configured one, or
+//! to the one used by most lines of the document so that mixed endings are
+//! fixed without converting the whole file. A range of lines can be formatted
+//! alone, and the value of an entry is aligned with the others of its section
+//! as a space is typed after its key.
+
+use std::{collections::HashMap, ops::Range};
+
+use ropey::Rope;
+use tower_lsp::lsp_types::TextEdit;
+
+use crate::{
+    document::{line_endings, LineEnding},
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    section_index::SectionIndex,
+};
+
+pub(crate) fn format_document(
+    rope: &Rope,
+    line_ending: Option<LineEnding>,
+    position_encoding: PositionEncodingKind,
+) -> Vec<TextEdit> {
+    format_range(rope, 0..rope.len_bytes(), line_ending, position_encoding)
+}
+
+/// Formats the lines of `range`, a byte range of the document. The line ending
+/// used by most lines is still the one of the whole document.
+pub(crate) fn format_range(
+    rope: &Rope,
+    range: Range<usize>,
+    line_ending: Option<LineEnding>,
+    position_encoding: PositionEncodingKind,
+) -> Vec<TextEdit> {
+    let target = line_ending.unwrap_or_else(|| LineEnding::detect(rope));
+    let first_line = rope.byte_to_line(range.start);
+    let last_line = match rope.byte_to_line(range.end) {
+        // a range ending at the start of a line, e.g. selected lines, doesn't
+        // contain that line
+        line if range.end > range.start && rope.line_to_byte(line) == range.end => line - 1,
+        line => line,
+    };
+
+    line_endings(rope)
+        .filter(|(range, line_ending)| {
+            let line = rope.byte_to_line(range.start);
+            *line_ending != target && first_line <= line && line <= last_line
+        })
+        .map(|(range, _)| {
+            TextEdit::new(
+                byte_range_to_lsp_range(rope, range, position_encoding),
+                target.as_str().to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Aligns the value of the entry whose key is followed by the space typed at
+/// `byte` with the values of the other entries of its section, e.g. `Tag |`
+/// becomes `Tag    |` below `Match  *`: at the column most of them start at,
+/// unless the key is longer.
+pub(crate) fn align_entry(
+    index: &SectionIndex,
+    rope: &Rope,
+    byte: usize,
+    position_encoding: PositionEncodingKind,
+) -> Option<TextEdit> {
+    let section = index.section_at(byte)?;
+    let line = rope.byte_to_line(byte);
+    let (i, entry) = section
+        .entries
+        .iter()
+        .enumerate()
+        .find(|(_, entry)| rope.byte_to_line(entry.key_range.start) == line)?;
+
+    let gap_start = entry.key_range.end;
+    let gap_end = match &entry.value_range {
+        Some(range) => range.start,
+        None => {
+            gap_start
+                + rope
+                    .bytes_at(gap_start)
+                    .take_while(|byte| matches!(byte, b' ' | b'\t'))
+                    .count()
+        }
+    };
+    // the space isn't typed between the key and the value
+    if !(gap_start < byte && byte <= gap_end) {
+        return None;
+    }
+
+    let column = |byte: usize| rope.byte_to_char(byte) - rope.line_to_char(rope.byte_to_line(byte));
+    let key_end = column(gap_start);
+    let mut columns: HashMap<usize, usize> = HashMap::new();
+    for (_, other) in section.entries.iter().enumerate().filter(|(j, _)| *j != i) {
+        let Some(range) = &other.value_range else {
+            continue;
+        };
+        let value_column = column(range.start);
+        if value_column > key_end {
+            *columns.entry(value_column).or_default() += 1;
+        }
+    }
+    let (value_column, _) = columns
+        .into_iter()
+        .max_by_key(|(column, count)| (*count, *column))?;
+
+    let spaces = " ".repeat(value_column - key_end);
+    if rope.byte_slice(gap_start..gap_end) == spaces.as_str() {
+        return None;
+    }
+    Some(TextEdit::new(
+        byte_range_to_lsp_range(rope, gap_start..gap_end, position_encoding),
+        spaces,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range, TextDocumentContentChangeEvent};
+
+    use super::*;
+    use crate::document::TextDocument;
+
+    /// Applies the edits the way a client would, last one first.
+    fn apply_edits(document: &mut TextDocument, mut edits: Vec<TextEdit>) {
+        edits.sort_by_key(|edit| edit.range.start);
+        for edit in edits.into_iter().rev() {
+            document
+                .apply_content_change(
+                    &TextDocumentContentChangeEvent {
+                        range: Some(edit.range),
+                        range_length: None,
+                        text: edit.new_text,
+                    },
+                    PositionEncodingKind::UTF16,
+                )
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn line_endings_of_crlf_documents() {
+        let mut document = TextDocument::new(
+            "[INPUT]\r\n    Name cpu\r\n    Tag  cpu.local\n\r\n[OUTPUT]\r\n    Name stdout\r\n",
+        );
+        assert_eq!(document.line_ending(), LineEnding::Crlf);
+
+        // editing keeps the CRLF endings and values don't contain `\r`
+        apply_edits(&mut document, vec![TextEdit::new(
+            Range::new(Position::new(1, 12), Position::new(1, 12)),
+            "\r\n    Interval_Sec 1".to_string(),
+        )]);
+        assert_eq!(
+            document.rope.to_string(),
+            "[INPUT]\r\n    Name cpu\r\n    Interval_Sec 1\r\n    Tag  cpu.local\n\r\n[OUTPUT]\r\n    Name stdout\r\n"
+        );
+        let values: Vec<_> = document.index.sections()[0]
+            .entries
+            .iter()
+            .map(|entry| entry.value.as_deref().unwrap())
+            .collect();
+        assert_eq!(values, ["cpu", "1", "cpu.local"]);
+        assert!(!document.tree.as_ref().unwrap().root_node().has_error());
+
+        // the stray LF is fixed
+        let edits = format_document(&document.rope, None, PositionEncodingKind::UTF16);
+        assert_eq!(edits, [TextEdit::new(
+            Range::new(Position::new(3, 18), Position::new(4, 0)),
+            "\r\n".to_string()
+        )]);
+        apply_edits(&mut document, edits);
+        assert_eq!(
+            document.rope.to_string(),
+            "[INPUT]\r\n    Name cpu\r\n    Interval_Sec 1\r\n    Tag  cpu.local\r\n\r\n[OUTPUT]\r\n    Name stdout\r\n"
+        );
+        assert!(format_document(&document.rope, None, PositionEncodingKind::UTF16).is_empty());
+
+        // until LF is configured
+        let edits = format_document(
+            &document.rope,
+            Some(LineEnding::Lf),
+            PositionEncodingKind::UTF16,
+        );
+        assert_eq!(edits.len(), 7);
+        apply_edits(&mut document, edits);
+        assert_eq!(
+            document.rope.to_string(),
+            "[INPUT]\n    Name cpu\n    Interval_Sec 1\n    Tag  cpu.local\n\n[OUTPUT]\n    Name stdout\n"
+        );
+        assert!(!document.tree.as_ref().unwrap().root_node().has_error());
+    }
+
+    #[test]
+    fn ranges_and_typed_spaces() {
+        let rope = Rope::from_str("[INPUT]\r\n    Name cpu\n    Tag  cpu.local\n\r\n[OUTPUT]\r\n");
+        // the second line, selected up to the start of the third
+        let edits = format_range(&rope, 9..22, None, PositionEncodingKind::UTF16);
+        assert_eq!(edits, [TextEdit::new(
+            Range::new(Position::new(1, 12), Position::new(2, 0)),
+            "\r\n".to_string()
+        )]);
+
+        let mut document = TextDocument::new(
+            "[OUTPUT]\n    Name   stdout\n    Match  *\n    Tag \n    json_date_key date\n",
+        );
+        // `Tag ` is typed on the fourth line
+        let byte = document.rope.line_to_byte(3) + 8;
+        let edit = align_entry(
+            &document.index,
+            &document.rope,
+            byte,
+            PositionEncodingKind::UTF16,
+        )
+        .unwrap();
+        assert_eq!(
+            edit.range,
+            Range::new(Position::new(3, 7), Position::new(3, 8))
+        );
+        apply_edits(&mut document, vec![edit]);
+        assert_eq!(document.rope.line(3).to_string(), "    Tag    \n",);
+        // longer keys keep a single space, and spaces typed in values aren't aligned
+        let byte = document.rope.line_to_byte(4) + 18;
+        assert!(align_entry(
+            &document.index,
+            &document.rope,
+            byte,
+            PositionEncodingKind::UTF16
+        )
+        .is_none());
+        let byte = document.rope.line_to_byte(1) + 16;
+        assert!(align_entry(
+            &document.index,
+            &document.rope,
+            byte,
+            PositionEncodingKind::UTF16
+        )
+        .is_none());
+    }
+}
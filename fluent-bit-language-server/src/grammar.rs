@@ -0,0 +1,73 @@
+//! The tree-sitter-fluentbit grammar classic documents are parsed with, and
+//! whether it has the node kinds and fields the server walks them with: a
+//! grammar which drifted apart from the server would silently break features.
+
+use tree_sitter::Language;
+
+use crate::lsp_ext::GrammarInfoResult;
+
+/// Version of tree-sitter-fluentbit the server is built with.
+pub(crate) const GRAMMAR_VERSION: &str = env!("TREE_SITTER_FLUENTBIT_VERSION");
+
+/// Named node kinds the server looks for in classic documents.
+const NODE_KINDS: &[&str] = &[
+    "config",
+    "section",
+    "section_header",
+    "section_header_type",
+    "section_body",
+    "entry",
+    "key_type",
+    "value_type",
+    "directive",
+    "directive_include",
+    "directive_set",
+    "comment",
+];
+
+/// Fields of the nodes the server looks for in classic documents.
+const FIELDS: &[&str] = &["header", "name", "body", "key", "value", "pattern"];
+
+/// Node kinds and fields the language lacks, fields written as `field:name`.
+fn missing(language: &Language, node_kinds: &[&str], fields: &[&str]) -> Vec<String> {
+    let node_kinds = node_kinds
+        .iter()
+        .filter(|kind| language.id_for_node_kind(kind, true) == 0)
+        .map(ToString::to_string);
+    let fields = fields
+        .iter()
+        .filter(|field| language.field_id_for_name(field).is_none())
+        .map(|field| format!("field:{field}"));
+
+    node_kinds.chain(fields).collect()
+}
+
+pub(crate) fn grammar_info() -> GrammarInfoResult {
+    let language: Language = tree_sitter_fluentbit::LANGUAGE.into();
+
+    GrammarInfoResult {
+        version: GRAMMAR_VERSION.to_string(),
+        abi_version: language.version(),
+        node_kinds: NODE_KINDS.iter().map(ToString::to_string).collect(),
+        fields: FIELDS.iter().map(ToString::to_string).collect(),
+        missing: missing(&language, NODE_KINDS, FIELDS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiled_grammar_has_the_expected_nodes() {
+        let info = grammar_info();
+        assert_eq!(info.missing, Vec::<String>::new());
+        assert_ne!(info.version, "unknown");
+
+        let language: Language = tree_sitter_fluentbit::LANGUAGE.into();
+        assert_eq!(
+            missing(&language, &["section", "list_item"], &["key", "items"]),
+            ["list_item", "field:items"]
+        );
+    }
+}
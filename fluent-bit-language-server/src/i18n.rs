@@ -0,0 +1,195 @@
+//! Translation of the strings the server writes itself: diagnostic messages,
+//! quick fix titles, hover notes and the docs of `[SERVICE]` keys. Plugin docs
+//! come from fluent-bit and aren't translated.
+//!
+//! Messages are looked up by id in the catalog of the locale, a JSON object in
+//! `src/assets/locales/<locale>.json` which is embedded at build time, so a
+//! translation can be contributed as a single file. Ids missing from it fall
+//! back to the English catalog, `en.json`. Arguments replace the `{name}`
+//! placeholders of the messages.
+//!
+//! The docs of `[SERVICE]` keys are written in English in the code, and
+//! translated by the ids `service.<key>`, e.g. `service.flush`.
+//!
+//! The locale is the `locale` setting, or the client's one, and English if the
+//! server has no catalog for it.
+
+use std::{collections::HashMap, fmt::Display, sync::RwLock};
+
+use once_cell::sync::Lazy;
+
+include!(concat!(env!("OUT_DIR"), "/locales.rs"));
+
+const DEFAULT_LOCALE: &str = "en";
+
+type Catalog = HashMap<String, String>;
+
+static CATALOGS: Lazy<HashMap<&'static str, Catalog>> = Lazy::new(|| {
+    LOCALES
+        .iter()
+        .map(|(locale, json)| {
+            let catalog = serde_json::from_str(json)
+                .unwrap_or_else(|e| panic!("invalid message catalog `{locale}`: {e}"));
+            (*locale, catalog)
+        })
+        .collect()
+});
+
+static LOCALE: RwLock<Option<&'static str>> = RwLock::new(None);
+
+/// Catalog of `locale`, e.g. `pt-BR`, or of its language, e.g. `pt`.
+fn catalog(locale: &str) -> Option<(&'static str, &'static Catalog)> {
+    let locale = locale.to_lowercase().replace('_', "-");
+    let language = locale.split('-').next().unwrap_or_default();
+    let found = [locale.as_str(), language]
+        .into_iter()
+        .find_map(|locale| CATALOGS.get_key_value(locale))
+        .map(|(locale, catalog)| (*locale, catalog));
+    found
+}
+
+/// Sets the locale of the messages, falling back to English without a
+/// catalog for it.
+pub(crate) fn set_locale(locale: &str) {
+    let locale = catalog(locale).map(|(locale, _)| locale);
+    *LOCALE.write().unwrap() = locale;
+}
+
+fn locale() -> &'static str {
+    LOCALE.read().unwrap().unwrap_or(DEFAULT_LOCALE)
+}
+
+/// Replaces the `{name}` placeholders of `template` with the arguments of
+/// the same name, leaving the other braces untouched.
+fn substitute(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut ret = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        ret.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let arg = rest.find('}').and_then(|end| {
+            let (_, value) = args.iter().find(|(name, _)| *name == &rest[1..end])?;
+            Some((end, value))
+        });
+        match arg {
+            Some((end, value)) => {
+                ret.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                ret.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    ret.push_str(rest);
+
+    ret
+}
+
+fn translate_in(locale: &str, id: &str, args: &[(&str, &dyn Display)]) -> String {
+    let template = [locale, DEFAULT_LOCALE]
+        .into_iter()
+        .find_map(|locale| catalog(locale)?.1.get(id))
+        .map_or(id, String::as_str);
+    substitute(template, args)
+}
+
+/// Message `id` in the current locale. Prefer [`t!`].
+pub(crate) fn translate(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    translate_in(locale(), id, args)
+}
+
+/// Translation of a text written in English in the code, e.g. the docs of a
+/// `[SERVICE]` key, if the catalog of the current locale has one.
+pub(crate) fn translate_text(id: &str, english: &str) -> String {
+    catalog(locale())
+        .and_then(|(_, catalog)| catalog.get(id))
+        .map_or_else(|| english.to_string(), String::clone)
+}
+
+/// Translates a message of the catalogs, e.g.
+/// `t!("duplicate-key", key = entry.key)`.
+macro_rules! t {
+    ($id:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::translate(
+            $id,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+}
+
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::is_service_key;
+
+    /// Names of the `{name}` placeholders of a message.
+    fn placeholders(message: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = message
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn catalogs_translate_the_english_messages() {
+        let english = &CATALOGS[DEFAULT_LOCALE];
+        for (locale, catalog) in CATALOGS.iter() {
+            for (id, message) in catalog {
+                if let Some(key) = id.strip_prefix("service.") {
+                    assert!(
+                        is_service_key(key),
+                        "{locale}: `{key}` isn't a [SERVICE] key"
+                    );
+                    continue;
+                }
+                let source = english
+                    .get(id)
+                    .unwrap_or_else(|| panic!("`{id}` of `{locale}` isn't an English message"));
+                assert_eq!(
+                    placeholders(message),
+                    placeholders(source),
+                    "{locale}: {id}"
+                );
+            }
+        }
+
+        assert_eq!(
+            translate_in("en-US", "duplicate-key", &[("key", &"Match")]),
+            "`Match` is already set in this section."
+        );
+        // unknown locales and ids fall back to English and the id
+        assert_eq!(
+            translate_in("xx", "missing-file", &[("path", &"a.conf")]),
+            "File `a.conf` doesn't exist."
+        );
+        assert_eq!(
+            translate_in("en", "no-such-message", &[]),
+            "no-such-message"
+        );
+    }
+
+    #[test]
+    fn substitute_placeholders() {
+        assert_eq!(
+            substitute("`{key} {value}` requires {missing}.", &[
+                ("key", &"Format"),
+                ("value", &"json"),
+                ("missing", &"`Json_Date_Key`"),
+            ]),
+            "`Format json` requires `Json_Date_Key`."
+        );
+        // braces of the arguments and unknown names are kept
+        assert_eq!(
+            substitute("{value} {other} {", &[("value", &"${HOME}")]),
+            "${HOME} {other} {"
+        );
+    }
+}
@@ -0,0 +1,1001 @@
+//! Graph of the files the open documents include, with `@INCLUDE` (`includes`
+//! in YAML) or as their parsers files, for workspace diagnostics.
+//!
+//! Included files which aren't open are read and parsed, through the
+//! [`DiskCache`], so that they're diagnosed too, while the open ones are
+//! analyzed as edited, unsaved changes included. Problems spanning files,
+//! e.g. a parser defined in two of them, a `Match` no input of the
+//! configuration produces records for, a variable no file sets, a variable of
+//! a YAML `env` nothing references or a `[SERVICE]` key an included file sets
+//! again, are reported. Each open document, and each closed one, is the root
+//! of its own graph, so unrelated configurations of the workspace don't
+//! conflict.
+//!
+//! Files larger than [`MAX_FILE_SIZE`], e.g. generated parsers files, are read
+//! line by line in a blocking task and only their section headers are indexed,
+//! to keep memory bounded. An information diagnostic tells they weren't
+//! analyzed.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use flb_schema::section::FlbSectionType;
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, Location,
+    NumberOrString, Position, Range, Url,
+};
+
+use crate::{
+    disk_cache::{DiskCache, DiskDocument},
+    document::TextDocument,
+    document_map::normalize_uri,
+    effective,
+    encoding::decode,
+    i18n::t,
+    lsp_ext::ConfigFormat,
+    overrides::{
+        self, config_items, location_label, overrides, ConfigItem, ServiceKey,
+        OVERRIDDEN_SERVICE_KEY,
+    },
+    parser_index::{parser_symbols, ParserSymbolKind},
+    path_checks::{document_dir, included_files},
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    routing::RoutingModel,
+    scope::is_excluded,
+    variables::{self, UNDEFINED_VARIABLE},
+    yaml_env::{self, EnvVariable, UNUSED_VARIABLE},
+};
+
+/// Diagnostic code of a parser defined more than once.
+pub(crate) const DUPLICATE_PARSER: &str = "duplicate-parser";
+/// Diagnostic code of a `Match` or `Match_Regex` no input's tag matches.
+pub(crate) const UNMATCHED_RULE: &str = "unmatched-rule";
+/// Diagnostic code of an input whose records no output matches.
+pub(crate) const UNROUTED_INPUT: &str = "unrouted-input";
+/// Diagnostic code of an included file too large to be analyzed.
+pub(crate) const LARGE_FILE: &str = "large-file";
+
+/// Files read beyond the open documents, not to load a whole filesystem from
+/// `@INCLUDE /**/*.conf`.
+const MAX_LOADED_FILES: usize = 256;
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+/// Larger files, up to this size, are indexed for their section headers only.
+const MAX_INDEXED_FILE_SIZE: u64 = 64 * 1024 * 1024;
+const INDEX_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the graph needs of a document, collected while it's locked.
+pub(crate) struct DocumentLinks {
+    base_dir: Option<String>,
+    /// `@INCLUDE` patterns and parsers files.
+    patterns: Vec<String>,
+    /// Names and ranges of the parsers the document defines.
+    parsers: Vec<(String, Range)>,
+    routing: RoutingModel,
+    /// Names of the variables the document sets.
+    variables: Vec<String>,
+    /// Names and ranges of the variables the document references.
+    references: Vec<(String, Range)>,
+    /// Variables of the `env` of a YAML document.
+    env: Vec<EnvVariable>,
+    /// `@INCLUDE` directives and `[SERVICE]` keys, in order.
+    items: Vec<ConfigItem>,
+}
+
+impl DocumentLinks {
+    pub(crate) fn new(
+        uri: &Url,
+        document: &TextDocument,
+        position_encoding: PositionEncodingKind,
+    ) -> Self {
+        let index = &document.index;
+        let includes = index
+            .includes()
+            .iter()
+            .map(|include| include.pattern.clone());
+        let parsers_files = index
+            .sections()
+            .iter()
+            .filter(|section| section.section_type == FlbSectionType::Service)
+            .flat_map(|section| &section.entries)
+            .filter(|entry| entry.key.eq_ignore_ascii_case("Parsers_File"))
+            .filter_map(|entry| entry.value.clone());
+
+        let parsers = parser_symbols(index)
+            .into_iter()
+            .filter(|symbol| symbol.kind == ParserSymbolKind::Definition)
+            .map(|symbol| {
+                let range =
+                    byte_range_to_lsp_range(&document.rope, symbol.range, position_encoding);
+                (symbol.name, range)
+            })
+            .collect();
+
+        let references = variables::references(index)
+            .into_iter()
+            .map(|reference| {
+                let range =
+                    byte_range_to_lsp_range(&document.rope, reference.range, position_encoding);
+                (reference.name, range)
+            })
+            .collect();
+
+        Self {
+            base_dir: document_dir(uri),
+            patterns: includes.chain(parsers_files).collect(),
+            parsers,
+            routing: RoutingModel::new(index, &document.rope, position_encoding),
+            variables: effective::variables(document).into_keys().collect(),
+            references,
+            env: yaml_env::env_variables(document, position_encoding),
+            items: config_items(document, position_encoding),
+        }
+    }
+}
+
+struct IncludeNode {
+    includes: Vec<Url>,
+    /// Names and ranges of the parsers the document defines.
+    parsers: Vec<(String, Range)>,
+    routing: RoutingModel,
+    variables: Vec<String>,
+    references: Vec<(String, Range)>,
+    env: Vec<EnvVariable>,
+    items: Vec<ConfigItem>,
+    /// Files included by each pattern.
+    resolved: HashMap<String, Vec<Url>>,
+}
+
+#[derive(Default)]
+pub(crate) struct IncludeGraph {
+    /// Open and closed documents, the roots of the graph.
+    roots: Vec<Url>,
+    nodes: HashMap<Url, IncludeNode>,
+    /// Included files which aren't open, and closed documents, read from the
+    /// filesystem.
+    pub(crate) loaded: Vec<(Url, Arc<TextDocument>)>,
+    /// Loaded files too large to be analyzed, by size, of which only the
+    /// section headers are indexed.
+    pub(crate) partial: HashMap<Url, u64>,
+}
+
+impl IncludeGraph {
+    /// Follows the includes of the open documents, and of the `closed` ones
+    /// read from their files, reading the included files which aren't open
+    /// through `cache` unless they're excluded by `exclude`. Included files
+    /// among the `other_open` documents, which aren't roots, are followed as
+    /// edited.
+    pub(crate) async fn load(
+        open: Vec<(Url, DocumentLinks)>,
+        other_open: Vec<(Url, DocumentLinks)>,
+        closed: &[Url],
+        cache: &DiskCache,
+        exclude: &[String],
+        position_encoding: PositionEncodingKind,
+    ) -> Self {
+        let mut graph = IncludeGraph::default();
+        let mut queue: VecDeque<(Url, DocumentLinks)> = open
+            .into_iter()
+            .map(|(uri, links)| (normalize_uri(&uri), links))
+            .collect();
+        let mut seen: HashSet<Url> = queue.iter().map(|(uri, _)| uri.clone()).collect();
+        let mut other_open: HashMap<Url, DocumentLinks> = other_open
+            .into_iter()
+            .map(|(uri, links)| (normalize_uri(&uri), links))
+            .collect();
+        for uri in closed {
+            let uri = normalize_uri(uri);
+            let Ok(path) = uri.to_file_path() else {
+                continue;
+            };
+            if is_excluded(&uri, exclude) || !seen.insert(uri.clone()) {
+                continue;
+            }
+            if let Some(links) = graph
+                .load_document(&path, &uri, cache, position_encoding)
+                .await
+            {
+                queue.push_back((uri, links));
+            }
+        }
+        graph.roots = queue.iter().map(|(uri, _)| uri.clone()).collect();
+
+        while let Some((uri, links)) = queue.pop_front() {
+            let mut includes = Vec::new();
+            let mut resolved: HashMap<String, Vec<Url>> = HashMap::new();
+            for pattern in &links.patterns {
+                for path in included_files(links.base_dir.as_deref(), pattern).await {
+                    let Ok(included) = Url::from_file_path(&path) else {
+                        continue;
+                    };
+                    let included = normalize_uri(&included);
+                    includes.push(included.clone());
+                    resolved
+                        .entry(pattern.clone())
+                        .or_default()
+                        .push(included.clone());
+
+                    if graph.loaded.len() >= MAX_LOADED_FILES
+                        || is_excluded(&included, exclude)
+                        || !seen.insert(included.clone())
+                    {
+                        continue;
+                    }
+                    if let Some(links) = other_open.remove(&included) {
+                        queue.push_back((included, links));
+                        continue;
+                    }
+                    if let Some(links) = graph
+                        .load_document(&path, &included, cache, position_encoding)
+                        .await
+                    {
+                        queue.push_back((included, links));
+                    }
+                }
+            }
+            let DocumentLinks {
+                parsers,
+                routing,
+                variables,
+                references,
+                env,
+                items,
+                ..
+            } = links;
+            graph.nodes.insert(uri, IncludeNode {
+                includes,
+                parsers,
+                routing,
+                variables,
+                references,
+                env,
+                items,
+                resolved,
+            });
+        }
+
+        graph
+    }
+
+    /// URIs of the documents of the graphs, the roots included.
+    pub(crate) fn files(&self) -> impl Iterator<Item = &Url> {
+        self.nodes.keys()
+    }
+
+    /// Reads the file of a document which isn't open, through the cache.
+    async fn load_document(
+        &mut self,
+        path: &Path,
+        uri: &Url,
+        cache: &DiskCache,
+        position_encoding: PositionEncodingKind,
+    ) -> Option<DocumentLinks> {
+        let DiskDocument {
+            document,
+            partial_size,
+        } = cache.read(path, uri).await?;
+        if let Some(size) = partial_size {
+            self.partial.insert(uri.clone(), size);
+        }
+        let links = DocumentLinks::new(uri, &document, position_encoding);
+        self.loaded.push((uri.clone(), document));
+
+        Some(links)
+    }
+
+    /// Information diagnostics of the files whose analysis was skipped, for
+    /// their size.
+    pub(crate) fn large_files(&self) -> HashMap<Url, Vec<Diagnostic>> {
+        let mebibytes = |size: u64| format!("{:.1}", size as f64 / (1024.0 * 1024.0));
+        self.partial
+            .iter()
+            .map(|(uri, size)| {
+                let diagnostic = Diagnostic {
+                    range: Range::default(),
+                    severity: Some(DiagnosticSeverity::INFORMATION),
+                    code: Some(NumberOrString::String(LARGE_FILE.to_string())),
+                    message: t!(
+                        LARGE_FILE,
+                        size = mebibytes(*size),
+                        limit = mebibytes(MAX_FILE_SIZE),
+                    ),
+                    ..Diagnostic::default()
+                };
+                (uri.clone(), vec![diagnostic])
+            })
+            .collect()
+    }
+
+    /// Documents reachable from `root`, itself included.
+    fn reachable<'a>(&'a self, root: &'a Url) -> Vec<&'a Url> {
+        let mut seen = HashSet::from([root]);
+        let mut stack = vec![root];
+        let mut ret = Vec::new();
+        while let Some(uri) = stack.pop() {
+            ret.push(uri);
+            let Some(node) = self.nodes.get(uri) else {
+                continue;
+            };
+            stack.extend(node.includes.iter().filter(|include| seen.insert(*include)));
+        }
+
+        ret
+    }
+
+    /// `[SERVICE]` keys of the configuration of `uri` flattened, included
+    /// files being inlined where they're included, except in the files
+    /// including them.
+    fn flatten<'a>(
+        &'a self,
+        uri: &'a Url,
+        including: &mut Vec<&'a Url>,
+        keys: &mut Vec<(&'a Url, &'a ServiceKey)>,
+    ) {
+        let Some(node) = self.nodes.get(uri) else {
+            return;
+        };
+        including.push(uri);
+        for item in &node.items {
+            match item {
+                ConfigItem::ServiceKey(key) => keys.push((uri, key)),
+                ConfigItem::Include(pattern) => {
+                    for included in node.resolved.get(pattern).into_iter().flatten() {
+                        if !including.contains(&included) {
+                            self.flatten(included, including, keys);
+                        }
+                    }
+                }
+            }
+        }
+        including.pop();
+    }
+
+    fn flattened_service_keys<'a>(&'a self, root: &'a Url) -> Vec<(&'a Url, &'a ServiceKey)> {
+        let mut keys = Vec::new();
+        self.flatten(root, &mut Vec::new(), &mut keys);
+        keys
+    }
+
+    /// Diagnostics of the `[SERVICE]` keys set again later in the flattened
+    /// configuration of an open document, by normalized URI of the document
+    /// they're in.
+    pub(crate) fn overridden_service_keys(&self) -> HashMap<Url, Vec<Diagnostic>> {
+        let mut reported = HashSet::new();
+        let mut ret: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+
+        for root in &self.roots {
+            let keys = self.flattened_service_keys(root);
+            for (overridden, winner) in overrides(&keys) {
+                let (uri, key) = keys[overridden];
+                let (winner_uri, winner_key) = keys[winner];
+                if !reported.insert((uri, key.range.start.line, key.range.start.character)) {
+                    continue;
+                }
+                ret.entry(uri.clone()).or_default().push(Diagnostic {
+                    range: key.range,
+                    severity: Some(DiagnosticSeverity::HINT),
+                    code: Some(NumberOrString::String(OVERRIDDEN_SERVICE_KEY.to_string())),
+                    message: t!(
+                        OVERRIDDEN_SERVICE_KEY,
+                        key = key.key,
+                        value = winner_key.value,
+                        location = location_label(winner_uri, &winner_key.range),
+                    ),
+                    related_information: Some(vec![DiagnosticRelatedInformation {
+                        location: Location::new(winner_uri.clone(), winner_key.range),
+                        message: t!("overriding-service-key"),
+                    }]),
+                    tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                    ..Diagnostic::default()
+                });
+            }
+        }
+
+        ret
+    }
+
+    /// Hover note of the `[SERVICE]` key at `position` of the document, in the
+    /// first configuration overriding it or being overridden by it.
+    pub(crate) fn service_key_note(&self, uri: &Url, position: Position) -> Option<String> {
+        let uri = normalize_uri(uri);
+        self.roots.iter().find_map(|root| {
+            overrides::hover_note(&self.flattened_service_keys(root), &uri, position)
+        })
+    }
+
+    /// Diagnostics of the parsers defined more than once in the graph of an
+    /// open document, by normalized URI of the document defining them.
+    pub(crate) fn duplicate_parsers(&self) -> HashMap<Url, Vec<Diagnostic>> {
+        let mut reported = HashSet::new();
+        let mut ret: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+
+        for root in &self.roots {
+            let mut definitions: HashMap<&str, Vec<Location>> = HashMap::new();
+            for uri in self.reachable(root) {
+                for (name, range) in self.nodes.get(uri).map_or(&[][..], |node| &node.parsers) {
+                    definitions
+                        .entry(name)
+                        .or_default()
+                        .push(Location::new(uri.clone(), *range));
+                }
+            }
+
+            for (name, locations) in definitions {
+                if locations.len() < 2 {
+                    continue;
+                }
+                for location in &locations {
+                    if !reported.insert((
+                        location.uri.clone(),
+                        location.range.start.line,
+                        location.range.start.character,
+                    )) {
+                        continue;
+                    }
+                    let related_information = locations
+                        .iter()
+                        .filter(|other| *other != location)
+                        .map(|other| DiagnosticRelatedInformation {
+                            location: other.clone(),
+                            message: t!("duplicate-parser-definition"),
+                        })
+                        .collect();
+                    ret.entry(location.uri.clone())
+                        .or_default()
+                        .push(Diagnostic {
+                            range: location.range,
+                            severity: Some(DiagnosticSeverity::WARNING),
+                            code: Some(NumberOrString::String(DUPLICATE_PARSER.to_string())),
+                            message: t!(DUPLICATE_PARSER, name = name),
+                            related_information: Some(related_information),
+                            ..Diagnostic::default()
+                        });
+                }
+            }
+        }
+
+        ret
+    }
+
+    /// Diagnostics of the rules matching no tag of the inputs, and of the
+    /// inputs whose records reach no output, by normalized URI of the document
+    /// they're in.
+    ///
+    /// Documents reachable from several open documents are reported when no
+    /// configuration routes them, and configurations without inputs or
+    /// outputs, e.g. included fragments being edited, aren't analyzed.
+    pub(crate) fn routing_problems(&self) -> HashMap<Url, Vec<Diagnostic>> {
+        let mut problems: HashMap<(Url, u32, u32), Diagnostic> = HashMap::new();
+        let mut routed: HashSet<(Url, u32, u32)> = HashSet::new();
+        let key = |uri: &Url, range: &Range| (uri.clone(), range.start.line, range.start.character);
+
+        for root in &self.roots {
+            let models: Vec<_> = self
+                .reachable(root)
+                .into_iter()
+                .filter_map(|uri| Some((uri, &self.nodes.get(uri)?.routing)))
+                .collect();
+            let inputs: Vec<_> = models
+                .iter()
+                .flat_map(|(uri, model)| model.inputs.iter().map(move |input| (*uri, input)))
+                .collect();
+            let stages: Vec<_> = models
+                .iter()
+                .flat_map(|(uri, model)| model.stages.iter().map(move |stage| (*uri, stage)))
+                .collect();
+            let tags: Vec<&str> = inputs
+                .iter()
+                .map(|(_, input)| input.tag.as_str())
+                .chain(
+                    models
+                        .iter()
+                        .flat_map(|(_, model)| model.rewritten_tags.iter().map(String::as_str)),
+                )
+                .collect();
+
+            if !inputs.is_empty() {
+                for (uri, stage) in &stages {
+                    let key = key(uri, &stage.range);
+                    if tags.iter().any(|tag| stage.rule.matches(tag)) {
+                        routed.insert(key);
+                        continue;
+                    }
+                    problems.entry(key).or_insert_with(|| Diagnostic {
+                        range: stage.range,
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        code: Some(NumberOrString::String(UNMATCHED_RULE.to_string())),
+                        message: t!(UNMATCHED_RULE, rule = stage.rule.as_str()),
+                        ..Diagnostic::default()
+                    });
+                }
+            }
+
+            if stages
+                .iter()
+                .any(|(_, stage)| stage.section_type == FlbSectionType::Output)
+            {
+                for (uri, input) in &inputs {
+                    let key = key(uri, &input.range);
+                    // records rewritten by a filter are routed with their new tag
+                    if stages.iter().any(|(_, stage)| {
+                        (stage.section_type == FlbSectionType::Output || stage.rewrites)
+                            && stage.rule.matches(&input.tag)
+                    }) {
+                        routed.insert(key);
+                        continue;
+                    }
+                    problems.entry(key).or_insert_with(|| Diagnostic {
+                        range: input.range,
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        code: Some(NumberOrString::String(UNROUTED_INPUT.to_string())),
+                        message: t!(UNROUTED_INPUT, tag = input.tag),
+                        ..Diagnostic::default()
+                    });
+                }
+            }
+        }
+
+        let mut ret: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+        for (key, diagnostic) in problems {
+            if !routed.contains(&key) {
+                ret.entry(key.0).or_default().push(diagnostic);
+            }
+        }
+        for diagnostics in ret.values_mut() {
+            diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+        }
+
+        ret
+    }
+
+    /// Diagnostics of the references to variables no file of the graph of an
+    /// open document sets, by normalized URI of the document they're in.
+    /// `known_variables` are set by the environment fluent-bit runs in, e.g. by
+    /// CI, and aren't reported.
+    ///
+    /// Documents reachable from several open documents are reported when no
+    /// configuration sets the variable.
+    pub(crate) fn undefined_variables(
+        &self,
+        known_variables: &[String],
+        severity: DiagnosticSeverity,
+    ) -> HashMap<Url, Vec<Diagnostic>> {
+        let mut problems: HashMap<(Url, u32, u32), Diagnostic> = HashMap::new();
+        let mut defined: HashSet<(Url, u32, u32)> = HashSet::new();
+
+        for root in &self.roots {
+            let nodes: Vec<_> = self
+                .reachable(root)
+                .into_iter()
+                .filter_map(|uri| Some((uri, self.nodes.get(uri)?)))
+                .collect();
+            let variables: HashSet<&str> = nodes
+                .iter()
+                .flat_map(|(_, node)| node.variables.iter().map(String::as_str))
+                .chain(known_variables.iter().map(String::as_str))
+                .collect();
+
+            for (uri, node) in &nodes {
+                for (name, range) in &node.references {
+                    let key = ((*uri).clone(), range.start.line, range.start.character);
+                    if variables.contains(name.as_str()) {
+                        defined.insert(key);
+                        continue;
+                    }
+                    problems.entry(key).or_insert_with(|| Diagnostic {
+                        range: *range,
+                        severity: Some(severity),
+                        code: Some(NumberOrString::String(UNDEFINED_VARIABLE.to_string())),
+                        message: t!(UNDEFINED_VARIABLE, reference = format!("${{{name}}}")),
+                        ..Diagnostic::default()
+                    });
+                }
+            }
+        }
+
+        let mut ret: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+        for (key, diagnostic) in problems {
+            if !defined.contains(&key) {
+                ret.entry(key.0).or_default().push(diagnostic);
+            }
+        }
+        for diagnostics in ret.values_mut() {
+            diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+        }
+
+        ret
+    }
+
+    /// Diagnostics of the variables the `env` of a YAML document sets which no
+    /// value of the graph of an open document references, by normalized URI
+    /// of the document.
+    ///
+    /// Documents reachable from several open documents are reported when no
+    /// configuration references the variable.
+    pub(crate) fn unused_variables(&self) -> HashMap<Url, Vec<Diagnostic>> {
+        let mut problems: HashMap<(Url, u32, u32), Diagnostic> = HashMap::new();
+        let mut used: HashSet<(Url, u32, u32)> = HashSet::new();
+
+        for root in &self.roots {
+            let nodes: Vec<_> = self
+                .reachable(root)
+                .into_iter()
+                .filter_map(|uri| Some((uri, self.nodes.get(uri)?)))
+                .collect();
+            let referenced: HashSet<&str> = nodes
+                .iter()
+                .flat_map(|(_, node)| node.references.iter().map(|(name, _)| name.as_str()))
+                .collect();
+
+            for (uri, node) in &nodes {
+                for variable in &node.env {
+                    let range = variable.range;
+                    let key = ((*uri).clone(), range.start.line, range.start.character);
+                    if referenced.contains(variable.name.as_str()) {
+                        used.insert(key);
+                        continue;
+                    }
+                    problems.entry(key).or_insert_with(|| Diagnostic {
+                        range,
+                        severity: Some(DiagnosticSeverity::HINT),
+                        code: Some(NumberOrString::String(UNUSED_VARIABLE.to_string())),
+                        message: t!(
+                            UNUSED_VARIABLE,
+                            reference = format!("${{{}}}", variable.name)
+                        ),
+                        tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                        ..Diagnostic::default()
+                    });
+                }
+            }
+        }
+
+        let mut ret: HashMap<Url, Vec<Diagnostic>> = HashMap::new();
+        for (key, diagnostic) in problems {
+            if !used.contains(&key) {
+                ret.entry(key.0).or_default().push(diagnostic);
+            }
+        }
+        for diagnostics in ret.values_mut() {
+            diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+        }
+
+        ret
+    }
+
+    /// Documents of the configurations of the open documents reaching `uri`.
+    fn configurations_of(&self, uri: &Url) -> Vec<&Url> {
+        let mut ret = Vec::new();
+        for root in &self.roots {
+            let reachable = self.reachable(root);
+            if !reachable.contains(&uri) {
+                continue;
+            }
+            for document in reachable {
+                if !ret.contains(&document) {
+                    ret.push(document);
+                }
+            }
+        }
+
+        ret
+    }
+
+    /// Hover note of the variable the `env` of the document sets at
+    /// `position`, with the references to it in the configurations including
+    /// the document.
+    pub(crate) fn env_variable_note(&self, uri: &Url, position: Position) -> Option<String> {
+        let uri = normalize_uri(uri);
+        let variable =
+            self.nodes.get(&uri)?.env.iter().find(|variable| {
+                variable.range.start <= position && position <= variable.range.end
+            })?;
+
+        let mut references: Vec<(&Url, Range)> = self
+            .configurations_of(&uri)
+            .into_iter()
+            .filter_map(|uri| Some((uri, self.nodes.get(uri)?)))
+            .flat_map(|(uri, node)| {
+                node.references
+                    .iter()
+                    .filter(|(name, _)| *name == variable.name)
+                    .map(move |(_, range)| (uri, *range))
+            })
+            .collect();
+        references.sort_by(|(a, a_range), (b, b_range)| {
+            (a.as_str(), a_range.start).cmp(&(b.as_str(), b_range.start))
+        });
+
+        Some(yaml_env::hover_note(&variable.name, &references))
+    }
+
+    /// Variables referenced in the configurations including the document which
+    /// none sets, by name, with their first reference. `known_variables` are
+    /// set by the environment fluent-bit runs in.
+    pub(crate) fn undefined_variable_references(
+        &self,
+        uri: &Url,
+        known_variables: &[String],
+    ) -> Vec<(String, &Url, Range)> {
+        let uri = normalize_uri(uri);
+        let nodes: Vec<_> = self
+            .configurations_of(&uri)
+            .into_iter()
+            .filter_map(|uri| Some((uri, self.nodes.get(uri)?)))
+            .collect();
+        let defined: HashSet<&str> = nodes
+            .iter()
+            .flat_map(|(_, node)| node.variables.iter().map(String::as_str))
+            .chain(known_variables.iter().map(String::as_str))
+            .collect();
+
+        let mut ret: Vec<(String, &Url, Range)> = Vec::new();
+        for (uri, node) in nodes {
+            for (name, range) in &node.references {
+                if !defined.contains(name.as_str()) && ret.iter().all(|(n, _, _)| n != name) {
+                    ret.push((name.clone(), uri, *range));
+                }
+            }
+        }
+        ret.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+
+        ret
+    }
+}
+
+/// Reads a file which isn't open, returning its size too if it's too large to be
+/// analyzed, only its section headers being indexed then.
+pub(crate) async fn read_document(path: &Path, uri: &Url) -> Option<(TextDocument, Option<u64>)> {
+    let format = ConfigFormat::from_uri(uri);
+    let size = tokio::fs::metadata(path).await.ok()?.len();
+    if size > MAX_INDEXED_FILE_SIZE {
+        return None;
+    }
+    if size > MAX_FILE_SIZE {
+        let path = path.to_path_buf();
+        let read = tokio::task::spawn_blocking(move || read_headers(&path, format));
+        let text = tokio::time::timeout(INDEX_TIMEOUT, read)
+            .await
+            .ok()?
+            .ok()?
+            .ok()?;
+        return Some((TextDocument::with_format(&text, format), Some(size)));
+    }
+
+    let read = tokio::fs::read(path);
+    let (text, encoding) = decode(tokio::time::timeout(READ_TIMEOUT, read).await.ok()?.ok()?);
+    let mut document = TextDocument::with_format(&text, format);
+    document.decoded_from = encoding;
+
+    Some((document, None))
+}
+
+/// Section headers and directives of a file, read line by line, other lines
+/// being blanked so that positions are kept. YAML files, whose sections are
+/// nested, keep their unindented lines.
+fn read_headers(path: &Path, format: ConfigFormat) -> io::Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut text = String::new();
+    let mut buf = Vec::new();
+    while reader.read_until(b'\n', &mut buf)? > 0 {
+        let line = String::from_utf8_lossy(&buf);
+        let header = match format {
+            ConfigFormat::Classic => matches!(line.trim_start().chars().next(), Some('[' | '@')),
+            ConfigFormat::Yaml => !line.starts_with(char::is_whitespace),
+        };
+        if header {
+            text.push_str(&line);
+        } else if line.ends_with('\n') {
+            text.push('\n');
+        }
+        buf.clear();
+    }
+
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn includes_and_duplicate_parsers_across_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::create_dir_all(dir.join("inputs")).unwrap();
+        fs::write(
+            dir.join("inputs/tail.conf"),
+            "@INCLUDE ../outputs.conf\n\n\
+             [INPUT]\n    Name tail\n    Path ${LOG_DIR}/*.log\n    Tag  ${HOSTNAME}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("outputs.conf"), "[OUTPUT]\n    Name stdout\n").unwrap();
+        fs::write(
+            dir.join("parsers.conf"),
+            "[PARSER]\n    Name   json\n    Format json\n",
+        )
+        .unwrap();
+
+        let encoding = PositionEncodingKind::UTF16;
+        let uri = Url::from_file_path(dir.join("fluent-bit.conf")).unwrap();
+        let document = TextDocument::new(
+            "@SET LOG_DIR=/var/log\n@INCLUDE inputs/*.conf\n\n\
+             [SERVICE]\n    Parsers_File parsers.conf\n\n\
+             [PARSER]\n    Name   json\n    Format json\n",
+        );
+        let links = DocumentLinks::new(&uri, &document, encoding);
+        // unrelated configurations don't conflict
+        let other_uri = Url::from_file_path(dir.join("other/fluent-bit.conf")).unwrap();
+        let other_links = DocumentLinks::new(&other_uri, &document, encoding);
+
+        let graph = IncludeGraph::load(
+            vec![(uri.clone(), links), (other_uri.clone(), other_links)],
+            Vec::new(),
+            &[],
+            &DiskCache::default(),
+            &[],
+            encoding,
+        )
+        .await;
+        let mut loaded: Vec<_> = graph
+            .loaded
+            .iter()
+            .map(|(uri, _)| uri.path().rsplit_once(&*dir.to_string_lossy()).unwrap().1)
+            .collect();
+        loaded.sort();
+        assert_eq!(loaded, [
+            "/inputs/tail.conf",
+            "/outputs.conf",
+            "/parsers.conf"
+        ]);
+
+        let duplicates = graph.duplicate_parsers();
+        let parsers_uri = Url::from_file_path(dir.join("parsers.conf")).unwrap();
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[&uri].len(), 1);
+        assert_eq!(
+            duplicates[&uri][0].message,
+            "Parser `json` is defined more than once."
+        );
+        let related = duplicates[&parsers_uri][0]
+            .related_information
+            .as_ref()
+            .unwrap();
+        assert_eq!(related[0].location.uri, uri);
+
+        // `LOG_DIR` is set by the including file
+        let undefined = graph.undefined_variables(&[], DiagnosticSeverity::INFORMATION);
+        let tail_uri = Url::from_file_path(dir.join("inputs/tail.conf")).unwrap();
+        assert_eq!(undefined.len(), 1);
+        let undefined: Vec<_> = undefined[&tail_uri]
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.range.start.line,
+                    diagnostic.range.start.character,
+                )
+            })
+            .collect();
+        assert_eq!(undefined, [(5, 9)]);
+        let known = ["HOSTNAME".to_string()];
+        assert!(graph
+            .undefined_variables(&known, DiagnosticSeverity::ERROR)
+            .is_empty());
+
+        // included files which are open are analyzed as edited
+        let parsers = TextDocument::new("[PARSER]\n    Name   logfmt\n    Format logfmt\n");
+        let graph = IncludeGraph::load(
+            vec![(uri.clone(), DocumentLinks::new(&uri, &document, encoding))],
+            vec![(
+                parsers_uri.clone(),
+                DocumentLinks::new(&parsers_uri, &parsers, encoding),
+            )],
+            &[],
+            &DiskCache::default(),
+            &[],
+            encoding,
+        )
+        .await;
+        assert!(graph.duplicate_parsers().is_empty());
+        assert!(!graph.loaded.iter().any(|(uri, _)| *uri == parsers_uri));
+        assert!(graph.files().any(|uri| *uri == parsers_uri));
+    }
+
+    #[tokio::test]
+    async fn large_files_are_indexed_for_their_headers() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let padding = "    # padding\n".repeat(MAX_FILE_SIZE as usize / 14 + 1);
+        let text =
+            format!("[PARSER]\n{padding}    Name json\n\n[MULTILINE_PARSER]\n    Name multi\n");
+        fs::write(dir.join("parsers.conf"), &text).unwrap();
+
+        let encoding = PositionEncodingKind::UTF16;
+        let uri = Url::from_file_path(dir.join("fluent-bit.conf")).unwrap();
+        let document = TextDocument::new("[SERVICE]\n    Parsers_File parsers.conf\n");
+        let links = DocumentLinks::new(&uri, &document, encoding);
+        let graph = IncludeGraph::load(
+            vec![(uri, links)],
+            Vec::new(),
+            &[],
+            &DiskCache::default(),
+            &[],
+            encoding,
+        )
+        .await;
+
+        let (parsers_uri, parsers) = &graph.loaded[0];
+        let sections: Vec<_> = parsers
+            .index
+            .sections()
+            .iter()
+            .map(|section| (section.section_type.to_string(), section.entries.len()))
+            .collect();
+        assert_eq!(sections, [
+            ("parser".to_string(), 0),
+            ("multiline_parser".to_string(), 0)
+        ]);
+        // positions are kept
+        assert_eq!(parsers.rope.len_lines(), text.lines().count() + 1);
+
+        let large_files = graph.large_files();
+        assert_eq!(
+            large_files[parsers_uri][0].message,
+            "The file is 1.0 MiB, over the 1.0 MiB analyzed: only its section headers are indexed."
+        );
+    }
+
+    #[tokio::test]
+    async fn routing_problems_of_configurations() {
+        let encoding = PositionEncodingKind::UTF16;
+        let uri: Url = "file:///fluent-bit.conf".parse().unwrap();
+        let document = TextDocument::new(
+            "[INPUT]\n    Name cpu\n\n\
+             [INPUT]\n    Name tail\n    Tag  kube.*\n\n\
+             [INPUT]\n    Name mem\n    Tag  mem.local\n\n\
+             [FILTER]\n    Name  rewrite_tag\n    Match kube.*\n    Rule  $log ^error errors false\n\n\
+             [FILTER]\n    Name  grep\n    Match app.*\n\n\
+             [OUTPUT]\n    Name  stdout\n    Match_Regex ^(cpu|errors)\n",
+        );
+        // fragments without inputs aren't analyzed
+        let fragment_uri: Url = "file:///outputs.conf".parse().unwrap();
+        let fragment = TextDocument::new("[OUTPUT]\n    Name  null\n    Match app.*\n");
+
+        let graph = IncludeGraph::load(
+            vec![
+                (uri.clone(), DocumentLinks::new(&uri, &document, encoding)),
+                (
+                    fragment_uri.clone(),
+                    DocumentLinks::new(&fragment_uri, &fragment, encoding),
+                ),
+            ],
+            Vec::new(),
+            &[],
+            &DiskCache::default(),
+            &[],
+            encoding,
+        )
+        .await;
+        let problems = graph.routing_problems();
+        assert_eq!(problems.len(), 1);
+        let problems: Vec<_> = problems[&uri]
+            .iter()
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(problems, [
+            (9, "No output matches the records tagged `mem.local`."),
+            (
+                18,
+                "No input of the configuration produces records `app.*` matches."
+            ),
+        ]);
+    }
+}
@@ -0,0 +1,216 @@
+//! Inlay hints (`textDocument/inlayHint`) showing, at the end of a section,
+//! the defaults fluent-bit uses for the important parameters the section
+//! doesn't set, e.g. that an input without `Mem_Buf_Limit` buffers without
+//! limit.
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+use tower_lsp::lsp_types::{InlayHint, InlayHintLabel, InlayHintTooltip, Range};
+
+use crate::{
+    completion::FlbData,
+    document::TextDocument,
+    effective::defaults,
+    i18n::t,
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    section_index::IndexedSection,
+};
+
+/// Default of a hinted parameter.
+enum HintedDefault {
+    /// The one of the schema, or of the `[SERVICE]` parameters.
+    Schema,
+    /// Of a parameter common to all plugins, which the schema lacks.
+    Value(&'static str),
+    /// No limit, for sizes and counts.
+    Unlimited,
+}
+
+struct HintedParameter {
+    section_type: FlbSectionType,
+    /// `None` if the parameter is common to all plugins of the sections.
+    plugin_name: Option<&'static str>,
+    key: &'static str,
+    default: HintedDefault,
+}
+
+const fn hinted(
+    section_type: FlbSectionType,
+    plugin_name: Option<&'static str>,
+    key: &'static str,
+    default: HintedDefault,
+) -> HintedParameter {
+    HintedParameter {
+        section_type,
+        plugin_name,
+        key,
+        default,
+    }
+}
+
+/// Parameters whose defaults change what fluent-bit does the most.
+static HINTED_PARAMETERS: &[HintedParameter] = &[
+    hinted(
+        FlbSectionType::Service,
+        None,
+        "Flush",
+        HintedDefault::Schema,
+    ),
+    hinted(
+        FlbSectionType::Service,
+        None,
+        "Log_Level",
+        HintedDefault::Schema,
+    ),
+    // ref: https://docs.fluentbit.io/manual/administration/buffering-and-storage
+    hinted(
+        FlbSectionType::Input,
+        None,
+        "Mem_Buf_Limit",
+        HintedDefault::Unlimited,
+    ),
+    hinted(
+        FlbSectionType::Input,
+        None,
+        "storage.type",
+        HintedDefault::Value("memory"),
+    ),
+    hinted(
+        FlbSectionType::Input,
+        Some("tail"),
+        "Read_from_Head",
+        HintedDefault::Schema,
+    ),
+    // ref: https://docs.fluentbit.io/manual/administration/scheduling-and-retries
+    hinted(
+        FlbSectionType::Output,
+        None,
+        "Retry_Limit",
+        HintedDefault::Value("1"),
+    ),
+    hinted(
+        FlbSectionType::Output,
+        None,
+        "storage.total_limit_size",
+        HintedDefault::Unlimited,
+    ),
+];
+
+/// Byte offset of the end of the line of the offset, before its line ending
+/// and trailing whitespace.
+fn line_end(rope: &Rope, byte: usize) -> usize {
+    let line = rope.byte_to_line(byte);
+    let start = rope.line_to_byte(line);
+    let text = rope.line(line).to_string();
+
+    start + text.trim_end().len()
+}
+
+/// Defaults of the hinted parameters the section doesn't set, by key.
+fn omitted_defaults(data: &FlbData, section: &IndexedSection) -> Vec<(&'static str, String)> {
+    let plugin_name = section.get_value("Name");
+    if plugin_name.is_none() && section.section_type != FlbSectionType::Service {
+        return Vec::new();
+    }
+    let schema_defaults = defaults(data, section, plugin_name);
+
+    HINTED_PARAMETERS
+        .iter()
+        .filter(|param| param.section_type == section.section_type)
+        .filter(|param| {
+            param.plugin_name.map_or(true, |name| {
+                plugin_name.is_some_and(|plugin_name| name.eq_ignore_ascii_case(plugin_name))
+            })
+        })
+        .filter(|param| !section.contains_key(param.key))
+        .filter_map(|param| {
+            let default = match param.default {
+                HintedDefault::Schema => schema_defaults
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(param.key))
+                    .and_then(|(_, default)| *default)?
+                    .to_string(),
+                HintedDefault::Value(value) => value.to_string(),
+                HintedDefault::Unlimited => t!("hint-unlimited"),
+            };
+            Some((param.key, default))
+        })
+        .collect()
+}
+
+pub(crate) fn inlay_hints(
+    data: &FlbData,
+    document: &TextDocument,
+    range: Range,
+    position_encoding: PositionEncodingKind,
+) -> Vec<InlayHint> {
+    let TextDocument { rope, index, .. } = document;
+
+    let mut hints = Vec::new();
+    for section in index.sections() {
+        let end = section
+            .entries
+            .iter()
+            .map(|entry| entry.value_range.as_ref().unwrap_or(&entry.key_range).end)
+            .max()
+            .unwrap_or(section.byte_range.start);
+        let end = line_end(rope, end);
+        let position = byte_range_to_lsp_range(rope, end..end, position_encoding).start;
+        if position < range.start || range.end < position {
+            continue;
+        }
+
+        for (key, default) in omitted_defaults(data, section) {
+            hints.push(InlayHint {
+                position,
+                label: InlayHintLabel::String(format!("{key}: {default}")),
+                kind: None,
+                text_edits: None,
+                tooltip: Some(InlayHintTooltip::String(t!("hint-default", key = key))),
+                padding_left: Some(true),
+                padding_right: None,
+                data: None,
+            });
+        }
+    }
+
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::Position;
+
+    use super::*;
+    use crate::completion::FLB_DATA;
+
+    #[test]
+    fn defaults_of_omitted_parameters() {
+        let document = TextDocument::new(
+            "[INPUT]\n    Name  tail\n    Path  /var/log/*.log  \n    storage.type filesystem\n\n\
+             [OUTPUT]\n    Name  stdout\n    Match *\n    Retry_Limit 5\n",
+        );
+        let range = Range::new(Position::new(0, 0), Position::new(10, 0));
+        let hints: Vec<_> = inlay_hints(&FLB_DATA, &document, range, PositionEncodingKind::UTF16)
+            .into_iter()
+            .map(|hint| {
+                let InlayHintLabel::String(label) = hint.label else {
+                    panic!("labels are strings");
+                };
+                (hint.position.line, hint.position.character, label)
+            })
+            .collect();
+        assert_eq!(hints, [
+            (3, 27, "Mem_Buf_Limit: unlimited".to_string()),
+            (3, 27, "Read_from_Head: false".to_string()),
+            (8, 17, "storage.total_limit_size: unlimited".to_string()),
+        ]);
+
+        // sections outside of the range aren't hinted
+        let range = Range::new(Position::new(5, 0), Position::new(10, 0));
+        assert_eq!(
+            inlay_hints(&FLB_DATA, &document, range, PositionEncodingKind::UTF16).len(),
+            1
+        );
+    }
+}
@@ -1,47 +1,487 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
 
 use flb_schema::section::FlbSectionType;
 use ropey::Rope;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use tokio::sync::RwLock;
 use tower_lsp::{
-    jsonrpc::Result as JsonRpcResult,
+    jsonrpc::{Error as JsonRpcError, Result as JsonRpcResult},
     lsp_types::{
-        CompletionItem, CompletionOptions, CompletionOptionsCompletionItem, CompletionParams,
-        CompletionResponse, Diagnostic, DiagnosticOptions, DiagnosticServerCapabilities,
-        DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
-        DocumentDiagnosticParams, DocumentDiagnosticReport, DocumentDiagnosticReportResult,
-        FullDocumentDiagnosticReport, Hover, HoverContents, HoverParams, HoverProviderCapability,
-        InitializeParams, InitializeResult, InitializedParams, MessageType, Position, Range,
-        RelatedFullDocumentDiagnosticReport, ServerCapabilities, TextDocumentContentChangeEvent,
-        TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+        notification::Notification, request::Request, ClientCapabilities, CodeAction,
+        CodeActionKind, CodeActionOptions, CodeActionOrCommand, CodeActionParams,
+        CodeActionProviderCapability, CodeActionResponse, CodeLens, CodeLensOptions,
+        CodeLensParams, Command, CompletionItem, CompletionOptions,
+        CompletionOptionsCompletionItem, CompletionParams, CompletionResponse, Diagnostic,
+        DiagnosticOptions, DiagnosticServerCapabilities, DiagnosticSeverity,
+        DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+        DidChangeWatchedFilesRegistrationOptions, DidCloseTextDocumentParams,
+        DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentDiagnosticParams,
+        DocumentDiagnosticReport, DocumentDiagnosticReportResult, DocumentFormattingParams,
+        DocumentOnTypeFormattingOptions, DocumentOnTypeFormattingParams,
+        DocumentRangeFormattingParams, DocumentSymbolParams, DocumentSymbolResponse,
+        ExecuteCommandOptions, ExecuteCommandParams, FileSystemWatcher, FoldingRange,
+        FoldingRangeParams, FoldingRangeProviderCapability, FullDocumentDiagnosticReport,
+        GlobPattern, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+        HoverParams, HoverProviderCapability, InitializeParams, InitializeResult,
+        InitializedParams, InlayHint, InlayHintParams, Location, MessageType, NumberOrString,
+        OneOf, Position, PrepareRenameResponse, ProgressToken, Range, ReferenceParams,
+        Registration, RelatedFullDocumentDiagnosticReport, RenameOptions, RenameParams,
+        SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensParams,
+        SemanticTokensResult, SemanticTokensServerCapabilities, ServerCapabilities, SetTraceParams,
+        SignatureHelp, SignatureHelpOptions, SignatureHelpParams, TextDocumentContentChangeEvent,
+        TextDocumentIdentifier, TextDocumentPositionParams, TextDocumentSyncCapability,
+        TextDocumentSyncKind, TextDocumentSyncOptions, TextDocumentSyncSaveOptions, TextEdit,
+        TraceValue, Url, WorkspaceDiagnosticParams, WorkspaceDiagnosticReport,
+        WorkspaceDiagnosticReportPartialResult, WorkspaceDiagnosticReportResult,
+        WorkspaceDocumentDiagnosticReport, WorkspaceEdit, WorkspaceFullDocumentDiagnosticReport,
     },
     Client, LanguageServer,
 };
-use tree_sitter::{Node, Point};
+use tracing::{debug, info, trace, warn};
+use tree_sitter::{Node, Point, Tree};
 
 use crate::{
-    completion::{get_completion, get_hover_info},
-    document::{PositionEncodingKind, TextDocument},
+    baseline::Baseline,
+    completion::{
+        indent_continuation_lines, paginate_completion, FlbConfigParameterInfo, FlbData, FLB_DATA,
+    },
+    debounce::Debouncer,
+    diagnostics::Diagnosis,
+    disk_cache::DiskCache,
+    document::{DocumentError, TextDocument},
+    document_map::{normalize_uri, DocumentMap},
+    effective, embedded,
+    encoding::EncodingProblem,
+    fixes,
+    folding::folding_ranges,
+    formatting::{align_entry, format_document, format_range},
+    grammar,
+    i18n::{self, t},
+    include_graph::{DocumentLinks, IncludeGraph},
+    inlay_hints::inlay_hints,
+    lints, logging,
+    lsp_ext::{
+        ClientExperimentalCapabilities, ClientExtensionCapabilities, ConfigFormat,
+        DiagnosticCounts, DocumentAnalyzed, DocumentAnalyzedParams, DocumentChecksumParams,
+        EffectiveSectionParams, EffectiveSectionResult, EmbeddedDocumentInfo,
+        EmbeddedDocumentsParams, ExperimentalCapabilities, GrammarInfoResult, MigrationChange,
+        MigrationReportArguments, MigrationReportResult, PipelineHierarchyParams,
+        PipelineHierarchyResult, PluginsParams, PluginsResult, ResyncDocument,
+        ResyncDocumentParams, ScaffoldPipelineArguments, ServerStatusResult, WorkspaceStatsResult,
+        FIX_ALL_COMMAND, MIGRATION_REPORT_COMMAND, RELOAD_SCHEMA_COMMAND,
+        SCAFFOLD_PIPELINE_COMMAND,
+    },
+    markdown::MarkupOptions,
+    metrics::RequestMetrics,
+    migration::migrated_keys,
+    parser_index::{
+        is_valid_parser_name, parser_symbol_at, parser_symbols, ParserSymbol, ParserSymbolKind,
+    },
+    path_checks::{document_dir, included_files, PathCache},
+    pipeline_summary::PipelineSummary,
+    plugins::{check_plugin_names, check_unknown_keys},
+    positions::{
+        byte_range_to_lsp_range, point_to_byte, position_to_point, resolve_position, to_lsp_range,
+        PositionEncodingKind,
+    },
+    progress::{PartialResultParams, WorkspaceDiagnosticPartialResult},
+    routing::{
+        check_section_order, pipeline_hierarchy, tag_at, tag_completion_items, tag_references, tags,
+    },
+    rules::{
+        check_conditional_requirements, check_duplicate_keys, check_incompatible_values,
+        check_mem_buf_limits, check_yaml_only_keys,
+    },
+    scaffold::scaffold_pipeline,
+    schema_store::SchemaStore,
+    scope::is_excluded,
+    section_index::{IndexedSection, SectionIndex},
+    section_names::{check_section_names, intended_section_type},
+    semantic_tokens,
+    service::{check_misplaced_service_keys, is_service_key},
+    settings::Settings,
+    signature_help,
+    symbols::document_symbols,
+    timeouts::RequestLimits,
+    tree_walk::descendants,
+    validation::{check_values, hover_notes, problems_note},
+    variables,
+    versions::FlbVersion,
+    workspace_edit::{supports_change_annotations, WorkspaceEditBuilder},
+    yaml, yaml_env,
 };
 
+/// Diagnostic code of a key of `[SERVICE]` sections fluent-bit doesn't know.
+pub(crate) const UNKNOWN_SERVICE_KEY: &str = "unknown-service-key";
+
+/// Number of documents reported per `$/progress` notification when the client
+/// asked for partial workspace diagnostic results.
+const WORKSPACE_DIAGNOSTIC_BATCH_SIZE: usize = 16;
+
+/// Name whose references `textDocument/references` lists.
+enum ReferenceTarget {
+    Parser(String),
+    Tag(String),
+}
+
+/// Document reported by `workspace/diagnostic`.
+enum ReportSource<'a> {
+    Open(&'a Url),
+    /// Included file which isn't open.
+    Included(&'a Url, &'a TextDocument),
+    /// Included file too large to be analyzed, of which only the section
+    /// headers are indexed.
+    Indexed(&'a Url, &'a TextDocument),
+}
+
+/// What the diagnostics of the open documents depend on, shared with the
+/// tasks analyzing documents once their changes settle.
+#[derive(Clone)]
+struct Analyzer {
+    client: Client,
+    map: Arc<RwLock<DocumentMap>>,
+    settings: Arc<RwLock<Settings>>,
+    schema: Arc<SchemaStore>,
+    paths: Arc<PathCache>,
+    baseline: Arc<RwLock<Baseline>>,
+    position_encoding: PositionEncodingKind,
+    /// Whether the client handles `flb/documentAnalyzed`.
+    notifies_analyzed: bool,
+}
+
+impl Analyzer {
+    /// Diagnostics of the document, `None` if it isn't open.
+    async fn get_diagnostics(&self, url: &Url) -> Option<Vec<Diagnostic>> {
+        let position_encoding = self.position_encoding;
+        let settings = self.settings.read().await.clone();
+        let excluded = is_excluded(url, &settings.exclude);
+        let data = self.schema.get().await;
+        let r = self.map.read().await;
+        let document = r.get(url)?;
+        if excluded {
+            return Some(vec![]);
+        }
+        // documents which failed to parse aren't diagnosed
+        document.tree.as_ref()?;
+
+        let diagnosis = Diagnosis::new(&data, document, Some(url), &settings, position_encoding);
+        // the filesystem is checked without holding the document
+        let rope = document.rope.clone();
+        drop(r);
+        let mut diagnostics = diagnosis.finish(&self.paths).await;
+        self.suppress_baselined(url, &rope, &mut diagnostics).await;
+
+        Some(diagnostics)
+    }
+
+    /// Drops the diagnostics of the document the baseline records.
+    async fn suppress_baselined(&self, url: &Url, rope: &Rope, diagnostics: &mut Vec<Diagnostic>) {
+        let Ok(path) = url.to_file_path() else {
+            return;
+        };
+        self.baseline
+            .read()
+            .await
+            .suppress(&path, rope, diagnostics);
+    }
+
+    /// Diagnostics of the document, sending `flb/documentAnalyzed` to clients
+    /// handling it.
+    async fn analyze_document(&self, url: &Url) -> Option<Vec<Diagnostic>> {
+        let start = Instant::now();
+        let diagnostics = self.get_diagnostics(url).await?;
+        let duration = start.elapsed();
+        if !self.notifies_analyzed {
+            return Some(diagnostics);
+        }
+
+        let analyzed = self.map.read().await.get(url).map(|document| {
+            let mut counts = DiagnosticCounts::default();
+            counts.add(&diagnostics);
+            DocumentAnalyzedParams {
+                text_document: TextDocumentIdentifier::new(url.clone()),
+                version: document.version(),
+                sections: document.index.sections().len(),
+                diagnostics: counts,
+                duration_ms: duration.as_secs_f64() * 1000.0,
+            }
+        });
+        if let Some(params) = analyzed {
+            self.client
+                .send_notification::<DocumentAnalyzed>(params)
+                .await;
+        }
+
+        Some(diagnostics)
+    }
+
+    /// Publishes the diagnostics of the document.
+    async fn publish(&self, url: &Url, version: Option<i32>) {
+        if let Some(diagnostics) = self.analyze_document(url).await {
+            self.client
+                .publish_diagnostics(url.clone(), diagnostics, version)
+                .await;
+        }
+    }
+
+    /// Publishes the diagnostics of the latest version of the document, which
+    /// may have changed since the analysis was scheduled.
+    async fn publish_latest(&self, url: &Url) {
+        let version = self
+            .map
+            .read()
+            .await
+            .get(url)
+            .and_then(TextDocument::version);
+        self.publish(url, version).await;
+    }
+}
+
+/// Documents reported by `workspace/diagnostic`: the open ones, then the
+/// files of the workspace which aren't.
+fn report_sources<'a>(urls: &'a [Url], graph: &'a IncludeGraph) -> Vec<ReportSource<'a>> {
+    urls.iter()
+        .map(ReportSource::Open)
+        .chain(
+            graph
+                .loaded
+                .iter()
+                .map(|(url, document)| match graph.partial.contains_key(url) {
+                    true => ReportSource::Indexed(url, document),
+                    false => ReportSource::Included(url, document),
+                }),
+        )
+        .collect()
+}
+
+fn full_report(url: &Url, items: Vec<Diagnostic>) -> WorkspaceDocumentDiagnosticReport {
+    WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+        uri: url.clone(),
+        version: None,
+        full_document_diagnostic_report: FullDocumentDiagnosticReport {
+            result_id: None,
+            items,
+        },
+    })
+}
+
+/// The argument of a `workspace/executeCommand` command, its first one.
+fn command_args<T: DeserializeOwned>(arguments: Vec<Value>) -> JsonRpcResult<T> {
+    let arg = arguments
+        .into_iter()
+        .next()
+        .ok_or_else(|| JsonRpcError::invalid_params("missing argument"))?;
+
+    serde_json::from_value(arg).map_err(|e| JsonRpcError::invalid_params(e.to_string()))
+}
+
+/// Partial results of `workspace/diagnostic`, sent as `$/progress`.
+struct PartialReports<'a> {
+    client: &'a Client,
+    token: ProgressToken,
+    /// Whether a report was sent, the first one having to be a full report
+    /// which the following ones are appended to.
+    started: bool,
+}
+
+impl<'a> PartialReports<'a> {
+    fn new(client: &'a Client, token: ProgressToken) -> Self {
+        Self {
+            client,
+            token,
+            started: false,
+        }
+    }
+
+    async fn send(&mut self, items: Vec<WorkspaceDocumentDiagnosticReport>) {
+        let value = match self.started {
+            false => WorkspaceDiagnosticReportResult::Report(WorkspaceDiagnosticReport { items }),
+            true => {
+                WorkspaceDiagnosticReportResult::Partial(WorkspaceDiagnosticReportPartialResult {
+                    items,
+                })
+            }
+        };
+        self.started = true;
+
+        self.client
+            .send_notification::<WorkspaceDiagnosticPartialResult>(PartialResultParams {
+                token: self.token.clone(),
+                value,
+            })
+            .await;
+    }
+}
+
 pub struct Backend {
     pub(crate) client: Client,
-    pub(crate) map: RwLock<HashMap<Url, TextDocument>>,
+    pub(crate) map: Arc<RwLock<DocumentMap>>,
+    pub(crate) client_capabilities: OnceLock<ClientCapabilities>,
+    pub(crate) position_encoding: OnceLock<PositionEncodingKind>,
+    pub(crate) settings: Arc<RwLock<Settings>>,
+    pub(crate) schema: Arc<SchemaStore>,
+    pub(crate) metrics: Arc<RequestMetrics>,
+    pub(crate) limits: Arc<RequestLimits>,
+    pub(crate) paths: Arc<PathCache>,
+    /// Known diagnostics, which aren't reported.
+    pub(crate) baseline: Arc<RwLock<Baseline>>,
+    /// Changes of the documents whose diagnostics aren't published yet.
+    debouncer: Arc<Debouncer>,
+    /// Documents read from their files, and the closed ones.
+    disk_cache: DiskCache,
 }
 
 impl Backend {
-    pub async fn open_file(&self, url: &Url, source_code: &str) {
-        let mut wr = self.map.write().await;
-        wr.insert(url.clone(), TextDocument::new(source_code));
+    pub fn new(client: Client) -> Self {
+        Self {
+            schema: Arc::new(SchemaStore::new(client.clone())),
+            metrics: Arc::new(RequestMetrics::new(client.clone())),
+            limits: Arc::new(RequestLimits::default()),
+            client,
+            map: Default::default(),
+            client_capabilities: OnceLock::new(),
+            position_encoding: OnceLock::new(),
+            settings: Default::default(),
+            paths: Default::default(),
+            baseline: Default::default(),
+            debouncer: Default::default(),
+            disk_cache: DiskCache::default(),
+        }
     }
 
-    pub async fn update_file(&self, url: &Url, change: &TextDocumentContentChangeEvent) {
+    /// Opens the document, replacing its state if it's open already.
+    pub async fn open_file(&self, url: &Url, source_code: &str, version: i32) {
+        let mut document = TextDocument::with_format(source_code, ConfigFormat::from_uri(url));
+        // a new document has no version yet
+        let _ = document.set_version(version);
+        let replaced = self.map.write().await.insert(url.clone(), document);
+
+        // Diagnostics published for a differently spelled URI would linger.
+        if let Some(replaced) = replaced.filter(|replaced| replaced != url) {
+            if self.uses_push_diagnostics() {
+                self.client
+                    .publish_diagnostics(replaced, vec![], None)
+                    .await;
+            }
+        }
+    }
+
+    /// Applies the changes of a version of the document. Requests don't see
+    /// the version before its changes are applied, e.g. the checksum of the
+    /// version.
+    pub async fn update_file(
+        &self,
+        url: &Url,
+        version: i32,
+        changes: &[TextDocumentContentChangeEvent],
+    ) -> Result<(), DocumentError> {
+        let position_encoding = self.position_encoding();
         let mut wr = self.map.write().await;
-        if let Some(document) = wr.get_mut(url) {
-            document
-                .apply_content_change(change, PositionEncodingKind::UTF16)
-                .unwrap();
+        let mut desynced = wr.is_desynced(url);
+        let Some(document) = wr.get_mut(url) else {
+            return Ok(());
+        };
+
+        // changes arriving out of order would be applied to the wrong content
+        document.set_version(version)?;
+        let mut synced = false;
+        for change in changes {
+            match change.range {
+                Some(range) => trace!(?range, "range change"),
+                None => trace!("full text change"),
+            }
+            // incremental changes of a desynced document apply to the wrong
+            // content, only its whole content syncs it again
+            if change.range.is_some() && desynced {
+                continue;
+            }
+            document.apply_content_change(change, position_encoding)?;
+            if change.range.is_none() {
+                desynced = false;
+                synced = true;
+            }
+        }
+        if synced {
+            wr.mark_synced(url);
         }
+
+        Ok(())
+    }
+
+    /// Checks the content of the document against the client's, requesting a
+    /// resync on a mismatch.
+    pub async fn document_checksum(&self, params: DocumentChecksumParams) {
+        let url = params.text_document.uri;
+        let checked = match self.map.read().await.get(&url) {
+            // the checksum of another version is of other changes
+            Some(document) if document.version() == Some(params.text_document.version) => {
+                document.check_content(params.length, params.hash, self.position_encoding())
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = checked {
+            self.resync_file(&url, e).await;
+        }
+    }
+
+    /// Asks the client to send the content of the document again, which is
+    /// out of sync with it, or the user to reopen it when the client doesn't
+    /// handle `flb/resyncDocument`. Until then, the document isn't analyzed
+    /// and requests about it get empty responses.
+    async fn resync_file(&self, url: &Url, error: DocumentError) {
+        warn!(%url, %error, "invalid change");
+        if !self.map.write().await.mark_desynced(url) {
+            // already requested
+            return;
+        }
+        self.debouncer.cancel(url);
+        if self.uses_push_diagnostics() {
+            self.client
+                .publish_diagnostics(url.clone(), vec![], None)
+                .await;
+        }
+
+        let reopen = t!(
+            "desynced-document",
+            name = url.path().rsplit('/').next().unwrap_or_default(),
+        );
+        if !self.supports_request(ResyncDocument::METHOD) {
+            self.client.show_message(MessageType::WARNING, reopen).await;
+            return;
+        }
+
+        // Don't block the handling of following notifications on the client.
+        let client = self.client.clone();
+        let params = ResyncDocumentParams {
+            text_document: TextDocumentIdentifier::new(url.clone()),
+        };
+        tokio::spawn(async move {
+            if let Err(e) = client.send_request::<ResyncDocument>(params).await {
+                warn!(error = %e, "resync request failed");
+                client.show_message(MessageType::WARNING, reopen).await;
+            }
+        });
+    }
+
+    /// Position encoding negotiated with the client.
+    pub fn position_encoding(&self) -> PositionEncodingKind {
+        self.position_encoding
+            .get()
+            .copied()
+            .unwrap_or(PositionEncodingKind::UTF16)
+    }
+
+    /// Converts a position sent by the client into a point of the document.
+    pub async fn get_point(&self, url: &Url, position: &Position) -> Option<Point> {
+        let r = self.map.read().await;
+        let document = r.get(url)?;
+        position_to_point(&document.rope, position, self.position_encoding()).ok()
     }
 
     pub async fn get_section_type_at_point(
@@ -50,27 +490,24 @@ impl Backend {
         point: &Point,
     ) -> Option<FlbSectionType> {
         let r = self.map.read().await;
-        let TextDocument { rope, tree, .. } = r.get(url)?;
+        let TextDocument {
+            rope, tree, format, ..
+        } = r.get(url)?;
         let Some(tree) = tree else {
             // could this happen?
             return None;
         };
+        if *format == ConfigFormat::Yaml {
+            let byte = point_to_byte(rope, *point)?;
+            return yaml::section_at(tree, rope, byte, point.column)
+                .map(|(section_type, _)| section_type);
+        }
 
         let node = tree
             .root_node()
             .descendant_for_point_range(*point, *point)?;
 
-        self.client
-            .log_message(
-                MessageType::INFO,
-                format!(
-                    "node.kind: {:?} / node: {:?} / point: {:?}",
-                    node.kind(),
-                    node.clone(),
-                    point
-                ),
-            )
-            .await;
+        trace!(kind = node.kind(), ?node, ?point, "node at point");
 
         match node.kind() {
             "section_body" => {
@@ -81,7 +518,7 @@ impl Backend {
                     None
                 }
             }
-            "key_type" => {
+            "key_type" | "value_type" => {
                 // should go up parent tree until it finds section node
                 let mut parent = node.parent();
                 while let Some(p) = parent {
@@ -102,8 +539,7 @@ impl Backend {
                 .child_by_field_name("header")
                 .and_then(|n| n.child_by_field_name("name"))
             {
-                let byte_range = section_name_node.byte_range();
-                let section_name = rope.slice(byte_range).as_str()?;
+                let section_name = rope.get_byte_slice(section_name_node.byte_range())?;
                 return Some(section_name.to_string());
             }
         }
@@ -112,35 +548,247 @@ impl Backend {
 
     pub async fn get_key_at_point(&self, url: &Url, point: &Point) -> Option<String> {
         let r = self.map.read().await;
-        let TextDocument { rope, tree, .. } = r.get(url)?;
+        let TextDocument {
+            rope,
+            tree,
+            index,
+            format,
+            ..
+        } = r.get(url)?;
         let Some(tree) = tree else {
             return None;
         };
+        if *format == ConfigFormat::Yaml {
+            let byte = point_to_byte(rope, *point)?;
+            return yaml::entry_at(index, byte)
+                .filter(|entry| entry.key_range.start <= byte && byte <= entry.key_range.end)
+                .map(|entry| entry.key.clone());
+        }
         let node = tree
             .root_node()
             .descendant_for_point_range(*point, *point)?;
 
-        self.client
-            .log_message(
-                MessageType::INFO,
-                format!(
-                    "node.kind: {:?} / node: {:?} / point: {:?}",
-                    node.kind(),
-                    node.clone(),
-                    point
-                ),
-            )
-            .await;
+        trace!(kind = node.kind(), ?node, ?point, "node at point");
 
         if node.kind() == "key_type" {
-            let byte_range = node.byte_range();
-            let key = rope.slice(byte_range).as_str().unwrap();
+            let key = rope.get_byte_slice(node.byte_range())?;
             return Some(key.to_string());
         }
 
         None
     }
 
+    /// Indexed section containing the point, if any.
+    pub async fn get_indexed_section_at_point(
+        &self,
+        url: &Url,
+        point: &Point,
+    ) -> Option<IndexedSection> {
+        let r = self.map.read().await;
+        Self::indexed_section_at(r.get(url)?, point)
+    }
+
+    /// Summary of the diagnostics of the section at the point, for the hover of
+    /// its `Name`.
+    async fn get_problems_note(&self, url: &Url, point: &Point) -> Option<String> {
+        let range = {
+            let r = self.map.read().await;
+            let document = r.get(url)?;
+            let section = Self::indexed_section_at(document, point)?;
+            byte_range_to_lsp_range(&document.rope, section.byte_range, self.position_encoding())
+        };
+        let diagnostics = self.get_diagnostics(url).await?;
+
+        problems_note(&diagnostics, range)
+    }
+
+    fn indexed_section_at(document: &TextDocument, point: &Point) -> Option<IndexedSection> {
+        let TextDocument {
+            rope,
+            tree,
+            index,
+            format,
+            ..
+        } = document;
+        let byte = point_to_byte(rope, *point)?;
+        match format {
+            ConfigFormat::Classic => index.section_at(byte).cloned(),
+            ConfigFormat::Yaml => {
+                let (_, start) = yaml::section_at(tree.as_ref()?, rope, byte, point.column)?;
+                let start = start?;
+                index
+                    .sections()
+                    .iter()
+                    .find(|section| section.byte_range.start == start)
+                    .cloned()
+            }
+        }
+    }
+
+    /// Plugin name at the point, i.e. the value of a `Name` entry.
+    pub async fn get_plugin_name_at_point(&self, url: &Url, point: &Point) -> Option<String> {
+        let r = self.map.read().await;
+        let TextDocument {
+            rope,
+            tree,
+            index,
+            format,
+            ..
+        } = r.get(url)?;
+        if *format == ConfigFormat::Yaml {
+            let byte = point_to_byte(rope, *point)?;
+            let entry = yaml::entry_at(index, byte)?;
+            let value_range = entry.value_range.as_ref()?;
+            return (entry.key.eq_ignore_ascii_case("name")
+                && value_range.start <= byte
+                && byte <= value_range.end)
+                .then(|| entry.value.clone())
+                .flatten();
+        }
+
+        let node = tree
+            .as_ref()?
+            .root_node()
+            .descendant_for_point_range(*point, *point)?;
+        if node.kind() != "value_type" {
+            return None;
+        }
+
+        let key_node = node.parent()?.child_by_field_name("key")?;
+        let key = rope.get_byte_slice(key_node.byte_range())?;
+        if !key.to_string().eq_ignore_ascii_case("name") {
+            return None;
+        }
+
+        let value = rope.get_byte_slice(node.byte_range())?.to_string();
+        Some(value.trim().to_string())
+    }
+
+    /// Handles to what the diagnostics of the documents depend on.
+    fn analyzer(&self) -> Analyzer {
+        Analyzer {
+            client: self.client.clone(),
+            map: self.map.clone(),
+            settings: self.settings.clone(),
+            schema: self.schema.clone(),
+            paths: self.paths.clone(),
+            baseline: self.baseline.clone(),
+            position_encoding: self.position_encoding(),
+            notifies_analyzed: self.supports_notification(DocumentAnalyzed::METHOD),
+        }
+    }
+
+    /// Diagnostics of the document, `None` if it isn't open.
+    pub async fn get_diagnostics(&self, url: &Url) -> Option<Vec<Diagnostic>> {
+        self.analyzer().get_diagnostics(url).await
+    }
+
+    /// Drops the diagnostics of the document the baseline records.
+    async fn suppress_baselined(&self, url: &Url, rope: &Rope, diagnostics: &mut Vec<Diagnostic>) {
+        self.analyzer()
+            .suppress_baselined(url, rope, diagnostics)
+            .await;
+    }
+
+    /// Diagnostics of the document, sending `flb/documentAnalyzed` to clients
+    /// handling it.
+    async fn analyze_document(&self, url: &Url) -> Option<Vec<Diagnostic>> {
+        self.analyzer().analyze_document(url).await
+    }
+
+    /// Publishes the diagnostics of the document, if the client doesn't pull
+    /// them.
+    async fn push_diagnostics(&self, url: &Url, version: Option<i32>) {
+        if self.uses_push_diagnostics() {
+            self.analyzer().publish(url, version).await;
+        }
+    }
+
+    /// Reads the baseline of the `baselinePath` setting.
+    async fn load_baseline(&self) {
+        let Some(path) = self.settings.read().await.baseline_path.clone() else {
+            return;
+        };
+
+        match Baseline::load(&path).await {
+            Ok(baseline) => *self.baseline.write().await = baseline,
+            Err(e) => {
+                let message = t!("baseline-load-failed", path = path.display(), error = e);
+                self.client
+                    .show_message(MessageType::WARNING, message)
+                    .await;
+            }
+        }
+    }
+
+    /// Diagnostics of the content of a document, which don't depend on the
+    /// filesystem.
+    pub(crate) fn analyze(
+        data: &FlbData,
+        document: &TextDocument,
+        position_encoding: PositionEncodingKind,
+    ) -> Vec<Diagnostic> {
+        let TextDocument {
+            rope, tree, index, ..
+        } = document;
+        let Some(tree) = tree else {
+            return Vec::new();
+        };
+        let encoding_problem = EncodingProblem::of(document);
+        if let Some(problem) = encoding_problem.filter(|problem| !problem.is_analyzable()) {
+            return vec![problem.diagnostic(rope, position_encoding)];
+        }
+
+        let mut diagnostics = match document.format {
+            ConfigFormat::Classic => {
+                Self::get_misplaced_comment_diagnostics(tree, rope, position_encoding)
+            }
+            ConfigFormat::Yaml => yaml::syntax_errors(tree, rope, position_encoding),
+        };
+        diagnostics.extend(Self::get_service_key_diagnostics(
+            index,
+            rope,
+            position_encoding,
+        ));
+        diagnostics.extend(check_conditional_requirements(
+            index,
+            rope,
+            position_encoding,
+        ));
+        diagnostics.extend(check_duplicate_keys(index, rope, position_encoding));
+        diagnostics.extend(check_incompatible_values(index, rope, position_encoding));
+        diagnostics.extend(check_mem_buf_limits(index, rope, position_encoding));
+        if document.format == ConfigFormat::Classic {
+            diagnostics.extend(check_section_names(index, rope, position_encoding));
+            diagnostics.extend(check_section_order(index, rope, position_encoding));
+            diagnostics.extend(check_yaml_only_keys(index, rope, position_encoding));
+            diagnostics.extend(check_misplaced_service_keys(
+                data,
+                index,
+                rope,
+                position_encoding,
+            ));
+        }
+        diagnostics.extend(check_values(data, index, rope, position_encoding));
+        diagnostics.extend(check_plugin_names(data, index, rope, position_encoding));
+        diagnostics.extend(check_unknown_keys(data, index, rope, position_encoding));
+        diagnostics.extend(lints::check_bom(rope, position_encoding));
+        diagnostics
+            .extend(encoding_problem.map(|problem| problem.diagnostic(rope, position_encoding)));
+        diagnostics.extend(lints::check_trailing_whitespace(
+            index,
+            rope,
+            position_encoding,
+        ));
+
+        diagnostics
+    }
+
+    /// Whether the document is excluded from the analysis by the settings.
+    async fn is_excluded(&self, url: &Url) -> bool {
+        is_excluded(url, &self.settings.read().await.exclude)
+    }
+
     /// There are some false-positive ERROR nodes in AST, due to reason below
     /// (https://github.com/sh-cho/tree-sitter-fluentbit/pull/20)
     /// So only simple check is done for now...
@@ -152,14 +800,12 @@ impl Backend {
     ///     #...
     /// ```
     ///
-    pub async fn get_diagnostics(&self, url: &Url) -> Option<Vec<Diagnostic>> {
-        let r = self.map.read().await;
-        let TextDocument { tree, .. } = r.get(url)?;
-        let Some(tree) = tree else { return None };
-
+    fn get_misplaced_comment_diagnostics(
+        tree: &Tree,
+        rope: &Rope,
+        position_encoding: PositionEncodingKind,
+    ) -> Vec<Diagnostic> {
         let mut diagnostics = Vec::new();
-        let root = tree.root_node();
-        let mut cursor = root.walk();
 
         // So, Find "ERROR" node and check if it has "comment" node inside.
         // --
@@ -171,165 +817,1121 @@ impl Backend {
         //         comment [7, 20] - [7, 25]
         // ...
 
-        'outer: loop {
-            if cursor.node().kind() == "ERROR" {
-                let error_node = cursor.node();
-                let mut error_cursor = error_node.walk();
-                while error_cursor.goto_first_child() {
-                    if error_cursor.node().kind() == "comment" {
-                        let range = error_cursor.node().range();
-                        let diagnostic = Diagnostic::new_simple(
-                            Range::new(
-                                Position::new(
-                                    range.start_point.row as u32,
-                                    range.start_point.column as u32,
-                                ),
-                                Position::new(
-                                    range.end_point.row as u32,
-                                    range.end_point.column as u32,
-                                ),
-                            ),
-                            r"Comment is not allowed here.".to_string(),
-                        );
-                        diagnostics.push(diagnostic);
-                    }
-                }
-            }
-
-            // Traverse
-            if cursor.goto_first_child() {
-                continue 'outer;
-            }
-            if cursor.goto_next_sibling() {
-                continue 'outer;
-            }
-
-            'inner: loop {
-                if !cursor.goto_parent() {
-                    break 'outer;
-                }
-
-                if cursor.goto_next_sibling() {
-                    break 'inner;
-                }
+        let errors = descendants(tree.root_node()).filter(|node| node.kind() == "ERROR");
+        for error_node in errors {
+            // first children, down from the error
+            let first_children = std::iter::successors(error_node.child(0), |node| node.child(0));
+            for node in first_children.filter(|node| node.kind() == "comment") {
+                let diagnostic = Diagnostic {
+                    range: to_lsp_range(rope, node.range(), position_encoding),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: Some(NumberOrString::String(lints::MISPLACED_COMMENT.to_string())),
+                    message: t!(lints::MISPLACED_COMMENT),
+                    ..Diagnostic::default()
+                };
+                diagnostics.push(diagnostic);
             }
         }
 
-        Some(diagnostics)
+        diagnostics
     }
-}
 
-#[tower_lsp::async_trait]
-impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> JsonRpcResult<InitializeResult> {
-        Ok(InitializeResult {
-            server_info: None,
-            capabilities: ServerCapabilities {
-                text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::INCREMENTAL,
-                )),
-                completion_provider: Some(CompletionOptions {
-                    resolve_provider: Some(false),
-                    trigger_characters: None,
-                    all_commit_characters: None,
-                    work_done_progress_options: Default::default(),
-                    completion_item: Some(CompletionOptionsCompletionItem {
-                        label_details_support: Some(true),
-                    }),
-                }),
-                hover_provider: Some(HoverProviderCapability::Simple(true)),
-                diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
-                    // TODO: Real diagnostics
-                    DiagnosticOptions {
-                        identifier: None,
-                        inter_file_dependencies: false,
-                        workspace_diagnostics: false,
-                        work_done_progress_options: Default::default(),
-                    },
-                )),
-                ..ServerCapabilities::default()
-            },
-        })
+    /// Warns about keys of `[SERVICE]` sections which fluent-bit doesn't know.
+    fn get_service_key_diagnostics(
+        index: &SectionIndex,
+        rope: &Rope,
+        position_encoding: PositionEncodingKind,
+    ) -> Vec<Diagnostic> {
+        index
+            .sections()
+            .iter()
+            .filter(|section| section.section_type == FlbSectionType::Service)
+            .flat_map(|section| &section.entries)
+            .filter(|entry| !is_service_key(&entry.key))
+            .map(|entry| Diagnostic {
+                range: byte_range_to_lsp_range(rope, entry.key_range.clone(), position_encoding),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(UNKNOWN_SERVICE_KEY.to_string())),
+                message: t!(UNKNOWN_SERVICE_KEY, key = entry.key),
+                ..Diagnostic::default()
+            })
+            .collect()
     }
 
-    async fn initialized(&self, _: InitializedParams) {
-        self.client
-            .log_message(MessageType::INFO, "fluent-bit language server initialized")
-            .await;
+    /// Format of the document, classic if it isn't open.
+    async fn get_format(&self, url: &Url) -> ConfigFormat {
+        let map = self.map.read().await;
+        map.get(url)
+            .map(|document| document.format)
+            .unwrap_or_default()
     }
 
-    async fn shutdown(&self) -> JsonRpcResult<()> {
-        Ok(())
+    /// Completions of the variables of the document, when a `${NAME}`
+    /// reference is being typed at the point.
+    async fn get_variable_completion(
+        &self,
+        url: &Url,
+        point: &Point,
+    ) -> Option<Vec<CompletionItem>> {
+        let map = self.map.read().await;
+        let document = map.get(url)?;
+        let byte = point_to_byte(&document.rope, *point)?;
+        let closed = variables::typed_reference(&document.rope, byte)?;
+
+        Some(variables::completion_items(
+            &effective::variables(document),
+            closed,
+        ))
     }
 
-    async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        self.client
-            .log_message(
-                MessageType::INFO,
-                format!("file opened / {}", params.text_document.uri),
-            )
-            .await;
+    /// Completions of the variables the configuration references without
+    /// setting them, when a key of the `env` of a YAML document is being typed
+    /// at the point.
+    async fn get_env_completion(&self, url: &Url, point: &Point) -> Option<Vec<CompletionItem>> {
+        {
+            let map = self.map.read().await;
+            let document = map
+                .get(url)
+                .filter(|document| document.format == ConfigFormat::Yaml)?;
+            let byte = point_to_byte(&document.rope, *point)?;
+            if !yaml::env_key_at(&document.rope, byte) {
+                return None;
+            }
+        }
 
-        let url = params.text_document.uri;
-        let source_code = params.text_document.text.as_str();
+        // references span the files the document includes
+        let graph = self.get_include_graph(std::slice::from_ref(url)).await;
+        let known_variables = self.settings.read().await.known_variables.clone();
+        let undefined = graph.undefined_variable_references(url, &known_variables);
 
-        self.open_file(&url, source_code).await;
+        Some(yaml_env::completion_items(&undefined))
     }
 
-    async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        self.client
-            .log_message(
-                MessageType::INFO,
-                format!("did_change: {}", params.text_document.uri),
-            )
-            .await;
-
-        let url = params.text_document.uri;
+    /// Completion of the conventional tags of the input whose `Tag` is edited
+    /// at the point.
+    async fn get_tag_completion(&self, url: &Url, point: &Point) -> Option<Vec<CompletionItem>> {
+        let map = self.map.read().await;
+        let document = map.get(url)?;
+        let byte = point_to_byte(&document.rope, *point)?;
+        let (section, entry) = signature_help::value_entry_at(document, byte)?;
+        if section.section_type != FlbSectionType::Input || !entry.key.eq_ignore_ascii_case("Tag") {
+            return None;
+        }
 
-        for c in params.content_changes {
-            // assume only changes
-            if let Some(range) = c.range {
-                self.client
-                    .log_message(MessageType::INFO, format!("range: {:?}", range))
-                    .await;
+        let value_range = entry.value_range.clone().unwrap_or(byte..byte);
+        let range = byte_range_to_lsp_range(
+            &document.rope,
+            value_range.start.min(byte)..value_range.end.max(byte),
+            self.position_encoding(),
+        );
 
-                self.update_file(&url, &c).await;
-            } else {
-                self.client
-                    .log_message(MessageType::INFO, "full text change".to_string())
-                    .await;
-            }
-        }
+        Some(tag_completion_items(&document.index, section, range))
     }
 
-    async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        self.client
-            .log_message(
-                MessageType::INFO,
-                format!("did_close: {}", params.text_document.uri),
-            )
-            .await;
-
-        let url = params.text_document.uri;
-        // self.map.borrow_mut()
-        //     .remove(&url);
+    /// Hover of the `${NAME}` reference at the point.
+    async fn get_variable_hover(&self, url: &Url, point: &Point) -> Option<String> {
+        let map = self.map.read().await;
+        let document = map.get(url)?;
+        let byte = point_to_byte(&document.rope, *point)?;
+        let reference = variables::reference_at(&document.index, byte)?;
 
-        self.map.write().await.remove(&url);
+        Some(variables::hover(
+            &reference,
+            &effective::variables(document),
+        ))
     }
 
-    async fn hover(&self, params: HoverParams) -> JsonRpcResult<Option<Hover>> {
+    /// Hover of the variable the `env` of a YAML document sets at the
+    /// position, with where it's referenced.
+    async fn get_env_hover(&self, url: &Url, position: Position) -> Option<String> {
+        let on_variable = {
+            let map = self.map.read().await;
+            let document = map.get(url)?;
+            yaml_env::env_variables(document, self.position_encoding())
+                .iter()
+                .any(|variable| variable.range.start <= position && position <= variable.range.end)
+        };
+        if !on_variable {
+            return None;
+        }
+
+        let graph = self.get_include_graph(std::slice::from_ref(url)).await;
+        graph.env_variable_note(url, position)
+    }
+
+    /// Word typed before the point, up to the previous whitespace.
+    async fn get_word_before_point(&self, url: &Url, point: &Point) -> Option<String> {
+        let map = self.map.read().await;
+        let line = map.get(url)?.rope.get_line(point.row)?;
+        let head = line.get_byte_slice(..point.column)?.to_string();
+        let word = head.rsplit(char::is_whitespace).next().unwrap_or_default();
+
+        Some(word.to_string())
+    }
+
+    /// Width of the `- ` of the YAML sequence items opened on the line before
+    /// the point, by which the following lines of completions are indented.
+    async fn get_sequence_item_indent(&self, url: &Url, point: &Point) -> usize {
+        let map = self.map.read().await;
+        let Some(document) = map.get(url) else {
+            return 0;
+        };
+        if document.format != ConfigFormat::Yaml {
+            return 0;
+        }
+        let Some(head) = document
+            .rope
+            .get_line(point.row)
+            .and_then(|line| line.get_byte_slice(..point.column))
+        else {
+            return 0;
+        };
+
+        let head = head.to_string();
+        let mut rest = head.trim_start();
+        while let Some(after) = rest
+            .strip_prefix('-')
+            .filter(|after| after.starts_with(' '))
+        {
+            rest = after.trim_start();
+        }
+        head.trim_start().len() - rest.len()
+    }
+
+    /// Parser definition or reference at the position.
+    async fn get_parser_symbol_at(&self, url: &Url, position: &Position) -> Option<ParserSymbol> {
+        let map = self.map.read().await;
+        let document = map.get(url)?;
+        let location = resolve_position(&document.rope, position, self.position_encoding()).ok()?;
+        parser_symbol_at(&document.index, location.byte_idx)
+    }
+
+    /// Parser or `Tag` at the position.
+    async fn get_reference_target_at(
+        &self,
+        url: &Url,
+        position: &Position,
+    ) -> Option<ReferenceTarget> {
+        let map = self.map.read().await;
+        let document = map.get(url)?;
+        let location = resolve_position(&document.rope, position, self.position_encoding()).ok()?;
+        if let Some(symbol) = parser_symbol_at(&document.index, location.byte_idx) {
+            return Some(ReferenceTarget::Parser(symbol.name));
+        }
+
+        tag_at(&document.index, location.byte_idx).map(ReferenceTarget::Tag)
+    }
+
+    /// References of the target in the document, and its definitions if
+    /// `include_declaration`, i.e. the parsers of the name or the inputs
+    /// tagging records with it.
+    fn find_references_in(
+        target: &ReferenceTarget,
+        url: &Url,
+        document: &TextDocument,
+        include_declaration: bool,
+        position_encoding: PositionEncodingKind,
+    ) -> Vec<Location> {
+        let index = &document.index;
+        let ranges = match target {
+            ReferenceTarget::Parser(name) => parser_symbols(index)
+                .into_iter()
+                .filter(|symbol| symbol.name == *name)
+                .filter(|symbol| include_declaration || symbol.kind == ParserSymbolKind::Reference)
+                .map(|symbol| symbol.range)
+                .collect(),
+            ReferenceTarget::Tag(tag) => {
+                let mut ranges = tag_references(index, tag);
+                if include_declaration {
+                    ranges.extend(tags(index).filter(|(t, _)| t == tag).map(|(_, r)| r));
+                }
+                ranges
+            }
+        };
+
+        ranges
+            .into_iter()
+            .map(|range| {
+                let range = byte_range_to_lsp_range(&document.rope, range, position_encoding);
+                Location::new(url.clone(), range)
+            })
+            .collect()
+    }
+
+    /// Directory of the document and pattern of the include on the line of
+    /// `position`, e.g. `@INCLUDE inputs/*.conf`.
+    async fn get_include_at(
+        &self,
+        url: &Url,
+        position: &Position,
+    ) -> Option<(Option<String>, String)> {
+        let map = self.map.read().await;
+        let document = map.get(url)?;
+        let include = document.index.includes().iter().find(|include| {
+            document.rope.try_byte_to_line(include.range.start).ok() == Some(position.line as usize)
+        })?;
+
+        Some((document_dir(url), include.pattern.clone()))
+    }
+
+    /// Definitions and references of the parser in every open document, and
+    /// in the included files and closed documents read from their files.
+    async fn find_parser_symbols(&self, name: &str) -> Vec<(Url, Range, ParserSymbolKind)> {
+        let exclude = self.settings.read().await.exclude.clone();
+        let graph = self.get_workspace_graph().await;
+        let symbols = |uri: &Url, document: &TextDocument| {
+            parser_symbols(&document.index)
+                .into_iter()
+                .filter(|symbol| symbol.name == name)
+                .map(|symbol| {
+                    let range = byte_range_to_lsp_range(
+                        &document.rope,
+                        symbol.range,
+                        self.position_encoding(),
+                    );
+                    (uri.clone(), range, symbol.kind)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut ret = Vec::new();
+        let map = self.map.read().await;
+        for uri in map.uris() {
+            let Some(document) = map.get(uri).filter(|_| !is_excluded(uri, &exclude)) else {
+                continue;
+            };
+            ret.extend(symbols(uri, document));
+        }
+        for (uri, document) in &graph.loaded {
+            ret.extend(symbols(uri, document));
+        }
+
+        ret
+    }
+
+    /// Whether diagnostics have to be published by the server, the client
+    /// not supporting pull diagnostics (`textDocument/diagnostic`).
+    fn uses_push_diagnostics(&self) -> bool {
+        self.client_capabilities
+            .get()
+            .and_then(|c| c.text_document.as_ref())
+            .map_or(true, |t| t.diagnostic.is_none())
+    }
+
+    /// Extensions the client declared that it handles.
+    fn client_extensions(&self) -> ClientExtensionCapabilities {
+        self.client_capabilities
+            .get()
+            .and_then(|c| c.experimental.clone())
+            .and_then(|experimental| {
+                serde_json::from_value::<ClientExperimentalCapabilities>(experimental).ok()
+            })
+            .map(|experimental| experimental.fluent_bit)
+            .unwrap_or_default()
+    }
+
+    /// Whether the client declared that it handles the server notification.
+    fn supports_notification(&self, method: &str) -> bool {
+        self.client_extensions()
+            .notifications
+            .iter()
+            .any(|notification| notification == method)
+    }
+
+    /// Whether the client declared that it handles the server request.
+    fn supports_request(&self, method: &str) -> bool {
+        self.client_extensions()
+            .requests
+            .iter()
+            .any(|request| request == method)
+    }
+
+    /// Edit fixing all the problems of the document which have a fix.
+    async fn get_fix_all_edit(&self, uri: &Url) -> Option<WorkspaceEdit> {
+        let diagnostics = self.get_diagnostics(uri).await?;
+        fixes::fix_all(
+            uri,
+            &diagnostics,
+            supports_change_annotations(self.client_capabilities.get()),
+        )
+    }
+
+    /// `source.fixAll` action, whose annotated edit clients can preview. Other
+    /// clients get a command applying the edit instead.
+    async fn fix_all_action(&self, uri: &Url) -> Option<CodeActionOrCommand> {
+        let edit = self.get_fix_all_edit(uri).await?;
+        let title = t!("fix-all");
+
+        let action = if supports_change_annotations(self.client_capabilities.get()) {
+            CodeAction {
+                title,
+                kind: Some(CodeActionKind::SOURCE_FIX_ALL),
+                edit: Some(edit),
+                ..CodeAction::default()
+            }
+        } else {
+            CodeAction {
+                command: Some(Command::new(
+                    title.clone(),
+                    FIX_ALL_COMMAND.to_string(),
+                    Some(vec![Value::String(uri.to_string())]),
+                )),
+                title,
+                kind: Some(CodeActionKind::SOURCE_FIX_ALL),
+                ..CodeAction::default()
+            }
+        };
+
+        Some(CodeActionOrCommand::CodeAction(action))
+    }
+
+    async fn fix_all(&self, arguments: Vec<Value>) -> JsonRpcResult<()> {
+        let uri: Url = command_args(arguments)?;
+
+        let Some(edit) = self.get_fix_all_edit(&uri).await else {
+            return Ok(());
+        };
+        match self.client.apply_edit(edit).await {
+            Ok(response) if !response.applied => {
+                warn!(
+                    reason = response.failure_reason.as_deref(),
+                    "fixes were not applied"
+                );
+            }
+            Ok(_) => {}
+            Err(e) => warn!(error = %e, "applying fixes failed"),
+        }
+
+        Ok(())
+    }
+
+    /// Keys of the open documents and of the files they include which were
+    /// renamed or removed between two versions, renamed when `apply` is set.
+    async fn migration_report(
+        &self,
+        arguments: Vec<Value>,
+    ) -> JsonRpcResult<MigrationReportResult> {
+        let args: MigrationReportArguments = command_args(arguments)?;
+        let parse = |version: &str| {
+            version
+                .parse::<FlbVersion>()
+                .map_err(|e| JsonRpcError::invalid_params(e.to_string()))
+        };
+        let (from, to) = (parse(&args.from)?, parse(&args.to)?);
+
+        let position_encoding = self.position_encoding();
+        let exclude = self.settings.read().await.exclude.clone();
+        let urls: Vec<Url> = self.map.read().await.uris().cloned().collect();
+        // the included files which aren't open, and the closed documents
+        let graph = self.get_workspace_graph().await;
+
+        let mut changes = Vec::new();
+        let mut collect = |url: &Url, document: &TextDocument| {
+            for key in migrated_keys(&document.index, Some(from), Some(to)) {
+                changes.push(MigrationChange {
+                    uri: url.clone(),
+                    range: byte_range_to_lsp_range(&document.rope, key.range, position_encoding),
+                    message: key.message,
+                    replacement: key.replacement,
+                });
+            }
+        };
+        {
+            let map = self.map.read().await;
+            for url in &urls {
+                if let Some(document) = map.get(url).filter(|_| !is_excluded(url, &exclude)) {
+                    collect(url, document);
+                }
+            }
+        }
+        for (url, document) in &graph.loaded {
+            collect(url, document);
+        }
+
+        if args.apply && changes.iter().any(|change| change.replacement.is_some()) {
+            let mut builder = WorkspaceEditBuilder::new(false);
+            for change in &changes {
+                if let Some(replacement) = &change.replacement {
+                    builder.edit(&change.uri, TextEdit {
+                        range: change.range,
+                        new_text: replacement.clone(),
+                    });
+                }
+            }
+            let response = self.client.apply_edit(builder.build()).await?;
+            if !response.applied {
+                warn!(
+                    reason = response.failure_reason.as_deref(),
+                    "migration was not applied"
+                );
+            }
+        }
+
+        Ok(MigrationReportResult {
+            from: from.to_string(),
+            to: to.to_string(),
+            changes,
+        })
+    }
+
+    /// Whether the client can be asked to pull diagnostics again.
+    fn supports_diagnostic_refresh(&self) -> bool {
+        self.client_capabilities
+            .get()
+            .and_then(|c| c.workspace.as_ref()?.diagnostic.as_ref()?.refresh_support)
+            .unwrap_or(false)
+    }
+
+    /// Loads the schema again and updates the diagnostics of every document,
+    /// which depend on the plugins it knows.
+    async fn reload_schema(&self) {
+        self.schema.invalidate().await;
+        let data = self.schema.get().await;
+        info!(version = %data.flb_version, "using fluent-bit schema");
+
+        if self.uses_push_diagnostics() {
+            let urls: Vec<Url> = self.map.read().await.uris().cloned().collect();
+            for url in urls {
+                self.push_diagnostics(&url, None).await;
+            }
+        } else if self.supports_diagnostic_refresh() {
+            if let Err(e) = self.client.workspace_diagnostic_refresh().await {
+                warn!(error = %e, "diagnostic refresh failed");
+            }
+        }
+    }
+
+    /// `$/setTrace`: traces the events below warnings to the client unless
+    /// it's `off`, and the latency of each message if it's `verbose`.
+    pub async fn set_trace(&self, params: SetTraceParams) {
+        self.apply_trace(params.value);
+    }
+
+    fn apply_trace(&self, trace: TraceValue) {
+        logging::set_trace(trace);
+        self.metrics.set_trace(trace == TraceValue::Verbose);
+    }
+
+    pub async fn grammar_info(&self) -> JsonRpcResult<GrammarInfoResult> {
+        Ok(grammar::grammar_info())
+    }
+
+    pub async fn server_status(&self) -> JsonRpcResult<ServerStatusResult> {
+        Ok(ServerStatusResult {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            open_documents: self.map.read().await.len(),
+            schema_path: self.schema.schema_path().await,
+            latency: self.metrics.latencies(),
+        })
+    }
+
+    pub async fn plugins(&self, params: PluginsParams) -> JsonRpcResult<PluginsResult> {
+        let data = self.schema.get().await;
+
+        Ok(PluginsResult {
+            fluent_bit_version: data.flb_version.clone(),
+            plugins: data.get_plugin_infos(&params.section_type, &FLB_DATA),
+        })
+    }
+
+    pub async fn pipeline_hierarchy(
+        &self,
+        params: PipelineHierarchyParams,
+    ) -> JsonRpcResult<PipelineHierarchyResult> {
+        let uri = params.text_document.uri;
+        let map = self.map.read().await;
+        let document = map
+            .get(&uri)
+            .ok_or_else(|| JsonRpcError::invalid_params(format!("unknown document {uri}")))?;
+
+        Ok(pipeline_hierarchy(
+            &uri,
+            &document.index,
+            &document.rope,
+            self.position_encoding(),
+        ))
+    }
+
+    pub async fn embedded_documents(
+        &self,
+        params: EmbeddedDocumentsParams,
+    ) -> JsonRpcResult<Vec<EmbeddedDocumentInfo>> {
+        let uri = params.text_document.uri;
+        let map = self.map.read().await;
+        let document = map
+            .get(&uri)
+            .ok_or_else(|| JsonRpcError::invalid_params(format!("unknown document {uri}")))?;
+
+        Ok(embedded::extract(document, self.position_encoding())
+            .iter()
+            .map(|embedded| embedded.to_info())
+            .collect())
+    }
+
+    pub async fn effective_section(
+        &self,
+        params: EffectiveSectionParams,
+    ) -> JsonRpcResult<Option<EffectiveSectionResult>> {
+        let data = self.schema.get().await;
+        let uri = params.text_document.uri;
+        let map = self.map.read().await;
+        let document = map
+            .get(&uri)
+            .ok_or_else(|| JsonRpcError::invalid_params(format!("unknown document {uri}")))?;
+
+        let position_encoding = self.position_encoding();
+        let Some(section) = position_to_point(&document.rope, &params.position, position_encoding)
+            .ok()
+            .and_then(|point| Self::indexed_section_at(document, &point))
+        else {
+            return Ok(None);
+        };
+        Ok(Some(effective::effective_section(
+            &data,
+            &section,
+            &effective::variables(document),
+            &document.rope,
+            position_encoding,
+        )))
+    }
+
+    pub async fn settings_schema(&self) -> JsonRpcResult<serde_json::Value> {
+        Ok(Settings::json_schema())
+    }
+
+    pub async fn workspace_stats(&self) -> JsonRpcResult<WorkspaceStatsResult> {
+        let data = self.schema.get().await;
+        let urls: Vec<Url> = self.map.read().await.uris().cloned().collect();
+
+        let mut stats = WorkspaceStatsResult::new(&data);
+        for url in urls {
+            if self.is_excluded(&url).await {
+                continue;
+            }
+            let diagnostics = self.get_diagnostics(&url).await.unwrap_or_default();
+            if let Some(document) = self.map.read().await.get(&url) {
+                stats.add_document(&data, &document.index, &diagnostics);
+            }
+        }
+
+        Ok(stats)
+    }
+
+    async fn scaffold_pipeline(&self, arguments: Vec<Value>) -> JsonRpcResult<()> {
+        let args: ScaffoldPipelineArguments = command_args(arguments)?;
+
+        let line_ending = self.settings.read().await.line_ending.unwrap_or_default();
+        let edit = scaffold_pipeline(&*self.schema.get().await, &args, line_ending)
+            .map_err(|e| JsonRpcError::invalid_params(e.to_string()))?;
+
+        let response = self.client.apply_edit(edit).await?;
+        if !response.applied {
+            return Err(JsonRpcError {
+                message: t!(
+                    "scaffold-failed",
+                    uri = args.uri,
+                    reason = response.failure_reason.unwrap_or_default(),
+                )
+                .into(),
+                ..JsonRpcError::internal_error()
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn hover_markup_options(&self) -> MarkupOptions {
+        let formats = self
+            .client_capabilities
+            .get()
+            .and_then(|c| c.text_document.as_ref())
+            .and_then(|t| t.hover.as_ref())
+            .and_then(|h| h.content_format.as_ref());
+
+        MarkupOptions::from_client_formats(
+            formats,
+            self.settings.read().await.documentation_max_length(),
+        )
+    }
+
+    async fn signature_markup_options(&self) -> MarkupOptions {
+        let formats = self
+            .client_capabilities
+            .get()
+            .and_then(|c| c.text_document.as_ref())
+            .and_then(|t| t.signature_help.as_ref())
+            .and_then(|s| s.signature_information.as_ref())
+            .and_then(|s| s.documentation_format.as_ref());
+
+        MarkupOptions::from_client_formats(
+            formats,
+            self.settings.read().await.documentation_max_length(),
+        )
+    }
+
+    /// Documentation of `key`, `[SERVICE]` keys being documented by the server.
+    fn get_parameter_info(
+        data: &FlbData,
+        section_type: &FlbSectionType,
+        key: &str,
+    ) -> Option<FlbConfigParameterInfo> {
+        let mut param_info = data.get_hover_info(section_type, key);
+        if *section_type == FlbSectionType::Service {
+            if let Some(info) = &mut param_info {
+                let id = format!("service.{}", key.to_lowercase());
+                info.description = i18n::translate_text(&id, &info.description);
+            }
+        }
+
+        param_info
+    }
+
+    async fn completion_markup_options(&self) -> MarkupOptions {
+        let formats = self
+            .client_capabilities
+            .get()
+            .and_then(|c| c.text_document.as_ref())
+            .and_then(|t| t.completion.as_ref())
+            .and_then(|c| c.completion_item.as_ref())
+            .and_then(|c| c.documentation_format.as_ref());
+
+        MarkupOptions::from_client_formats(
+            formats,
+            self.settings.read().await.documentation_max_length(),
+        )
+    }
+
+    /// Asks the client to notify changes of the runtime schema file, so that
+    /// it can be reloaded.
+    async fn watch_schema_file(&self) {
+        let Some(schema_path) = self.schema.schema_path().await else {
+            return;
+        };
+
+        let dynamic_registration = self
+            .client_capabilities
+            .get()
+            .and_then(|c| c.workspace.as_ref())
+            .and_then(|w| w.did_change_watched_files)
+            .and_then(|d| d.dynamic_registration)
+            .unwrap_or(false);
+        if !dynamic_registration {
+            return;
+        }
+
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String(schema_path.to_string_lossy().into_owned()),
+                kind: None,
+            }],
+        };
+        let registration = Registration {
+            id: "fluent-bit-language-server/schema-watcher".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            warn!(error = %e, "failed to watch fluent-bit schema file");
+        }
+    }
+
+    /// Graph of the files included by the open documents.
+    async fn get_include_graph(&self, urls: &[Url]) -> IncludeGraph {
+        self.load_include_graph(urls, &[]).await
+    }
+
+    /// Graph of the files included by the open documents and by the closed
+    /// ones, for the analyses spanning the workspace.
+    async fn get_workspace_graph(&self) -> IncludeGraph {
+        let urls: Vec<Url> = self.map.read().await.uris().cloned().collect();
+        self.load_include_graph(&urls, &self.disk_cache.closed())
+            .await
+    }
+
+    /// Graph of the documents of `urls` and `closed`, the open documents they
+    /// include being analyzed as edited rather than as saved.
+    async fn load_include_graph(&self, urls: &[Url], closed: &[Url]) -> IncludeGraph {
+        let position_encoding = self.position_encoding();
+        let exclude = self.settings.read().await.exclude.clone();
+        let (open, other_open): (Vec<_>, Vec<_>) = {
+            let map = self.map.read().await;
+            let roots: HashSet<Url> = urls.iter().map(normalize_uri).collect();
+            map.uris()
+                .filter(|url| !is_excluded(url, &exclude))
+                .filter_map(|url| {
+                    let links = DocumentLinks::new(url, map.get(url)?, position_encoding);
+                    Some((url.clone(), links))
+                })
+                .partition(|(url, _)| roots.contains(&normalize_uri(url)))
+        };
+
+        IncludeGraph::load(
+            open,
+            other_open,
+            closed,
+            &self.disk_cache,
+            &exclude,
+            position_encoding,
+        )
+        .await
+    }
+
+    /// Diagnostics of an included file which isn't open.
+    async fn get_included_diagnostics(
+        &self,
+        url: &Url,
+        document: &TextDocument,
+    ) -> Vec<Diagnostic> {
+        let position_encoding = self.position_encoding();
+        let settings = self.settings.read().await.clone();
+        let data = self.schema.get().await;
+
+        let mut diagnostics =
+            Diagnosis::new(&data, document, Some(url), &settings, position_encoding)
+                .finish(&self.paths)
+                .await;
+        self.suppress_baselined(url, &document.rope, &mut diagnostics)
+            .await;
+
+        diagnostics
+    }
+
+    /// Problems spanning the files of the workspace, by normalized URI.
+    async fn get_cross_file_problems(&self, graph: &IncludeGraph) -> HashMap<Url, Vec<Diagnostic>> {
+        let undefined_variables = {
+            let settings = self.settings.read().await;
+            graph.undefined_variables(
+                &settings.known_variables,
+                settings.undefined_variables.into(),
+            )
+        };
+        let mut cross_file = graph.duplicate_parsers();
+        for (url, diagnostics) in graph
+            .routing_problems()
+            .into_iter()
+            .chain(undefined_variables)
+            .chain(graph.overridden_service_keys())
+            .chain(graph.unused_variables())
+            .chain(graph.large_files())
+        {
+            cross_file.entry(url).or_default().extend(diagnostics);
+        }
+
+        cross_file
+    }
+
+    /// Report of the document, with the problems spanning files it has. Its
+    /// own diagnostics are computed unless they're `known`.
+    async fn get_workspace_document_report(
+        &self,
+        source: &ReportSource<'_>,
+        cross_file: &HashMap<Url, Vec<Diagnostic>>,
+        known: Option<Vec<Diagnostic>>,
+    ) -> Option<WorkspaceDocumentDiagnosticReport> {
+        let (url, mut items) = match (source, known) {
+            (ReportSource::Open(url), Some(items)) => (*url, items),
+            (ReportSource::Open(url), None) => (*url, self.get_diagnostics(url).await?),
+            (ReportSource::Included(url, document), _) => {
+                (*url, self.get_included_diagnostics(url, document).await)
+            }
+            (ReportSource::Indexed(url, _), _) => (*url, Vec::new()),
+        };
+        // codes spanning files aren't reported by single documents, which
+        // suppressed theirs
+        let mut cross_file_items = cross_file
+            .get(&normalize_uri(url))
+            .cloned()
+            .unwrap_or_default();
+        let rope = match source {
+            ReportSource::Open(url) => self.map.read().await.get(url).map(|d| d.rope.clone()),
+            ReportSource::Included(_, document) | ReportSource::Indexed(_, document) => {
+                Some(document.rope.clone())
+            }
+        };
+        if let Some(rope) = rope {
+            self.suppress_baselined(url, &rope, &mut cross_file_items)
+                .await;
+        }
+        items.extend(cross_file_items);
+
+        Some(full_report(url, items))
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, params: InitializeParams) -> JsonRpcResult<InitializeResult> {
+        let (settings, errors) =
+            Settings::from_initialization_options(params.initialization_options);
+        for error in &errors {
+            warn!(%error, "invalid setting, using its default");
+        }
+        if let Some(level) = settings.log_level() {
+            logging::set_level(level);
+        }
+        self.apply_trace(params.trace.unwrap_or(TraceValue::Off));
+        i18n::set_locale(
+            settings
+                .locale
+                .as_deref()
+                .or(params.locale.as_deref())
+                .unwrap_or_default(),
+        );
+        if !errors.is_empty() {
+            let message = t!("invalid-settings", errors = errors.join("; "));
+            self.client
+                .show_message(MessageType::WARNING, message)
+                .await;
+        }
+        self.schema.set_work_done_progress(
+            params
+                .capabilities
+                .window
+                .as_ref()
+                .and_then(|window| window.work_done_progress)
+                .unwrap_or(false),
+        );
+        self.schema
+            .set_schema_path(settings.schema_path.clone())
+            .await;
+        self.schema
+            .set_version(settings.fluent_bit_version.clone())
+            .await;
+        self.limits.set_timeout(settings.request_timeout());
+        self.metrics.set_log(settings.log_request_latency);
+        *self.settings.write().await = settings;
+        let position_encoding = PositionEncodingKind::negotiate(
+            params
+                .capabilities
+                .general
+                .as_ref()
+                .and_then(|general| general.position_encodings.as_deref()),
+        );
+        let _ = self.position_encoding.set(position_encoding);
+        let _ = self.client_capabilities.set(params.capabilities);
+
+        Ok(InitializeResult {
+            server_info: None,
+            capabilities: ServerCapabilities {
+                position_encoding: Some(position_encoding.to_lsp()),
+                text_document_sync: Some(TextDocumentSyncCapability::Options(
+                    TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(TextDocumentSyncKind::INCREMENTAL),
+                        save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                        ..TextDocumentSyncOptions::default()
+                    },
+                )),
+                completion_provider: Some(CompletionOptions {
+                    resolve_provider: Some(false),
+                    // `${` of variable references
+                    trigger_characters: Some(vec!["{".to_string()]),
+                    all_commit_characters: None,
+                    work_done_progress_options: Default::default(),
+                    completion_item: Some(CompletionOptionsCompletionItem {
+                        label_details_support: Some(true),
+                    }),
+                }),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(
+                        signature_help::TRIGGER_CHARACTERS
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect(),
+                    ),
+                    retrigger_characters: None,
+                    work_done_progress_options: Default::default(),
+                }),
+                document_formatting_provider: Some(OneOf::Left(true)),
+                document_range_formatting_provider: Some(OneOf::Left(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: " ".to_string(),
+                    more_trigger_character: None,
+                }),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(false),
+                }),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                semantic_tokens_provider: Some(
+                    SemanticTokensServerCapabilities::SemanticTokensOptions(
+                        SemanticTokensOptions {
+                            legend: semantic_tokens::legend(),
+                            full: Some(SemanticTokensFullOptions::Bool(true)),
+                            range: None,
+                            work_done_progress_options: Default::default(),
+                        },
+                    ),
+                ),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::SOURCE_FIX_ALL,
+                        ]),
+                        ..CodeActionOptions::default()
+                    },
+                )),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        SCAFFOLD_PIPELINE_COMMAND.to_string(),
+                        RELOAD_SCHEMA_COMMAND.to_string(),
+                        FIX_ALL_COMMAND.to_string(),
+                        MIGRATION_REPORT_COMMAND.to_string(),
+                    ],
+                    work_done_progress_options: Default::default(),
+                }),
+                diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
+                    DiagnosticOptions {
+                        identifier: None,
+                        inter_file_dependencies: true,
+                        workspace_diagnostics: true,
+                        work_done_progress_options: Default::default(),
+                    },
+                )),
+                experimental: serde_json::to_value(ExperimentalCapabilities::new()).ok(),
+                ..ServerCapabilities::default()
+            },
+        })
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        info!("fluent-bit language server initialized");
+
+        self.watch_schema_file().await;
+        self.load_baseline().await;
+
+        // classic documents are still served, without what needs the missing nodes
+        let missing = grammar::grammar_info().missing;
+        if !missing.is_empty() {
+            let message = t!(
+                "grammar-missing-nodes",
+                version = grammar::GRAMMAR_VERSION,
+                nodes = missing.join(", "),
+            );
+            self.client
+                .show_message(MessageType::WARNING, message)
+                .await;
+        }
+    }
+
+    async fn shutdown(&self) -> JsonRpcResult<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        debug!(uri = %params.text_document.uri, "file opened");
+
+        let url = params.text_document.uri;
+        let source_code = params.text_document.text.as_str();
+
+        self.disk_cache.open(&url);
+        self.open_file(&url, source_code, params.text_document.version)
+            .await;
+        self.push_diagnostics(&url, Some(params.text_document.version))
+            .await;
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        debug!(
+            uri = %params.text_document.uri,
+            version = params.text_document.version,
+            "file changed"
+        );
+
+        let url = params.text_document.uri;
+        if let Err(e) = self
+            .update_file(&url, params.text_document.version, &params.content_changes)
+            .await
+        {
+            self.resync_file(&url, e).await;
+            return;
+        }
+
+        if !self.uses_push_diagnostics() {
+            return;
+        }
+        let delay = self.settings.read().await.diagnostics_debounce();
+        let analyzer = self.analyzer();
+        if delay.is_zero() {
+            analyzer.publish_latest(&url).await;
+            return;
+        }
+        let analyzed = url.clone();
+        self.debouncer.schedule(&url, delay, async move {
+            analyzer.publish_latest(&analyzed).await;
+        });
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        self.push_diagnostics(&params.text_document.uri, None).await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        debug!(uri = %params.text_document.uri, "file closed");
+
+        let url = params.text_document.uri;
+        self.debouncer.cancel(&url);
+        // self.map.borrow_mut()
+        //     .remove(&url);
+
+        self.map.write().await.remove(&url);
+        // still part of the workspace, read from its file
+        self.disk_cache.close(&url);
+        if self.uses_push_diagnostics() {
+            self.client.publish_diagnostics(url, vec![], None).await;
+        }
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let Some(schema_path) = self.schema.schema_path().await else {
+            return;
+        };
+
+        let schema_changed = params.changes.iter().any(|change| {
+            change
+                .uri
+                .to_file_path()
+                .is_ok_and(|path| path == schema_path)
+        });
+        if schema_changed {
+            info!("fluent-bit schema file changed, reloading");
+            self.reload_schema().await;
+        }
+    }
+
+    async fn hover(&self, params: HoverParams) -> JsonRpcResult<Option<Hover>> {
         let TextDocumentPositionParams {
             text_document,
             position,
         } = params.text_document_position_params;
 
-        let point = Point {
-            row: position.line as usize,
-            column: position.character as usize,
-        };
-        let Some(key) = self.get_key_at_point(&text_document.uri, &point).await else {
+        let Some(point) = self.get_point(&text_document.uri, &position).await else {
             return Ok(None);
         };
+        if let Some(note) = self.get_variable_hover(&text_document.uri, &point).await {
+            let markup_options = self.hover_markup_options().await;
+            return Ok(Some(Hover {
+                contents: HoverContents::Markup(markup_options.render(&note, None)),
+                range: None,
+            }));
+        }
+        if let Some(note) = self.get_env_hover(&text_document.uri, position).await {
+            let markup_options = self.hover_markup_options().await;
+            return Ok(Some(Hover {
+                contents: HoverContents::Markup(markup_options.render(&note, None)),
+                range: None,
+            }));
+        }
         let Some(section_type) = self
             .get_section_type_at_point(&text_document.uri, &point)
             .await
@@ -337,17 +1939,100 @@ impl LanguageServer for Backend {
             return Ok(None);
         };
 
-        let Some(param_info) = get_hover_info(&section_type, &key) else {
+        if let Some(plugin_name) = self
+            .get_plugin_name_at_point(&text_document.uri, &point)
+            .await
+        {
+            let markup_options = self.hover_markup_options().await;
+            let contents = self.schema.get().await.get_plugin_hover(
+                &section_type,
+                &plugin_name,
+                &markup_options,
+            );
+            let note = self.get_problems_note(&text_document.uri, &point).await;
+            let contents = match (contents, note) {
+                (Some(mut contents), Some(note)) => {
+                    contents.value.push_str("\n\n---\n\n");
+                    contents.value.push_str(&note);
+                    contents
+                }
+                (Some(contents), None) => contents,
+                (None, Some(note)) => markup_options.render(&note, None),
+                (None, None) => return Ok(None),
+            };
+
+            return Ok(Some(Hover {
+                contents: HoverContents::Markup(contents),
+                range: None,
+            }));
+        }
+
+        let Some(key) = self.get_key_at_point(&text_document.uri, &point).await else {
             return Ok(None);
         };
 
+        let section = self
+            .get_indexed_section_at_point(&text_document.uri, &point)
+            .await;
+        let markup_options = self.hover_markup_options().await;
+        let data = self.schema.get().await;
+        let param_info = Self::get_parameter_info(&data, &section_type, &key);
+        let mut notes = hover_notes(&section_type, section.as_ref(), &key, param_info.as_ref());
+        if section_type == FlbSectionType::Service {
+            // overrides span the files the document includes
+            let graph = self
+                .get_include_graph(std::slice::from_ref(&text_document.uri))
+                .await;
+            notes.extend(graph.service_key_note(&text_document.uri, position));
+        }
+
+        let contents = match param_info {
+            Some(param_info) => param_info.to_markup_content_with_notes(&markup_options, &notes),
+            None if !notes.is_empty() => markup_options.render(&notes.join("\n\n"), None),
+            None => return Ok(None),
+        };
+
         Ok(Some(Hover {
-            contents: HoverContents::Markup(param_info.into()),
+            contents: HoverContents::Markup(contents),
             range: None,
         }))
     }
 
-    // TODO: Supply snippet only when there's no "Name" entry
+    async fn signature_help(
+        &self,
+        params: SignatureHelpParams,
+    ) -> JsonRpcResult<Option<SignatureHelp>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+
+        let markup_options = self.signature_markup_options().await;
+        let data = self.schema.get().await;
+        let map = self.map.read().await;
+        let Some(document) = map.get(&text_document.uri) else {
+            return Ok(None);
+        };
+        let Some(byte) = position_to_point(&document.rope, &position, self.position_encoding())
+            .ok()
+            .and_then(|point| point_to_byte(&document.rope, point))
+        else {
+            return Ok(None);
+        };
+        let Some((section, entry)) = signature_help::value_entry_at(document, byte) else {
+            return Ok(None);
+        };
+
+        let info = Self::get_parameter_info(&data, &section.section_type, &entry.key);
+        Ok(signature_help::signature_help(
+            section,
+            &entry.key,
+            info.as_ref(),
+            document.format,
+            &markup_options,
+        ))
+    }
+
     async fn completion(
         &self,
         params: CompletionParams,
@@ -357,48 +2042,453 @@ impl LanguageServer for Backend {
             position,
         } = params.text_document_position;
 
-        let point = Point {
-            row: position.line as usize,
-            column: position.character as usize,
+        let Some(point) = self.get_point(&text_document.uri, &position).await else {
+            return Ok(None);
         };
 
-        // TEMP
+        if let Some(items) = self
+            .get_variable_completion(&text_document.uri, &point)
+            .await
+        {
+            return Ok(Some(CompletionResponse::Array(items)));
+        }
+        let triggered_by_brace = params
+            .context
+            .and_then(|context| context.trigger_character)
+            .is_some_and(|character| character == "{");
+        if triggered_by_brace {
+            return Ok(None);
+        }
+        if let Some(items) = self.get_tag_completion(&text_document.uri, &point).await {
+            return Ok(Some(CompletionResponse::Array(items)));
+        }
+        if let Some(items) = self.get_env_completion(&text_document.uri, &point).await {
+            return Ok(Some(CompletionResponse::Array(items)));
+        }
+
         let section_type = self
             .get_section_type_at_point(&text_document.uri, &point)
-            .await;
+            .await
+            .map(intended_section_type);
         let mut ret: Vec<CompletionItem> = Vec::new();
 
-        self.client
-            .log_message(
-                MessageType::INFO,
-                format!("section_type: {:?}", section_type),
-            )
-            .await;
+        debug!(?section_type, "completion");
 
-        if let Some(section) = section_type {
-            ret.extend(get_completion(&section));
+        if let Some(section_type) = section_type {
+            let section = self
+                .get_indexed_section_at_point(&text_document.uri, &point)
+                .await;
+            let markup_options = self.completion_markup_options().await;
+            let format = self.get_format(&text_document.uri).await;
+            let version = self.settings.read().await.target_version();
+            ret.extend(self.schema.get().await.get_completion(
+                &section_type,
+                section.as_ref(),
+                &markup_options,
+                format,
+                version,
+            ));
+            let indent = self
+                .get_sequence_item_indent(&text_document.uri, &point)
+                .await;
+            indent_continuation_lines(&mut ret, indent);
         } else {
             return Ok(None);
         }
 
-        Ok(Some(CompletionResponse::Array(ret)))
+        let word = self
+            .get_word_before_point(&text_document.uri, &point)
+            .await
+            .unwrap_or_default();
+        Ok(Some(paginate_completion(ret, &word)))
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> JsonRpcResult<Option<GotoDefinitionResponse>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+
+        if let Some((base_dir, pattern)) = self.get_include_at(&text_document.uri, &position).await
+        {
+            let locations: Vec<Location> = included_files(base_dir.as_deref(), &pattern)
+                .await
+                .into_iter()
+                .filter_map(|path| Url::from_file_path(path).ok())
+                .map(|uri| Location::new(uri, Range::default()))
+                .collect();
+            return Ok((!locations.is_empty()).then_some(GotoDefinitionResponse::Array(locations)));
+        }
+
+        let Some(symbol) = self
+            .get_parser_symbol_at(&text_document.uri, &position)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        let locations: Vec<Location> = self
+            .find_parser_symbols(&symbol.name)
+            .await
+            .into_iter()
+            .filter(|(_, _, kind)| *kind == ParserSymbolKind::Definition)
+            .map(|(uri, range, _)| Location::new(uri, range))
+            .collect();
+
+        Ok((!locations.is_empty()).then_some(GotoDefinitionResponse::Array(locations)))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> JsonRpcResult<Option<Vec<Location>>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position;
+        let Some(target) = self
+            .get_reference_target_at(&text_document.uri, &position)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        let include_declaration = params.context.include_declaration;
+        let position_encoding = self.position_encoding();
+        let exclude = self.settings.read().await.exclude.clone();
+        let urls: Vec<Url> = self.map.read().await.uris().cloned().collect();
+        // the included files which aren't open, and the closed documents
+        let graph = self.get_workspace_graph().await;
+
+        let mut locations = Vec::new();
+        {
+            let map = self.map.read().await;
+            for url in &urls {
+                let Some(document) = map.get(url).filter(|_| !is_excluded(url, &exclude)) else {
+                    continue;
+                };
+                locations.extend(Self::find_references_in(
+                    &target,
+                    url,
+                    document,
+                    include_declaration,
+                    position_encoding,
+                ));
+            }
+        }
+        for (url, document) in &graph.loaded {
+            locations.extend(Self::find_references_in(
+                &target,
+                url,
+                document,
+                include_declaration,
+                position_encoding,
+            ));
+        }
+
+        Ok((!locations.is_empty()).then_some(locations))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> JsonRpcResult<Option<PrepareRenameResponse>> {
+        let map = self.map.read().await;
+        let Some(document) = map.get(&params.text_document.uri) else {
+            return Ok(None);
+        };
+        let Ok(location) =
+            resolve_position(&document.rope, &params.position, self.position_encoding())
+        else {
+            return Ok(None);
+        };
+
+        Ok(
+            parser_symbol_at(&document.index, location.byte_idx).map(|symbol| {
+                PrepareRenameResponse::Range(byte_range_to_lsp_range(
+                    &document.rope,
+                    symbol.range,
+                    self.position_encoding(),
+                ))
+            }),
+        )
+    }
+
+    async fn rename(&self, params: RenameParams) -> JsonRpcResult<Option<WorkspaceEdit>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position;
+
+        if !is_valid_parser_name(&params.new_name) {
+            return Err(JsonRpcError::invalid_params(t!(
+                "invalid-parser-name",
+                name = params.new_name
+            )));
+        }
+        let Some(symbol) = self
+            .get_parser_symbol_at(&text_document.uri, &position)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        let mut edit = WorkspaceEditBuilder::new(false);
+        for (uri, range, _) in self.find_parser_symbols(&symbol.name).await {
+            edit.edit(&uri, TextEdit::new(range, params.new_name.clone()));
+        }
+
+        Ok(Some(edit.build()))
+    }
+
+    async fn formatting(
+        &self,
+        params: DocumentFormattingParams,
+    ) -> JsonRpcResult<Option<Vec<TextEdit>>> {
+        let line_ending = self.settings.read().await.line_ending;
+        let map = self.map.read().await;
+        let Some(document) = map.get(&params.text_document.uri) else {
+            return Ok(None);
+        };
+        // only the classic format is formatted
+        if document.format != ConfigFormat::Classic {
+            return Ok(None);
+        }
+
+        Ok(Some(format_document(
+            &document.rope,
+            line_ending,
+            self.position_encoding(),
+        )))
+    }
+
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> JsonRpcResult<Option<Vec<TextEdit>>> {
+        let line_ending = self.settings.read().await.line_ending;
+        let map = self.map.read().await;
+        let Some(document) = map.get(&params.text_document.uri) else {
+            return Ok(None);
+        };
+        if document.format != ConfigFormat::Classic {
+            return Ok(None);
+        }
+
+        let position_encoding = self.position_encoding();
+        let (Ok(start), Ok(end)) = (
+            resolve_position(&document.rope, &params.range.start, position_encoding),
+            resolve_position(&document.rope, &params.range.end, position_encoding),
+        ) else {
+            return Ok(None);
+        };
+        Ok(Some(format_range(
+            &document.rope,
+            start.byte_idx..end.byte_idx,
+            line_ending,
+            position_encoding,
+        )))
+    }
+
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> JsonRpcResult<Option<Vec<TextEdit>>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position;
+        let map = self.map.read().await;
+        let Some(document) = map.get(&text_document.uri) else {
+            return Ok(None);
+        };
+        if params.ch != " " || document.format != ConfigFormat::Classic {
+            return Ok(None);
+        }
+
+        let position_encoding = self.position_encoding();
+        let Ok(location) = resolve_position(&document.rope, &position, position_encoding) else {
+            return Ok(None);
+        };
+        Ok(align_entry(
+            &document.index,
+            &document.rope,
+            location.byte_idx,
+            position_encoding,
+        )
+        .map(|edit| vec![edit]))
+    }
+
+    async fn folding_range(
+        &self,
+        params: FoldingRangeParams,
+    ) -> JsonRpcResult<Option<Vec<FoldingRange>>> {
+        let map = self.map.read().await;
+        let Some(document) = map.get(&params.text_document.uri) else {
+            return Ok(None);
+        };
+
+        Ok(Some(folding_ranges(document)))
+    }
+
+    /// Summary of the pipeline of the configuration, on its `[SERVICE]`
+    /// sections.
+    async fn code_lens(&self, params: CodeLensParams) -> JsonRpcResult<Option<Vec<CodeLens>>> {
+        let url = params.text_document.uri;
+        let ranges: Vec<Range> = {
+            let map = self.map.read().await;
+            let Some(document) = map.get(&url) else {
+                return Ok(None);
+            };
+            document
+                .index
+                .sections()
+                .iter()
+                .filter(|section| section.section_type == FlbSectionType::Service)
+                .map(|section| {
+                    let start = section.byte_range.start;
+                    byte_range_to_lsp_range(&document.rope, start..start, self.position_encoding())
+                })
+                .collect()
+        };
+        if ranges.is_empty() || self.is_excluded(&url).await {
+            return Ok(None);
+        }
+
+        // the files the configuration includes, the open ones as edited
+        let graph = self.get_include_graph(std::slice::from_ref(&url)).await;
+        let mut summary = PipelineSummary::default();
+        let map = self.map.read().await;
+        for uri in graph.files() {
+            let loaded = || {
+                graph
+                    .loaded
+                    .iter()
+                    .find(|(loaded, _)| loaded == uri)
+                    .map(|(_, document)| document.as_ref())
+            };
+            if let Some(document) = map.get(uri).or_else(loaded) {
+                summary.add(&document.index);
+            }
+        }
+        drop(map);
+
+        Ok(Some(
+            ranges
+                .into_iter()
+                .flat_map(|range| summary.code_lenses(range))
+                .collect(),
+        ))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> JsonRpcResult<Option<Vec<InlayHint>>> {
+        let data = self.schema.get().await;
+        let map = self.map.read().await;
+        let Some(document) = map.get(&params.text_document.uri) else {
+            return Ok(None);
+        };
+
+        Ok(Some(inlay_hints(
+            &data,
+            document,
+            params.range,
+            self.position_encoding(),
+        )))
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> JsonRpcResult<Option<SemanticTokensResult>> {
+        let data = self.schema.get().await;
+        let map = self.map.read().await;
+        let Some(document) = map.get(&params.text_document.uri) else {
+            return Ok(None);
+        };
+
+        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens::semantic_tokens(&data, document, self.position_encoding()),
+        })))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> JsonRpcResult<Option<DocumentSymbolResponse>> {
+        let map = self.map.read().await;
+        let Some(document) = map.get(&params.text_document.uri) else {
+            return Ok(None);
+        };
+
+        Ok(Some(DocumentSymbolResponse::Nested(document_symbols(
+            &document.index,
+            &document.rope,
+            self.position_encoding(),
+        ))))
+    }
+
+    async fn code_action(
+        &self,
+        params: CodeActionParams,
+    ) -> JsonRpcResult<Option<CodeActionResponse>> {
+        let uri = &params.text_document.uri;
+        let only = |kind: &CodeActionKind| {
+            params.context.only.as_ref().map_or(true, |only| {
+                only.iter()
+                    .any(|only| kind.as_str().starts_with(only.as_str()))
+            })
+        };
+
+        let mut actions: Vec<CodeActionOrCommand> = Vec::new();
+        if only(&CodeActionKind::QUICKFIX) {
+            let data = self.schema.get().await;
+            let map = self.map.read().await;
+            let document = map.get(uri);
+            actions.extend(
+                params
+                    .context
+                    .diagnostics
+                    .iter()
+                    .filter_map(|diagnostic| {
+                        fixes::quick_fix(&data, uri, document, diagnostic, self.position_encoding())
+                    })
+                    .map(CodeActionOrCommand::CodeAction),
+            );
+        }
+        if only(&CodeActionKind::SOURCE_FIX_ALL) {
+            actions.extend(self.fix_all_action(uri).await);
+        }
+
+        Ok(Some(actions))
+    }
+
+    async fn execute_command(&self, params: ExecuteCommandParams) -> JsonRpcResult<Option<Value>> {
+        match params.command.as_str() {
+            SCAFFOLD_PIPELINE_COMMAND => self.scaffold_pipeline(params.arguments).await?,
+            RELOAD_SCHEMA_COMMAND => self.reload_schema().await,
+            FIX_ALL_COMMAND => self.fix_all(params.arguments).await?,
+            MIGRATION_REPORT_COMMAND => {
+                let report = self.migration_report(params.arguments).await?;
+                return Ok(serde_json::to_value(report).ok());
+            }
+            command => {
+                return Err(JsonRpcError::invalid_params(format!(
+                    "unknown command `{command}`"
+                )))
+            }
+        }
+
+        Ok(None)
     }
 
     async fn diagnostic(
         &self,
         params: DocumentDiagnosticParams,
     ) -> JsonRpcResult<DocumentDiagnosticReportResult> {
-        // for test just mark second line is error
-
         let DocumentDiagnosticParams { text_document, .. } = params;
         let url = text_document.uri;
-
-        // let diagnostics: Vec<Diagnostic> = vec![Diagnostic::new_simple(
-        //     Range::new(Position::new(1, 0), Position::new(2, 10)),
-        //     "error message".to_string(),
-        // )];
-
-        let diagnostics = self.get_diagnostics(&url).await.unwrap_or_default();
+        let diagnostics = self.analyze_document(&url).await.unwrap_or_default();
 
         Ok(DocumentDiagnosticReportResult::Report(
             DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
@@ -410,4 +2500,420 @@ impl LanguageServer for Backend {
             }),
         ))
     }
+
+    /// Reports diagnostics of every known document, and of the files they
+    /// include which aren't open, with the problems spanning files.
+    ///
+    /// When the client sends a `partialResultToken`, reports are streamed in
+    /// batches through `$/progress` as soon as they are computed, and the final
+    /// response is left empty as required by the spec.
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> JsonRpcResult<WorkspaceDiagnosticReportResult> {
+        let urls: Vec<Url> = self.map.read().await.uris().cloned().collect();
+        let Some(token) = params.partial_result_params.partial_result_token else {
+            let graph = self.get_workspace_graph().await;
+            let cross_file = self.get_cross_file_problems(&graph).await;
+            let mut items = Vec::new();
+            for source in report_sources(&urls, &graph) {
+                items.extend(
+                    self.get_workspace_document_report(&source, &cross_file, None)
+                        .await,
+                );
+            }
+
+            return Ok(WorkspaceDiagnosticReportResult::Report(
+                WorkspaceDiagnosticReport { items },
+            ));
+        };
+
+        // The open documents are reported first, before the workspace is
+        // loaded, then the included files and the problems spanning files.
+        let mut partial = PartialReports::new(&self.client, token);
+        let mut open_items = HashMap::new();
+        for chunk in urls.chunks(WORKSPACE_DIAGNOSTIC_BATCH_SIZE) {
+            let mut items = Vec::with_capacity(chunk.len());
+            for url in chunk {
+                let Some(diagnostics) = self.get_diagnostics(url).await else {
+                    continue;
+                };
+                items.push(full_report(url, diagnostics.clone()));
+                open_items.insert(url.clone(), diagnostics);
+            }
+            partial.send(items).await;
+        }
+
+        let graph = self.get_workspace_graph().await;
+        let cross_file = self.get_cross_file_problems(&graph).await;
+        // open documents are reported again only with problems spanning files
+        let sources: Vec<ReportSource> = report_sources(&urls, &graph)
+            .into_iter()
+            .filter(|source| match source {
+                ReportSource::Open(url) => {
+                    open_items.contains_key(*url) && cross_file.contains_key(&normalize_uri(url))
+                }
+                _ => true,
+            })
+            .collect();
+        for chunk in sources.chunks(WORKSPACE_DIAGNOSTIC_BATCH_SIZE) {
+            let mut items = Vec::with_capacity(chunk.len());
+            for source in chunk {
+                let known = match source {
+                    ReportSource::Open(url) => open_items.remove(*url),
+                    _ => None,
+                };
+                items.extend(
+                    self.get_workspace_document_report(source, &cross_file, known)
+                        .await,
+                );
+            }
+            partial.send(items).await;
+        }
+
+        Ok(WorkspaceDiagnosticReportResult::Report(
+            WorkspaceDiagnosticReport { items: vec![] },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    use serde_json::json;
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+    use tower::Service;
+    use tower_lsp::{
+        jsonrpc::{self, Response},
+        lsp_types::Range,
+        LspService,
+    };
+
+    use super::*;
+
+    /// Server initialized with the client capabilities, and the messages it
+    /// sends to the client.
+    async fn start_server(
+        capabilities: Value,
+    ) -> (LspService<Backend>, UnboundedReceiver<jsonrpc::Request>) {
+        let (mut service, socket) = LspService::new(Backend::new);
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(socket.for_each(move |message| {
+            let _ = sender.send(message);
+            async {}
+        }));
+
+        request(
+            &mut service,
+            "initialize",
+            json!({ "capabilities": capabilities }),
+        )
+        .await;
+        notify(&mut service, "initialized", json!({})).await;
+        (service, receiver)
+    }
+
+    async fn request(
+        service: &mut LspService<Backend>,
+        method: &'static str,
+        params: Value,
+    ) -> Response {
+        let request = jsonrpc::Request::build(method)
+            .params(params)
+            .id(1)
+            .finish();
+        service.call(request).await.unwrap().unwrap()
+    }
+
+    async fn notify(service: &mut LspService<Backend>, method: &'static str, params: Value) {
+        let notification = jsonrpc::Request::build(method).params(params).finish();
+        service.call(notification).await.unwrap();
+    }
+
+    /// Messages the server sent to the client, until it sends none for longer
+    /// than the debounce of the diagnostics.
+    async fn sent_messages(
+        receiver: &mut UnboundedReceiver<jsonrpc::Request>,
+    ) -> Vec<jsonrpc::Request> {
+        let mut messages = Vec::new();
+        while let Ok(Some(message)) =
+            tokio::time::timeout(Duration::from_millis(500), receiver.recv()).await
+        {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn count(messages: &[jsonrpc::Request], method: &str) -> usize {
+        messages
+            .iter()
+            .filter(|message| message.method() == method)
+            .count()
+    }
+
+    #[tokio::test]
+    async fn changes_are_diagnosed_once_settled() {
+        let uri = "file:///etc/fluent-bit/fluent-bit.conf";
+        let (mut service, mut receiver) = start_server(json!({})).await;
+        let open = json!({
+            "textDocument": { "uri": uri, "languageId": "fluent-bit", "version": 1, "text": "[INPUT]\n" }
+        });
+        notify(&mut service, "textDocument/didOpen", open).await;
+        sent_messages(&mut receiver).await;
+
+        // the handler doesn't wait for the changes to settle
+        let start = std::time::Instant::now();
+        let change = json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": "[INPUT]\n    Name cpu\n    Name mem\n" }]
+        });
+        notify(&mut service, "textDocument/didChange", change).await;
+        assert!(start.elapsed() < crate::debounce::DEFAULT_DEBOUNCE);
+
+        let messages = sent_messages(&mut receiver).await;
+        let published: Vec<_> = messages
+            .iter()
+            .filter(|message| message.method() == "textDocument/publishDiagnostics")
+            .map(|message| message.params().unwrap())
+            .collect();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0]["version"], 2);
+        assert_eq!(published[0]["diagnostics"][0]["code"], "duplicate-key");
+    }
+
+    #[tokio::test]
+    async fn workspace_diagnostics_stream_the_open_documents_first() {
+        let temp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp.path().join("inputs.conf"),
+            "[INPUT]\n    Name cpu\n    Name mem\n",
+        )
+        .unwrap();
+        let uri = Url::from_file_path(temp.path().join("fluent-bit.conf")).unwrap();
+        let included = Url::from_file_path(temp.path().join("inputs.conf")).unwrap();
+
+        let (mut service, mut receiver) = start_server(json!({})).await;
+        let open = json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "fluent-bit",
+                "version": 1,
+                "text": "@INCLUDE inputs.conf\n\n[OUTPUT]\n    Name  file\n    Path  ${LOG_DIR}\n"
+            }
+        });
+        notify(&mut service, "textDocument/didOpen", open).await;
+        sent_messages(&mut receiver).await;
+
+        let params = json!({ "previousResultIds": [], "partialResultToken": "workspace" });
+        let response = request(&mut service, "workspace/diagnostic", params).await;
+        // everything was sent as partial results
+        assert_eq!(response.result(), Some(&json!({ "items": [] })));
+
+        let partials: Vec<Vec<(String, Vec<String>)>> = sent_messages(&mut receiver)
+            .await
+            .iter()
+            .filter(|message| message.method() == "$/progress")
+            .map(|message| {
+                let params = message.params().unwrap();
+                assert_eq!(params["token"], "workspace");
+                let items = params["value"]["items"].as_array().unwrap();
+                items
+                    .iter()
+                    .map(|item| {
+                        let codes = item["items"]
+                            .as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|diagnostic| diagnostic["code"].as_str().unwrap().to_string())
+                            .collect();
+                        (item["uri"].as_str().unwrap().to_string(), codes)
+                    })
+                    .collect()
+            })
+            .collect();
+        // the open document, before the workspace is loaded, then the
+        // included file and the open document again with the problems
+        // spanning files
+        assert_eq!(partials, [vec![(uri.to_string(), vec![])], vec![
+            (uri.to_string(), vec!["undefined-variable".to_string()]),
+            (included.to_string(), vec!["duplicate-key".to_string()]),
+        ],]);
+    }
+
+    #[tokio::test]
+    async fn invalid_changes_request_a_resync() {
+        let uri = "file:///etc/fluent-bit/fluent-bit.conf";
+        let open = json!({
+            "textDocument": { "uri": uri, "languageId": "fluent-bit", "version": 1, "text": "[INPUT]\n    Name cpu\n" }
+        });
+        let reversed_change = |version| {
+            json!({
+                "textDocument": { "uri": uri, "version": version },
+                "contentChanges": [{
+                    "range": { "start": { "line": 1, "character": 4 }, "end": { "line": 0, "character": 2 } },
+                    "text": ""
+                }]
+            })
+        };
+
+        // the user is asked to reopen the document, once
+        let (mut service, mut receiver) = start_server(json!({})).await;
+        notify(&mut service, "textDocument/didOpen", open.clone()).await;
+        notify(&mut service, "textDocument/didChange", reversed_change(2)).await;
+        notify(&mut service, "textDocument/didChange", reversed_change(3)).await;
+        let messages = sent_messages(&mut receiver).await;
+        assert_eq!(count(&messages, "window/showMessage"), 1);
+        assert_eq!(count(&messages, ResyncDocument::METHOD), 0);
+        // and it isn't served meanwhile, even after valid changes
+        let symbols = json!({ "textDocument": { "uri": uri } });
+        let response = request(&mut service, "textDocument/documentSymbol", symbols.clone()).await;
+        assert_eq!(response.result(), Some(&Value::Null));
+        let rename_change = json!({
+            "textDocument": { "uri": uri, "version": 4 },
+            "contentChanges": [{
+                "range": { "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 8 } },
+                "text": "Tag"
+            }]
+        });
+        notify(
+            &mut service,
+            "textDocument/didChange",
+            rename_change.clone(),
+        )
+        .await;
+        let url = Url::parse(uri).unwrap();
+        assert!(service.inner().map.read().await.get(&url).is_none());
+
+        // until its whole content is sent again
+        let full_change = json!({
+            "textDocument": { "uri": uri, "version": 5 },
+            "contentChanges": [{ "text": "[INPUT]\n    Name mem\n" }]
+        });
+        notify(&mut service, "textDocument/didChange", full_change).await;
+        let response = request(&mut service, "textDocument/documentSymbol", symbols).await;
+        assert_eq!(response.result().unwrap()[0]["name"], "mem");
+
+        // reopening it asks again on the next invalid change
+        notify(&mut service, "textDocument/didOpen", open.clone()).await;
+        notify(&mut service, "textDocument/didChange", reversed_change(2)).await;
+        let messages = sent_messages(&mut receiver).await;
+        assert_eq!(count(&messages, "window/showMessage"), 1);
+
+        // clients handling it are requested a resync instead
+        let capabilities =
+            json!({ "experimental": { "fluentBit": { "requests": [ResyncDocument::METHOD] } } });
+        let (mut service, mut receiver) = start_server(capabilities.clone()).await;
+        notify(&mut service, "textDocument/didOpen", open.clone()).await;
+        notify(&mut service, "textDocument/didChange", reversed_change(2)).await;
+        let messages = sent_messages(&mut receiver).await;
+        assert_eq!(count(&messages, "window/showMessage"), 0);
+        assert_eq!(count(&messages, ResyncDocument::METHOD), 1);
+
+        // changes in bounds, applied to other content than the client's
+        let (mut service, mut receiver) = start_server(capabilities).await;
+        notify(&mut service, "textDocument/didOpen", open).await;
+        notify(&mut service, "textDocument/didChange", rename_change).await;
+        let checksum = |version, length| {
+            serde_json::from_value(json!({
+                "textDocument": { "uri": uri, "version": version },
+                "length": length,
+            }))
+            .unwrap()
+        };
+        let backend = service.inner();
+        // of another version, or matching the content
+        backend.document_checksum(checksum(3, 21)).await;
+        backend.document_checksum(checksum(4, 20)).await;
+        assert!(backend.map.read().await.get(&url).is_some());
+        backend.document_checksum(checksum(4, 21)).await;
+        assert!(backend.map.read().await.get(&url).is_none());
+        let messages = sent_messages(&mut receiver).await;
+        assert_eq!(count(&messages, ResyncDocument::METHOD), 1);
+    }
+
+    #[tokio::test]
+    async fn parsers_are_resolved_across_formats() {
+        let parsers = "file:///etc/fluent-bit/parsers.conf";
+        let pipeline = "file:///etc/fluent-bit/fluent-bit.yaml";
+        let (mut service, _receiver) = start_server(json!({})).await;
+        let open = |uri, language_id, text| {
+            json!({
+                "textDocument": { "uri": uri, "languageId": language_id, "version": 1, "text": text }
+            })
+        };
+        notify(
+            &mut service,
+            "textDocument/didOpen",
+            open(
+                parsers,
+                "fluent-bit",
+                "[PARSER]\n    Name   json_ts\n    Format json\n",
+            ),
+        )
+        .await;
+        notify(
+            &mut service,
+            "textDocument/didOpen",
+            open(
+                pipeline,
+                "yaml",
+                "pipeline:\n  inputs:\n    - name: tail\n      path: /var/log/app.log\n      parser: json_ts\n",
+            ),
+        )
+        .await;
+
+        // from the `parser:` of the YAML pipeline to the classic `[PARSER]`
+        let at_reference = json!({
+            "textDocument": { "uri": pipeline },
+            "position": { "line": 4, "character": 16 }
+        });
+        let response = request(
+            &mut service,
+            "textDocument/definition",
+            at_reference.clone(),
+        )
+        .await;
+        let definition = json!({
+            "uri": parsers,
+            "range": { "start": { "line": 1, "character": 11 }, "end": { "line": 1, "character": 18 } }
+        });
+        assert_eq!(response.result(), Some(&json!([definition])));
+
+        // both are renamed
+        let mut params = at_reference;
+        params["newName"] = json!("json_time");
+        let response = request(&mut service, "textDocument/rename", params).await;
+        let changes = &response.result().unwrap()["changes"];
+        assert_eq!(
+            changes[parsers],
+            json!([{ "range": definition["range"], "newText": "json_time" }])
+        );
+        assert_eq!(
+            changes[pipeline],
+            json!([{
+                "range": { "start": { "line": 4, "character": 14 }, "end": { "line": 4, "character": 21 } },
+                "newText": "json_time"
+            }])
+        );
+    }
+
+    #[test]
+    fn unknown_service_key_diagnostics() {
+        let document = TextDocument::new(
+            "[SERVICE]\n    Flush        1\n    Hot_Reload   on\n    Flsuh        5\n\n[INPUT]\n    Name  cpu\n",
+        );
+        let diagnostics = Backend::get_service_key_diagnostics(
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Unknown SERVICE key `Flsuh`.");
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(3, 4), Position::new(3, 9))
+        );
+    }
 }
@@ -0,0 +1,151 @@
+//! Checks of the raw text which fluent-bit's classic parser is sensitive to,
+//! together with their quick fixes.
+
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, TextEdit};
+
+use crate::{
+    document::TextDocument,
+    i18n::t,
+    lsp_ext::DiagnosticFix,
+    positions::{byte_range_to_lsp_range, resolve_position, PositionEncodingKind},
+    section_index::SectionIndex,
+};
+
+const BOM: char = '\u{feff}';
+
+/// Diagnostic code of a UTF-8 byte order mark at the start of the file.
+pub(crate) const UTF8_BOM: &str = "utf8-bom";
+/// Diagnostic code of whitespace at the end of a value.
+pub(crate) const TRAILING_WHITESPACE: &str = "trailing-whitespace";
+/// Diagnostic code of a comment where the classic format doesn't allow one.
+pub(crate) const MISPLACED_COMMENT: &str = "misplaced-comment";
+
+pub(crate) fn check_bom(
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Option<Diagnostic> {
+    if rope.get_char(0) != Some(BOM) {
+        return None;
+    }
+
+    Some(Diagnostic {
+        range: byte_range_to_lsp_range(rope, 0..BOM.len_utf8(), position_encoding),
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(NumberOrString::String(UTF8_BOM.to_string())),
+        message: t!(UTF8_BOM),
+        ..Diagnostic::default()
+    })
+}
+
+pub(crate) fn check_trailing_whitespace(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    index
+        .sections()
+        .iter()
+        .flat_map(|section| &section.entries)
+        .filter_map(|entry| {
+            let value_range = entry.value_range.clone()?;
+            let value = rope.get_byte_slice(value_range.clone())?.to_string();
+            let trimmed_len = value.trim_end_matches([' ', '\t']).len();
+            if trimmed_len == value.len() {
+                return None;
+            }
+
+            Some(Diagnostic {
+                range: byte_range_to_lsp_range(
+                    rope,
+                    value_range.start + trimmed_len..value_range.end,
+                    position_encoding,
+                ),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(TRAILING_WHITESPACE.to_string())),
+                message: t!(TRAILING_WHITESPACE, key = entry.key),
+                ..Diagnostic::default()
+            })
+        })
+        .collect()
+}
+
+/// Fix of a diagnostic reported by this module, which removes the offending
+/// text.
+pub(crate) fn fix(diagnostic: &Diagnostic) -> Option<DiagnosticFix> {
+    let title = match &diagnostic.code {
+        Some(NumberOrString::String(code)) if code == UTF8_BOM => t!("fix-remove-bom"),
+        Some(NumberOrString::String(code)) if code == TRAILING_WHITESPACE => {
+            t!("fix-remove-trailing-whitespace")
+        }
+        _ => return None,
+    };
+
+    Some(DiagnosticFix {
+        title,
+        edits: vec![TextEdit::new(diagnostic.range, String::new())],
+    })
+}
+
+/// Removes the misplaced comment, with the whitespace before it.
+pub(crate) fn misplaced_comment_fix(
+    document: &TextDocument,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    if diagnostic.code != Some(NumberOrString::String(MISPLACED_COMMENT.to_string())) {
+        return None;
+    }
+
+    let rope = &document.rope;
+    let start = resolve_position(rope, &diagnostic.range.start, position_encoding).ok()?;
+    let end = resolve_position(rope, &diagnostic.range.end, position_encoding).ok()?;
+    let line_start = rope.line_to_byte(rope.byte_to_line(start.byte_idx));
+    let before = rope.get_byte_slice(line_start..start.byte_idx)?.to_string();
+    let start = line_start + before.trim_end_matches([' ', '\t']).len();
+
+    Some(DiagnosticFix {
+        title: t!("fix-remove-comment"),
+        edits: vec![TextEdit::new(
+            byte_range_to_lsp_range(rope, start..end.byte_idx, position_encoding),
+            String::new(),
+        )],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+    use crate::document::TextDocument;
+
+    #[test]
+    fn bom_and_trailing_whitespace() {
+        let document = TextDocument::new("\u{feff}[INPUT]\n    Name cpu  \n    Tag  cpu.local\t\n");
+
+        let bom = check_bom(&document.rope, PositionEncodingKind::UTF16).unwrap();
+        assert_eq!(
+            bom.range,
+            Range::new(Position::new(0, 0), Position::new(0, 1))
+        );
+        let bom = check_bom(&document.rope, PositionEncodingKind::UTF8).unwrap();
+        assert_eq!(
+            bom.range,
+            Range::new(Position::new(0, 0), Position::new(0, 3))
+        );
+
+        let diagnostics =
+            check_trailing_whitespace(&document.index, &document.rope, PositionEncodingKind::UTF16);
+        let ranges: Vec<_> = diagnostics.iter().map(|d| d.range).collect();
+        assert_eq!(ranges, [
+            Range::new(Position::new(1, 12), Position::new(1, 14)),
+            Range::new(Position::new(2, 18), Position::new(2, 19)),
+        ]);
+
+        assert_eq!(fix(&diagnostics[0]).unwrap().edits, [TextEdit::new(
+            diagnostics[0].range,
+            String::new()
+        )]);
+    }
+}
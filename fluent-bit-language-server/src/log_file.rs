@@ -0,0 +1,136 @@
+//! `--log-file` of the server: the messages it sends to the client
+//! (`window/logMessage` and `window/showMessage`), the events logged below
+//! warnings, which the client only gets as traces, and its panics are
+//! appended to a file, e.g. for editors which don't keep them.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use futures::{Stream, StreamExt};
+use tower_lsp::{
+    jsonrpc::Request,
+    lsp_types::{LogMessageParams, MessageType},
+    ClientSocket, Loopback,
+};
+
+#[derive(Clone)]
+pub(crate) struct LogFile {
+    file: Arc<Mutex<File>>,
+    start: Instant,
+}
+
+impl LogFile {
+    pub(crate) fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+            start: Instant::now(),
+        })
+    }
+
+    /// Appends a line, prefixed with the time elapsed since the server started.
+    pub(crate) fn write(&self, level: &str, message: &str) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "[{elapsed:10.3}s {level:<5}] {message}");
+        }
+    }
+
+    /// Writes the message of a `window/logMessage` or `window/showMessage`
+    /// notification, other requests being ignored.
+    fn log_request(&self, request: &Request) {
+        if !matches!(request.method(), "window/logMessage" | "window/showMessage") {
+            return;
+        }
+        // both have the same parameters
+        let Some(params) = request
+            .params()
+            .and_then(|params| serde_json::from_value::<LogMessageParams>(params.clone()).ok())
+        else {
+            return;
+        };
+
+        let level = match params.typ {
+            MessageType::ERROR => "ERROR",
+            MessageType::WARNING => "WARN",
+            MessageType::INFO => "INFO",
+            _ => "LOG",
+        };
+        self.write(level, &params.message);
+    }
+
+    /// Appends panics to the file as well, after the default hook printed them.
+    pub(crate) fn log_panics(&self) {
+        let log_file = self.clone();
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            log_file.write("PANIC", &info.to_string());
+            default_hook(info);
+        }));
+    }
+}
+
+/// Socket of the server-to-client messages writing the logged ones to the log
+/// file, if any.
+pub(crate) struct LoggingSocket {
+    pub(crate) socket: ClientSocket,
+    pub(crate) log_file: Option<LogFile>,
+}
+
+impl Loopback for LoggingSocket {
+    type RequestStream = Pin<Box<dyn Stream<Item = Request> + Send>>;
+    type ResponseSink = <ClientSocket as Loopback>::ResponseSink;
+
+    fn split(self) -> (Self::RequestStream, Self::ResponseSink) {
+        let (requests, responses) = Loopback::split(self.socket);
+        let Some(log_file) = self.log_file else {
+            return (Box::pin(requests), responses);
+        };
+
+        let requests = requests.inspect(move |request| log_file.log_request(request));
+        (Box::pin(requests), responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn logged_messages_are_written() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("flb-log-file.log");
+        let log_file = LogFile::open(&path).unwrap();
+
+        log_file.log_request(
+            &Request::build("window/showMessage")
+                .params(json!({ "type": 1, "message": "failed to load fluent-bit schema" }))
+                .finish(),
+        );
+        log_file.log_request(
+            &Request::build("workspace/applyEdit")
+                .params(json!({ "edit": {} }))
+                .id(1)
+                .finish(),
+        );
+        log_file.write("INFO", "exiting");
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = contents
+            .lines()
+            .map(|line| line.split_once("s ").unwrap().1)
+            .collect();
+        assert_eq!(lines, [
+            "ERROR] failed to load fluent-bit schema",
+            "INFO ] exiting"
+        ]);
+    }
+}
@@ -0,0 +1,237 @@
+//! Logging of the server with `tracing`: events at the level of `--log-level`,
+//! or of the `logLevel` setting, and above are logged. Warnings and errors are
+//! sent to the client as `window/logMessage`, the other events are only
+//! written to the `--log-file`, not to flood the output of the editor, unless
+//! the client turns tracing on with `$/setTrace`: they're traced to it as
+//! `$/logTrace` then, their fields as the verbose details.
+
+use std::{
+    fmt::{Debug, Write},
+    sync::{Arc, OnceLock, RwLock},
+};
+
+use tower_lsp::{
+    lsp_types::{notification::LogTrace, LogTraceParams, MessageType, TraceValue},
+    Client,
+};
+use tracing::{
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    span::{Attributes, Id, Record},
+    subscriber::Interest,
+    Event, Level, Metadata, Subscriber,
+};
+
+use crate::log_file::LogFile;
+
+static LOGGER: OnceLock<Arc<Logger>> = OnceLock::new();
+
+pub(crate) struct Logger {
+    level: RwLock<LevelFilter>,
+    /// Whether `--log-level` set the level, which the setting doesn't override
+    /// then.
+    level_from_args: bool,
+    log_file: Option<LogFile>,
+    client: OnceLock<Client>,
+    trace: RwLock<TraceValue>,
+}
+
+impl Logger {
+    fn new(level: Option<LevelFilter>, log_file: Option<LogFile>) -> Self {
+        Self {
+            level: RwLock::new(level.unwrap_or(LevelFilter::INFO)),
+            level_from_args: level.is_some(),
+            log_file,
+            client: OnceLock::new(),
+            trace: RwLock::new(TraceValue::Off),
+        }
+    }
+
+    fn level(&self) -> LevelFilter {
+        self.level.read().map_or(LevelFilter::INFO, |level| *level)
+    }
+
+    fn trace(&self) -> TraceValue {
+        self.trace.read().map_or(TraceValue::Off, |trace| *trace)
+    }
+}
+
+/// Installs the logger of the server, `INFO` being the default level.
+pub(crate) fn init(level: Option<LevelFilter>, log_file: Option<LogFile>) {
+    let logger = LOGGER.get_or_init(|| Arc::new(Logger::new(level, log_file)));
+    let _ = tracing::subscriber::set_global_default(logger.clone());
+}
+
+/// Sets the client warnings and errors are sent to.
+pub(crate) fn set_client(client: Client) {
+    if let Some(logger) = LOGGER.get() {
+        let _ = logger.client.set(client);
+    }
+}
+
+/// Sets the level of the `logLevel` setting, unless `--log-level` was given.
+pub(crate) fn set_level(level: LevelFilter) {
+    let Some(logger) = LOGGER.get().filter(|logger| !logger.level_from_args) else {
+        return;
+    };
+    if let Ok(mut current) = logger.level.write() {
+        *current = level;
+    }
+}
+
+/// Sets the trace of `$/setTrace`, or of the `initialize` request: the events
+/// below warnings are traced to the client unless it's `off`.
+pub(crate) fn set_trace(trace: TraceValue) {
+    if let Some(logger) = LOGGER.get() {
+        if let Ok(mut current) = logger.trace.write() {
+            *current = trace;
+        }
+    }
+}
+
+/// Message of an event followed by its other fields, e.g.
+/// `file opened uri=file:///fluent-bit.conf`.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message.push_str(value),
+            name => {
+                let _ = write!(self.fields, " {name}={value}");
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        match field.name() {
+            "message" => {
+                let _ = write!(self.message, "{value:?}");
+            }
+            name => {
+                let _ = write!(self.fields, " {name}={value:?}");
+            }
+        }
+    }
+}
+
+/// `$/logTrace` of an event, its fields being the verbose details.
+fn trace_params(message: String, fields: &str, trace: TraceValue) -> LogTraceParams {
+    LogTraceParams {
+        message,
+        verbose: (trace == TraceValue::Verbose && !fields.is_empty())
+            .then(|| fields.trim_start().to_string()),
+    }
+}
+
+impl Subscriber for Logger {
+    fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+        // the level changes with the settings
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= &self.level()
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        // spans aren't recorded, only events
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let MessageVisitor { message, fields } = visitor;
+        // events of dependencies, e.g. tower-lsp
+        let own = metadata.target().starts_with(env!("CARGO_CRATE_NAME"));
+        let mut line = message.clone() + &fields;
+        if !own {
+            line = format!("{}: {line}", metadata.target());
+        }
+
+        let level = *metadata.level();
+        let runtime = tokio::runtime::Handle::try_current();
+        match (self.client.get(), runtime) {
+            // the log file gets them from the socket, as `window/logMessage`
+            (Some(client), Ok(runtime)) if level <= Level::WARN => {
+                let client = client.clone();
+                let typ = match level {
+                    Level::ERROR => MessageType::ERROR,
+                    _ => MessageType::WARNING,
+                };
+                runtime.spawn(async move { client.log_message(typ, line).await });
+            }
+            // not the ones of dependencies, which trace the messages sent,
+            // the traces included
+            (Some(client), Ok(runtime)) if own && self.trace() != TraceValue::Off => {
+                if let Some(log_file) = &self.log_file {
+                    log_file.write(level.as_str(), &line);
+                }
+                let client = client.clone();
+                let params = trace_params(message, &fields, self.trace());
+                runtime.spawn(async move { client.send_notification::<LogTrace>(params).await });
+            }
+            _ => {
+                if let Some(log_file) = &self.log_file {
+                    log_file.write(level.as_str(), &line);
+                }
+            }
+        }
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_at_the_level_are_logged() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("flb-logging.log");
+        let logger = Logger::new(None, Some(LogFile::open(&path).unwrap()));
+        *logger.level.write().unwrap() = LevelFilter::DEBUG;
+
+        tracing::subscriber::with_default(logger, || {
+            tracing::debug!(uri = "file:///fluent-bit.conf", version = 2, "file changed");
+            tracing::trace!("range changed");
+            tracing::warn!(target: "tower_lsp", "invalid request");
+        });
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = contents
+            .lines()
+            .map(|line| line.split_once("s ").unwrap().1)
+            .collect();
+        assert_eq!(lines, [
+            "DEBUG] file changed uri=file:///fluent-bit.conf version=2",
+            "WARN ] tower_lsp: invalid request",
+        ]);
+
+        let fields = " uri=file:///fluent-bit.conf version=2";
+        let params = trace_params("file changed".to_string(), fields, TraceValue::Messages);
+        assert_eq!(params.verbose, None);
+        let params = trace_params("file changed".to_string(), fields, TraceValue::Verbose);
+        assert_eq!(
+            params.verbose.as_deref(),
+            Some("uri=file:///fluent-bit.conf version=2")
+        );
+        assert_eq!(
+            trace_params("file changed".to_string(), "", TraceValue::Verbose).verbose,
+            None
+        );
+    }
+}
@@ -0,0 +1,603 @@
+//! fluent-bit specific extensions to the LSP.
+//!
+//! Extensions are advertised with their version in the `experimental`
+//! server capabilities (see [`ExperimentalCapabilities`]), a version being
+//! bumped on incompatible changes.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use flb_schema::section::FlbSectionType;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{
+    notification::Notification, request::Request, Location, Position, Range,
+    TextDocumentIdentifier, TextEdit, Url, VersionedTextDocumentIdentifier,
+};
+
+use crate::metrics::MethodLatency;
+
+/// Server capabilities of the extensions, returned as
+/// `capabilities.experimental` from `initialize`.
+///
+/// ```json
+/// {
+///     "fluentBit": {
+///         "requests": { "flb/plugins": 1, "flb/serverStatus": 1, "flb/workspaceStats": 1 },
+///         "notifications": { "flb/documentChecksum": 1 },
+///         "serverRequests": { "flb/resyncDocument": 1 },
+///         "serverNotifications": { "flb/documentAnalyzed": 1 },
+///         "commands": { "flb.scaffoldPipeline": 1 }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExperimentalCapabilities {
+    pub fluent_bit: ExtensionCapabilities,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionCapabilities {
+    /// Requests handled by the server, by method.
+    pub requests: BTreeMap<String, u32>,
+    /// Notifications handled by the server, by method.
+    pub notifications: BTreeMap<String, u32>,
+    /// Requests the server may send to the client, by method.
+    pub server_requests: BTreeMap<String, u32>,
+    /// Notifications the server sends to clients declaring them in
+    /// [`ClientExperimentalCapabilities`], by method.
+    pub server_notifications: BTreeMap<String, u32>,
+    /// `workspace/executeCommand` commands.
+    pub commands: BTreeMap<String, u32>,
+}
+
+impl ExperimentalCapabilities {
+    pub fn new() -> Self {
+        let versions = |extensions: &[(&str, u32)]| {
+            extensions
+                .iter()
+                .map(|(name, version)| (name.to_string(), *version))
+                .collect()
+        };
+
+        Self {
+            fluent_bit: ExtensionCapabilities {
+                requests: versions(&[
+                    (ServerStatus::METHOD, ServerStatus::VERSION),
+                    (Plugins::METHOD, Plugins::VERSION),
+                    (WorkspaceStats::METHOD, WorkspaceStats::VERSION),
+                    (PipelineHierarchy::METHOD, PipelineHierarchy::VERSION),
+                    (EmbeddedDocuments::METHOD, EmbeddedDocuments::VERSION),
+                    (SettingsSchema::METHOD, SettingsSchema::VERSION),
+                    (EffectiveSection::METHOD, EffectiveSection::VERSION),
+                    (GrammarInfo::METHOD, GrammarInfo::VERSION),
+                ]),
+                notifications: versions(&[(DocumentChecksum::METHOD, DocumentChecksum::VERSION)]),
+                server_requests: versions(&[(ResyncDocument::METHOD, ResyncDocument::VERSION)]),
+                server_notifications: versions(&[(
+                    DocumentAnalyzed::METHOD,
+                    DocumentAnalyzed::VERSION,
+                )]),
+                commands: versions(&[
+                    (SCAFFOLD_PIPELINE_COMMAND, SCAFFOLD_PIPELINE_VERSION),
+                    (RELOAD_SCHEMA_COMMAND, RELOAD_SCHEMA_VERSION),
+                    (FIX_ALL_COMMAND, FIX_ALL_VERSION),
+                    (MIGRATION_REPORT_COMMAND, MIGRATION_REPORT_VERSION),
+                ]),
+            },
+        }
+    }
+}
+
+/// Extensions the client handles, declared as `capabilities.experimental` in
+/// `initialize`.
+///
+/// ```json
+/// {
+///   "fluentBit": {
+///     "notifications": ["flb/documentAnalyzed"],
+///     "requests": ["flb/resyncDocument"]
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClientExperimentalCapabilities {
+    pub fluent_bit: ClientExtensionCapabilities,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClientExtensionCapabilities {
+    /// Server notifications the client handles, by method.
+    pub notifications: Vec<String>,
+    /// Server requests the client handles, by method.
+    pub requests: Vec<String>,
+}
+
+pub enum ServerStatus {}
+
+impl ServerStatus {
+    pub const VERSION: u32 = 1;
+}
+
+impl Request for ServerStatus {
+    type Params = ();
+    type Result = ServerStatusResult;
+    const METHOD: &'static str = "flb/serverStatus";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatusResult {
+    pub version: String,
+    pub open_documents: usize,
+    /// Runtime schema in use, `None` when using the builtin one.
+    pub schema_path: Option<PathBuf>,
+    /// Latency of handled requests and notifications, by method.
+    pub latency: Vec<MethodLatency>,
+}
+
+/// The grammar classic documents are parsed with, e.g. to report issues, and
+/// the node kinds and fields the server expects of it.
+pub enum GrammarInfo {}
+
+impl GrammarInfo {
+    pub const VERSION: u32 = 1;
+}
+
+impl Request for GrammarInfo {
+    type Params = ();
+    type Result = GrammarInfoResult;
+    const METHOD: &'static str = "flb/grammarInfo";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrammarInfoResult {
+    /// Version of tree-sitter-fluentbit
+    pub version: String,
+    /// Version of the tree-sitter ABI the grammar was generated for.
+    pub abi_version: usize,
+    pub node_kinds: Vec<String>,
+    pub fields: Vec<String>,
+    /// Expected node kinds and fields (`field:name`) the grammar lacks.
+    pub missing: Vec<String>,
+}
+
+/// JSON Schema of the settings the server accepts as `initializationOptions`,
+/// also printed by `fluent-bit-language-server --settings-schema`.
+pub enum SettingsSchema {}
+
+impl SettingsSchema {
+    pub const VERSION: u32 = 1;
+}
+
+impl Request for SettingsSchema {
+    type Params = ();
+    type Result = serde_json::Value;
+    const METHOD: &'static str = "flb/settingsSchema";
+}
+
+/// Statistics about the documents known to the server, e.g. to be shown in
+/// dashboards.
+pub enum WorkspaceStats {}
+
+impl WorkspaceStats {
+    pub const VERSION: u32 = 1;
+}
+
+impl Request for WorkspaceStats {
+    type Params = ();
+    type Result = WorkspaceStatsResult;
+    const METHOD: &'static str = "flb/workspaceStats";
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceStatsResult {
+    /// fluent-bit version of the schema in use.
+    pub fluent_bit_version: String,
+    /// Pipelines, not counting the parsers files.
+    pub configs: usize,
+    /// Documents only defining parsers, e.g. `parsers.conf`.
+    pub parsers_files: usize,
+    /// Number of sections by type, e.g. `{ "input": 2, "output": 1 }`
+    pub sections: BTreeMap<String, usize>,
+    /// Sorted by section type and name.
+    pub plugins: Vec<PluginUsage>,
+    pub diagnostics: DiagnosticCounts,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginUsage {
+    /// e.g. `input`
+    pub section_type: String,
+    pub name: String,
+    /// Number of sections using the plugin.
+    pub count: usize,
+    /// Whether the plugin is part of the schema's fluent-bit version.
+    pub available: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticCounts {
+    pub error: usize,
+    pub warning: usize,
+    pub information: usize,
+    pub hint: usize,
+}
+
+/// Routing of the records of a document: its inputs, each with the filters
+/// and outputs matching its tag, e.g. to be shown in a tree view.
+pub enum PipelineHierarchy {}
+
+impl PipelineHierarchy {
+    pub const VERSION: u32 = 1;
+}
+
+impl Request for PipelineHierarchy {
+    type Params = PipelineHierarchyParams;
+    type Result = PipelineHierarchyResult;
+    const METHOD: &'static str = "flb/pipelineHierarchy";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineHierarchyParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineHierarchyResult {
+    /// In the order of the document.
+    pub inputs: Vec<PipelineInput>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineInput {
+    pub plugin_name: String,
+    /// `Tag` of the input, or the one fluent-bit gives it (e.g. `cpu.0`).
+    pub tag: String,
+    /// Range of the section.
+    pub location: Location,
+    /// In the order records go through them.
+    pub filters: Vec<PipelineStage>,
+    pub outputs: Vec<PipelineStage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineStage {
+    pub plugin_name: Option<String>,
+    /// `Match` pattern, or `Match_Regex` regular expression.
+    pub rule: String,
+    pub location: Location,
+}
+
+/// Lists the fluent-bit configurations embedded in a YAML document, e.g. the
+/// `fluent-bit.conf: |` of a Helm ConfigMap, which clients may open as virtual
+/// documents. Their diagnostics are reported with the document's.
+pub enum EmbeddedDocuments {}
+
+impl EmbeddedDocuments {
+    pub const VERSION: u32 = 1;
+}
+
+impl Request for EmbeddedDocuments {
+    type Params = EmbeddedDocumentsParams;
+    type Result = Vec<EmbeddedDocumentInfo>;
+    const METHOD: &'static str = "flb/embeddedDocuments";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedDocumentsParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedDocumentInfo {
+    /// Name of the file, e.g. `fluent-bit.conf`, or its path.
+    pub name: String,
+    pub format: ConfigFormat,
+    /// Range of the content in the document.
+    pub range: Range,
+    /// Content, without its indentation.
+    pub content: String,
+}
+
+/// Values fluent-bit uses for the plugin of the section at a position: the ones
+/// set by the section, with the variables of the configuration substituted,
+/// and the defaults of the other parameters. `null` outside of sections.
+pub enum EffectiveSection {}
+
+impl EffectiveSection {
+    pub const VERSION: u32 = 1;
+}
+
+impl Request for EffectiveSection {
+    type Params = EffectiveSectionParams;
+    type Result = Option<EffectiveSectionResult>;
+    const METHOD: &'static str = "flb/effectiveSection";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveSectionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveSectionResult {
+    pub section_type: FlbSectionType,
+    pub plugin_name: Option<String>,
+    /// Range of the section.
+    pub range: Range,
+    /// Set ones in the order of the section, then defaults.
+    pub parameters: Vec<EffectiveParameter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveParameter {
+    pub key: String,
+    /// Value with the variables of the configuration substituted.
+    pub value: String,
+    pub source: ValueSource,
+    /// Value as written, for set ones.
+    pub raw: Option<String>,
+    /// Range of the entry, for set ones.
+    pub range: Option<Range>,
+    /// Variables the configuration doesn't set, only known at runtime from the
+    /// environment.
+    pub unresolved: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValueSource {
+    /// Default of the schema.
+    Default,
+    /// Set by the section.
+    Explicit,
+    /// Set by the section, referencing variables set by the configuration
+    /// (`@SET`, or `env` in YAML).
+    Variable,
+    /// Set by the section, referencing environment variables.
+    Environment,
+}
+
+/// `data` of the diagnostics reported by the server, so that tools consuming
+/// them can apply fixes without analyzing the document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticData {
+    /// Rule reporting the diagnostic, also its `code`, e.g. `duplicate-key`
+    pub rule: String,
+    pub fix: Option<DiagnosticFix>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticFix {
+    pub title: String,
+    /// Edits of the document, applied together.
+    pub edits: Vec<TextEdit>,
+}
+
+/// Sent by the server when it lost track of a document's content, e.g. after
+/// an invalid change or a change to an older version, to clients declaring it
+/// in their `requests`. The client should send the whole content again with a
+/// range-less `textDocument/didChange` (or close and reopen the document).
+pub enum ResyncDocument {}
+
+impl ResyncDocument {
+    pub const VERSION: u32 = 1;
+}
+
+impl Request for ResyncDocument {
+    type Params = ResyncDocumentParams;
+    type Result = ();
+    const METHOD: &'static str = "flb/resyncDocument";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResyncDocumentParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// Sent by the client after the changes of a version of a document, for the
+/// server to check that applying them gave the client's content. On a
+/// mismatch, the document is out of sync: it isn't analyzed until the client
+/// sends its whole content again, as requested by [`ResyncDocument`].
+pub enum DocumentChecksum {}
+
+impl DocumentChecksum {
+    pub const VERSION: u32 = 1;
+}
+
+impl Notification for DocumentChecksum {
+    type Params = DocumentChecksumParams;
+    const METHOD: &'static str = "flb/documentChecksum";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentChecksumParams {
+    pub text_document: VersionedTextDocumentIdentifier,
+    /// Length of the content, in code units of the negotiated position
+    /// encoding.
+    pub length: usize,
+    /// 32-bit FNV-1a hash of the UTF-8 content, if the client computes it.
+    pub hash: Option<u32>,
+}
+
+/// Sent after each analysis of an open document, e.g. for clients to show its
+/// problems in a status bar without polling.
+pub enum DocumentAnalyzed {}
+
+impl DocumentAnalyzed {
+    pub const VERSION: u32 = 1;
+}
+
+impl Notification for DocumentAnalyzed {
+    type Params = DocumentAnalyzedParams;
+    const METHOD: &'static str = "flb/documentAnalyzed";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentAnalyzedParams {
+    pub text_document: TextDocumentIdentifier,
+    /// Version of the document analyzed, `None` until the client sent one.
+    pub version: Option<i32>,
+    pub sections: usize,
+    pub diagnostics: DiagnosticCounts,
+    /// Time the analysis took.
+    pub duration_ms: f64,
+}
+
+/// Lists the plugins known for a section type, e.g. to build plugin pickers in
+/// clients.
+pub enum Plugins {}
+
+impl Plugins {
+    pub const VERSION: u32 = 1;
+}
+
+impl Request for Plugins {
+    type Params = PluginsParams;
+    type Result = PluginsResult;
+    const METHOD: &'static str = "flb/plugins";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginsParams {
+    /// e.g. `input`, `filter`, `output`
+    pub section_type: FlbSectionType,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginsResult {
+    /// fluent-bit version of the schema in use.
+    pub fluent_bit_version: String,
+    pub plugins: Vec<PluginInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    /// Name used in the configuration file, e.g. `tail`
+    pub name: String,
+    /// Human readable name, e.g. `Tail`
+    pub label: String,
+    pub description: String,
+    pub documentation_url: Option<String>,
+    /// Whether the plugin exists in the fluent-bit version of the schema in
+    /// use. Plugins only known to the builtin schema are listed as
+    /// unavailable.
+    pub available: bool,
+}
+
+/// `workspace/executeCommand` command creating a new configuration file with
+/// the chosen plugins, see [`ScaffoldPipelineArguments`].
+pub const SCAFFOLD_PIPELINE_COMMAND: &str = "flb.scaffoldPipeline";
+pub const SCAFFOLD_PIPELINE_VERSION: u32 = 1;
+
+/// `workspace/executeCommand` command reading the runtime schema file again,
+/// e.g. after switching to another fluent-bit version. Takes no arguments.
+pub const RELOAD_SCHEMA_COMMAND: &str = "flb.reloadSchema";
+pub const RELOAD_SCHEMA_VERSION: u32 = 1;
+
+/// `workspace/executeCommand` command applying the fixes of all the problems
+/// of a document through `workspace/applyEdit`. Takes the URI of the document.
+///
+/// Fallback of the `source.fixAll` code action for clients which can't review
+/// annotated edits.
+pub const FIX_ALL_COMMAND: &str = "flb.fixAll";
+pub const FIX_ALL_VERSION: u32 = 1;
+
+/// `workspace/executeCommand` command listing the keys of the configuration
+/// which were renamed or removed between two fluent-bit versions, see
+/// [`MigrationReportArguments`]. Returns a [`MigrationReportResult`].
+pub const MIGRATION_REPORT_COMMAND: &str = "flb.migrationReport";
+pub const MIGRATION_REPORT_VERSION: u32 = 1;
+
+/// Format of a configuration file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigFormat {
+    #[default]
+    Classic,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Format of a file, told by its extension.
+    pub fn from_uri(uri: &Url) -> Self {
+        let path = uri.path().to_ascii_lowercase();
+        if path.ends_with(".yaml") || path.ends_with(".yml") {
+            ConfigFormat::Yaml
+        } else {
+            ConfigFormat::Classic
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReportArguments {
+    /// Version the configuration was written for, e.g. `1.9`
+    pub from: String,
+    /// Version to migrate it to, e.g. `3.1`
+    pub to: String,
+    /// Whether to rename the renamed keys through `workspace/applyEdit`.
+    #[serde(default)]
+    pub apply: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReportResult {
+    pub from: String,
+    pub to: String,
+    pub changes: Vec<MigrationChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationChange {
+    pub uri: Url,
+    /// Of the key
+    pub range: Range,
+    pub message: String,
+    /// Key it was renamed to, `None` if it was removed.
+    pub replacement: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScaffoldPipelineArguments {
+    /// File to create, it must not exist yet.
+    pub uri: Url,
+    #[serde(default)]
+    pub format: ConfigFormat,
+    /// Plugin names, e.g. `["tail"]`
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub filters: Vec<String>,
+    #[serde(default)]
+    pub outputs: Vec<String>,
+}
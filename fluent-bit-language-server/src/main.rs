@@ -1,27 +1,192 @@
-use std::collections::HashMap;
+use std::path::PathBuf;
 
-use tokio::sync::RwLock;
-use tower_lsp::{LspService, Server};
+use clap::{Parser, Subcommand};
+use tower::Layer;
+use tower_lsp::{
+    lsp_types::{
+        notification::{Notification, SetTrace},
+        request::Request,
+    },
+    LspService, Server,
+};
+use tracing::level_filters::LevelFilter;
 
-use crate::language_server::Backend;
+use crate::{
+    cli::LintArgs,
+    language_server::Backend,
+    log_file::{LogFile, LoggingSocket},
+    lsp_ext::{
+        DocumentChecksum, EffectiveSection, EmbeddedDocuments, GrammarInfo, PipelineHierarchy,
+        Plugins, ServerStatus, SettingsSchema, WorkspaceStats,
+    },
+    metrics::LatencyLayer,
+    settings::Settings,
+    timeouts::TimeoutLayer,
+    transport::Transport,
+};
 
 mod assets;
+mod baseline;
+mod cli;
 mod completion;
+mod debounce;
+mod diagnostic_rules;
+mod diagnostics;
+mod disk_cache;
 mod document;
+mod document_map;
+mod effective;
+mod embedded;
+mod encoding;
+mod file_role;
+mod fixes;
+mod folding;
+mod formatting;
+mod grammar;
+mod i18n;
+mod include_graph;
+mod inlay_hints;
 mod language_server;
+mod lints;
+mod log_file;
+mod logging;
+mod lsp_ext;
+mod markdown;
+mod metrics;
+mod migration;
+mod overlay;
+mod overrides;
+mod parser_index;
+mod path_checks;
+mod pipeline_summary;
+mod plugins;
+mod positions;
+mod progress;
+mod regexes;
+mod routing;
+mod rules;
+mod sarif;
+mod scaffold;
+mod schema_store;
+mod scope;
+mod section_index;
+mod section_names;
+mod semantic_tokens;
+mod service;
+mod settings;
+mod signature_help;
+mod stats;
+mod symbols;
+mod timeouts;
+mod transport;
+mod tree_walk;
+mod validation;
+mod values;
+mod variables;
+mod versions;
+mod workspace_edit;
+mod yaml;
+mod yaml_env;
+
+/// Language server for fluent-bit configuration files, talking to the editor
+/// over stdio unless `--listen` is given.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Talk to the editor over stdio (default)
+    #[arg(long, conflicts_with = "listen")]
+    stdio: bool,
+
+    /// Listen on this TCP address, e.g. `127.0.0.1:9257`, and talk to the
+    /// first editor connecting
+    #[arg(long, value_name = "ADDR")]
+    listen: Option<String>,
+
+    /// Talk to the editor connecting to `--listen` over WebSocket, e.g. from
+    /// a browser
+    #[arg(long, requires = "listen")]
+    websocket: bool,
+
+    /// Accept the WebSocket handshakes of browsers from this origin, e.g.
+    /// `https://vscode.dev`, besides the ones from the address listened on
+    #[arg(long, value_name = "ORIGIN", requires = "websocket")]
+    allow_origin: Vec<String>,
+
+    /// Append the messages logged, and panics, to this file
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Level of the messages logged (off, error, warn, info, debug or trace),
+    /// overriding the `logLevel` setting [default: info]
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<LevelFilter>,
+
+    /// Print the JSON Schema of the settings and exit
+    #[arg(long)]
+    settings_schema: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Report the diagnostics of configuration files, e.g. in CI
+    Lint(LintArgs),
+}
 
 #[tokio::main]
 async fn main() {
-    let stdin = tokio::io::stdin();
-    let stdout = tokio::io::stdout();
+    let args = Args::parse();
+    if args.settings_schema {
+        println!("{:#}", Settings::json_schema());
+        return;
+    }
+    if let Some(Command::Lint(lint_args)) = args.command {
+        std::process::exit(cli::lint(lint_args).await);
+    }
+    let log_file = args.log_file.as_deref().map(|path| {
+        LogFile::open(path).unwrap_or_else(|e| {
+            eprintln!("error: failed to open {}: {e}", path.display());
+            std::process::exit(2);
+        })
+    });
+    if let Some(log_file) = &log_file {
+        log_file.log_panics();
+    }
+    logging::init(args.log_level, log_file.clone());
 
-    let (service, socket) = LspService::build(|client| Backend {
-        client,
-        map: RwLock::new(HashMap::new()),
+    let (service, socket) = LspService::build(|client| {
+        logging::set_client(client.clone());
+        Backend::new(client)
     })
+    .custom_method(ServerStatus::METHOD, Backend::server_status)
+    .custom_method(Plugins::METHOD, Backend::plugins)
+    .custom_method(WorkspaceStats::METHOD, Backend::workspace_stats)
+    .custom_method(PipelineHierarchy::METHOD, Backend::pipeline_hierarchy)
+    .custom_method(EmbeddedDocuments::METHOD, Backend::embedded_documents)
+    .custom_method(SettingsSchema::METHOD, Backend::settings_schema)
+    .custom_method(EffectiveSection::METHOD, Backend::effective_section)
+    .custom_method(GrammarInfo::METHOD, Backend::grammar_info)
+    .custom_method(SetTrace::METHOD, Backend::set_trace)
+    .custom_method(DocumentChecksum::METHOD, Backend::document_checksum)
     .finish();
+    let metrics = service.inner().metrics.clone();
+    let service = TimeoutLayer::new(service.inner().limits.clone()).layer(service);
+    let service = LatencyLayer::new(metrics).layer(service);
+    let socket = LoggingSocket { socket, log_file };
 
-    // TODO: support other commands (e.g. `--version`)
-
-    Server::new(stdin, stdout, socket).serve(service).await;
+    let transport = match args.listen {
+        Some(addr) if args.websocket => Transport::WebSocket {
+            addr,
+            allowed_origins: args.allow_origin,
+        },
+        Some(addr) => Transport::Tcp(addr),
+        None => Transport::Stdio,
+    };
+    let (input, output) = transport.connect().await.unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        std::process::exit(2);
+    });
+    Server::new(input, output, socket).serve(service).await;
 }
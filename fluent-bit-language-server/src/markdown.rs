@@ -0,0 +1,239 @@
+//! Post-processing of documentation shown in hovers and completions.
+//!
+//! The bundled docs are scraped from the fluent-bit documentation and may
+//! contain raw HTML or huge tables, which render badly (or not at all) in some
+//! clients. Documentation is sanitized and clamped before being sent.
+
+use tower_lsp::lsp_types::{MarkupContent, MarkupKind};
+
+/// Maximum length (in bytes) of documentation sent to the client, unless
+/// configured otherwise.
+pub(crate) const DEFAULT_MAX_LENGTH: usize = 6000;
+
+const CODE_FENCE: &str = "```";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct MarkupOptions {
+    /// Format the client is able to render.
+    pub(crate) kind: MarkupKind,
+    pub(crate) max_length: Option<usize>,
+}
+
+impl MarkupOptions {
+    /// Picks the format from the ones supported by the client, in the order
+    /// they were given. Markdown is assumed if the client didn't tell.
+    pub(crate) fn from_client_formats(
+        formats: Option<&Vec<MarkupKind>>,
+        max_length: Option<usize>,
+    ) -> Self {
+        let kind = match formats {
+            Some(formats) if !formats.is_empty() && !formats.contains(&MarkupKind::Markdown) => {
+                MarkupKind::PlainText
+            }
+            _ => MarkupKind::Markdown,
+        };
+
+        Self { kind, max_length }
+    }
+
+    /// Sanitizes `markdown` and clamps it to the maximum length, linking to
+    /// `read_more_url` when something was cut off.
+    pub(crate) fn render(&self, markdown: &str, read_more_url: Option<&str>) -> MarkupContent {
+        let mut value = strip_html(markdown);
+
+        if let Some(max_length) = self.max_length {
+            if value.len() > max_length {
+                value = clamp(&value, max_length);
+                value.push_str("\n\n…");
+                if let Some(url) = read_more_url {
+                    match self.kind {
+                        MarkupKind::Markdown => {
+                            value.push_str(&format!("\n\n[Read more]({url})"));
+                        }
+                        MarkupKind::PlainText => {
+                            value.push_str(&format!("\n\nRead more: {url}"));
+                        }
+                    }
+                }
+            }
+        }
+
+        MarkupContent {
+            kind: self.kind.clone(),
+            value,
+        }
+    }
+}
+
+/// Removes raw HTML tags, leaving their text content. Code blocks and code
+/// spans are kept as is.
+pub(crate) fn strip_html(markdown: &str) -> String {
+    let mut ret = String::with_capacity(markdown.len());
+    let mut in_code_block = false;
+
+    for line in markdown.split_inclusive('\n') {
+        if line.trim_start().starts_with(CODE_FENCE) {
+            in_code_block = !in_code_block;
+            ret.push_str(line);
+            continue;
+        }
+        if in_code_block {
+            ret.push_str(line);
+            continue;
+        }
+
+        let mut in_code_span = false;
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == '`' {
+                in_code_span = !in_code_span;
+            } else if c == '<' && !in_code_span && is_tag_start(&line[i + 1..]) {
+                if let Some(end) = line[i..].find('>') {
+                    // skip the whole tag
+                    while chars.peek().is_some_and(|(j, _)| *j <= i + end) {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+            ret.push(c);
+        }
+    }
+
+    ret
+}
+
+/// First paragraph of prose in `markdown`, skipping the front matter,
+/// headings, images and comments which usually come first in plugin docs.
+pub(crate) fn summary(markdown: &str) -> String {
+    split_summary(markdown).0
+}
+
+/// First paragraph of prose in `markdown`, as [`summary`], and the rest of the
+/// document after it.
+pub(crate) fn split_summary(markdown: &str) -> (String, &str) {
+    let lines: Vec<&str> = markdown.split_inclusive('\n').collect();
+    let mut i = 0;
+    if lines.first().is_some_and(|line| line.trim() == "---") {
+        i = lines[1..]
+            .iter()
+            .position(|line| line.trim() == "---")
+            .map_or(lines.len(), |end| end + 2);
+    }
+
+    let is_prose = |line: &str| {
+        !(line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("![")
+            || line.starts_with("[//]")
+            || line.starts_with("{%")
+            || line.starts_with('<'))
+    };
+    while i < lines.len() && !is_prose(lines[i].trim()) {
+        i += 1;
+    }
+    let start = i;
+    while i < lines.len() && !lines[i].trim().is_empty() {
+        i += 1;
+    }
+
+    let paragraph: Vec<&str> = lines[start..i].iter().map(|line| line.trim()).collect();
+    let rest = &markdown[lines[..i].iter().map(|line| line.len()).sum::<usize>()..];
+    (paragraph.join(" "), rest.trim_start())
+}
+
+fn is_tag_start(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!')
+}
+
+/// Cuts `markdown` at a paragraph (or line) boundary not beyond `max_length`,
+/// closing a code block left open by the cut.
+fn clamp(markdown: &str, max_length: usize) -> String {
+    let mut end = max_length.min(markdown.len());
+    while !markdown.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let head = &markdown[..end];
+    let cut = head
+        .rfind("\n\n")
+        .or_else(|| head.rfind('\n'))
+        .unwrap_or(end);
+    let mut ret = markdown[..cut].trim_end().to_string();
+
+    let open_fences = ret
+        .lines()
+        .filter(|line| line.trim_start().starts_with(CODE_FENCE))
+        .count();
+    if open_fences % 2 == 1 {
+        ret.push('\n');
+        ret.push_str(CODE_FENCE);
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_html_keeps_code() {
+        let markdown = "<p align=\"center\">Hello <b>world</b></p>\n\
+                        `<not a tag>` and 1 < 2\n\
+                        ```\n<tag>\n```\n";
+
+        assert_eq!(
+            strip_html(markdown),
+            "Hello world\n`<not a tag>` and 1 < 2\n```\n<tag>\n```\n"
+        );
+    }
+
+    #[test]
+    fn render_clamps_with_read_more() {
+        let markdown = "# Title\n\nfirst paragraph\n\n```\nlong code block\n\nstill code\n```\n";
+        let options = MarkupOptions {
+            kind: MarkupKind::Markdown,
+            max_length: Some(50),
+        };
+
+        let content = options.render(markdown, Some("https://docs.fluentbit.io"));
+        assert_eq!(
+            content.value,
+            "# Title\n\nfirst paragraph\n\n```\nlong code block\n```\n\n…\n\n[Read more](https://docs.fluentbit.io)"
+        );
+
+        let content = options.render("short", Some("https://docs.fluentbit.io"));
+        assert_eq!(content.value, "short");
+    }
+
+    #[test]
+    fn summary_skips_front_matter_and_headings() {
+        let markdown = "---\ndescription: Send logs to S3\n---\n\n# Amazon S3\n\n\
+                        ![](image.png)\n\nThe Amazon S3 output plugin\nallows you to ingest.\n\n\
+                        ## Configuration\n";
+
+        assert_eq!(
+            summary(markdown),
+            "The Amazon S3 output plugin allows you to ingest."
+        );
+        assert_eq!(summary("Tail files"), "Tail files");
+        assert_eq!(split_summary(markdown).1, "## Configuration\n");
+        assert_eq!(split_summary("Tail files"), ("Tail files".to_string(), ""));
+    }
+
+    #[test]
+    fn markup_kind_from_client_formats() {
+        let options = MarkupOptions::from_client_formats(Some(&vec![MarkupKind::PlainText]), None);
+        assert_eq!(options.kind, MarkupKind::PlainText);
+
+        let options = MarkupOptions::from_client_formats(
+            Some(&vec![MarkupKind::PlainText, MarkupKind::Markdown]),
+            None,
+        );
+        assert_eq!(options.kind, MarkupKind::Markdown);
+
+        let options = MarkupOptions::from_client_formats(None, None);
+        assert_eq!(options.kind, MarkupKind::Markdown);
+    }
+}
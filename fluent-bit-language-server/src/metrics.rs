@@ -0,0 +1,200 @@
+//! Per-method latency of handled requests and notifications.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tower::{Layer, Service};
+use tower_lsp::{
+    jsonrpc::Request,
+    lsp_types::{notification::LogTrace, LogTraceParams},
+    Client,
+};
+use tracing::info;
+
+/// Number of latest samples kept per method.
+const MAX_SAMPLES: usize = 512;
+
+pub struct RequestMetrics {
+    client: Client,
+    /// `logRequestLatency`.
+    log: AtomicBool,
+    /// Whether the client turned a `verbose` trace on.
+    trace: AtomicBool,
+    samples: Mutex<HashMap<String, MethodSamples>>,
+}
+
+#[derive(Default)]
+struct MethodSamples {
+    count: u64,
+    latest: VecDeque<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodLatency {
+    pub method: String,
+    /// Number of handled messages, including the ones no longer sampled.
+    pub count: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+}
+
+impl RequestMetrics {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            log: AtomicBool::new(false),
+            trace: AtomicBool::new(false),
+            samples: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether each handled message should be logged with its latency, the
+    /// `logRequestLatency` setting.
+    pub fn set_log(&self, log: bool) {
+        self.log.store(log, Ordering::Relaxed);
+    }
+
+    /// Whether each handled message should be traced with its latency, with a
+    /// `verbose` trace.
+    pub fn set_trace(&self, trace: bool) {
+        self.trace.store(trace, Ordering::Relaxed);
+    }
+
+    fn record(&self, method: &str, elapsed: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        let method_samples = samples.entry(method.to_string()).or_default();
+        method_samples.count += 1;
+        if method_samples.latest.len() == MAX_SAMPLES {
+            method_samples.latest.pop_front();
+        }
+        method_samples.latest.push_back(elapsed);
+    }
+
+    pub fn latencies(&self) -> Vec<MethodLatency> {
+        let samples = self.samples.lock().unwrap();
+
+        let mut ret: Vec<MethodLatency> = samples
+            .iter()
+            .map(|(method, samples)| {
+                let mut sorted: Vec<Duration> = samples.latest.iter().copied().collect();
+                sorted.sort();
+
+                MethodLatency {
+                    method: method.clone(),
+                    count: samples.count,
+                    p50_ms: percentile(&sorted, 50).as_secs_f64() * 1000.0,
+                    p95_ms: percentile(&sorted, 95).as_secs_f64() * 1000.0,
+                }
+            })
+            .collect();
+        ret.sort_by(|a, b| a.method.cmp(&b.method));
+        ret
+    }
+}
+
+/// Nearest-rank percentile of already sorted samples.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// Records the latency of every message handled by the wrapped service.
+pub struct LatencyLayer {
+    metrics: Arc<RequestMetrics>,
+}
+
+impl LatencyLayer {
+    pub fn new(metrics: Arc<RequestMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> Layer<S> for LatencyLayer {
+    type Service = LatencyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LatencyService {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+pub struct LatencyService<S> {
+    inner: S,
+    metrics: Arc<RequestMetrics>,
+}
+
+impl<S> Service<Request> for LatencyService<S>
+where
+    S: Service<Request>,
+    S::Response: Send,
+    S::Error: Send,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let method = req.method().to_string();
+        let metrics = self.metrics.clone();
+        let start = Instant::now();
+        let future = self.inner.call(req);
+
+        Box::pin(async move {
+            let response = future.await;
+
+            let elapsed = start.elapsed();
+            metrics.record(&method, elapsed);
+            let message = format!("{method} took {:.3}ms", elapsed.as_secs_f64() * 1000.0);
+            if metrics.trace.load(Ordering::Relaxed) {
+                metrics
+                    .client
+                    .send_notification::<LogTrace>(LogTraceParams {
+                        message,
+                        verbose: None,
+                    })
+                    .await;
+            } else if metrics.log.load(Ordering::Relaxed) {
+                info!("{message}");
+            }
+
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentile() {
+        let samples: Vec<Duration> = (1..=20).map(Duration::from_millis).collect();
+
+        assert_eq!(percentile(&samples, 50), Duration::from_millis(10));
+        assert_eq!(percentile(&samples, 95), Duration::from_millis(19));
+        assert_eq!(percentile(&samples[..1], 95), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50), Duration::ZERO);
+    }
+}
@@ -0,0 +1,175 @@
+//! Migration of configurations between fluent-bit versions: the keys versions
+//! renamed or removed, listed by the `flb.migrationReport` command for a source
+//! and a target version, and reported with a fix for the renames when the
+//! `fluentBitVersion` setting targets a version which changed them.
+//!
+//! Migrations are generated by `cargo xtask generate`, from the schemas
+//! `flb-schema` bundles and the renames xtask lists.
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, TextEdit};
+
+use crate::{
+    document::TextDocument,
+    i18n::t,
+    lsp_ext::DiagnosticFix,
+    positions::{byte_range_to_lsp_range, resolve_position, PositionEncodingKind},
+    section_index::SectionIndex,
+    versions::FlbVersion,
+};
+
+/// Diagnostic code of a key the targeted version renamed or removed.
+pub(crate) const OUTDATED_KEY: &str = "outdated-key";
+
+/// Key of the sections of a plugin a version renamed or removed.
+struct Migration {
+    version: &'static str,
+    section_type: FlbSectionType,
+    plugin_name: &'static str,
+    key: &'static str,
+    /// Key replacing it, `None` if it was removed.
+    renamed_to: Option<&'static str>,
+}
+
+include!("migrations.generated.rs");
+
+/// Key of a document to migrate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct MigratedKey {
+    pub(crate) range: std::ops::Range<usize>,
+    pub(crate) message: String,
+    /// Key to rename it to, in the case of the document.
+    pub(crate) replacement: Option<String>,
+}
+
+/// Keys of the document which versions after `from`, up to `to` included,
+/// renamed or removed, any version when they're `None`.
+pub(crate) fn migrated_keys(
+    index: &SectionIndex,
+    from: Option<FlbVersion>,
+    to: Option<FlbVersion>,
+) -> Vec<MigratedKey> {
+    let migrations: Vec<_> = MIGRATIONS
+        .iter()
+        .filter_map(|migration| Some((migration, migration.version.parse::<FlbVersion>().ok()?)))
+        .filter(|(_, version)| {
+            from.map_or(true, |from| from < *version) && to.map_or(true, |to| *version <= to)
+        })
+        .collect();
+    let mut keys = Vec::new();
+
+    for section in index.sections() {
+        let Some(plugin_name) = section.get_value("Name") else {
+            continue;
+        };
+        let section_migrations = migrations.iter().filter(|(migration, _)| {
+            migration.section_type == section.section_type
+                && migration.plugin_name.eq_ignore_ascii_case(plugin_name)
+        });
+        for (migration, version) in section_migrations {
+            let entries = section
+                .entries
+                .iter()
+                .filter(|entry| entry.key.eq_ignore_ascii_case(migration.key));
+            for entry in entries {
+                // YAML keys are lowercase
+                let replacement = migration.renamed_to.map(|key| {
+                    match entry.key.chars().any(char::is_uppercase) {
+                        true => key.to_string(),
+                        false => key.to_lowercase(),
+                    }
+                });
+                let message = match &replacement {
+                    Some(replacement) => t!(
+                        "migration-renamed",
+                        key = entry.key,
+                        replacement = replacement,
+                        version = version,
+                    ),
+                    None => t!("migration-removed", key = entry.key, version = version),
+                };
+                keys.push(MigratedKey {
+                    range: entry.key_range.clone(),
+                    message,
+                    replacement,
+                });
+            }
+        }
+    }
+
+    keys
+}
+
+/// Reports the keys the targeted version renamed or removed.
+pub(crate) fn check_migrations(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+    version: FlbVersion,
+) -> Vec<Diagnostic> {
+    migrated_keys(index, None, Some(version))
+        .into_iter()
+        .map(|key| Diagnostic {
+            range: byte_range_to_lsp_range(rope, key.range, position_encoding),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(OUTDATED_KEY.to_string())),
+            message: key.message,
+            ..Diagnostic::default()
+        })
+        .collect()
+}
+
+/// Renames the outdated key to the key replacing it.
+pub(crate) fn outdated_key_fix(
+    document: &TextDocument,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    if diagnostic.code != Some(NumberOrString::String(OUTDATED_KEY.to_string())) {
+        return None;
+    }
+
+    let start =
+        resolve_position(&document.rope, &diagnostic.range.start, position_encoding).ok()?;
+    let replacement = migrated_keys(&document.index, None, None)
+        .into_iter()
+        .find(|key| key.range.start == start.byte_idx)?
+        .replacement?;
+
+    Some(DiagnosticFix {
+        title: t!("fix-replace-key", suggestion = replacement),
+        edits: vec![TextEdit {
+            range: diagnostic.range,
+            new_text: replacement,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_renamed_between_versions() {
+        let text = "[FILTER]\n    Name           kubernetes\n    Match          kube.*\n    Merge_JSON_Log On\n";
+        let document = TextDocument::new(text);
+        let version = |version: &str| version.parse::<FlbVersion>().ok();
+
+        let keys = migrated_keys(&document.index, version("0.12"), version("1.9"));
+        let start = text.find("Merge_JSON_Log").unwrap();
+        assert_eq!(keys, [MigratedKey {
+            range: start..start + "Merge_JSON_Log".len(),
+            message: "`Merge_JSON_Log` was renamed `Merge_Log` in fluent-bit 0.13.0.".to_string(),
+            replacement: Some("Merge_Log".to_string()),
+        }]);
+        assert!(migrated_keys(&document.index, version("1.0"), version("1.9")).is_empty());
+
+        let encoding = PositionEncodingKind::UTF16;
+        let target = version("2.2").unwrap();
+        let diagnostics = check_migrations(&document.index, &document.rope, encoding, target);
+        let fix = outdated_key_fix(&document, &diagnostics[0], encoding).unwrap();
+        assert_eq!(fix.title, "Replace with `Merge_Log`");
+        assert_eq!(fix.edits[0].new_text, "Merge_Log");
+    }
+}
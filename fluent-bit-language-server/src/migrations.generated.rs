@@ -0,0 +1,19 @@
+/// Generated by `cargo xtask generate`
+/// Don't modify this file manually.
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "0.13.0",
+        section_type: FlbSectionType::Filter,
+        plugin_name: "kubernetes",
+        key: "Merge_JSON_Log",
+        renamed_to: Some("Merge_Log"),
+    },
+    Migration {
+        version: "0.13.0",
+        section_type: FlbSectionType::Filter,
+        plugin_name: "kubernetes",
+        key: "Merge_JSON_Key",
+        renamed_to: Some("Merge_Log_Key"),
+    },
+];
@@ -0,0 +1,403 @@
+//! Curated knowledge about parameters which the fluent-bit schema lacks, e.g.
+//! the range of values an integer parameter accepts, whether it may be set
+//! multiple times, whether it's a path, its type when the schema doesn't tell,
+//! or whether it's only supported by YAML configurations.
+//!
+//! Entries are maintained by hand from the fluent-bit documentation.
+
+use std::fmt::Display;
+
+use flb_schema::{config::FlbPropertyType, section::FlbSectionType};
+
+use crate::{
+    i18n::t,
+    values::{group_thousands, locale_hint, parse_integer},
+};
+
+/// Range of an integer parameter, which may also accept some keywords.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct IntegerBounds {
+    pub(crate) min: i64,
+    pub(crate) max: Option<i64>,
+    /// Non-integer values accepted as well (case insensitive).
+    pub(crate) keywords: &'static [&'static str],
+}
+
+impl IntegerBounds {
+    /// Checks `value`, returning why it isn't accepted.
+    pub(crate) fn check(&self, value: &str) -> Result<(), String> {
+        if self
+            .keywords
+            .iter()
+            .any(|keyword| keyword.eq_ignore_ascii_case(value))
+        {
+            return Ok(());
+        }
+
+        match parse_integer(value) {
+            Some(n) if n >= self.min && self.max.map_or(true, |max| n <= max) => Ok(()),
+            Some(n) => Err(t!("out-of-range", value = n, bounds = self)),
+            None => match locale_hint(value) {
+                Some(hint) => Err(t!("not-an-integer-hint", value = value, hint = hint)),
+                None => Err(t!("not-an-integer-in-bounds", value = value, bounds = self)),
+            },
+        }
+    }
+}
+
+/// The alternate form (`{:#}`) groups the digits of the bounds by thousands.
+impl Display for IntegerBounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let number = |n: i64| match f.alternate() {
+            true => group_thousands(n),
+            false => n.to_string(),
+        };
+        let bounds = match self.max {
+            Some(max) => t!("bounds-range", min = number(self.min), max = number(max)),
+            None => t!("bounds-minimum", min = number(self.min)),
+        };
+        f.write_str(&bounds)?;
+        for keyword in self.keywords {
+            write!(f, ", `{keyword}`")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Kind of path the value of a parameter is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum PathKind {
+    /// A file which must exist.
+    File,
+    /// A wildcard pattern, e.g. `/var/log/*.log`
+    Glob,
+}
+
+struct ParameterOverlay {
+    section_types: &'static [FlbSectionType],
+    /// `None` if the parameter is common to all plugins of the sections.
+    plugin_name: Option<&'static str>,
+    key: &'static str,
+    bounds: Option<IntegerBounds>,
+    /// Whether the key may appear multiple times in a section, each entry
+    /// adding a value (e.g. a rule).
+    repeatable: bool,
+    /// Kind of path the value is, checked for existence.
+    path: Option<PathKind>,
+    /// Type of the value, for parameters the schema doesn't type or lacks.
+    value_type: Option<FlbPropertyType>,
+    /// Whether the key is only supported by YAML configurations.
+    yaml_only: bool,
+}
+
+impl ParameterOverlay {
+    fn matches(&self, section_type: &FlbSectionType, plugin_name: Option<&str>, key: &str) -> bool {
+        self.key.eq_ignore_ascii_case(key)
+            && self.section_types.contains(section_type)
+            && self.plugin_name.map_or(true, |name| {
+                plugin_name.is_some_and(|plugin_name| name.eq_ignore_ascii_case(plugin_name))
+            })
+    }
+}
+
+const fn bounded(
+    section_types: &'static [FlbSectionType],
+    key: &'static str,
+    bounds: IntegerBounds,
+) -> ParameterOverlay {
+    ParameterOverlay {
+        section_types,
+        plugin_name: None,
+        key,
+        bounds: Some(bounds),
+        repeatable: false,
+        path: None,
+        value_type: None,
+        yaml_only: false,
+    }
+}
+
+const fn repeatable(
+    section_types: &'static [FlbSectionType],
+    plugin_name: Option<&'static str>,
+    key: &'static str,
+) -> ParameterOverlay {
+    ParameterOverlay {
+        section_types,
+        plugin_name,
+        key,
+        bounds: None,
+        repeatable: true,
+        path: None,
+        value_type: None,
+        yaml_only: false,
+    }
+}
+
+const fn path(
+    section_types: &'static [FlbSectionType],
+    plugin_name: Option<&'static str>,
+    key: &'static str,
+    kind: PathKind,
+) -> ParameterOverlay {
+    ParameterOverlay {
+        section_types,
+        plugin_name,
+        key,
+        bounds: None,
+        repeatable: false,
+        path: Some(kind),
+        value_type: None,
+        yaml_only: false,
+    }
+}
+
+const fn yaml_only(
+    section_types: &'static [FlbSectionType],
+    key: &'static str,
+) -> ParameterOverlay {
+    ParameterOverlay {
+        section_types,
+        plugin_name: None,
+        key,
+        bounds: None,
+        repeatable: false,
+        path: None,
+        value_type: None,
+        yaml_only: true,
+    }
+}
+
+const fn typed(
+    section_types: &'static [FlbSectionType],
+    plugin_name: Option<&'static str>,
+    key: &'static str,
+    value_type: FlbPropertyType,
+) -> ParameterOverlay {
+    ParameterOverlay {
+        section_types,
+        plugin_name,
+        key,
+        bounds: None,
+        repeatable: false,
+        path: None,
+        value_type: Some(value_type),
+        yaml_only: false,
+    }
+}
+
+const PORT: IntegerBounds = IntegerBounds {
+    min: 1,
+    max: Some(65535),
+    keywords: &[],
+};
+
+const NON_NEGATIVE: IntegerBounds = IntegerBounds {
+    min: 0,
+    max: None,
+    keywords: &[],
+};
+
+const INPUT_OUTPUT: &[FlbSectionType] = &[FlbSectionType::Input, FlbSectionType::Output];
+const INPUT: &[FlbSectionType] = &[FlbSectionType::Input];
+const SERVICE: &[FlbSectionType] = &[FlbSectionType::Service];
+const FILTER: &[FlbSectionType] = &[FlbSectionType::Filter];
+const OUTPUT: &[FlbSectionType] = &[FlbSectionType::Output];
+const PARSER: &[FlbSectionType] = &[FlbSectionType::Parser];
+const MULTILINE_PARSER: &[FlbSectionType] = &[FlbSectionType::MultilineParser];
+
+static PARAMETER_OVERLAYS: &[ParameterOverlay] = &[
+    bounded(INPUT_OUTPUT, "Port", PORT),
+    bounded(SERVICE, "HTTP_Port", PORT),
+    bounded(SERVICE, "Grace", NON_NEGATIVE),
+    // ref: https://docs.fluentbit.io/manual/administration/scheduling-and-retries
+    // fluent-bit replaces a limit below 1 with 1
+    bounded(OUTPUT, "Retry_Limit", IntegerBounds {
+        min: 1,
+        max: None,
+        keywords: &["False", "no_limits", "no_retries"],
+    }),
+    bounded(OUTPUT, "Workers", NON_NEGATIVE),
+    repeatable(SERVICE, None, "Parsers_File"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/filters/modify
+    repeatable(FILTER, Some("modify"), "Set"),
+    repeatable(FILTER, Some("modify"), "Add"),
+    repeatable(FILTER, Some("modify"), "Remove"),
+    repeatable(FILTER, Some("modify"), "Remove_wildcard"),
+    repeatable(FILTER, Some("modify"), "Remove_regex"),
+    repeatable(FILTER, Some("modify"), "Rename"),
+    repeatable(FILTER, Some("modify"), "Hard_rename"),
+    repeatable(FILTER, Some("modify"), "Copy"),
+    repeatable(FILTER, Some("modify"), "Hard_copy"),
+    repeatable(FILTER, Some("modify"), "Condition"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/filters/rewrite-tag
+    repeatable(FILTER, Some("rewrite_tag"), "Rule"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/filters/grep
+    repeatable(FILTER, Some("grep"), "Regex"),
+    repeatable(FILTER, Some("grep"), "Exclude"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/filters/record-modifier
+    repeatable(FILTER, Some("record_modifier"), "Record"),
+    repeatable(FILTER, Some("record_modifier"), "Remove_key"),
+    repeatable(FILTER, Some("record_modifier"), "Allowlist_key"),
+    repeatable(FILTER, Some("record_modifier"), "Whitelist_key"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/filters/nest
+    repeatable(FILTER, Some("nest"), "Wildcard"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/filters/parser
+    repeatable(FILTER, Some("parser"), "Parser"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/filters/type-converter
+    repeatable(FILTER, Some("type_converter"), "int_key"),
+    repeatable(FILTER, Some("type_converter"), "uint_key"),
+    repeatable(FILTER, Some("type_converter"), "float_key"),
+    repeatable(FILTER, Some("type_converter"), "str_key"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/inputs/systemd
+    repeatable(INPUT, Some("systemd"), "Systemd_Filter"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/outputs/http
+    repeatable(OUTPUT, Some("http"), "Header"),
+    repeatable(OUTPUT, Some("opentelemetry"), "Header"),
+    // ref: https://docs.fluentbit.io/manual/pipeline/parsers/decoders
+    repeatable(PARSER, None, "Decode_Field"),
+    repeatable(PARSER, None, "Decode_Field_As"),
+    // ref: https://docs.fluentbit.io/manual/administration/configuring-fluent-bit/multiline-parsing
+    repeatable(MULTILINE_PARSER, None, "rule"),
+    path(SERVICE, None, "Parsers_File", PathKind::File),
+    path(SERVICE, None, "Plugins_File", PathKind::File),
+    path(SERVICE, None, "Streams_File", PathKind::File),
+    // ref: https://docs.fluentbit.io/manual/administration/transport-security
+    path(INPUT_OUTPUT, None, "tls.ca_file", PathKind::File),
+    path(INPUT_OUTPUT, None, "tls.crt_file", PathKind::File),
+    path(INPUT_OUTPUT, None, "tls.key_file", PathKind::File),
+    // ref: https://docs.fluentbit.io/manual/pipeline/inputs/tail
+    path(INPUT, Some("tail"), "Path", PathKind::Glob),
+    // ref: https://docs.fluentbit.io/manual/pipeline/processors
+    yaml_only(INPUT_OUTPUT, "processors"),
+    typed(SERVICE, None, "Flush", FlbPropertyType::Double),
+    // ref: https://docs.fluentbit.io/manual/administration/buffering-and-storage
+    typed(
+        SERVICE,
+        None,
+        "storage.max_chunks_up",
+        FlbPropertyType::Integer,
+    ),
+    typed(
+        SERVICE,
+        None,
+        "storage.backlog.mem_limit",
+        FlbPropertyType::Size,
+    ),
+    typed(INPUT, None, "Mem_Buf_Limit", FlbPropertyType::Size),
+    typed(
+        OUTPUT,
+        None,
+        "storage.total_limit_size",
+        FlbPropertyType::Size,
+    ),
+    // ref: https://docs.fluentbit.io/manual/pipeline/inputs/tail
+    typed(
+        INPUT,
+        Some("tail"),
+        "Buffer_Chunk_Size",
+        FlbPropertyType::Size,
+    ),
+    typed(
+        INPUT,
+        Some("tail"),
+        "Buffer_Max_Size",
+        FlbPropertyType::Size,
+    ),
+];
+
+/// Bounds of `key`, if it's an integer parameter whose range is known.
+pub(crate) fn integer_bounds(
+    section_type: &FlbSectionType,
+    plugin_name: Option<&str>,
+    key: &str,
+) -> Option<&'static IntegerBounds> {
+    PARAMETER_OVERLAYS
+        .iter()
+        .filter(|overlay| overlay.matches(section_type, plugin_name, key))
+        .find_map(|overlay| overlay.bounds.as_ref())
+}
+
+/// Type of `key`, if it's curated.
+pub(crate) fn value_type(
+    section_type: &FlbSectionType,
+    plugin_name: Option<&str>,
+    key: &str,
+) -> Option<&'static FlbPropertyType> {
+    PARAMETER_OVERLAYS
+        .iter()
+        .filter(|overlay| overlay.matches(section_type, plugin_name, key))
+        .find_map(|overlay| overlay.value_type.as_ref())
+}
+
+/// Whether `key` may appear multiple times in a section.
+pub(crate) fn is_repeatable(
+    section_type: &FlbSectionType,
+    plugin_name: Option<&str>,
+    key: &str,
+) -> bool {
+    PARAMETER_OVERLAYS
+        .iter()
+        .any(|overlay| overlay.repeatable && overlay.matches(section_type, plugin_name, key))
+}
+
+/// Kind of path the value of `key` is, if it's one.
+pub(crate) fn path_kind(
+    section_type: &FlbSectionType,
+    plugin_name: Option<&str>,
+    key: &str,
+) -> Option<PathKind> {
+    PARAMETER_OVERLAYS
+        .iter()
+        .filter(|overlay| overlay.matches(section_type, plugin_name, key))
+        .find_map(|overlay| overlay.path)
+}
+
+/// Whether `key` is only supported by YAML configurations.
+pub(crate) fn is_yaml_only(
+    section_type: &FlbSectionType,
+    plugin_name: Option<&str>,
+    key: &str,
+) -> bool {
+    PARAMETER_OVERLAYS
+        .iter()
+        .any(|overlay| overlay.yaml_only && overlay.matches(section_type, plugin_name, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameter_overlays() {
+        let port = integer_bounds(&FlbSectionType::Input, Some("tcp"), "port").unwrap();
+        assert_eq!(port.to_string(), "1 to 65535");
+        assert_eq!(port.check("24224"), Ok(()));
+        assert_eq!(
+            port.check("70000"),
+            Err("70000 is out of range, expected 1 to 65535".to_string())
+        );
+        assert_eq!(
+            port.check("http"),
+            Err("`http` is not an integer, expected 1 to 65535".to_string())
+        );
+
+        let retry_limit = integer_bounds(&FlbSectionType::Output, None, "Retry_Limit").unwrap();
+        assert_eq!(
+            retry_limit.to_string(),
+            "1 or more, `False`, `no_limits`, `no_retries`"
+        );
+        assert_eq!(retry_limit.check("false"), Ok(()));
+        assert!(retry_limit.check("0").is_err());
+
+        assert_eq!(integer_bounds(&FlbSectionType::Filter, None, "Port"), None);
+
+        assert!(is_repeatable(
+            &FlbSectionType::Filter,
+            Some("Modify"),
+            "add"
+        ));
+        assert!(!is_repeatable(&FlbSectionType::Filter, None, "add"));
+    }
+}
@@ -0,0 +1,216 @@
+//! Overrides between the `[SERVICE]` sections of a configuration: fluent-bit
+//! applies them in the order of the flattened configuration, `@INCLUDE`
+//! inlining the included files where it's written, so that a key set again
+//! later overrides the values set before.
+//!
+//! The overridden keys are reported, and the hover of a key tells where the
+//! value which wins is set.
+
+use std::collections::HashMap;
+
+use flb_schema::section::FlbSectionType;
+use tower_lsp::lsp_types::{Position, Range, Url};
+
+use crate::{
+    document::TextDocument,
+    i18n::t,
+    overlay::is_repeatable,
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+};
+
+/// Diagnostic code of a `[SERVICE]` key set again later in the configuration.
+pub(crate) const OVERRIDDEN_SERVICE_KEY: &str = "overridden-service-key";
+
+/// Key of a `[SERVICE]` section.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ServiceKey {
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) range: Range,
+    /// Byte offset of its section, duplicates in a section being reported as
+    /// `duplicate-key` already.
+    section_start: usize,
+}
+
+/// Item of a document the configuration is flattened from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ConfigItem {
+    /// Pattern of an `@INCLUDE`.
+    Include(String),
+    ServiceKey(ServiceKey),
+}
+
+/// `@INCLUDE` directives and `[SERVICE]` keys of the document, in order.
+/// Repeatable keys, e.g. `Parsers_File`, don't override each other.
+pub(crate) fn config_items(
+    document: &TextDocument,
+    position_encoding: PositionEncodingKind,
+) -> Vec<ConfigItem> {
+    let index = &document.index;
+    let includes = index.includes().iter().map(|include| {
+        (
+            include.range.start,
+            ConfigItem::Include(include.pattern.clone()),
+        )
+    });
+    let keys = index
+        .sections()
+        .iter()
+        .filter(|section| section.section_type == FlbSectionType::Service)
+        .flat_map(|section| {
+            section
+                .entries
+                .iter()
+                .filter(|entry| !is_repeatable(&FlbSectionType::Service, None, &entry.key))
+                .filter_map(|entry| {
+                    let key = ServiceKey {
+                        key: entry.key.clone(),
+                        value: entry.value.clone()?,
+                        range: byte_range_to_lsp_range(
+                            &document.rope,
+                            entry.key_range.clone(),
+                            position_encoding,
+                        ),
+                        section_start: section.byte_range.start,
+                    };
+                    Some((entry.key_range.start, ConfigItem::ServiceKey(key)))
+                })
+        });
+
+    let mut items: Vec<_> = includes.chain(keys).collect();
+    items.sort_by_key(|(start, _)| *start);
+    items.into_iter().map(|(_, item)| item).collect()
+}
+
+/// For each overridden key of the flattened configuration, its index and the
+/// index of the key which wins, the last one set.
+pub(crate) fn overrides(keys: &[(&Url, &ServiceKey)]) -> Vec<(usize, usize)> {
+    let mut last = HashMap::new();
+    for (i, (_, key)) in keys.iter().enumerate() {
+        last.insert(key.key.to_lowercase(), i);
+    }
+
+    keys.iter()
+        .enumerate()
+        .filter_map(|(i, (uri, key))| {
+            let winner = last[&key.key.to_lowercase()];
+            let (winner_uri, winner_key) = keys[winner];
+            let same_section = *uri == winner_uri && key.section_start == winner_key.section_start;
+            (winner != i && !same_section).then_some((i, winner))
+        })
+        .collect()
+}
+
+/// `fluent-bit.conf:3`, the file name and the line of the range.
+pub(crate) fn location_label(uri: &Url, range: &Range) -> String {
+    let name = uri
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or(uri.as_str());
+    format!("{name}:{}", range.start.line + 1)
+}
+
+/// Markdown link to the location, for hovers.
+pub(crate) fn location_link(uri: &Url, range: &Range) -> String {
+    format!(
+        "[`{}`]({uri}#L{})",
+        location_label(uri, range),
+        range.start.line + 1
+    )
+}
+
+/// Hover note of the key at `position` of the flattened configuration, if
+/// another value overrides it or it overrides others.
+pub(crate) fn hover_note(
+    keys: &[(&Url, &ServiceKey)],
+    uri: &Url,
+    position: Position,
+) -> Option<String> {
+    let hovered = keys.iter().position(|(key_uri, key)| {
+        *key_uri == uri && key.range.start <= position && position <= key.range.end
+    })?;
+    let overrides = overrides(keys);
+
+    if let Some((_, winner)) = overrides.iter().find(|(i, _)| *i == hovered) {
+        let (winner_uri, winner_key) = keys[*winner];
+        return Some(t!(
+            "note-overridden",
+            value = winner_key.value,
+            location = location_link(winner_uri, &winner_key.range),
+        ));
+    }
+
+    let overridden: Vec<_> = overrides
+        .iter()
+        .filter(|(_, winner)| *winner == hovered)
+        .map(|(i, _)| location_link(keys[*i].0, &keys[*i].1.range))
+        .collect();
+    (!overridden.is_empty()).then(|| t!("note-overrides", locations = overridden.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        disk_cache::DiskCache,
+        include_graph::{DocumentLinks, IncludeGraph},
+    };
+
+    #[tokio::test]
+    async fn service_keys_overridden_by_included_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(
+            dir.join("service.conf"),
+            "[SERVICE]\n    Flush        5\n    Parsers_File extra.conf\n",
+        )
+        .unwrap();
+
+        let encoding = PositionEncodingKind::UTF16;
+        let uri = Url::from_file_path(dir.join("fluent-bit.conf")).unwrap();
+        let document = TextDocument::new(
+            "[SERVICE]\n    Flush        1\n    Log_Level    info\n    Parsers_File parsers.conf\n\n\
+             @INCLUDE service.conf\n",
+        );
+        let items = config_items(&document, encoding);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[2], ConfigItem::Include("service.conf".to_string()));
+
+        let links = DocumentLinks::new(&uri, &document, encoding);
+        let graph = IncludeGraph::load(
+            vec![(uri.clone(), links)],
+            Vec::new(),
+            &[],
+            &DiskCache::default(),
+            &[],
+            encoding,
+        )
+        .await;
+        let included = Url::from_file_path(dir.join("service.conf")).unwrap();
+        let diagnostics = graph.overridden_service_keys();
+        let messages: Vec<_> = diagnostics[&uri]
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(messages, [
+            "`Flush` is overridden by `5`, set later in service.conf:2."
+        ]);
+        assert!(!diagnostics.contains_key(&included));
+
+        let flush = Position::new(1, 6);
+        assert_eq!(
+            graph.service_key_note(&uri, flush).unwrap(),
+            format!(
+                "Overridden by `5`, set later in [`service.conf:2`]({included}#L2): fluent-bit \
+                 applies the [SERVICE] sections in order, included files where they're included."
+            )
+        );
+        assert_eq!(
+            graph.service_key_note(&included, flush).unwrap(),
+            format!("Overrides the value set in [`fluent-bit.conf:2`]({uri}#L2).")
+        );
+        assert_eq!(graph.service_key_note(&uri, Position::new(2, 6)), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Index of parser names: the `[PARSER]` and `[MULTILINE_PARSER]` sections
+//! defining them and the entries referencing them, for go to definition and
+//! rename across the open documents.
+//!
+//! Symbols are collected from a [`SectionIndex`], which doesn't depend on the
+//! format of the configuration, so definitions and references match whatever
+//! files they come from.
+
+use std::ops::Range;
+
+use flb_schema::section::FlbSectionType;
+
+use crate::section_index::{IndexedEntry, SectionIndex};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ParserSymbolKind {
+    Definition,
+    Reference,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ParserSymbol {
+    /// Parser names are case sensitive.
+    pub(crate) name: String,
+    pub(crate) range: Range<usize>,
+    pub(crate) kind: ParserSymbolKind,
+}
+
+/// Whether the value of the key names a parser, e.g. `Parser` of the parser
+/// filter or `Parser_Firstline` and `Parser_1` of the tail input.
+fn references_parser(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    match key.strip_prefix("parser_") {
+        Some(suffix) => suffix == "firstline" || suffix.bytes().all(|b| b.is_ascii_digit()),
+        None => key == "parser" || key == "docker_mode_parser",
+    }
+}
+
+/// Whether the value of the key is a comma separated list of multiline parser
+/// names.
+fn references_multiline_parsers(key: &str) -> bool {
+    key.eq_ignore_ascii_case("multiline.parser")
+}
+
+/// Names of the value of the entry, split on commas for lists.
+fn entry_names(entry: &IndexedEntry, list: bool) -> Vec<(String, Range<usize>)> {
+    let (Some(value), Some(value_range)) = (&entry.value, &entry.value_range) else {
+        return Vec::new();
+    };
+    if !list {
+        return vec![(
+            value.clone(),
+            value_range.start..value_range.start + value.len(),
+        )];
+    }
+
+    let mut names = Vec::new();
+    let mut offset = value_range.start;
+    for item in value.split(',') {
+        let name = item.trim();
+        if !name.is_empty() {
+            let start = offset + (item.len() - item.trim_start().len());
+            names.push((name.to_string(), start..start + name.len()));
+        }
+        offset += item.len() + 1;
+    }
+
+    names
+}
+
+pub(crate) fn parser_symbols(index: &SectionIndex) -> Vec<ParserSymbol> {
+    let mut symbols = Vec::new();
+
+    for section in index.sections() {
+        let defines_parser = matches!(
+            section.section_type,
+            FlbSectionType::Parser | FlbSectionType::MultilineParser
+        );
+
+        for entry in &section.entries {
+            let (kind, list) = if defines_parser && entry.key.eq_ignore_ascii_case("Name") {
+                (ParserSymbolKind::Definition, false)
+            } else if section.section_type != FlbSectionType::Parser
+                && references_parser(&entry.key)
+            {
+                (ParserSymbolKind::Reference, false)
+            } else if references_multiline_parsers(&entry.key) {
+                (ParserSymbolKind::Reference, true)
+            } else {
+                continue;
+            };
+
+            symbols.extend(
+                entry_names(entry, list)
+                    .into_iter()
+                    .map(|(name, range)| ParserSymbol { name, range, kind }),
+            );
+        }
+    }
+
+    symbols
+}
+
+/// Symbol at the byte offset, its end included.
+pub(crate) fn parser_symbol_at(index: &SectionIndex, byte: usize) -> Option<ParserSymbol> {
+    parser_symbols(index)
+        .into_iter()
+        .find(|symbol| symbol.range.start <= byte && byte <= symbol.range.end)
+}
+
+/// Whether the document is a parsers file, e.g. the `parsers.conf` of
+/// `Parsers_File`, rather than a pipeline: it only defines parsers.
+pub(crate) fn is_parsers_file(index: &SectionIndex) -> bool {
+    !index.sections().is_empty()
+        && index.sections().iter().all(|section| {
+            matches!(
+                section.section_type,
+                FlbSectionType::Parser | FlbSectionType::MultilineParser
+            )
+        })
+}
+
+/// Whether `name` can be given to a parser, i.e. it's a single value.
+pub(crate) fn is_valid_parser_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || c == ',')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{document::TextDocument, lsp_ext::ConfigFormat};
+
+    #[test]
+    fn parser_definitions_and_references() {
+        let document = TextDocument::new(
+            "[PARSER]\n    Name   json_ts\n    Format json\n\n\
+             [MULTILINE_PARSER]\n    name   java_trace\n    parser json_ts\n\n\
+             [INPUT]\n    Name             tail\n    Parser           json_ts\n    multiline.parser docker, java_trace\n\n\
+             [FILTER]\n    Name     parser\n    Key_Name log\n    Parser   json_ts\n",
+        );
+
+        let symbols: Vec<_> = parser_symbols(&document.index)
+            .into_iter()
+            .map(|symbol| {
+                let text = document.rope.byte_slice(symbol.range.clone()).to_string();
+                assert_eq!(text, symbol.name);
+                (symbol.name, symbol.kind)
+            })
+            .collect();
+        assert_eq!(symbols, [
+            ("json_ts".to_string(), ParserSymbolKind::Definition),
+            ("java_trace".to_string(), ParserSymbolKind::Definition),
+            ("json_ts".to_string(), ParserSymbolKind::Reference),
+            ("json_ts".to_string(), ParserSymbolKind::Reference),
+            ("docker".to_string(), ParserSymbolKind::Reference),
+            ("java_trace".to_string(), ParserSymbolKind::Reference),
+            ("json_ts".to_string(), ParserSymbolKind::Reference),
+        ]);
+
+        assert!(is_valid_parser_name("json_ts"));
+        assert!(!is_valid_parser_name("json ts"));
+
+        // YAML references match the definitions of classic parsers files
+        let yaml = TextDocument::with_format(
+            "pipeline:\n  inputs:\n    - name: tail\n      path: /var/log/app.log\n      parser: json_ts\n",
+            ConfigFormat::Yaml,
+        );
+        let symbols: Vec<_> = parser_symbols(&yaml.index)
+            .into_iter()
+            .map(|symbol| {
+                let text = yaml.rope.byte_slice(symbol.range.clone()).to_string();
+                (text, symbol.kind)
+            })
+            .collect();
+        assert_eq!(symbols, [(
+            "json_ts".to_string(),
+            ParserSymbolKind::Reference
+        )]);
+    }
+}
@@ -0,0 +1,487 @@
+//! Checks of the paths the configuration references, e.g. parsers files or the
+//! files read by `tail` or included.
+//!
+//! Paths are collected while the document is locked, and checked afterwards
+//! without holding it. Each check is bounded by a timeout, so that an
+//! unreachable network mount doesn't freeze the diagnostics, and its result is
+//! cached for a while, so that validating an unchanged document again doesn't
+//! hit the filesystem.
+
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use glob::MatchOptions;
+use percent_encoding::percent_decode_str;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range, Url};
+
+use crate::{
+    i18n::t,
+    overlay::{path_kind, PathKind},
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    section_index::SectionIndex,
+    validation::is_env_reference,
+};
+
+/// Diagnostic code of a file which doesn't exist.
+pub(crate) const MISSING_FILE: &str = "missing-file";
+/// Diagnostic code of a wildcard pattern matching no file.
+pub(crate) const UNMATCHED_GLOB: &str = "unmatched-glob";
+
+const CACHE_TTL: Duration = Duration::from_secs(10);
+const MAX_CACHE_ENTRIES: usize = 1024;
+const CHECK_TIMEOUT: Duration = Duration::from_millis(500);
+/// Time spent checking the paths of a document, beyond which the remaining
+/// ones are skipped.
+const CHECK_BUDGET: Duration = Duration::from_secs(1);
+/// Options of glob(3) as fluent-bit calls it.
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: !cfg!(windows),
+    require_literal_separator: true,
+    require_literal_leading_dot: true,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PathStatus {
+    Exists,
+    Missing,
+    /// The check failed or timed out, e.g. on a permission error.
+    Unknown,
+}
+
+/// Path referenced by an entry, to be checked.
+#[derive(Debug)]
+pub(crate) struct PathCheck {
+    kind: PathKind,
+    path: PathBuf,
+    range: Range,
+}
+
+/// Whether `path` is a Windows path, with a drive letter (`C:\`, `C:/`) or
+/// UNC (`\\server\share`).
+fn is_windows_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+        || path.starts_with("\\\\")
+}
+
+fn is_absolute(path: &str) -> bool {
+    path.starts_with(['/', '\\']) || is_windows_path(path)
+}
+
+/// Root of an absolute path with its separator, e.g. `/`, `C:\` or
+/// `\\server\share\`, and the rest of the path.
+fn split_root(path: &str) -> (String, &str) {
+    if !is_windows_path(path) {
+        return ("/".to_string(), path.trim_start_matches('/'));
+    }
+
+    if let Some(unc) = path.strip_prefix("\\\\") {
+        let mut parts = unc.splitn(3, ['\\', '/']);
+        let server = parts.next().unwrap_or_default();
+        let share = parts.next().unwrap_or_default();
+        return (
+            format!("\\\\{server}\\{share}\\"),
+            parts.next().unwrap_or_default(),
+        );
+    }
+    (
+        format!("{}\\", &path[..2]),
+        path[2..].trim_start_matches(['/', '\\']),
+    )
+}
+
+/// Joins `path` to `base_dir` unless it's absolute, and removes its `.` and
+/// `..` components. Windows paths are written with `\`, whichever separator
+/// they were written with.
+pub(crate) fn resolve_path(base_dir: Option<&str>, path: &str) -> Option<String> {
+    let joined = match base_dir {
+        _ if is_absolute(path) => path.to_string(),
+        Some(base_dir) => format!("{}/{path}", base_dir.trim_end_matches(['/', '\\'])),
+        None => return None,
+    };
+
+    let windows = is_windows_path(&joined);
+    let separators: &[char] = if windows { &['/', '\\'] } else { &['/'] };
+    let (root, rest) = split_root(&joined);
+    let mut components = Vec::new();
+    for component in rest.split(separators) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+
+    Some(root + &components.join(if windows { "\\" } else { "/" }))
+}
+
+/// Directory of a document, written as on its platform: `file:///C:/conf/a.conf`
+/// is in `C:\conf`, `file://server/share/a.conf` in `\\server\share`.
+pub(crate) fn document_dir(uri: &Url) -> Option<String> {
+    if uri.scheme() != "file" {
+        return None;
+    }
+    let path = percent_decode_str(uri.path()).decode_utf8().ok()?;
+    let (dir, _) = path.rsplit_once('/')?;
+
+    let host = uri
+        .host_str()
+        .filter(|host| !host.is_empty() && *host != "localhost");
+    Some(match (host, dir.strip_prefix('/')) {
+        (Some(host), _) => format!("\\\\{host}{}", dir.replace('/', "\\")),
+        (None, Some(dir)) if is_windows_path(dir) => dir.replace('/', "\\"),
+        (None, _) if dir.is_empty() => "/".to_string(),
+        (None, _) => dir.to_string(),
+    })
+}
+
+/// Whether the path can be checked where the server runs, Windows paths only
+/// being on Windows.
+fn is_native(path: &str) -> bool {
+    cfg!(windows) || !is_windows_path(path)
+}
+
+/// Collects the paths referenced by the document. Relative paths are resolved
+/// against `base_dir`, the directory of the document, and skipped without it.
+pub(crate) fn collect_paths(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+    base_dir: Option<&str>,
+) -> Vec<PathCheck> {
+    let mut checks = Vec::new();
+    let mut push = |kind, value: &str, start: usize| {
+        if is_env_reference(value) {
+            return;
+        }
+        let Some(path) = resolve_path(base_dir, value).filter(|path| is_native(path)) else {
+            return;
+        };
+        checks.push(PathCheck {
+            kind,
+            path: PathBuf::from(path),
+            range: byte_range_to_lsp_range(rope, start..start + value.len(), position_encoding),
+        });
+    };
+
+    for include in index.includes() {
+        let kind = match include.pattern.contains(['*', '?', '[']) {
+            true => PathKind::Glob,
+            false => PathKind::File,
+        };
+        push(kind, &include.pattern, include.range.start);
+    }
+
+    for section in index.sections() {
+        let plugin_name = section.get_value("Name");
+        for entry in &section.entries {
+            let Some(kind) = path_kind(&section.section_type, plugin_name, &entry.key) else {
+                continue;
+            };
+            let (Some(value), Some(value_range)) = (&entry.value, &entry.value_range) else {
+                continue;
+            };
+
+            // `tail` accepts several patterns, separated by commas
+            let mut offset = 0;
+            let parts: Vec<&str> = match kind {
+                PathKind::File => vec![value.as_str()],
+                PathKind::Glob => value.split(',').collect(),
+            };
+            for part in parts {
+                let start = value_range.start + offset + (part.len() - part.trim_start().len());
+                offset += part.len() + 1;
+                let part = part.trim();
+                if !part.is_empty() {
+                    push(kind, part, start);
+                }
+            }
+        }
+    }
+
+    checks
+}
+
+/// Cache of the results of path checks.
+#[derive(Default)]
+pub(crate) struct PathCache {
+    entries: Mutex<HashMap<(PathKind, PathBuf), (Instant, PathStatus)>>,
+}
+
+impl PathCache {
+    async fn status(&self, kind: PathKind, path: &Path) -> PathStatus {
+        let key = (kind, path.to_path_buf());
+        if let Some((checked_at, status)) = self.entries.lock().unwrap().get(&key) {
+            if checked_at.elapsed() < CACHE_TTL {
+                return *status;
+            }
+        }
+
+        let check = async {
+            match kind {
+                PathKind::File => file_status(path).await,
+                PathKind::Glob => {
+                    // the glob crate walks directories synchronously
+                    let pattern = path.to_path_buf();
+                    tokio::task::spawn_blocking(move || glob_status(&pattern))
+                        .await
+                        .unwrap_or(PathStatus::Unknown)
+                }
+            }
+        };
+        // a timed out check is cached too, not to wait for the mount again
+        let status = tokio::time::timeout(CHECK_TIMEOUT, check)
+            .await
+            .unwrap_or(PathStatus::Unknown);
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_CACHE_ENTRIES {
+            entries.retain(|_, (checked_at, _)| checked_at.elapsed() < CACHE_TTL);
+            if entries.len() >= MAX_CACHE_ENTRIES {
+                entries.clear();
+            }
+        }
+        entries.insert(key, (Instant::now(), status));
+
+        status
+    }
+}
+
+/// Checks the collected paths, reporting the missing ones.
+pub(crate) async fn check_paths(cache: &PathCache, checks: Vec<PathCheck>) -> Vec<Diagnostic> {
+    let started = Instant::now();
+    let mut diagnostics = Vec::new();
+
+    for check in checks {
+        if started.elapsed() > CHECK_BUDGET {
+            break;
+        }
+        if cache.status(check.kind, &check.path).await != PathStatus::Missing {
+            continue;
+        }
+
+        let path = check.path.display();
+        let (severity, code, message) = match check.kind {
+            PathKind::File => (
+                DiagnosticSeverity::WARNING,
+                MISSING_FILE,
+                t!(MISSING_FILE, path = path),
+            ),
+            PathKind::Glob => (
+                DiagnosticSeverity::INFORMATION,
+                UNMATCHED_GLOB,
+                t!(UNMATCHED_GLOB, path = path),
+            ),
+        };
+        diagnostics.push(Diagnostic {
+            range: check.range,
+            severity: Some(severity),
+            code: Some(NumberOrString::String(code.to_string())),
+            message,
+            ..Diagnostic::default()
+        });
+    }
+
+    diagnostics
+}
+
+async fn file_status(path: &Path) -> PathStatus {
+    match tokio::fs::metadata(path).await {
+        Ok(_) => PathStatus::Exists,
+        Err(e) if e.kind() == ErrorKind::NotFound => PathStatus::Missing,
+        Err(_) => PathStatus::Unknown,
+    }
+}
+
+/// Files included by `pattern` of `@INCLUDE`, resolved against `base_dir` and
+/// expanded like glob(3) does, sorted.
+pub(crate) async fn included_files(base_dir: Option<&str>, pattern: &str) -> Vec<PathBuf> {
+    if is_env_reference(pattern) {
+        return Vec::new();
+    }
+    let Some(path) = resolve_path(base_dir, pattern).filter(|path| is_native(path)) else {
+        return Vec::new();
+    };
+
+    let find = async {
+        if !pattern.contains(['*', '?', '[']) {
+            let path = PathBuf::from(path);
+            return match file_status(&path).await {
+                PathStatus::Exists => vec![path],
+                _ => Vec::new(),
+            };
+        }
+        tokio::task::spawn_blocking(move || {
+            let mut paths: Vec<PathBuf> = glob::glob_with(&path, GLOB_OPTIONS)
+                .map(|paths| {
+                    paths
+                        .filter_map(Result::ok)
+                        .filter(|p| p.is_file())
+                        .collect()
+                })
+                .unwrap_or_default();
+            paths.sort();
+            paths
+        })
+        .await
+        .unwrap_or_default()
+    };
+    tokio::time::timeout(CHECK_TIMEOUT, find)
+        .await
+        .unwrap_or_default()
+}
+
+/// Whether any file matches `pattern`, evaluated like glob(3) does.
+fn glob_status(pattern: &Path) -> PathStatus {
+    let pattern = pattern.to_string_lossy();
+    // `~` isn't expanded
+    if pattern.starts_with('~') {
+        return PathStatus::Unknown;
+    }
+
+    let Ok(paths) = glob::glob_with(&pattern, GLOB_OPTIONS) else {
+        return PathStatus::Unknown;
+    };
+
+    // unreadable directories leave the result unknown, unless a path matches
+    let mut status = PathStatus::Missing;
+    for path in paths {
+        match path {
+            Ok(_) => return PathStatus::Exists,
+            Err(_) => status = PathStatus::Unknown,
+        }
+    }
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tower_lsp::lsp_types::Position;
+
+    use super::*;
+    use crate::document::TextDocument;
+
+    #[tokio::test]
+    async fn missing_paths_are_cached() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        fs::write(dir.join("logs/app.log"), "").unwrap();
+        fs::write(dir.join("logs/.hidden.txt"), "").unwrap();
+        fs::write(dir.join("parsers.conf"), "").unwrap();
+
+        let document = TextDocument::new(
+            "@INCLUDE inputs/*.conf\n\n\
+             [SERVICE]\n    Parsers_File parsers.conf\n    Parsers_File missing.conf\n    Plugins_File ${PLUGINS}\n\n\
+             [INPUT]\n    Name tail\n    Path logs/*.log, logs/*.txt\n",
+        );
+        let checks = collect_paths(
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+            dir.to_str(),
+        );
+        assert_eq!(checks.len(), 5);
+
+        let cache = PathCache::default();
+        let diagnostics = check_paths(&cache, checks).await;
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.clone()).collect();
+        assert_eq!(messages, [
+            format!("No file matches `{}`.", dir.join("inputs/*.conf").display()),
+            format!(
+                "File `{}` doesn't exist.",
+                dir.join("missing.conf").display()
+            ),
+            format!("No file matches `{}`.", dir.join("logs/*.txt").display()),
+        ]);
+        assert_eq!(
+            diagnostics[2].range,
+            Range::new(Position::new(9, 21), Position::new(9, 31))
+        );
+
+        // results are cached until they expire
+        fs::remove_file(dir.join("parsers.conf")).unwrap();
+        let checks = collect_paths(
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+            dir.to_str(),
+        );
+        assert_eq!(check_paths(&cache, checks).await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn files_included_by_patterns() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::create_dir_all(dir.join("inputs")).unwrap();
+        for file in [
+            "inputs/tail.conf",
+            "inputs/cpu.conf",
+            "inputs/.swap.conf",
+            "outputs.conf",
+        ] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+
+        let base_dir = dir.to_str();
+        assert_eq!(included_files(base_dir, "inputs/*.conf").await, [
+            dir.join("inputs/cpu.conf"),
+            dir.join("inputs/tail.conf"),
+        ]);
+        assert_eq!(included_files(base_dir, "./outputs.conf").await, [
+            dir.join("outputs.conf")
+        ]);
+        assert!(included_files(base_dir, "filters.conf").await.is_empty());
+        assert!(included_files(base_dir, "${CONF_DIR}/a.conf")
+            .await
+            .is_empty());
+        assert!(included_files(None, "outputs.conf").await.is_empty());
+    }
+
+    #[test]
+    fn resolve_windows_paths() {
+        let uri: Url = "file:///c%3A/Program%20Files/fluent-bit/conf/fluent-bit.conf"
+            .parse()
+            .unwrap();
+        let dir = document_dir(&uri).unwrap();
+        assert_eq!(dir, "c:\\Program Files\\fluent-bit\\conf");
+        assert_eq!(
+            resolve_path(Some(&dir), "..\\parsers/*.conf").as_deref(),
+            Some("c:\\Program Files\\fluent-bit\\parsers\\*.conf")
+        );
+        assert_eq!(
+            resolve_path(Some(&dir), "D:/logs/./app.log").as_deref(),
+            Some("D:\\logs\\app.log")
+        );
+
+        let uri: Url = "file://server/share/fluent-bit/fluent-bit.conf"
+            .parse()
+            .unwrap();
+        let dir = document_dir(&uri).unwrap();
+        assert_eq!(dir, "\\\\server\\share\\fluent-bit");
+        assert_eq!(
+            resolve_path(Some(&dir), "../../../parsers.conf").as_deref(),
+            Some("\\\\server\\share\\parsers.conf")
+        );
+
+        let uri: Url = "file:///etc/fluent-bit/fluent-bit.conf".parse().unwrap();
+        let dir = document_dir(&uri).unwrap();
+        assert_eq!(
+            resolve_path(Some(&dir), "../parsers.conf").as_deref(),
+            Some("/etc/parsers.conf")
+        );
+        assert_eq!(resolve_path(None, "parsers.conf"), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! Summary of the size of a configuration's pipeline, shown as code lenses on
+//! its `[SERVICE]` section: the inputs, filters and outputs of the files it
+//! includes too, the plugins they use, and the memory the inputs may buffer,
+//! the total of their `Mem_Buf_Limit`.
+
+use flb_schema::section::FlbSectionType;
+use tower_lsp::lsp_types::{CodeLens, Command, Range};
+
+use crate::{
+    i18n::t,
+    section_index::SectionIndex,
+    validation::is_env_reference,
+    values::{format_size, parse_size},
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct PipelineSummary {
+    /// Plugin of each section, lowercase, `None` if it has no `Name`.
+    inputs: Vec<Option<String>>,
+    filters: Vec<Option<String>>,
+    outputs: Vec<Option<String>>,
+    /// Total of the `Mem_Buf_Limit` of the inputs, in bytes.
+    mem_buf_limit: i64,
+    /// Inputs without `Mem_Buf_Limit`, or with `0`, which buffer without limit.
+    unlimited_inputs: usize,
+}
+
+/// Number of sections and the plugins they use, e.g. `3 (cpu, tail)`.
+fn describe(plugins: &[Option<String>]) -> String {
+    let mut names: Vec<&str> = plugins.iter().flatten().map(String::as_str).collect();
+    names.sort_unstable();
+    names.dedup();
+
+    match names.is_empty() {
+        true => plugins.len().to_string(),
+        false => format!("{} ({})", plugins.len(), names.join(", ")),
+    }
+}
+
+impl PipelineSummary {
+    /// Adds the sections of a document of the configuration.
+    pub(crate) fn add(&mut self, index: &SectionIndex) {
+        for section in index.sections() {
+            let plugin = section.get_value("Name").map(str::to_lowercase);
+            match section.section_type {
+                FlbSectionType::Input => self.inputs.push(plugin),
+                FlbSectionType::Filter => self.filters.push(plugin),
+                FlbSectionType::Output => self.outputs.push(plugin),
+                _ => continue,
+            }
+            if section.section_type != FlbSectionType::Input {
+                continue;
+            }
+
+            match section.get_value("Mem_Buf_Limit") {
+                // only known at runtime
+                Some(value) if is_env_reference(value) => {}
+                Some(value) => match parse_size(value) {
+                    Some(0) => self.unlimited_inputs += 1,
+                    Some(bytes) => self.mem_buf_limit = self.mem_buf_limit.saturating_add(bytes),
+                    None => {}
+                },
+                None => self.unlimited_inputs += 1,
+            }
+        }
+    }
+
+    /// Lenses of the summary, at `range`.
+    pub(crate) fn code_lenses(&self, range: Range) -> Vec<CodeLens> {
+        let lens = |title: String| CodeLens {
+            range,
+            // informational, there's nothing to run
+            command: Some(Command {
+                title,
+                command: String::new(),
+                arguments: None,
+            }),
+            data: None,
+        };
+
+        let mut lenses = vec![lens(t!(
+            "lens-pipeline",
+            inputs = describe(&self.inputs),
+            filters = describe(&self.filters),
+            outputs = describe(&self.outputs),
+        ))];
+        if !self.inputs.is_empty() {
+            let total = format_size(self.mem_buf_limit);
+            lenses.push(lens(match self.unlimited_inputs {
+                0 => t!("lens-memory", total = total),
+                count => t!("lens-memory-unlimited", total = total, count = count),
+            }));
+        }
+
+        lenses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::TextDocument;
+
+    #[test]
+    fn summary_of_configurations() {
+        let mut summary = PipelineSummary::default();
+        for text in [
+            "[SERVICE]\n    Flush 1\n\n[INPUT]\n    Name cpu\n    Mem_Buf_Limit 5M\n\n\
+             [INPUT]\n    Name tail\n    Mem_Buf_Limit 1.5MB\n",
+            "[INPUT]\n    Name tail\n\n[OUTPUT]\n    Name stdout\n    Match *\n",
+        ] {
+            summary.add(&TextDocument::new(text).index);
+        }
+
+        let titles: Vec<_> = summary
+            .code_lenses(Range::default())
+            .into_iter()
+            .filter_map(|lens| Some(lens.command?.title))
+            .collect();
+        assert_eq!(titles, [
+            "Inputs: 3 (cpu, tail) · Filters: 0 · Outputs: 1 (stdout)",
+            "Mem_Buf_Limit: 6.5M in total, unlimited for 1 of the inputs",
+        ]);
+    }
+}
@@ -0,0 +1,10 @@
+/// Generated by `cargo xtask generate`
+/// Don't modify this file manually.
+
+const PLUGIN_VERSIONS: &[(FlbSectionType, &str, &str)] = &[
+    (FlbSectionType::Filter, "wasm", "2.0.0"),
+    (FlbSectionType::Input, "opentelemetry", "2.0.0"),
+    (FlbSectionType::Input, "prometheus_scrape", "1.9.0"),
+    (FlbSectionType::Output, "opentelemetry", "1.9.0"),
+    (FlbSectionType::Output, "prometheus_remote_write", "1.9.0"),
+];
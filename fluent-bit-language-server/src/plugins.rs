@@ -0,0 +1,433 @@
+//! Diagnostics of the `Name` of sections referencing plugins fluent-bit doesn't
+//! have, e.g. `Name tial`, and of keys their plugin doesn't have, e.g. `Pathx`
+//! under `tail`, with the nearest name as a fix, and of sections without a
+//! `Name`.
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, TextEdit};
+
+use crate::{
+    completion::FlbData,
+    document::TextDocument,
+    fixes::insert_keys,
+    i18n::t,
+    lsp_ext::DiagnosticFix,
+    positions::{byte_range_to_lsp_range, resolve_position, PositionEncodingKind},
+    section_index::{IndexedSection, SectionIndex},
+    service::is_service_key,
+    validation::is_env_reference,
+};
+
+/// Diagnostic code of a `Name` which isn't a plugin of its section type.
+pub(crate) const UNKNOWN_PLUGIN: &str = "unknown-plugin";
+/// Diagnostic code of a key which isn't a parameter of the section's plugin.
+pub(crate) const UNKNOWN_KEY: &str = "unknown-key";
+/// Diagnostic code of a plugin section without `Name`.
+pub(crate) const MISSING_NAME: &str = "missing-name";
+
+/// Sections whose `Name` is a plugin.
+const PLUGIN_SECTIONS: &[FlbSectionType] = &[
+    FlbSectionType::Input,
+    FlbSectionType::Filter,
+    FlbSectionType::Output,
+    FlbSectionType::Custom,
+];
+
+/// Keys fluent-bit accepts in the sections of all plugins of a type, which the
+/// plugins don't declare. Keys ending with `.` are prefixes, e.g. `net.`.
+///
+/// ref: `flb_input_set_property()`, `flb_filter_set_property()` and
+/// `flb_output_set_property()` in fluent-bit
+const COMMON_KEYS: &[(FlbSectionType, &[&str])] = &[
+    (FlbSectionType::Input, &[
+        "tag",
+        "routable",
+        "mem_buf_limit",
+        "listen",
+        "host",
+        "port",
+        "threaded",
+        "storage.type",
+        "storage.pause_on_chunks_overlimit",
+        "processors",
+        "tls",
+        "tls.",
+        "net.",
+    ]),
+    (FlbSectionType::Filter, &["match", "match_regex"]),
+    (FlbSectionType::Output, &[
+        "match",
+        "match_regex",
+        "host",
+        "port",
+        "ipv6",
+        "retry_limit",
+        "workers",
+        "storage.total_limit_size",
+        "processors",
+        "tls",
+        "tls.",
+        "net.",
+    ]),
+];
+
+/// Keys common to the sections of all plugins.
+const PLUGIN_KEYS: &[&str] = &["name", "alias", "log_level", "log_suppress_interval"];
+
+/// Levenshtein distance of `a` and `b`, ignoring case.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Candidate with the name nearest to `name`, if it's near enough to be a
+/// typo.
+pub(crate) fn nearest<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = (name.chars().count() + 2) / 3;
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        // ties are broken by name, as the candidates aren't ordered
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn nearest_plugin<'a>(
+    data: &'a FlbData,
+    section_type: &FlbSectionType,
+    name: &str,
+) -> Option<&'a str> {
+    nearest(name, data.plugin_names(section_type))
+}
+
+/// Keys of the plugin's sections: its parameters and the common keys. `None`
+/// if the plugin is unknown.
+fn plugin_keys<'a>(
+    data: &'a FlbData,
+    section_type: &FlbSectionType,
+    plugin_name: &str,
+) -> Option<impl Iterator<Item = &'a str> + Clone> {
+    let params = data.get_plugin_params(section_type, plugin_name)?;
+    let common = COMMON_KEYS
+        .iter()
+        .find(|(type_, _)| type_ == section_type)
+        .map_or(&[][..], |(_, keys)| keys);
+
+    Some(
+        params
+            .keys()
+            .map(String::as_str)
+            .chain(common.iter().copied())
+            .chain(PLUGIN_KEYS.iter().copied()),
+    )
+}
+
+/// Name of the plugin of the section, if it's known.
+fn known_plugin<'a>(data: &FlbData, section: &'a IndexedSection) -> Option<&'a str> {
+    if !PLUGIN_SECTIONS.contains(&section.section_type) {
+        return None;
+    }
+
+    section
+        .get_value("Name")
+        .filter(|name| !is_env_reference(name) && data.has_plugin(&section.section_type, name))
+}
+
+pub(crate) fn check_plugin_names(
+    data: &FlbData,
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for section in index.sections() {
+        let section_type = &section.section_type;
+        // without data about the plugins of the section type, every name would
+        // be reported
+        if !PLUGIN_SECTIONS.contains(section_type) || data.plugin_names(section_type).count() == 0 {
+            continue;
+        }
+
+        let Some(entry) = section
+            .entries
+            .iter()
+            .find(|entry| entry.key.eq_ignore_ascii_case("Name"))
+        else {
+            // the first line of the section, e.g. its header
+            let start = section.byte_range.start;
+            let line = rope.byte_slice(start..section.byte_range.end).to_string();
+            let len = line.lines().next().unwrap_or_default().trim_end().len();
+            diagnostics.push(Diagnostic {
+                range: byte_range_to_lsp_range(rope, start..start + len, position_encoding),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(MISSING_NAME.to_string())),
+                message: t!(MISSING_NAME, section = section.name),
+                ..Diagnostic::default()
+            });
+            continue;
+        };
+        let (Some(name), Some(value_range)) = (&entry.value, &entry.value_range) else {
+            continue;
+        };
+        if name.is_empty() || is_env_reference(name) || data.has_plugin(section_type, name) {
+            continue;
+        }
+
+        let section_name = section_type.to_string();
+        let message = match nearest_plugin(data, section_type, name) {
+            Some(suggestion) => t!(
+                "unknown-plugin-suggestion",
+                section_type = section_name,
+                name = name,
+                suggestion = suggestion,
+            ),
+            None => t!(UNKNOWN_PLUGIN, section_type = section_name, name = name),
+        };
+        diagnostics.push(Diagnostic {
+            range: byte_range_to_lsp_range(
+                rope,
+                value_range.start..value_range.start + name.len(),
+                position_encoding,
+            ),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String(UNKNOWN_PLUGIN.to_string())),
+            message,
+            ..Diagnostic::default()
+        });
+    }
+
+    diagnostics
+}
+
+/// Reports the keys of plugin sections which are neither parameters of the
+/// plugin nor common to its section type. `[SERVICE]` keys are reported as
+/// misplaced instead.
+pub(crate) fn check_unknown_keys(
+    data: &FlbData,
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for section in index.sections() {
+        let Some(plugin_name) = known_plugin(data, section) else {
+            continue;
+        };
+        let Some(keys) = plugin_keys(data, &section.section_type, plugin_name) else {
+            continue;
+        };
+
+        for entry in &section.entries {
+            let key = entry.key.to_lowercase();
+            let known = keys.clone().any(|known| match known.strip_suffix('.') {
+                Some(_) => key.starts_with(known),
+                None => key == known,
+            });
+            if known || is_service_key(&key) {
+                continue;
+            }
+
+            let candidates = keys.clone().filter(|known| !known.ends_with('.'));
+            let message = match nearest(&key, candidates) {
+                Some(suggestion) => t!(
+                    "unknown-key-suggestion",
+                    key = entry.key,
+                    plugin = plugin_name,
+                    section_type = section.section_type,
+                    suggestion = suggestion,
+                ),
+                None => t!(
+                    UNKNOWN_KEY,
+                    key = entry.key,
+                    plugin = plugin_name,
+                    section_type = section.section_type,
+                ),
+            };
+            diagnostics.push(Diagnostic {
+                range: byte_range_to_lsp_range(rope, entry.key_range.clone(), position_encoding),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(UNKNOWN_KEY.to_string())),
+                message,
+                ..Diagnostic::default()
+            });
+        }
+    }
+
+    diagnostics
+}
+
+/// Replaces the unknown key with the key of the plugin nearest to it.
+pub(crate) fn unknown_key_fix(
+    data: &FlbData,
+    document: &TextDocument,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    if diagnostic.code != Some(NumberOrString::String(UNKNOWN_KEY.to_string())) {
+        return None;
+    }
+
+    let start =
+        resolve_position(&document.rope, &diagnostic.range.start, position_encoding).ok()?;
+    let (section, entry) = document.index.sections().iter().find_map(|section| {
+        let entry = section
+            .entries
+            .iter()
+            .find(|entry| entry.key_range.start == start.byte_idx)?;
+        Some((section, entry))
+    })?;
+    let plugin_name = known_plugin(data, section)?;
+    let keys = plugin_keys(data, &section.section_type, plugin_name)?;
+    let suggestion = nearest(
+        &entry.key.to_lowercase(),
+        keys.filter(|known| !known.ends_with('.')),
+    )?;
+
+    Some(DiagnosticFix {
+        title: t!("fix-replace-key", suggestion = suggestion),
+        edits: vec![TextEdit {
+            range: diagnostic.range,
+            new_text: suggestion.to_string(),
+        }],
+    })
+}
+
+/// Inserts the missing `Name` of the section.
+pub(crate) fn missing_name_fix(
+    document: &TextDocument,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    if diagnostic.code != Some(NumberOrString::String(MISSING_NAME.to_string())) {
+        return None;
+    }
+
+    let start =
+        resolve_position(&document.rope, &diagnostic.range.start, position_encoding).ok()?;
+    let section = document
+        .index
+        .sections()
+        .iter()
+        .find(|section| section.byte_range.start == start.byte_idx)?;
+
+    Some(DiagnosticFix {
+        title: t!("fix-insert-name"),
+        edits: vec![insert_keys(
+            document,
+            section,
+            &["Name"],
+            true,
+            position_encoding,
+        )],
+    })
+}
+
+/// Replaces the unknown plugin name with the nearest one.
+pub(crate) fn unknown_plugin_fix(
+    data: &FlbData,
+    document: &TextDocument,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    if diagnostic.code != Some(NumberOrString::String(UNKNOWN_PLUGIN.to_string())) {
+        return None;
+    }
+
+    let start =
+        resolve_position(&document.rope, &diagnostic.range.start, position_encoding).ok()?;
+    let (section, name) = document.index.sections().iter().find_map(|section| {
+        let entry = section.entries.iter().find(|entry| {
+            entry
+                .value_range
+                .as_ref()
+                .is_some_and(|range| range.start == start.byte_idx)
+        })?;
+        Some((section, entry.value.as_deref()?))
+    })?;
+    let suggestion = nearest_plugin(data, &section.section_type, name)?;
+
+    Some(DiagnosticFix {
+        title: t!("fix-replace-plugin", suggestion = suggestion),
+        edits: vec![TextEdit {
+            range: diagnostic.range,
+            new_text: suggestion.to_string(),
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+    use crate::completion::FLB_DATA;
+
+    #[test]
+    fn unknown_plugins_with_the_nearest_match() {
+        assert_eq!(edit_distance("tial", "tail"), 2);
+        assert_eq!(edit_distance("Stdout", "stdout"), 0);
+        assert_eq!(edit_distance("", "cpu"), 3);
+
+        let document = TextDocument::new(
+            "[INPUT]\n    Name tial\n\n[INPUT]\n    Name CPU\n\n\
+             [FILTER]\n    Name ${FILTER}\n\n[OUTPUT]\n    Name nothing_like_it\n",
+        );
+        let encoding = PositionEncodingKind::UTF16;
+        let diagnostics = check_plugin_names(&FLB_DATA, &document.index, &document.rope, encoding);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Unknown input plugin `tial`, did you mean `tail`?",
+            "Unknown output plugin `nothing_like_it`.",
+        ]);
+
+        let fix = unknown_plugin_fix(&FLB_DATA, &document, &diagnostics[0], encoding).unwrap();
+        assert_eq!(fix.title, "Replace with `tail`");
+        assert_eq!(fix.edits[0].new_text, "tail");
+        assert_eq!(fix.edits[0].range, diagnostics[0].range);
+        assert!(unknown_plugin_fix(&FLB_DATA, &document, &diagnostics[1], encoding).is_none());
+    }
+
+    #[test]
+    fn keys_unknown_to_the_plugin() {
+        let document = TextDocument::new(
+            "[INPUT]\n    Name  tail\n    Pathx /var/log/*.log\n    Tag   app\n    tls.verify off\n\n\
+             [INPUT]\n    Name  tial\n    Pathx /var/log/*.log\n\n\
+             [OUTPUT]\n    Name   kafka\n    Match  *\n    rdkafka.log.connection.close false\n    \
+             Flush  1\n    Zzz    1\n",
+        );
+        let encoding = PositionEncodingKind::UTF16;
+        let diagnostics = check_unknown_keys(&FLB_DATA, &document.index, &document.rope, encoding);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Unknown key `Pathx` for the `tail` input plugin, did you mean `path`?",
+            "Unknown key `Zzz` for the `kafka` output plugin.",
+        ]);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(2, 4), Position::new(2, 9))
+        );
+
+        let fix = unknown_key_fix(&FLB_DATA, &document, &diagnostics[0], encoding).unwrap();
+        assert_eq!(fix.title, "Replace with `path`");
+        assert_eq!(fix.edits[0].new_text, "path");
+    }
+}
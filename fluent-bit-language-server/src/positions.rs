@@ -0,0 +1,243 @@
+//! Conversions between the ways positions are expressed: LSP positions, whose
+//! character offset depends on the negotiated encoding, char and byte indices
+//! into the rope, and tree-sitter points, whose column is a byte offset.
+
+use ropey::{Rope, RopeSlice};
+use tower_lsp::{
+    lsp_types,
+    lsp_types::{Position, Range},
+};
+use tree_sitter::Point;
+
+use crate::document::DocumentError;
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+/// We redeclare this enum here because the `lsp_types` crate exports a Cow
+/// type that is unconvenient to deal with.
+pub enum PositionEncodingKind {
+    UTF8,
+    UTF16,
+    UTF32,
+}
+
+impl PositionEncodingKind {
+    /// Picks the encoding used for positions from the ones supported by the
+    /// client. UTF-8 is preferred as it matches tree-sitter's byte columns,
+    /// UTF-16 is the mandatory fallback.
+    pub fn negotiate(client_encodings: Option<&[lsp_types::PositionEncodingKind]>) -> Self {
+        let supports = |encoding: &lsp_types::PositionEncodingKind| {
+            client_encodings.is_some_and(|encodings| encodings.contains(encoding))
+        };
+
+        if supports(&lsp_types::PositionEncodingKind::UTF8) {
+            PositionEncodingKind::UTF8
+        } else if supports(&lsp_types::PositionEncodingKind::UTF32) {
+            PositionEncodingKind::UTF32
+        } else {
+            PositionEncodingKind::UTF16
+        }
+    }
+
+    pub fn to_lsp(self) -> lsp_types::PositionEncodingKind {
+        match self {
+            PositionEncodingKind::UTF8 => lsp_types::PositionEncodingKind::UTF8,
+            PositionEncodingKind::UTF16 => lsp_types::PositionEncodingKind::UTF16,
+            PositionEncodingKind::UTF32 => lsp_types::PositionEncodingKind::UTF32,
+        }
+    }
+}
+
+/// Length of the content, in code units of `encoding`.
+pub fn len_in(rope: &Rope, encoding: PositionEncodingKind) -> usize {
+    match encoding {
+        PositionEncodingKind::UTF8 => rope.len_bytes(),
+        PositionEncodingKind::UTF16 => rope.len_utf16_cu(),
+        PositionEncodingKind::UTF32 => rope.len_chars(),
+    }
+}
+
+/// A location in the document, in all the units the server deals with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub char_idx: usize,
+    pub byte_idx: usize,
+    pub point: Point,
+}
+
+/// Converts the character offset of `position` into a char index into `line`.
+fn line_char_idx(
+    line: &RopeSlice,
+    position: &Position,
+    position_encoding: PositionEncodingKind,
+) -> Result<usize, DocumentError> {
+    match position_encoding {
+        PositionEncodingKind::UTF8 => line.try_byte_to_char(position.character as usize),
+        PositionEncodingKind::UTF16 => line.try_utf16_cu_to_char(position.character as usize),
+        PositionEncodingKind::UTF32 => Ok(position.character as usize),
+    }
+    .ok()
+    .filter(|char_idx| *char_idx <= line.len_chars())
+    .ok_or(DocumentError::PositionOutOfBounds(
+        position.line,
+        position.character,
+    ))
+}
+
+/// Resolves an LSP position into a location of the document.
+pub fn resolve_position(
+    rope: &Rope,
+    position: &Position,
+    position_encoding: PositionEncodingKind,
+) -> Result<Location, DocumentError> {
+    let line_idx = position.line as usize;
+    let line = rope
+        .get_line(line_idx)
+        .ok_or(DocumentError::PositionOutOfBounds(
+            position.line,
+            position.character,
+        ))?;
+    let line_char_idx = line_char_idx(&line, position, position_encoding)?;
+    let column = line.char_to_byte(line_char_idx);
+
+    Ok(Location {
+        char_idx: rope.line_to_char(line_idx) + line_char_idx,
+        byte_idx: rope.line_to_byte(line_idx) + column,
+        point: Point {
+            row: line_idx,
+            column,
+        },
+    })
+}
+
+/// Converts an LSP position into a tree-sitter point.
+pub fn position_to_point(
+    rope: &Rope,
+    position: &Position,
+    position_encoding: PositionEncodingKind,
+) -> Result<Point, DocumentError> {
+    resolve_position(rope, position, position_encoding).map(|location| location.point)
+}
+
+/// Converts a tree-sitter point into an LSP position. Points beyond the end of
+/// their line are clamped to it.
+pub fn point_to_position(
+    rope: &Rope,
+    point: Point,
+    position_encoding: PositionEncodingKind,
+) -> Position {
+    let Some(line) = rope.get_line(point.row) else {
+        return Position::new(point.row as u32, 0);
+    };
+    let char_idx = line.byte_to_char(point.column.min(line.len_bytes()));
+
+    let character = match position_encoding {
+        PositionEncodingKind::UTF8 => line.char_to_byte(char_idx),
+        PositionEncodingKind::UTF16 => line.char_to_utf16_cu(char_idx),
+        PositionEncodingKind::UTF32 => char_idx,
+    };
+
+    Position::new(point.row as u32, character as u32)
+}
+
+/// Converts a tree-sitter point into a byte offset into the document, `None`
+/// if it's beyond the end of its line.
+pub fn point_to_byte(rope: &Rope, point: Point) -> Option<usize> {
+    let line = rope.get_line(point.row)?;
+    if point.column > line.len_bytes() {
+        return None;
+    }
+
+    Some(rope.line_to_byte(point.row) + point.column)
+}
+
+/// Converts a byte offset into the document into a tree-sitter point.
+pub fn byte_to_point(rope: &Rope, byte: usize) -> Point {
+    let byte = byte.min(rope.len_bytes());
+    let row = rope.byte_to_line(byte);
+
+    Point {
+        row,
+        column: byte - rope.line_to_byte(row),
+    }
+}
+
+/// Converts a byte range of the document into an LSP range.
+pub fn byte_range_to_lsp_range(
+    rope: &Rope,
+    range: std::ops::Range<usize>,
+    position_encoding: PositionEncodingKind,
+) -> Range {
+    Range::new(
+        point_to_position(rope, byte_to_point(rope, range.start), position_encoding),
+        point_to_position(rope, byte_to_point(rope, range.end), position_encoding),
+    )
+}
+
+/// Converts the range of a tree-sitter node into an LSP range.
+pub fn to_lsp_range(
+    rope: &Rope,
+    range: tree_sitter::Range,
+    position_encoding: PositionEncodingKind,
+) -> Range {
+    Range::new(
+        point_to_position(rope, range.start_point, position_encoding),
+        point_to_position(rope, range.end_point, position_encoding),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position_conversions_with_multibyte_content() {
+        // "🤗" is 4 bytes, 2 UTF-16 code units and 1 char
+        let rope = Rope::from_str("[INPUT]\n    Tag 🤗.log  # é\n");
+        let point = Point { row: 1, column: 16 }; // after "🤗.log"
+
+        for (encoding, character) in [
+            (PositionEncodingKind::UTF8, 16),
+            (PositionEncodingKind::UTF16, 14),
+            (PositionEncodingKind::UTF32, 13),
+        ] {
+            let position = Position::new(1, character);
+            assert_eq!(
+                position_to_point(&rope, &position, encoding).unwrap(),
+                point
+            );
+            assert_eq!(point_to_position(&rope, point, encoding), position);
+        }
+
+        assert!(
+            position_to_point(&rope, &Position::new(1, 100), PositionEncodingKind::UTF32).is_err()
+        );
+        assert!(
+            position_to_point(&rope, &Position::new(5, 0), PositionEncodingKind::UTF16).is_err()
+        );
+    }
+
+    #[test]
+    fn locations_in_all_units() {
+        // "é" is 2 bytes, 1 UTF-16 code unit and 1 char
+        let rope = Rope::from_str("[INPUT]\n    Tag é.log\n");
+
+        let location =
+            resolve_position(&rope, &Position::new(1, 10), PositionEncodingKind::UTF16).unwrap();
+        assert_eq!(location, Location {
+            char_idx: 18,
+            byte_idx: 19,
+            point: Point { row: 1, column: 11 },
+        });
+        assert_eq!(point_to_byte(&rope, location.point), Some(19));
+        assert_eq!(byte_to_point(&rope, 19), location.point);
+        assert_eq!(
+            byte_range_to_lsp_range(&rope, 12..22, PositionEncodingKind::UTF32),
+            Range::new(Position::new(1, 4), Position::new(1, 13))
+        );
+
+        // the end of the last line, and beyond
+        assert_eq!(point_to_byte(&rope, Point { row: 2, column: 0 }), Some(23));
+        assert_eq!(point_to_byte(&rope, Point { row: 1, column: 30 }), None);
+        assert_eq!(point_to_byte(&rope, Point { row: 3, column: 0 }), None);
+    }
+}
@@ -0,0 +1,86 @@
+//! Progress reporting through `$/progress`.
+//!
+//! `lsp_types::ProgressParamsValue` only covers work done progress, so partial
+//! results (e.g. for `workspace/diagnostic`) are sent with a dedicated
+//! notification type carrying the request's own result type.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+use tower_lsp::{
+    lsp_types::{
+        notification::{Notification, Progress},
+        request::WorkDoneProgressCreate,
+        ProgressParams, ProgressParamsValue, ProgressToken, WorkDoneProgress,
+        WorkDoneProgressBegin, WorkDoneProgressCreateParams, WorkDoneProgressEnd,
+        WorkspaceDiagnosticReportResult,
+    },
+    Client,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialResultParams<T> {
+    pub token: ProgressToken,
+    pub value: T,
+}
+
+pub enum WorkspaceDiagnosticPartialResult {}
+
+impl Notification for WorkspaceDiagnosticPartialResult {
+    type Params = PartialResultParams<WorkspaceDiagnosticReportResult>;
+    const METHOD: &'static str = "$/progress";
+}
+
+/// Work done progress initiated by the server (`window/workDoneProgress/create`).
+pub struct ServerProgress {
+    client: Client,
+    token: ProgressToken,
+}
+
+impl ServerProgress {
+    /// Asks the client to create a progress and reports its beginning.
+    ///
+    /// Returns `None` when the client refused to create it.
+    pub async fn begin(client: &Client, title: &str) -> Option<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        let token = ProgressToken::String(format!(
+            "fluent-bit-language-server/{}",
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .ok()?;
+
+        client
+            .send_notification::<Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(WorkDoneProgress::Begin(
+                    WorkDoneProgressBegin {
+                        title: title.to_string(),
+                        ..WorkDoneProgressBegin::default()
+                    },
+                )),
+            })
+            .await;
+
+        Some(Self {
+            client: client.clone(),
+            token,
+        })
+    }
+
+    pub async fn end(self, message: Option<String>) {
+        self.client
+            .send_notification::<Progress>(ProgressParams {
+                token: self.token,
+                value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(WorkDoneProgressEnd {
+                    message,
+                })),
+            })
+            .await;
+    }
+}
@@ -0,0 +1,129 @@
+//! Checks of the regular expressions of `Match_Regex`.
+//!
+//! fluent-bit compiles them with Oniguruma, in its Ruby syntax. They're checked
+//! here with the `regex` crate, which parses a subset of it: the constructs it
+//! lacks, e.g. look-arounds or backreferences, are recognized so that valid
+//! patterns using them aren't reported as invalid.
+//!
+//! ref: `flb_regex_create()` in fluent-bit
+
+use regex::Regex;
+
+use crate::i18n::t;
+
+pub(crate) enum RegexStatus {
+    Valid,
+    /// Uses Oniguruma constructs which can't be checked, e.g. look-arounds.
+    Unchecked(&'static str),
+    /// Error of the pattern, e.g. `unclosed group`.
+    Invalid(String),
+}
+
+/// Oniguruma construct of `pattern` which the `regex` crate doesn't support.
+fn unsupported_construct(pattern: &str) -> Option<&'static str> {
+    let bytes = pattern.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        let construct = match rest {
+            [b'\\', b'1'..=b'9', ..] | [b'\\', b'k', b'<', ..] => Some("backreferences"),
+            [b'\\', b'h' | b'H', ..] => Some("`\\h`"),
+            [b'\\', b'G' | b'Z', ..] => Some("`\\G` or `\\Z` anchors"),
+            [b'\\', ..] => {
+                // escaped characters are skipped with their escape
+                i += 2;
+                continue;
+            }
+            [b'(', b'?', b'=' | b'!', ..] | [b'(', b'?', b'<', b'=' | b'!', ..] => {
+                Some("look-arounds")
+            }
+            [b'(', b'?', b'>', ..] => Some("atomic groups"),
+            _ => None,
+        };
+        if construct.is_some() {
+            return construct;
+        }
+        i += 1;
+    }
+
+    None
+}
+
+pub(crate) fn check_regex(pattern: &str) -> RegexStatus {
+    let error = match Regex::new(pattern) {
+        Ok(_) => return RegexStatus::Valid,
+        Err(e) => e,
+    };
+    if let Some(construct) = unsupported_construct(pattern) {
+        return RegexStatus::Unchecked(construct);
+    }
+
+    // syntax errors end with `error: <reason>`, after a diagram of the pattern
+    let error = error.to_string();
+    let reason = error
+        .lines()
+        .last()
+        .map(|line| line.trim_start_matches("error: ").to_string())
+        .unwrap_or(error);
+    RegexStatus::Invalid(reason)
+}
+
+/// Paragraphs of the hover of `Match_Regex`: its flavor, a cheat sheet, and
+/// whether `value` is valid.
+pub(crate) fn match_regex_notes(value: Option<&str>) -> Vec<String> {
+    let mut notes = vec![t!("note-regex-flavor"), t!("note-regex-cheat-sheet")];
+
+    if let Some(value) = value.filter(|value| !value.is_empty()) {
+        notes.push(match check_regex(value) {
+            RegexStatus::Valid => t!("note-regex-valid", value = value),
+            RegexStatus::Unchecked(construct) => {
+                t!("note-regex-unchecked", value = value, construct = construct)
+            }
+            RegexStatus::Invalid(reason) => {
+                t!("note-regex-invalid", value = value, reason = reason)
+            }
+        });
+    }
+
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regexes_in_oniguruma_syntax() {
+        assert!(matches!(
+            check_regex(r"^kube\.var\.log\..+$"),
+            RegexStatus::Valid
+        ));
+        assert!(matches!(
+            check_regex("(?<app>[a-z]+)-.*"),
+            RegexStatus::Valid
+        ));
+
+        for (pattern, construct) in [
+            (r"^(?<!tmp\.)app\..*", "look-arounds"),
+            (r"^(a)\1$", "backreferences"),
+            (r"(?>a+)b", "atomic groups"),
+            (r"^app\.\h+$", "`\\h`"),
+        ] {
+            match check_regex(pattern) {
+                RegexStatus::Unchecked(found) => assert_eq!(found, construct, "{pattern}"),
+                _ => panic!("{pattern} should be unchecked"),
+            }
+        }
+        // escaped parentheses aren't groups
+        assert!(unsupported_construct(r"\(?=").is_none());
+
+        match check_regex("app.(log") {
+            RegexStatus::Invalid(reason) => assert_eq!(reason, "unclosed group"),
+            _ => panic!("`app.(log` should be invalid"),
+        }
+        assert_eq!(
+            match_regex_notes(Some("app.(log")).last().unwrap(),
+            "`app.(log` is invalid: unclosed group."
+        );
+    }
+}
@@ -0,0 +1,639 @@
+//! Routing of records from inputs to the filters and outputs matching their
+//! tag, with `Match` wildcard patterns or `Match_Regex` regular expressions.
+//! The rules routing the records of a tag are its references.
+//!
+//! The routing model of a document lists the tags it produces and the rules it
+//! routes them with, to find rules matching no tag and inputs reaching no
+//! output across the files of a configuration.
+//!
+//! The order of the sections of classic files is checked too: fluent-bit
+//! filters records before routing them to any output, and reads `[SERVICE]`
+//! wherever it is, so filters after the outputs of their records and a
+//! `[SERVICE]` after other sections read in another order than they run.
+//!
+//! ref: `flb_router_match()` in fluent-bit
+
+use std::{collections::HashMap, ops::Range};
+
+use flb_schema::section::FlbSectionType;
+use regex::Regex;
+use ropey::Rope;
+use tower_lsp::lsp_types::{
+    self, CompletionItem, CompletionItemKind, CompletionTextEdit, Diagnostic, DiagnosticSeverity,
+    Location, NumberOrString, TextEdit, Url,
+};
+
+use crate::{
+    i18n::t,
+    lsp_ext::{PipelineHierarchyResult, PipelineInput, PipelineStage},
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    section_index::{IndexedEntry, IndexedSection, SectionIndex},
+    section_names::name_range,
+};
+
+/// Diagnostic code of a filter after an output matching the same records.
+pub(crate) const FILTER_AFTER_OUTPUT: &str = "filter-after-output";
+/// Diagnostic code of a `[SERVICE]` section which isn't the first one.
+pub(crate) const SERVICE_NOT_FIRST: &str = "service-not-first";
+
+pub(crate) enum MatchRule {
+    /// `*` matches any sequence of characters.
+    Wildcard(String),
+    Regex(Regex),
+}
+
+impl MatchRule {
+    /// Rule of a filter or output, `Match_Regex` taking precedence like in
+    /// fluent-bit. `None` if there's none, or the regular expression is
+    /// invalid.
+    pub(crate) fn of_section(section: &IndexedSection) -> Option<Self> {
+        if let Some(regex) = section.get_value("Match_Regex") {
+            return Regex::new(regex).ok().map(MatchRule::Regex);
+        }
+
+        section
+            .get_value("Match")
+            .map(|pattern| MatchRule::Wildcard(pattern.to_string()))
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            MatchRule::Wildcard(pattern) => pattern,
+            MatchRule::Regex(regex) => regex.as_str(),
+        }
+    }
+
+    /// Whether records tagged `tag` are routed through the rule. Tags may
+    /// contain wildcards too, which inputs like tail expand at runtime, so
+    /// that a wildcard pattern matches if any expansion would.
+    pub(crate) fn matches(&self, tag: &str) -> bool {
+        match self {
+            MatchRule::Wildcard(pattern) => wildcards_intersect(pattern.as_bytes(), tag.as_bytes()),
+            MatchRule::Regex(regex) => regex.is_match(tag),
+        }
+    }
+}
+
+/// Whether some string matches both wildcard patterns.
+fn wildcards_intersect(a: &[u8], b: &[u8]) -> bool {
+    // reachable[i][j]: a[i..] and b[j..] are left to match
+    let mut reachable = vec![vec![false; b.len() + 1]; a.len() + 1];
+    reachable[0][0] = true;
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            if !reachable[i][j] {
+                continue;
+            }
+            if i < a.len() && a[i] == b'*' {
+                // the wildcard matches nothing more, or the next character
+                reachable[i + 1][j] = true;
+                if j < b.len() {
+                    reachable[i][j + 1] = true;
+                }
+            }
+            if j < b.len() && b[j] == b'*' {
+                reachable[i][j + 1] = true;
+                if i < a.len() {
+                    reachable[i + 1][j] = true;
+                }
+            }
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                reachable[i + 1][j + 1] = true;
+            }
+        }
+    }
+
+    reachable[a.len()][b.len()]
+}
+
+/// Range of the value of the entry, without the spaces around it.
+fn value_range(entry: &IndexedEntry) -> Option<Range<usize>> {
+    let (value, range) = (entry.value.as_deref()?, entry.value_range.as_ref()?);
+    Some(range.start..range.start + value.len())
+}
+
+/// Range of the value of the entry applying the rule of the section.
+fn rule_range(section: &IndexedSection, rule: &MatchRule) -> Option<Range<usize>> {
+    let key = match rule {
+        MatchRule::Wildcard(_) => "Match",
+        MatchRule::Regex(_) => "Match_Regex",
+    };
+    section
+        .entries
+        .iter()
+        .find(|entry| entry.key.eq_ignore_ascii_case(key))
+        .and_then(value_range)
+}
+
+/// Inputs tagging the records they receive with the tags of their clients
+/// unless `Tag` is set.
+const CLIENT_TAGGED_INPUTS: &[&str] = &["forward", "http", "opentelemetry", "splunk"];
+
+/// Inputs and the tags of their records, fluent-bit tagging inputs without
+/// `Tag` with their instance name.
+fn input_tags(index: &SectionIndex) -> Vec<(&IndexedSection, String)> {
+    let mut instances: HashMap<String, usize> = HashMap::new();
+    index
+        .sections()
+        .iter()
+        .filter(|section| section.section_type == FlbSectionType::Input)
+        .filter_map(|section| {
+            let plugin_name = section.get_value("Name")?;
+            let instance = instances.entry(plugin_name.to_lowercase()).or_default();
+            let tag = section
+                .get_value("Tag")
+                .map_or_else(|| format!("{plugin_name}.{instance}"), ToString::to_string);
+            *instance += 1;
+
+            Some((section, tag))
+        })
+        .collect()
+}
+
+/// Input of a routing model.
+pub(crate) struct RoutedInput {
+    pub(crate) tag: String,
+    /// Range of its `Tag`, or of its `Name` without one.
+    pub(crate) range: lsp_types::Range,
+}
+
+/// Filter or output of a routing model.
+pub(crate) struct RoutedStage {
+    pub(crate) section_type: FlbSectionType,
+    pub(crate) rule: MatchRule,
+    /// Whether it's a `rewrite_tag` filter, routing records elsewhere.
+    pub(crate) rewrites: bool,
+    /// Range of its `Match` or `Match_Regex`.
+    pub(crate) range: lsp_types::Range,
+}
+
+/// What a document contributes to the routing of a configuration.
+#[derive(Default)]
+pub(crate) struct RoutingModel {
+    pub(crate) inputs: Vec<RoutedInput>,
+    pub(crate) stages: Vec<RoutedStage>,
+    /// Tags `rewrite_tag` filters emit records with.
+    pub(crate) rewritten_tags: Vec<String>,
+}
+
+impl RoutingModel {
+    pub(crate) fn new(
+        index: &SectionIndex,
+        rope: &Rope,
+        position_encoding: PositionEncodingKind,
+    ) -> Self {
+        let lsp_range = |range| byte_range_to_lsp_range(rope, range, position_encoding);
+        let inputs = input_tags(index)
+            .into_iter()
+            .filter_map(|(section, tag)| {
+                let client_tagged = section.get_value("Tag").is_none()
+                    && section.get_value("Name").is_some_and(|name| {
+                        CLIENT_TAGGED_INPUTS
+                            .iter()
+                            .any(|input| input.eq_ignore_ascii_case(name))
+                    });
+                // `*` stands for tags only known at runtime, e.g. `${HOSTNAME}.logs`
+                let tag = match client_tagged || tag.contains('$') {
+                    true => "*".to_string(),
+                    false => tag,
+                };
+                let entry = ["Tag", "Name"].iter().find_map(|key| {
+                    section
+                        .entries
+                        .iter()
+                        .find(|entry| entry.key.eq_ignore_ascii_case(key))
+                })?;
+                Some(RoutedInput {
+                    tag,
+                    range: lsp_range(value_range(entry)?),
+                })
+            })
+            .collect();
+
+        let mut model = RoutingModel {
+            inputs,
+            ..RoutingModel::default()
+        };
+        for section in index.sections() {
+            if !matches!(
+                section.section_type,
+                FlbSectionType::Filter | FlbSectionType::Output
+            ) {
+                continue;
+            }
+            let rewrites = section.section_type == FlbSectionType::Filter
+                && section
+                    .get_value("Name")
+                    .is_some_and(|name| name.eq_ignore_ascii_case("rewrite_tag"));
+            if rewrites {
+                // `Rule $KEY REGEX NEW_TAG KEEP`
+                let new_tags = section
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.key.eq_ignore_ascii_case("Rule"))
+                    .filter_map(|entry| entry.value.as_deref()?.split_whitespace().nth(2))
+                    .map(|tag| match tag.contains('$') {
+                        true => "*".to_string(),
+                        false => tag.to_string(),
+                    });
+                model.rewritten_tags.extend(new_tags);
+            }
+
+            let Some(rule) = MatchRule::of_section(section) else {
+                continue;
+            };
+            let Some(range) = rule_range(section, &rule) else {
+                continue;
+            };
+            model.stages.push(RoutedStage {
+                section_type: section.section_type.clone(),
+                rule,
+                rewrites,
+                range: lsp_range(range),
+            });
+        }
+
+        model
+    }
+}
+
+/// `Tag` entries of the inputs, and the ranges of their values.
+pub(crate) fn tags(index: &SectionIndex) -> impl Iterator<Item = (&str, Range<usize>)> {
+    index
+        .sections()
+        .iter()
+        .filter(|section| section.section_type == FlbSectionType::Input)
+        .flat_map(|section| &section.entries)
+        .filter(|entry| entry.key.eq_ignore_ascii_case("Tag"))
+        .filter_map(|entry| Some((entry.value.as_deref()?, value_range(entry)?)))
+}
+
+/// `Tag` at the byte offset, its end included.
+pub(crate) fn tag_at(index: &SectionIndex, byte: usize) -> Option<String> {
+    tags(index)
+        .find(|(_, range)| range.start <= byte && byte <= range.end)
+        .map(|(tag, _)| tag.to_string())
+}
+
+/// Prefix of the tags the `kubernetes` filter reads the pod of records from,
+/// unless `Kube_Tag_Prefix` is set.
+const KUBE_TAG_PREFIX: &str = "kube.var.log.containers.";
+
+/// Directory of the container logs of a node, as `tail` expands it in tags.
+const CONTAINER_LOGS_TAG_PATH: &str = "var.log.containers.";
+
+/// Tags conventional for the input `section` of the document, and what they
+/// are conventional for.
+fn conventional_tags(index: &SectionIndex, section: &IndexedSection) -> Vec<(String, String)> {
+    let Some(plugin_name) = section.get_value("Name") else {
+        return Vec::new();
+    };
+    let plugin_name = plugin_name.to_lowercase();
+    let mut tags = Vec::new();
+
+    if plugin_name == "tail" {
+        // `*` expands to the path of each file, e.g. `kube.var.log.containers.*`
+        let kubernetes_tags = index
+            .sections()
+            .iter()
+            .filter(|filter| filter.section_type == FlbSectionType::Filter)
+            .filter(|filter| {
+                filter
+                    .get_value("Name")
+                    .is_some_and(|name| name.eq_ignore_ascii_case("kubernetes"))
+            })
+            .map(|filter| {
+                let prefix = filter
+                    .get_value("Kube_Tag_Prefix")
+                    .unwrap_or(KUBE_TAG_PREFIX);
+                let prefix = prefix
+                    .strip_suffix(CONTAINER_LOGS_TAG_PATH)
+                    .unwrap_or(prefix);
+                format!("{prefix}*")
+            });
+        for tag in kubernetes_tags {
+            tags.push((tag, t!("tag-kubernetes")));
+        }
+        tags.push(("tail.*".to_string(), t!("tag-file-path")));
+    }
+
+    // fluent-bit tags inputs without `Tag` with their instance name
+    let instance = index
+        .sections()
+        .iter()
+        .take_while(|input| input.byte_range != section.byte_range)
+        .filter(|input| input.section_type == FlbSectionType::Input)
+        .filter(|input| {
+            input
+                .get_value("Name")
+                .is_some_and(|name| name.eq_ignore_ascii_case(&plugin_name))
+        })
+        .count();
+    tags.push((format!("{plugin_name}.{instance}"), t!("tag-instance-name")));
+
+    let mut seen = Vec::new();
+    tags.retain(|(tag, _)| match seen.contains(tag) {
+        true => false,
+        false => {
+            seen.push(tag.clone());
+            true
+        }
+    });
+
+    tags
+}
+
+/// Completions of the `Tag` of the input `section`, replacing `range`.
+pub(crate) fn tag_completion_items(
+    index: &SectionIndex,
+    section: &IndexedSection,
+    range: lsp_types::Range,
+) -> Vec<CompletionItem> {
+    conventional_tags(index, section)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (tag, detail))| CompletionItem {
+            label: tag.clone(),
+            kind: Some(CompletionItemKind::VALUE),
+            detail: Some(detail),
+            // in order of convention, not alphabetically
+            sort_text: Some(format!("{i:02}")),
+            filter_text: Some(tag.clone()),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(range, tag))),
+            ..CompletionItem::default()
+        })
+        .collect()
+}
+
+/// Ranges of the `Match` patterns and `Match_Regex` regular expressions of the
+/// filters and outputs which records tagged `tag` are routed through.
+pub(crate) fn tag_references(index: &SectionIndex, tag: &str) -> Vec<Range<usize>> {
+    index
+        .sections()
+        .iter()
+        .filter(|section| {
+            matches!(
+                section.section_type,
+                FlbSectionType::Filter | FlbSectionType::Output
+            )
+        })
+        .filter_map(|section| {
+            let rule = MatchRule::of_section(section).filter(|rule| rule.matches(tag))?;
+            rule_range(section, &rule)
+        })
+        .collect()
+}
+
+/// Whether the rules of two sections route records of the same tag: a tag of
+/// the inputs both match, or, for the wildcard patterns of files without the
+/// inputs, a tag both patterns match.
+fn rules_overlap(a: &MatchRule, b: &MatchRule, tags: &[String]) -> bool {
+    if tags.iter().any(|tag| a.matches(tag) && b.matches(tag)) {
+        return true;
+    }
+    match (a, b) {
+        (MatchRule::Wildcard(a), MatchRule::Wildcard(b)) => {
+            wildcards_intersect(a.as_bytes(), b.as_bytes())
+        }
+        _ => false,
+    }
+}
+
+/// Reports the filters after an output matching the same records, and the
+/// `[SERVICE]` sections after other sections, of classic files.
+pub(crate) fn check_section_order(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    let header_range = |section: &IndexedSection| {
+        let range = name_range(rope, section).unwrap_or_else(|| section.byte_range.clone());
+        byte_range_to_lsp_range(rope, range, position_encoding)
+    };
+    let sections = index.sections();
+    let mut diagnostics = Vec::new();
+
+    if let Some(position) = sections
+        .iter()
+        .position(|section| section.section_type == FlbSectionType::Service)
+        .filter(|position| *position > 0)
+    {
+        diagnostics.push(Diagnostic {
+            range: header_range(&sections[position]),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(SERVICE_NOT_FIRST.to_string())),
+            message: t!(SERVICE_NOT_FIRST),
+            ..Diagnostic::default()
+        });
+    }
+
+    let tags: Vec<String> = input_tags(index).into_iter().map(|(_, tag)| tag).collect();
+    let mut outputs: Vec<(&IndexedSection, MatchRule)> = Vec::new();
+    for section in sections {
+        let Some(rule) = MatchRule::of_section(section) else {
+            continue;
+        };
+        match section.section_type {
+            FlbSectionType::Output => outputs.push((section, rule)),
+            FlbSectionType::Filter => {
+                let Some((output, _)) = outputs
+                    .iter()
+                    .find(|(_, output_rule)| rules_overlap(&rule, output_rule, &tags))
+                else {
+                    continue;
+                };
+                let line = header_range(output).start.line + 1;
+                diagnostics.push(Diagnostic {
+                    range: header_range(section),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: Some(NumberOrString::String(FILTER_AFTER_OUTPUT.to_string())),
+                    message: t!(FILTER_AFTER_OUTPUT, line = line),
+                    ..Diagnostic::default()
+                });
+            }
+            _ => {}
+        }
+    }
+
+    diagnostics
+}
+
+pub(crate) fn pipeline_hierarchy(
+    uri: &Url,
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> PipelineHierarchyResult {
+    let location = |section: &IndexedSection| {
+        Location::new(
+            uri.clone(),
+            byte_range_to_lsp_range(rope, section.byte_range.clone(), position_encoding),
+        )
+    };
+    let stages = |section_type: FlbSectionType| -> Vec<_> {
+        index
+            .sections()
+            .iter()
+            .filter(|section| section.section_type == section_type)
+            .filter_map(|section| Some((section, MatchRule::of_section(section)?)))
+            .collect()
+    };
+    let filters = stages(FlbSectionType::Filter);
+    let outputs = stages(FlbSectionType::Output);
+    let matching = |stages: &[(&IndexedSection, MatchRule)], tag: &str| -> Vec<_> {
+        stages
+            .iter()
+            .filter(|(_, rule)| rule.matches(tag))
+            .map(|(section, rule)| PipelineStage {
+                plugin_name: section.get_value("Name").map(ToString::to_string),
+                rule: rule.as_str().to_string(),
+                location: location(section),
+            })
+            .collect()
+    };
+
+    let inputs = input_tags(index)
+        .into_iter()
+        .map(|(section, tag)| PipelineInput {
+            filters: matching(&filters, &tag),
+            outputs: matching(&outputs, &tag),
+            location: location(section),
+            plugin_name: section.get_value("Name").unwrap_or_default().to_string(),
+            tag,
+        })
+        .collect();
+
+    PipelineHierarchyResult { inputs }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::TextDocument;
+
+    #[test]
+    fn wildcard_patterns() {
+        let matches = |pattern: &str, tag| MatchRule::Wildcard(pattern.to_string()).matches(tag);
+
+        assert!(matches("*", "cpu.0"));
+        assert!(matches("kube.*", "kube.var.log"));
+        assert!(matches("*.log", "app.log"));
+        assert!(!matches("kube.*", "app.log"));
+        assert!(!matches("cpu", "cpu.0"));
+        // tags expanded at runtime
+        assert!(matches("kube.var.*", "kube.*"));
+        assert!(!matches("app.*", "kube.*"));
+    }
+
+    #[test]
+    fn conventional_tags_of_inputs() {
+        let document = TextDocument::new(
+            "[INPUT]\n    Name tail\n\n\
+             [INPUT]\n    Name tail\n    Tag \n\n\
+             [INPUT]\n    Name cpu\n\n\
+             [FILTER]\n    Name  kubernetes\n    Match kube.*\n",
+        );
+        let sections = document.index.sections();
+        let tags = |section| -> Vec<_> {
+            conventional_tags(&document.index, section)
+                .into_iter()
+                .map(|(tag, _)| tag)
+                .collect()
+        };
+
+        assert_eq!(tags(&sections[1]), ["kube.*", "tail.*", "tail.1"]);
+        assert_eq!(tags(&sections[2]), ["cpu.0"]);
+    }
+
+    #[test]
+    fn order_of_sections() {
+        let document = TextDocument::new(
+            "[INPUT]\n    Name cpu\n\n\
+             [OUTPUT]\n    Name  stdout\n    Match cpu.*\n\n\
+             [FILTER]\n    Name  grep\n    Match *\n\n\
+             [FILTER]\n    Name        modify\n    Match_Regex ^kube\n\n\
+             [SERVICE]\n    Flush 1\n",
+        );
+        let diagnostics =
+            check_section_order(&document.index, &document.rope, PositionEncodingKind::UTF16);
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(messages, [
+            (
+                15,
+                "`[SERVICE]` isn't the first section: fluent-bit reads it before the others wherever it is."
+            ),
+            (
+                7,
+                "`[FILTER]` comes after the `[OUTPUT]` on line 4 matching the same records: fluent-bit filters records before routing them to outputs, whatever the order of the sections."
+            ),
+        ]);
+    }
+
+    #[test]
+    fn inputs_are_routed_by_tag() {
+        let document = TextDocument::new(
+            "[INPUT]\n    Name cpu\n\n\
+             [INPUT]\n    Name tail\n    Tag  kube.*\n\n\
+             [FILTER]\n    Name  grep\n    Match kube.*\n\n\
+             [OUTPUT]\n    Name        stdout\n    Match_Regex ^cpu\\.\\d+$\n\n\
+             [OUTPUT]\n    Name  null\n    Match *\n",
+        );
+        let uri: Url = "file:///fluent-bit.conf".parse().unwrap();
+
+        let hierarchy = pipeline_hierarchy(
+            &uri,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let routes: Vec<_> = hierarchy
+            .inputs
+            .iter()
+            .map(|input| {
+                let names = |stages: &[PipelineStage]| -> Vec<_> {
+                    stages
+                        .iter()
+                        .map(|stage| stage.plugin_name.clone().unwrap())
+                        .collect()
+                };
+                (
+                    input.tag.as_str(),
+                    names(&input.filters),
+                    names(&input.outputs),
+                )
+            })
+            .collect();
+        assert_eq!(routes, [
+            ("cpu.0", vec![], vec![
+                "stdout".to_string(),
+                "null".to_string()
+            ]),
+            ("kube.*", vec!["grep".to_string()], vec!["null".to_string()]),
+        ]);
+        assert_eq!(hierarchy.inputs[1].location.range.start.line, 3);
+    }
+
+    #[test]
+    fn references_of_tags() {
+        let document = TextDocument::new(
+            "[INPUT]\n    Name tail\n    Tag  kube.app\n\n\
+             [FILTER]\n    Name  grep\n    Match kube.*\n\n\
+             [FILTER]\n    Name  grep\n    Match app.*\n\n\
+             [OUTPUT]\n    Name        stdout\n    Match_Regex ^kube\\.\n",
+        );
+        let index = &document.index;
+        let text = |range: Range<usize>| document.rope.byte_slice(range).to_string();
+
+        let byte = document.rope.line_to_byte(2) + 12;
+        assert_eq!(tag_at(index, byte).as_deref(), Some("kube.app"));
+        assert_eq!(tag_at(index, byte - 5), None);
+        let references: Vec<_> = tag_references(index, "kube.app")
+            .into_iter()
+            .map(text)
+            .collect();
+        assert_eq!(references, ["kube.*", "^kube\\."]);
+    }
+}
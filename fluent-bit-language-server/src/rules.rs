@@ -0,0 +1,529 @@
+//! Requirements between the keys of a section which can't be expressed by the
+//! schema, e.g. keys only required for some value of another key, keys which
+//! can't be set twice, keys the classic format doesn't support, keys which
+//! another key makes ineffective, or values of two keys which can't be
+//! combined.
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::{
+    document::TextDocument,
+    fixes::insert_keys,
+    i18n::t,
+    lsp_ext::DiagnosticFix,
+    overlay::{is_repeatable, is_yaml_only},
+    positions::{byte_range_to_lsp_range, resolve_position, PositionEncodingKind},
+    section_index::{IndexedEntry, IndexedSection, SectionIndex},
+    values::parse_bool,
+};
+
+/// Diagnostic code of a key missing for the value of another key.
+pub(crate) const MISSING_REQUIRED_KEY: &str = "missing-required-key";
+/// Diagnostic code of a key set twice in a section.
+pub(crate) const DUPLICATE_KEY: &str = "duplicate-key";
+/// Diagnostic code of a key the classic format doesn't support.
+pub(crate) const YAML_ONLY_KEY: &str = "yaml-only-key";
+/// Diagnostic code of a `Mem_Buf_Limit` which doesn't pause its input.
+pub(crate) const MEM_BUF_LIMIT_FILESYSTEM: &str = "mem-buf-limit-filesystem";
+/// Diagnostic code of values of two keys which can't be combined.
+pub(crate) const INCOMPATIBLE_VALUES: &str = "incompatible-values";
+
+/// Keys required by a plugin when another key has a given value.
+struct ConditionalRequirement {
+    section_type: FlbSectionType,
+    /// `None` for sections without plugins, e.g. parsers, named by users.
+    plugin_name: Option<&'static str>,
+    /// Key and value (case insensitive) enabling the requirement.
+    when: (&'static str, &'static str),
+    requires: &'static [&'static str],
+}
+
+static CONDITIONAL_REQUIREMENTS: &[ConditionalRequirement] = &[
+    // ref: https://docs.fluentbit.io/manual/pipeline/filters/nest
+    ConditionalRequirement {
+        section_type: FlbSectionType::Filter,
+        plugin_name: Some("nest"),
+        when: ("Operation", "nest"),
+        requires: &["Wildcard", "Nest_under"],
+    },
+    ConditionalRequirement {
+        section_type: FlbSectionType::Filter,
+        plugin_name: Some("nest"),
+        when: ("Operation", "lift"),
+        requires: &["Nested_under"],
+    },
+    // ref: https://docs.fluentbit.io/manual/pipeline/parsers/regular-expression
+    ConditionalRequirement {
+        section_type: FlbSectionType::Parser,
+        plugin_name: None,
+        when: ("Format", "regex"),
+        requires: &["Regex"],
+    },
+    // ref: https://docs.fluentbit.io/manual/administration/configuring-fluent-bit/multiline-parsing
+    ConditionalRequirement {
+        section_type: FlbSectionType::MultilineParser,
+        plugin_name: None,
+        when: ("type", "regex"),
+        requires: &["rule"],
+    },
+];
+
+impl ConditionalRequirement {
+    /// Entry enabling the requirement in the section, and the required keys
+    /// it misses.
+    fn missing<'a>(
+        &self,
+        section: &'a IndexedSection,
+    ) -> Option<(&'a IndexedEntry, Vec<&'static str>)> {
+        if section.section_type != self.section_type
+            || self.plugin_name.is_some_and(|plugin_name| {
+                !section
+                    .get_value("Name")
+                    .is_some_and(|name| name.eq_ignore_ascii_case(plugin_name))
+            })
+        {
+            return None;
+        }
+
+        let (key, value) = self.when;
+        let entry = section.entries.iter().find(|entry| {
+            entry.key.eq_ignore_ascii_case(key)
+                && entry
+                    .value
+                    .as_deref()
+                    .is_some_and(|v| v.eq_ignore_ascii_case(value))
+        })?;
+
+        let missing: Vec<&str> = self
+            .requires
+            .iter()
+            .copied()
+            .filter(|required| !section.contains_key(required))
+            .collect();
+
+        (!missing.is_empty()).then_some((entry, missing))
+    }
+
+    fn check(
+        &self,
+        section: &IndexedSection,
+        rope: &Rope,
+        position_encoding: PositionEncodingKind,
+    ) -> Option<Diagnostic> {
+        let (entry, missing) = self.missing(section)?;
+        let (key, value) = self.when;
+        let missing: Vec<String> = missing
+            .iter()
+            .map(|required| format!("`{required}`"))
+            .collect();
+
+        let end = entry
+            .value_range
+            .as_ref()
+            .map_or(entry.key_range.end, |range| range.end);
+        Some(Diagnostic {
+            range: byte_range_to_lsp_range(rope, entry.key_range.start..end, position_encoding),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String(MISSING_REQUIRED_KEY.to_string())),
+            message: t!(
+                MISSING_REQUIRED_KEY,
+                key = key,
+                value = value,
+                missing = missing.join(", "),
+            ),
+            ..Diagnostic::default()
+        })
+    }
+}
+
+/// Reports the conditional requirements which aren't met in the document.
+pub(crate) fn check_conditional_requirements(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    index
+        .sections()
+        .iter()
+        .flat_map(|section| {
+            CONDITIONAL_REQUIREMENTS
+                .iter()
+                .filter_map(|requirement| requirement.check(section, rope, position_encoding))
+        })
+        .collect()
+}
+
+/// Inserts the keys the requirement reported by the diagnostic misses.
+pub(crate) fn missing_required_key_fix(
+    document: &TextDocument,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    if diagnostic.code != Some(NumberOrString::String(MISSING_REQUIRED_KEY.to_string())) {
+        return None;
+    }
+
+    let start =
+        resolve_position(&document.rope, &diagnostic.range.start, position_encoding).ok()?;
+    let (section, missing) = document.index.sections().iter().find_map(|section| {
+        let missing = CONDITIONAL_REQUIREMENTS.iter().find_map(|requirement| {
+            let (entry, missing) = requirement.missing(section)?;
+            (entry.key_range.start == start.byte_idx).then_some(missing)
+        })?;
+        Some((section, missing))
+    })?;
+    let keys: Vec<String> = missing.iter().map(|key| format!("`{key}`")).collect();
+
+    Some(DiagnosticFix {
+        title: t!("fix-insert-keys", keys = keys.join(", ")),
+        edits: vec![insert_keys(
+            document,
+            section,
+            &missing,
+            false,
+            position_encoding,
+        )],
+    })
+}
+
+/// Values of a key of a plugin which another key doesn't support, e.g. the
+/// compressions of an output which need another upload API.
+struct IncompatibleValues {
+    section_type: FlbSectionType,
+    plugin_name: &'static str,
+    /// Key and values (case insensitive, booleans in any form) reported.
+    key: (&'static str, &'static [&'static str]),
+    /// Key and values they can't be combined with.
+    conflicts_with: (&'static str, &'static [&'static str]),
+    /// Value of the other key when it's unset.
+    default: Option<&'static str>,
+}
+
+static INCOMPATIBLE_VALUES_MATRIX: &[IncompatibleValues] = &[
+    // ref: https://docs.fluentbit.io/manual/pipeline/outputs/s3
+    IncompatibleValues {
+        section_type: FlbSectionType::Output,
+        plugin_name: "s3",
+        key: ("compression", &["arrow", "parquet"]),
+        conflicts_with: ("use_put_object", &["off"]),
+        default: Some("off"),
+    },
+    // ref: https://docs.fluentbit.io/manual/pipeline/outputs/azure_blob
+    IncompatibleValues {
+        section_type: FlbSectionType::Output,
+        plugin_name: "azure_blob",
+        key: ("compress_blob", &["on"]),
+        conflicts_with: ("blob_type", &["appendblob"]),
+        default: Some("appendblob"),
+    },
+];
+
+fn value_matches(value: &str, expected: &str) -> bool {
+    value.eq_ignore_ascii_case(expected)
+        || parse_bool(value).is_some_and(|value| parse_bool(expected) == Some(value))
+}
+
+impl IncompatibleValues {
+    fn check(
+        &self,
+        section: &IndexedSection,
+        rope: &Rope,
+        position_encoding: PositionEncodingKind,
+    ) -> Option<Diagnostic> {
+        if section.section_type != self.section_type
+            || !section
+                .get_value("Name")
+                .is_some_and(|name| name.eq_ignore_ascii_case(self.plugin_name))
+        {
+            return None;
+        }
+
+        let (key, values) = self.key;
+        let entry = section.entries.iter().find(|entry| {
+            entry.key.eq_ignore_ascii_case(key)
+                && entry
+                    .value
+                    .as_deref()
+                    .is_some_and(|v| values.iter().any(|value| value_matches(v, value)))
+        })?;
+
+        let (other_key, conflicts) = self.conflicts_with;
+        let other = section
+            .entries
+            .iter()
+            .find(|entry| entry.key.eq_ignore_ascii_case(other_key));
+        let other_value = match other {
+            Some(other) => other.value.as_deref()?,
+            None => self.default?,
+        };
+        if !conflicts
+            .iter()
+            .any(|conflict| value_matches(other_value, conflict))
+        {
+            return None;
+        }
+
+        let end = entry
+            .value_range
+            .as_ref()
+            .map_or(entry.key_range.end, |range| range.end);
+        let id = match other {
+            Some(_) => INCOMPATIBLE_VALUES,
+            None => "incompatible-values-default",
+        };
+        Some(Diagnostic {
+            range: byte_range_to_lsp_range(rope, entry.key_range.start..end, position_encoding),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String(INCOMPATIBLE_VALUES.to_string())),
+            message: t!(
+                id,
+                key = entry.key,
+                value = entry.value.as_deref().unwrap_or_default(),
+                other_key = other.map_or(other_key, |other| &other.key),
+                other_value = other_value,
+            ),
+            ..Diagnostic::default()
+        })
+    }
+}
+
+/// Reports the values of the document which can't be combined.
+pub(crate) fn check_incompatible_values(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    index
+        .sections()
+        .iter()
+        .flat_map(|section| {
+            INCOMPATIBLE_VALUES_MATRIX
+                .iter()
+                .filter_map(|rule| rule.check(section, rope, position_encoding))
+        })
+        .collect()
+}
+
+/// Reports keys set more than once in a section, unless the parameter is
+/// repeatable.
+pub(crate) fn check_duplicate_keys(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for section in index.sections() {
+        let plugin_name = section.get_value("Name");
+        for (i, entry) in section.entries.iter().enumerate() {
+            let is_duplicate = section.entries[..i]
+                .iter()
+                .any(|previous| previous.key.eq_ignore_ascii_case(&entry.key));
+            if !is_duplicate || is_repeatable(&section.section_type, plugin_name, &entry.key) {
+                continue;
+            }
+
+            diagnostics.push(Diagnostic {
+                range: byte_range_to_lsp_range(rope, entry.key_range.clone(), position_encoding),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(DUPLICATE_KEY.to_string())),
+                message: t!(DUPLICATE_KEY, key = entry.key),
+                ..Diagnostic::default()
+            });
+        }
+    }
+
+    diagnostics
+}
+
+/// Reports keys only supported by YAML configurations, documents being in the
+/// classic format.
+pub(crate) fn check_yaml_only_keys(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    index
+        .sections()
+        .iter()
+        .flat_map(|section| {
+            let plugin_name = section.get_value("Name");
+            section
+                .entries
+                .iter()
+                .filter(move |entry| is_yaml_only(&section.section_type, plugin_name, &entry.key))
+        })
+        .map(|entry| Diagnostic {
+            range: byte_range_to_lsp_range(rope, entry.key_range.clone(), position_encoding),
+            severity: Some(DiagnosticSeverity::ERROR),
+            code: Some(NumberOrString::String(YAML_ONLY_KEY.to_string())),
+            message: t!(YAML_ONLY_KEY, key = entry.key),
+            ..Diagnostic::default()
+        })
+        .collect()
+}
+
+/// Whether the input buffers its chunks in the filesystem, where
+/// `Mem_Buf_Limit` doesn't pause it.
+///
+/// ref: https://docs.fluentbit.io/manual/administration/buffering-and-storage
+pub(crate) fn is_filesystem_buffered(section: &IndexedSection) -> bool {
+    section.section_type == FlbSectionType::Input
+        && section
+            .get_value("storage.type")
+            .is_some_and(|value| value.eq_ignore_ascii_case("filesystem"))
+}
+
+/// Reports the `Mem_Buf_Limit` of inputs buffered in the filesystem.
+pub(crate) fn check_mem_buf_limits(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    index
+        .sections()
+        .iter()
+        .filter(|section| is_filesystem_buffered(section))
+        .flat_map(|section| &section.entries)
+        .filter(|entry| entry.key.eq_ignore_ascii_case("Mem_Buf_Limit"))
+        .map(|entry| Diagnostic {
+            range: byte_range_to_lsp_range(rope, entry.key_range.clone(), position_encoding),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(MEM_BUF_LIMIT_FILESYSTEM.to_string())),
+            message: t!(MEM_BUF_LIMIT_FILESYSTEM, key = entry.key),
+            ..Diagnostic::default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+    use crate::{document::TextDocument, parser_index::is_parsers_file, validation::hover_notes};
+
+    #[test]
+    fn nest_and_lift_requirements() {
+        let document = TextDocument::new(
+            "[FILTER]\n    Name       nest\n    Match      *\n    Operation  nest\n    Wildcard   Mem.*\n\n\
+             [FILTER]\n    Name         nest\n    Match        *\n    Operation    lift\n    Nested_under memstats\n\n\
+             [FILTER]\n    Name       nest\n    Operation  LIFT\n",
+        );
+
+        let diagnostics = check_conditional_requirements(
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "`Operation nest` requires `Nest_under`.",
+            "`Operation lift` requires `Nested_under`."
+        ]);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(3, 4), Position::new(3, 19))
+        );
+    }
+
+    #[test]
+    fn parsers_file_rules() {
+        let document = TextDocument::new(
+            "[PARSER]\n    Name            syslog\n    Format          regex\n\
+             \x20   Decode_Field_As escaped_utf8 log do_next\n    Decode_Field_As json log\n\n\
+             [MULTILINE_PARSER]\n    name  java\n    type  regex\n    rule  \"start_state\" \"/^\\d+/\" \"cont\"\n    rule  \"cont\" \"/^\\s+at/\" \"cont\"\n\n\
+             [MULTILINE_PARSER]\n    name  go\n    type  REGEX\n",
+        );
+        assert!(is_parsers_file(&document.index));
+
+        let encoding = PositionEncodingKind::UTF16;
+        let diagnostics = check_conditional_requirements(&document.index, &document.rope, encoding);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "`Format regex` requires `Regex`.",
+            "`type regex` requires `rule`."
+        ]);
+        // rules and decoders are repeatable
+        assert!(check_duplicate_keys(&document.index, &document.rope, encoding).is_empty());
+    }
+
+    #[test]
+    fn incompatible_compressions() {
+        let document = TextDocument::new(
+            "[OUTPUT]\n    Name        s3\n    compression arrow\n\n\
+             [OUTPUT]\n    Name           s3\n    compression    parquet\n    use_put_object true\n\n\
+             [OUTPUT]\n    Name          azure_blob\n    compress_blob yes\n    blob_type     AppendBlob\n\n\
+             [OUTPUT]\n    Name          azure_blob\n    compress_blob on\n    blob_type     blockblob\n",
+        );
+
+        let diagnostics =
+            check_incompatible_values(&document.index, &document.rope, PositionEncodingKind::UTF16);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "`compression arrow` can't be combined with `use_put_object off`, its default.",
+            "`compress_blob yes` can't be combined with `blob_type AppendBlob`."
+        ]);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(2, 4), Position::new(2, 21))
+        );
+    }
+
+    #[test]
+    fn duplicate_and_yaml_only_keys() {
+        let document = TextDocument::new(
+            "[FILTER]\n    Name  modify\n    Match *\n    Add   a 1\n    Add   b 2\n    match x\n\n\
+             [INPUT]\n    Name       dummy\n    processors logs\n",
+        );
+
+        let diagnostics =
+            check_duplicate_keys(&document.index, &document.rope, PositionEncodingKind::UTF16);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, ["`match` is already set in this section."]);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(5, 4), Position::new(5, 9))
+        );
+
+        let diagnostics =
+            check_yaml_only_keys(&document.index, &document.rope, PositionEncodingKind::UTF16);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "`processors` is only supported in YAML configuration files."
+        ]);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(9, 4), Position::new(9, 14))
+        );
+    }
+
+    #[test]
+    fn mem_buf_limit_of_filesystem_buffered_inputs() {
+        let document = TextDocument::new(
+            "[INPUT]\n    Name          tail\n    Mem_Buf_Limit 5MB\n\n\
+             [INPUT]\n    Name          tail\n    storage.type  Filesystem\n    Mem_Buf_Limit 5MB\n",
+        );
+
+        let diagnostics =
+            check_mem_buf_limits(&document.index, &document.rope, PositionEncodingKind::UTF16);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "`Mem_Buf_Limit` doesn't pause the input with `storage.type filesystem`: chunks beyond the limit are written to the filesystem instead."
+        ]);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(7, 4), Position::new(7, 17))
+        );
+
+        let notes = |i: usize| {
+            let section = &document.index.sections()[i];
+            hover_notes(&FlbSectionType::Input, Some(section), "mem_buf_limit", None)
+        };
+        assert_eq!(notes(0).len(), 1);
+        assert!(notes(0)[0].starts_with("When the chunks of the input buffered in memory"));
+        assert!(notes(1)[1].starts_with("`storage.type` is `filesystem`"));
+    }
+}
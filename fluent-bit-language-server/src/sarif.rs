@@ -0,0 +1,146 @@
+//! [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html)
+//! reports of lint results, e.g. for GitHub code scanning, with the metadata of
+//! the rules from [`DIAGNOSTIC_RULES`].
+
+use serde_json::{json, Value};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range};
+
+use crate::{diagnostic_rules::DIAGNOSTIC_RULES, lsp_ext::DiagnosticData};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const INFORMATION_URI: &str = "https://github.com/sh-cho/fluent-bit-lsp";
+
+fn level(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::WARNING => "warning",
+        DiagnosticSeverity::INFORMATION | DiagnosticSeverity::HINT => "note",
+        _ => "error",
+    }
+}
+
+fn region(range: Range) -> Value {
+    json!({
+        "startLine": range.start.line + 1,
+        "startColumn": range.start.character + 1,
+        "endLine": range.end.line + 1,
+        "endColumn": range.end.character + 1,
+    })
+}
+
+fn result(uri: &str, diagnostic: &Diagnostic) -> Value {
+    let mut result = json!({
+        "level": level(diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR)),
+        "message": { "text": diagnostic.message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": uri },
+                "region": region(diagnostic.range),
+            },
+        }],
+    });
+    if let Some(NumberOrString::String(code)) = &diagnostic.code {
+        result["ruleId"] = json!(code);
+        if let Some(index) = DIAGNOSTIC_RULES.iter().position(|rule| rule.code == code) {
+            result["ruleIndex"] = json!(index);
+        }
+    }
+    // the fix of the `data`, for tools applying them
+    let fix = diagnostic
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<DiagnosticData>(data).ok())
+        .and_then(|data| data.fix);
+    if let Some(fix) = fix {
+        let replacements: Vec<Value> = fix
+            .edits
+            .iter()
+            .map(|edit| {
+                json!({
+                    "deletedRegion": region(edit.range),
+                    "insertedContent": { "text": edit.new_text },
+                })
+            })
+            .collect();
+        result["fixes"] = json!([{
+            "description": { "text": fix.title },
+            "artifactChanges": [{
+                "artifactLocation": { "uri": uri },
+                "replacements": replacements,
+            }],
+        }]);
+    }
+
+    result
+}
+
+/// Report of the diagnostics of the files, by URI relative to the root of the
+/// repository. Columns are counted in characters.
+pub(crate) fn report(files: &[(String, Vec<Diagnostic>)]) -> Value {
+    let rules: Vec<Value> = DIAGNOSTIC_RULES
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.code,
+                "shortDescription": { "text": rule.description },
+                "defaultConfiguration": { "level": level(rule.severity) },
+            })
+        })
+        .collect();
+    let results: Vec<Value> = files
+        .iter()
+        .flat_map(|(uri, diagnostics)| diagnostics.iter().map(|d| result(uri, d)))
+        .collect();
+
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": INFORMATION_URI,
+                    "rules": rules,
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+    use crate::rules::DUPLICATE_KEY;
+
+    #[test]
+    fn results_reference_their_rule() {
+        let diagnostic = Diagnostic {
+            range: Range::new(Position::new(2, 4), Position::new(2, 8)),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(DUPLICATE_KEY.to_string())),
+            message: "`Name` is already set in this section.".to_string(),
+            ..Diagnostic::default()
+        };
+        let report = report(&[("conf/fluent-bit.conf".to_string(), vec![diagnostic])]);
+
+        let run = &report["runs"][0];
+        let result = &run["results"][0];
+        let index = result["ruleIndex"].as_u64().unwrap() as usize;
+        assert_eq!(run["tool"]["driver"]["rules"][index]["id"], DUPLICATE_KEY);
+        assert_eq!(result["level"], "warning");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"],
+            json!({ "startLine": 3, "startColumn": 5, "endLine": 3, "endColumn": 9 })
+        );
+
+        // each rule is listed once
+        let mut codes: Vec<_> = DIAGNOSTIC_RULES.iter().map(|rule| rule.code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), DIAGNOSTIC_RULES.len());
+    }
+}
@@ -0,0 +1,192 @@
+//! Generation of configuration file skeletons (`flb.scaffoldPipeline`).
+
+use anyhow::anyhow;
+use flb_schema::section::FlbSectionType;
+use tower_lsp::lsp_types::{
+    CreateFile, CreateFileOptions, DocumentChangeOperation, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, Position, Range, ResourceOp, TextDocumentEdit,
+    TextEdit, WorkspaceEdit,
+};
+
+use crate::{
+    completion::{FlbData, KEY_WIDTH},
+    document::LineEnding,
+    lsp_ext::{ConfigFormat, ScaffoldPipelineArguments},
+    yaml,
+};
+
+const SERVICE_ENTRIES: &[(&str, &str)] = &[("Flush", "1"), ("Log_Level", "info")];
+
+/// Plugin of the pipeline, with the key routing records to or from it.
+struct PipelineEntry<'a> {
+    section_type: FlbSectionType,
+    name: &'a str,
+    routing: (&'static str, String),
+}
+
+/// Builds an edit creating `args.uri` with a configuration using the chosen
+/// plugins. Fails if any of them is unknown to `data`.
+pub(crate) fn scaffold_pipeline(
+    data: &FlbData,
+    args: &ScaffoldPipelineArguments,
+    line_ending: LineEnding,
+) -> anyhow::Result<WorkspaceEdit> {
+    let text = line_ending.apply(&scaffold_pipeline_text(data, args)?);
+
+    Ok(WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Operations(vec![
+            DocumentChangeOperation::Op(ResourceOp::Create(CreateFile {
+                uri: args.uri.clone(),
+                options: Some(CreateFileOptions {
+                    overwrite: Some(false),
+                    ignore_if_exists: Some(false),
+                }),
+                annotation_id: None,
+            })),
+            DocumentChangeOperation::Edit(TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier {
+                    uri: args.uri.clone(),
+                    version: None,
+                },
+                edits: vec![OneOf::Left(TextEdit {
+                    range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+                    new_text: text,
+                })],
+            }),
+        ])),
+        ..WorkspaceEdit::default()
+    })
+}
+
+fn scaffold_pipeline_text(
+    data: &FlbData,
+    args: &ScaffoldPipelineArguments,
+) -> anyhow::Result<String> {
+    let mut entries = Vec::new();
+    let sections = [
+        (FlbSectionType::Input, &args.inputs),
+        (FlbSectionType::Filter, &args.filters),
+        (FlbSectionType::Output, &args.outputs),
+    ];
+    for (section_type, names) in sections {
+        for name in names {
+            let snippet = data
+                .get_snippet(&section_type, name)
+                .ok_or_else(|| anyhow!("unknown {section_type} plugin `{name}`"))?;
+            let name = snippet.plugin_name();
+            let routing = match section_type {
+                FlbSectionType::Input => ("Tag", name.to_string()),
+                _ => ("Match", "*".to_string()),
+            };
+            entries.push(PipelineEntry {
+                section_type: section_type.clone(),
+                name,
+                routing,
+            });
+        }
+    }
+
+    Ok(match args.format {
+        ConfigFormat::Classic => to_classic(&entries),
+        ConfigFormat::Yaml => to_yaml(&entries),
+    })
+}
+
+fn to_classic(entries: &[PipelineEntry]) -> String {
+    let mut ret = String::from("[SERVICE]\n");
+    for (key, value) in SERVICE_ENTRIES {
+        ret.push_str(&format!("    {key:KEY_WIDTH$} {value}\n"));
+    }
+
+    for entry in entries {
+        let (routing_key, routing_value) = &entry.routing;
+        ret.push_str(&format!(
+            "\n[{}]\n    {:KEY_WIDTH$} {}\n    {routing_key:KEY_WIDTH$} {routing_value}\n",
+            entry.section_type.to_string().to_uppercase(),
+            "Name",
+            entry.name,
+        ));
+    }
+
+    ret
+}
+
+fn to_yaml(entries: &[PipelineEntry]) -> String {
+    let mut ret = String::from("service:\n");
+    for (key, value) in SERVICE_ENTRIES {
+        ret.push_str(&format!("  {}: {value}\n", key.to_lowercase()));
+    }
+
+    ret.push_str("\npipeline:\n");
+    for section_type in [
+        FlbSectionType::Input,
+        FlbSectionType::Filter,
+        FlbSectionType::Output,
+    ] {
+        let mut section_entries = entries
+            .iter()
+            .filter(|entry| entry.section_type == section_type)
+            .peekable();
+        if section_entries.peek().is_none() {
+            continue;
+        }
+
+        ret.push_str(&format!("  {section_type}s:\n"));
+        for entry in section_entries {
+            let (routing_key, routing_value) = &entry.routing;
+            ret.push_str(&format!(
+                "    - name: {}\n      {}: {}\n",
+                entry.name,
+                routing_key.to_lowercase(),
+                yaml::quote(routing_value),
+            ));
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::FLB_DATA;
+
+    #[test]
+    fn scaffold_classic_and_yaml() {
+        let mut args = ScaffoldPipelineArguments {
+            uri: "file:///fluent-bit.conf".parse().unwrap(),
+            format: ConfigFormat::Classic,
+            inputs: vec!["Tail".to_string()],
+            filters: vec![],
+            outputs: vec!["stdout".to_string()],
+        };
+
+        assert_eq!(
+            scaffold_pipeline_text(&FLB_DATA, &args).unwrap(),
+            "[SERVICE]\n    \
+             Flush           1\n    \
+             Log_Level       info\n\
+             \n\
+             [INPUT]\n    \
+             Name            tail\n    \
+             Tag             tail\n\
+             \n\
+             [OUTPUT]\n    \
+             Name            stdout\n    \
+             Match           *\n"
+        );
+
+        args.format = ConfigFormat::Yaml;
+        assert_eq!(
+            scaffold_pipeline_text(&FLB_DATA, &args).unwrap(),
+            "service:\n  flush: 1\n  log_level: info\n\
+             \n\
+             pipeline:\n  \
+             inputs:\n    - name: tail\n      tag: tail\n  \
+             outputs:\n    - name: stdout\n      match: '*'\n"
+        );
+
+        args.filters.push("no_such_filter".to_string());
+        assert!(scaffold_pipeline_text(&FLB_DATA, &args).is_err());
+    }
+}
@@ -3,7 +3,7 @@
 
 #[rustfmt::skip::macros(add_snippet)]
 pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
-    let mut data = FlbData::new();
+    let mut data = FlbData::new("3.1.5");
 
     //// Customs
     add_snippet!(data, FlbSectionType::Custom, "Calyptia", "calyptia", "custom/calyptia", [
@@ -21,7 +21,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("fleet.interval_nsec", Some(r#"-1"#), r#"Set the collector interval (nanoseconds)"#),
         ("fleet_name", None, r#"Fleet name to be used when registering agent in a fleet"#),
         ("pipeline_id", None, r#"Pipeline ID for reporting to calyptia cloud."#),
-    ]);
+    ], required: ["api_key"]);
 
     //// Input
     add_snippet!(data, FlbSectionType::Input, "CPU Log Based Metrics", "cpu", "input/cpu-metrics", [
@@ -189,7 +189,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("group_id", None, r#"Set the kafka group_id."#),
         ("rdkafka.", None, r#"Set the librdkafka options"#),
         ("buffer_max_size", Some(r#"4M"#), r#"Set the maximum size of chunk"#),
-    ]);
+    ], required: ["topics", "brokers"]);
     add_snippet!(data, FlbSectionType::Input, "Fluent Bit Metrics", "fluentbit_metrics", "input/fluentbit-metrics", [
         ("scrape_interval", Some(r#"2"#), r#"scrape interval to collect the internal metrics of Fluent Bit."#),
         ("scrape_on_start", Some(r#"false"#), r#"scrape metrics upon start, useful to avoid waiting for 'scrape_interval' for the first round of metrics."#),
@@ -252,7 +252,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("parser_firstline", None, r#"name of the parser that matches the beginning of a multiline message. Note that the regular expression defined in the parser must include a group name (named capture)."#),
         ("parser_", None, r#"optional extra parser to interpret and structure multiline entries. This option can be used to define multiple parsers, e.g: Parser_1 ab1, Parser_2 ab2, Parser_N abN."#),
         ("multiline.parser", None, r#"specify one or multiple multiline parsers: docker, cri, go, java, etc."#),
-    ]);
+    ], required: ["path"]);
     add_snippet!(data, FlbSectionType::Input, "Dummy", "dummy", "input/dummy", [
         ("samples", Some(r#"0"#), r#"set a number of times to generate event."#),
         ("dummy", Some(r#"{"message":"dummy"}"#), r#"set the sample record to be generated. It should be a JSON object."#),
@@ -275,7 +275,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("add_path", Some(r#"false"#), r#"append filepath to records"#),
         ("interval_sec", Some(r#"1"#), r#"Set the collector interval"#),
         ("interval_nsec", Some(r#"0"#), r#"Set the collector interval (nanoseconds)"#),
-    ]);
+    ], required: ["file"]);
     add_snippet!(data, FlbSectionType::Input, "Health", "health", "input/health", [
         ("listen", Some(r#"0.0.0.0"#), r#"Listen Address"#),
         ("host", Some(r#"localhost"#), r#"Hostname"#),
@@ -440,7 +440,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("separator", None, r#"Set the record separator"#),
         ("format", None, r#"Set the serial format: json or none"#),
         ("min_bytes", Some(r#"0"#), r#"Set the serial minimum bytes"#),
-    ]);
+    ], required: ["file", "bitrate"]);
     add_snippet!(data, FlbSectionType::Input, "Standard Input", "stdin", "input/standard-input", [
         ("parser", None, r#"Set and use a fluent-bit parser"#),
         ("buffer_size", None, r#"Set the read buffer size"#),
@@ -479,7 +479,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("bool", Some(r#"false"#), r#"execute the command only once"#),
         ("wasm_heap_size", Some(r#"8192"#), r#"Set the heap size of wasm runtime"#),
         ("wasm_stack_size", Some(r#"8192"#), r#"Set the stack size of wasm runtime"#),
-    ]);
+    ], required: ["wasi_path"]);
     add_snippet!(data, FlbSectionType::Input, "TCP", "tcp", "input/tcp", [
         ("listen", Some(r#"0.0.0.0"#), r#"Listen Address"#),
         ("host", Some(r#"localhost"#), r#"Hostname"#),
@@ -569,7 +569,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("ignore_case", Some(r#"false"#), r#"Compare strings by ignoring case."#),
         ("lookup_key", Some(r#"log"#), r#"Name of the key to lookup."#),
         ("record", None, r#"Name of record key to add and its value, it accept two values,e.g 'record mykey my val'. You can add many 'record' entries as needed."#),
-    ]);
+    ], required: ["file", "lookup_key"]);
     add_snippet!(data, FlbSectionType::Filter, "ECS Metadata", "ecs", "filter/ecs-metadata", [
         ("add", None, r#"Add a metadata key/value pair with the given key and given value from the given template. Format is `Add KEY TEMPLATE`."#),
         ("ecs_tag_prefix", Some(r#""#), r#"This filter must obtain the 12 character container short ID to query for ECS Task metadata. The filter removes the prefx from the tag and then assumes the next 12 characters are the short container ID. If the container short ID, is not found in the tag, the filter can/must fallback to only attaching cluster metadata (cluster name, container instance ID/ARN, and ECS Agent version)."#),
@@ -668,7 +668,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("emitter_name", None, r#""#),
         ("emitter_storage.type", Some(r#"memory"#), r#""#),
         ("emitter_mem_buf_limit", Some(r#"10M"#), r#"set a memory buffer limit to restrict memory usage of emitter"#),
-    ]);
+    ], required: ["multiline.parser"]);
     add_snippet!(data, FlbSectionType::Filter, "Nest", "nest", "filter/nest", [
         ("Operation", None, r#"Select the operation nest or lift"#),
         ("Wildcard", None, r#"Nest records which field matches the wildcard"#),
@@ -676,14 +676,14 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("Nested_under", None, r#"Lift records nested under the Nested_under key"#),
         ("Add_prefix", None, r#"Prefix affected keys with this string"#),
         ("Remove_prefix", None, r#"Remove prefix from affected keys if it matches this string"#),
-    ]);
+    ], required: ["Operation"]);
     add_snippet!(data, FlbSectionType::Filter, "Parser", "parser", "filter/parser", [
         ("Key_Name", None, r#"Specify field name in record to parse."#),
         ("Parser", None, r#"Specify the parser name to interpret the field. Multiple Parser entries are allowed (one per line)."#),
         ("Preserve_Key", Some(r#"false"#), r#"Keep original Key_Name field in the parsed result. If false, the field will be removed."#),
         ("Reserve_Data", Some(r#"false"#), r#"Keep all other original fields in the parsed result. If false, all other original fields will be removed."#),
         ("Unescape_key", None, r#"(deprecated)"#),
-    ]);
+    ], required: ["Key_Name", "Parser"]);
     add_snippet!(data, FlbSectionType::Filter, "Expect", "expect", "filter/expect", [
         ("key_exists", None, r#"check that the given key name exists in the record"#),
         ("key_not_exists", None, r#"check that the given key name do not exists in the record"#),
@@ -703,7 +703,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("emitter_name", None, r#""#),
         ("emitter_storage.type", Some(r#"memory"#), r#""#),
         ("emitter_mem_buf_limit", Some(r#"10M"#), r#"set a memory buffer limit to restrict memory usage of emitter"#),
-    ]);
+    ], required: ["rule"]);
     add_snippet!(data, FlbSectionType::Filter, "Log to Metrics", "log_to_metrics", "filter/log_to_metrics", [
         ("regex", None, r#"Optional filter for records in which the content of KEY matches the regular expression."#),
         ("exclude", None, r#"Optional filter for records in which the content of KEY does not matches the regular expression."#),
@@ -721,7 +721,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("emitter_name", None, r#"Name of the emitter (advanced users)"#),
         ("emitter_mem_buf_limit", Some(r#"10M"#), r#"set a buffer limit to restrict memory usage of metrics emitter"#),
         ("discard_logs", Some(r#"false"#), r#"Flag that defines if logs should be discarded after processing. This applies for all logs, no matter if they have emitted metrics or not."#),
-    ]);
+    ], required: ["metric_mode", "metric_name", "metric_description", "tag"]);
     add_snippet!(data, FlbSectionType::Filter, "Lua", "lua", "filter/lua", [
         ("script", None, r#"The path of lua script."#),
         ("code", None, r#"String that contains the Lua script source code"#),
@@ -731,14 +731,14 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("protected_mode", Some(r#"true"#), r#"If enabled, Lua script will be executed in protected mode. It prevents to crash when invalid Lua script is executed."#),
         ("time_as_table", Some(r#"false"#), r#"If enabled, Fluent-bit will pass the timestamp as a Lua table with keys "sec" for seconds since epoch and "nsec" for nanoseconds."#),
         ("enable_flb_null", Some(r#"false"#), r#"If enabled, null will be converted to flb_null in Lua. It is useful to prevent removing key/value since nil is a special value to remove key value from map in Lua."#),
-    ]);
+    ], required: ["script", "call"]);
     add_snippet!(data, FlbSectionType::Filter, "Standard Output", "stdout", "filter/standard-output", [
     ]);
     add_snippet!(data, FlbSectionType::Filter, "GeoIP2 Filter", "geoip2", "filter/geoip2-filter", [
         ("database", None, r#"Set the geoip2 database path"#),
         ("lookup_key", None, r#"Add a lookup_key"#),
         ("record", None, r#"Add a record to the output base on geoip2"#),
-    ]);
+    ], required: ["database"]);
     add_snippet!(data, FlbSectionType::Filter, "Nightfall", "nightfall", "filter/nightfall", [
         ("nightfall_api_key", None, r#"The Nightfall API key to scan your logs with."#),
         ("policy_id", None, r#"The Nightfall policy ID to scan your logs with."#),
@@ -747,7 +747,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("tls.verify", Some(r#"true"#), r#"Enable or disable verification of TLS peer certificate"#),
         ("tls.vhost", None, r#"Set optional TLS virtual host"#),
         ("tls.ca_path", None, r#"Path to root certificates on the system"#),
-    ]);
+    ], required: ["nightfall_api_key", "policy_id"]);
     add_snippet!(data, FlbSectionType::Filter, "Wasm", "wasm", "filter/wasm", [
         ("event_format", None, r#"Sepecify the ingesting event format for wasm program"#),
         ("wasm_path", None, r#"Set the wasm path to execute"#),
@@ -755,7 +755,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("function_name", None, r#"Set the function name in wasm to execute"#),
         ("wasm_heap_size", Some(r#"8192"#), r#"Set the heap size of wasm runtime"#),
         ("wasm_stack_size", Some(r#"8192"#), r#"Set the stack size of wasm runtime"#),
-    ]);
+    ], required: ["wasm_path", "function_name"]);
 
     //// Output
     add_snippet!(data, FlbSectionType::Output, "Azure Log Analytics", "azure", "output/azure", [
@@ -767,7 +767,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("log_type_key", None, r#"If included, the value for this key will be looked upon in the record and if present, will over-write the `log_type`. If the key/value is not found in the record then the `log_type` option will be used. "#),
         ("time_key", Some(r#"@timestamp"#), r#"Optional parameter to specify the key name where the timestamp will be stored."#),
         ("time_generated", Some(r#"false"#), r#"If enabled, the HTTP request header 'time-generated-field' will be included so Azure can override the timestamp with the key specified by 'time_key' option."#),
-    ]);
+    ], required: ["customer_id", "shared_key"]);
     add_snippet!(data, FlbSectionType::Output, "Azure Blob", "azure_blob", "output/azure_blob", [
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
@@ -794,7 +794,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("date_key", Some(r#"@timestamp"#), r#"Name of the key that will have the record timestamp"#),
         ("auth_type", Some(r#"key"#), r#"Set the auth type: key or sas"#),
         ("sas_token", None, r#"Azure Blob SAS token"#),
-    ]);
+    ], required: ["account_name", "container_name"]);
     add_snippet!(data, FlbSectionType::Output, "Azure Logs Ingestion API", "azure_logs_ingestion", "output/azure_logs_ingestion", [
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
@@ -807,7 +807,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("time_key", Some(r#"@timestamp"#), r#"[Optional] Specify the key name where the timestamp will be stored."#),
         ("time_generated", Some(r#"false"#), r#"If enabled, will generate a timestamp and append it to JSON. The key name is set by the 'time_key' parameter"#),
         ("compress", Some(r#"false"#), r#"Enable HTTP payload compression (gzip)."#),
-    ]);
+    ], required: ["tenant_id", "client_id", "client_secret", "dce_url", "dcr_id", "table_name"]);
     add_snippet!(data, FlbSectionType::Output, "Azure Data Explorer", "azure_kusto", "output/azure_kusto", [
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
@@ -823,7 +823,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("tag_key", Some(r#"tag"#), r#"The key name of tag. If 'include_tag_key' is false, This property is ignored"#),
         ("include_time_key", Some(r#"true"#), r#"If enabled, time is appended to output. The key name is used 'time_key' property."#),
         ("time_key", Some(r#"timestamp"#), r#"The key name of the time. If 'include_time_key' is false, This property is ignored"#),
-    ]);
+    ], required: ["tenant_id", "client_id", "client_secret", "ingestion_endpoint", "database_name", "table_name"]);
     add_snippet!(data, FlbSectionType::Output, "Google Cloud BigQuery", "bigquery", "output/bigquery", [
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
@@ -841,7 +841,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("table_id", None, r#"Set the table id"#),
         ("skip_invalid_rows", Some(r#"false"#), r#"Enable skipping of invalid rows"#),
         ("ignore_unknown_values", Some(r#"false"#), r#"Enable ignoring unknown value"#),
-    ]);
+    ], required: ["dataset_id", "table_id"]);
     add_snippet!(data, FlbSectionType::Output, "Counter", "counter", "output/counter", [
     ]);
     add_snippet!(data, FlbSectionType::Output, "Datadog", "datadog", "output/datadog", [
@@ -869,7 +869,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("dd_message_key", Some(r#"log"#), r#"By default, the plugin searches for the key 'log' and remap the value to the key 'message'. If the property is set, the plugin will search the property name key."#),
         ("provider", None, r#"To activate the remapping, specify configuration flag provider with value 'ecs'"#),
         ("json_date_key", Some(r#"timestamp"#), r#"Date key name for output."#),
-    ]);
+    ], required: ["apikey"]);
     add_snippet!(data, FlbSectionType::Output, "Elasticsearch", "es", "output/elasticsearch", [
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
@@ -1035,7 +1035,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("tags", Some(r#""#), r#"Tags (optional)"#),
         ("file", None, r#"Name of the monitored file (optional)"#),
         ("app", Some(r#"Fluent Bit"#), r#"Name of the application generating the data (optional)"#),
-    ]);
+    ], required: ["api_key"]);
     add_snippet!(data, FlbSectionType::Output, "Loki", "loki", "output/loki", [
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
@@ -1226,7 +1226,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
         ("webhook", None, r#""#),
-    ]);
+    ], required: ["webhook"]);
     add_snippet!(data, FlbSectionType::Output, "Splunk", "splunk", "output/splunk", [
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
@@ -1351,7 +1351,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("Database", None, r#"Set the Database file"#),
         ("Table", None, r#"Set the Database Table"#),
         ("Region", None, r#"Set the Region: us or jp"#),
-    ]);
+    ], required: ["API", "Database", "Table"]);
     add_snippet!(data, FlbSectionType::Output, "FlowCounter", "flowcounter", "output/flowcounter", [
         ("unit", None, r#""#),
         ("event_based", Some(r#"false"#), r#""#),
@@ -1419,7 +1419,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("metric_dimensions", None, r#"Metric dimensions is a list of lists. If you have only one list of dimensions, put the values as a comma seperated string. If you want to put list of lists, use the list as semicolon seperated strings. If your value is 'd1,d2;d3', we will consider it as [[d1, d2],[d3]]."#),
         ("profile", None, r#"AWS Profile name. AWS Profiles can be configured with AWS CLI and are usually stored in $HOME/.aws/ directory."#),
         ("log_group_class", Some(r#""#), r#"Specify the log storage class. Valid values are STANDARD (default) and INFREQUENT_ACCESS."#),
-    ]);
+    ], required: ["region"]);
     add_snippet!(data, FlbSectionType::Output, "Amazon Kinesis Data Firehose", "kinesis_firehose", "output/firehose", [
         ("region", None, r#"The AWS region of your delivery stream"#),
         ("delivery_stream", None, r#"Firehose delivery stream name"#),
@@ -1433,7 +1433,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("log_key", None, r#"By default, the whole log record will be sent to Firehose. If you specify a key name with this option, then only the value of that key will be sent to Firehose. For example, if you are using the Fluentd Docker log driver, you can specify `log_key log` and only the log message will be sent to Firehose."#),
         ("auto_retry_requests", Some(r#"true"#), r#"Immediately retry failed requests to AWS services once. This option does not affect the normal Fluent Bit retry mechanism with backoff. Instead, it enables an immediate retry with no delay for networking errors, which may help improve throughput when there are transient/random networking issues."#),
         ("profile", None, r#"AWS Profile name. AWS Profiles can be configured with AWS CLI and are usually stored in $HOME/.aws/ directory."#),
-    ]);
+    ], required: ["region", "delivery_stream"]);
     add_snippet!(data, FlbSectionType::Output, "Amazon Kinesis Data Streams", "kinesis_streams", "output/kinesis", [
         ("region", None, r#"The AWS region of your kinesis stream"#),
         ("stream", None, r#"Kinesis stream name"#),
@@ -1446,7 +1446,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("log_key", None, r#"By default, the whole log record will be sent to Kinesis. If you specify a key name with this option, then only the value of that key will be sent to Kinesis. For example, if you are using the Fluentd Docker log driver, you can specify `log_key log` and only the log message will be sent to Kinesis."#),
         ("auto_retry_requests", Some(r#"true"#), r#"Immediately retry failed requests to AWS services once. This option does not affect the normal Fluent Bit retry mechanism with backoff. Instead, it enables an immediate retry with no delay for networking errors, which may help improve throughput when there are transient/random networking issues."#),
         ("profile", None, r#"AWS Profile name. AWS Profiles can be configured with AWS CLI and are usually stored in $HOME/.aws/ directory."#),
-    ]);
+    ], required: ["region", "stream"]);
     add_snippet!(data, FlbSectionType::Output, "OpenTelemetry", "opentelemetry", "output/opentelemetry", [
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
@@ -1553,7 +1553,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("static_file_path", Some(r#"false"#), r#"Disables behavior where UUID string is automatically appended to end of S3 key name when $UUID is not provided in s3_key_format. $UUID, time formatters, $TAG, and other dynamic key formatters all work as expected while this feature is set to true."#),
         ("storage_class", None, r#"Specify the storage class for S3 objects. If this option is not specified, objects will be stored with the default 'STANDARD' storage class."#),
         ("profile", None, r#"AWS Profile name. AWS Profiles can be configured with AWS CLI and are usually stored in $HOME/.aws/ directory."#),
-    ]);
+    ], required: ["bucket", "region"]);
     add_snippet!(data, FlbSectionType::Output, "Vivo Exporter", "vivo_exporter", "output/vivo-exporter", [
         ("host", Some(r#""#), r#"Host Address"#),
         ("port", Some(r#"0"#), r#"host Port"#),
@@ -1572,7 +1572,7 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
         ("log_type", None, r#"Set the log type"#),
         ("region", None, r#"Set the region"#),
         ("log_key", None, r#"Set the log key"#),
-    ]);
+    ], required: ["customer_id", "log_type"]);
 
     data
 });
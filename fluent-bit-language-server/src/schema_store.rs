@@ -0,0 +1,155 @@
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use flb_schema::{config::FlbConfigSchema, versions::bundled_schema};
+use tokio::sync::{OnceCell, RwLock};
+use tower_lsp::{lsp_types::MessageType, Client};
+use tracing::{error, info};
+
+use crate::{
+    completion::{FlbData, FLB_DATA},
+    progress::ServerProgress,
+};
+
+/// Handle to the data set currently used for completion, hover, etc.
+#[derive(Clone)]
+pub enum FlbDataRef {
+    Builtin(&'static FlbData),
+    Loaded(Arc<FlbData>),
+}
+
+impl Deref for FlbDataRef {
+    type Target = FlbData;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            FlbDataRef::Builtin(data) => data,
+            FlbDataRef::Loaded(data) => data,
+        }
+    }
+}
+
+/// Holds the fluent-bit data set, optionally loaded from a runtime schema
+/// file, or from the schema `flb-schema` bundles for the targeted version.
+///
+/// Loading is single-flight: requests arriving while the schema is being
+/// loaded wait for the same load instead of racing each other. Invalidating
+/// the store (e.g. when the schema file changed on disk) makes the next
+/// request load it again.
+pub struct SchemaStore {
+    client: Client,
+    schema_path: RwLock<Option<PathBuf>>,
+    /// `fluentBitVersion`, whose bundled schema is used without a schema file.
+    version: RwLock<Option<String>>,
+    data: RwLock<Arc<OnceCell<FlbDataRef>>>,
+    work_done_progress: AtomicBool,
+}
+
+impl SchemaStore {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            schema_path: RwLock::new(None),
+            version: RwLock::new(None),
+            data: RwLock::new(Arc::new(OnceCell::new())),
+            work_done_progress: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the client supports server initiated work done progress.
+    pub fn set_work_done_progress(&self, supported: bool) {
+        self.work_done_progress.store(supported, Ordering::Relaxed);
+    }
+
+    pub async fn schema_path(&self) -> Option<PathBuf> {
+        self.schema_path.read().await.clone()
+    }
+
+    pub async fn set_schema_path(&self, schema_path: Option<PathBuf>) {
+        *self.schema_path.write().await = schema_path;
+        self.invalidate().await;
+    }
+
+    pub async fn set_version(&self, version: Option<String>) {
+        *self.version.write().await = version;
+        self.invalidate().await;
+    }
+
+    /// Drops the loaded data, next [`SchemaStore::get`] loads it again.
+    pub async fn invalidate(&self) {
+        *self.data.write().await = Arc::new(OnceCell::new());
+    }
+
+    pub async fn get(&self) -> FlbDataRef {
+        // Clone the cell so that an invalidation during the load doesn't
+        // block on it.
+        let cell = self.data.read().await.clone();
+        cell.get_or_init(|| self.load()).await.clone()
+    }
+
+    async fn load(&self) -> FlbDataRef {
+        let Some(schema_path) = self.schema_path().await else {
+            return self.load_bundled().await;
+        };
+
+        let progress = if self.work_done_progress.load(Ordering::Relaxed) {
+            ServerProgress::begin(&self.client, "Loading fluent-bit schema").await
+        } else {
+            None
+        };
+
+        let result = Self::load_schema(&schema_path).await;
+
+        if let Some(progress) = progress {
+            progress.end(None).await;
+        }
+
+        match result {
+            Ok(data) => {
+                info!(path = %schema_path.display(), "loaded fluent-bit schema");
+                FlbDataRef::Loaded(Arc::new(data))
+            }
+            Err(e) => {
+                self.client
+                    .show_message(
+                        MessageType::ERROR,
+                        format!(
+                            "failed to load fluent-bit schema from {}, using builtin one: {e}",
+                            schema_path.display()
+                        ),
+                    )
+                    .await;
+                FlbDataRef::Builtin(&FLB_DATA)
+            }
+        }
+    }
+
+    /// Data of the bundled schema of the targeted version, the builtin data
+    /// set if there's none.
+    async fn load_bundled(&self) -> FlbDataRef {
+        let Some(version) = self.version.read().await.clone() else {
+            return FlbDataRef::Builtin(&FLB_DATA);
+        };
+
+        match bundled_schema(&version) {
+            Some(Ok(schema)) => FlbDataRef::Loaded(Arc::new(FlbData::from_schema(&schema))),
+            Some(Err(e)) => {
+                error!(%version, error = %e, "invalid bundled schema of fluent-bit");
+                FlbDataRef::Builtin(&FLB_DATA)
+            }
+            None => FlbDataRef::Builtin(&FLB_DATA),
+        }
+    }
+
+    async fn load_schema(schema_path: &Path) -> anyhow::Result<FlbData> {
+        let json = tokio::fs::read_to_string(schema_path).await?;
+        let schema: FlbConfigSchema = serde_json::from_str(&json)?;
+        Ok(FlbData::from_schema(&schema))
+    }
+}
@@ -0,0 +1,157 @@
+//! Scope of the analysis: documents excluded by the `exclude` setting, e.g.
+//! vendored examples, aren't analyzed, and templates, e.g. Helm charts or
+//! Jinja templates rendering configurations, are analyzed leniently as their
+//! markers (`{{ }}`, `{% %}`) make fluent-bit syntax look wrong.
+//!
+//! In templates, the expressions are opaque: the lines containing them aren't
+//! diagnosed, e.g. `Port {{ .Values.port }}` isn't an invalid port, and the
+//! other diagnostics are downgraded.
+
+use glob::{MatchOptions, Pattern};
+use percent_encoding::percent_decode_str;
+use ropey::Rope;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Url};
+
+/// Markers opening and closing the expressions and statements of templates.
+const TEMPLATE_MARKERS: &[(&str, &str)] = &[("{{", "}}"), ("{%", "%}")];
+
+/// Whether documents are analyzed as templates.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateMode {
+    // documents containing template markers are templates
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl TemplateMode {
+    pub(crate) fn is_template(self, rope: &Rope) -> bool {
+        match self {
+            TemplateMode::Auto => is_template(rope),
+            TemplateMode::Always => true,
+            TemplateMode::Never => false,
+        }
+    }
+}
+
+/// Whether the document matches one of the `exclude` patterns. Patterns which
+/// aren't absolute match at any depth, e.g. `examples/**` excludes
+/// `/repo/examples/a.conf` and `/repo/charts/examples/b.conf`.
+pub(crate) fn is_excluded(uri: &Url, patterns: &[String]) -> bool {
+    matches_any(uri, patterns)
+}
+
+/// Whether the path of the document matches one of the glob patterns, the ones
+/// which aren't absolute matching at any depth.
+pub(crate) fn matches_any(uri: &Url, patterns: &[String]) -> bool {
+    if patterns.is_empty() {
+        return false;
+    }
+
+    let path = percent_decode_str(uri.path()).decode_utf8_lossy();
+    let options = MatchOptions {
+        case_sensitive: !cfg!(windows),
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.replace('\\', "/");
+        let pattern = match pattern.starts_with('/') || pattern.starts_with("**") {
+            true => pattern,
+            false => format!("**/{pattern}"),
+        };
+        Pattern::new(&pattern).is_ok_and(|pattern| pattern.matches_with(&path, options))
+    })
+}
+
+/// Whether the text contains a template expression, e.g. `{{ .Values.tag }}`.
+pub(crate) fn has_template_expression(text: &str) -> bool {
+    TEMPLATE_MARKERS.iter().any(|(open, close)| {
+        text.find(open)
+            .is_some_and(|start| text[start + open.len()..].contains(close))
+    })
+}
+
+fn is_template(rope: &Rope) -> bool {
+    rope.lines()
+        .any(|line| has_template_expression(&line.to_string()))
+}
+
+/// Drops the diagnostics of the lines of a template containing expressions,
+/// and lowers the errors and warnings of the others to information, as they
+/// may be about text the template replaces.
+pub(crate) fn tolerate_template(diagnostics: &mut Vec<Diagnostic>, rope: &Rope) {
+    diagnostics.retain(|diagnostic| {
+        let line = diagnostic.range.start.line as usize;
+        !rope
+            .get_line(line)
+            .is_some_and(|line| has_template_expression(&line.to_string()))
+    });
+
+    for diagnostic in diagnostics {
+        if diagnostic.severity.map_or(true, |severity| {
+            severity == DiagnosticSeverity::ERROR || severity == DiagnosticSeverity::WARNING
+        }) {
+            diagnostic.severity = Some(DiagnosticSeverity::INFORMATION);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+
+    #[test]
+    fn excluded_documents_and_templates() {
+        let uri = |path: &str| Url::parse(&format!("file://{path}")).unwrap();
+        let patterns = ["examples/**".to_string(), "/etc/**/*.tpl".to_string()];
+        assert!(is_excluded(&uri("/repo/examples/a.conf"), &patterns));
+        assert!(is_excluded(&uri("/repo/charts/examples/b.conf"), &patterns));
+        assert!(is_excluded(&uri("/etc/fluent-bit/a.tpl"), &patterns));
+        assert!(is_excluded(&uri("/my%20repo/examples/a.conf"), &[
+            "my repo/examples/*".to_string()
+        ]));
+        assert!(!is_excluded(&uri("/repo/fluent-bit.conf"), &patterns));
+        assert!(!is_excluded(&uri("/repo/examples.conf"), &patterns));
+        assert!(!is_excluded(&uri("/repo/examples/a.conf"), &[]));
+
+        let template = Rope::from_str(
+            "[OUTPUT]\n    Name  es\n    Port  {{ .Values.es.port }}\n    Tls   maybe\n",
+        );
+        assert!(TemplateMode::Auto.is_template(&template));
+        assert!(TemplateMode::Auto
+            .is_template(&Rope::from_str("{% if tls %}\n    tls on\n{% endif %}\n")));
+        // environment variables aren't template markers
+        let config =
+            Rope::from_str("[INPUT]\n    Name tail\n    Path ${LOG_PATH}\n    Tag  {app}\n");
+        assert!(!TemplateMode::Auto.is_template(&config));
+        assert!(TemplateMode::Always.is_template(&config));
+        assert!(!TemplateMode::Never.is_template(&template));
+
+        let diagnostic = |line, severity| Diagnostic {
+            range: Range::new(Position::new(line, 10), Position::new(line, 14)),
+            severity: Some(severity),
+            ..Diagnostic::default()
+        };
+        let mut diagnostics = vec![
+            diagnostic(2, DiagnosticSeverity::ERROR),
+            diagnostic(3, DiagnosticSeverity::ERROR),
+            diagnostic(3, DiagnosticSeverity::HINT),
+        ];
+        tolerate_template(&mut diagnostics, &template);
+        let diagnostics: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.range.start.line, d.severity))
+            .collect();
+        assert_eq!(diagnostics, [
+            (3, Some(DiagnosticSeverity::INFORMATION)),
+            (3, Some(DiagnosticSeverity::HINT)),
+        ]);
+    }
+}
@@ -0,0 +1,268 @@
+//! Index of the sections of a document and their entries.
+//!
+//! The index is updated incrementally after each edit: sections untouched by
+//! the edit are only shifted, and just the sections overlapping the edited
+//! range or the ranges tree-sitter reports as changed are indexed again.
+
+use std::{ops::Range, str::FromStr};
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+use tree_sitter::{InputEdit, Node, Tree};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedEntry {
+    pub key: String,
+    pub key_range: Range<usize>,
+    /// Value with surrounding whitespace trimmed.
+    pub value: Option<String>,
+    pub value_range: Option<Range<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedSection {
+    /// Name as written in the header, e.g. `INPUT`
+    pub name: String,
+    pub section_type: FlbSectionType,
+    pub byte_range: Range<usize>,
+    pub entries: Vec<IndexedEntry>,
+}
+
+impl IndexedSection {
+    /// Value of the first entry with the given key (case insensitive).
+    pub fn get_value(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key.eq_ignore_ascii_case(key))
+            .and_then(|entry| entry.value.as_deref())
+    }
+
+    /// Whether an entry has the given key (case insensitive).
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.key.eq_ignore_ascii_case(key))
+    }
+
+    fn shift(&mut self, delta: isize) {
+        let shift_range = |range: &mut Range<usize>| {
+            range.start = range.start.saturating_add_signed(delta);
+            range.end = range.end.saturating_add_signed(delta);
+        };
+
+        shift_range(&mut self.byte_range);
+        for entry in &mut self.entries {
+            shift_range(&mut entry.key_range);
+            if let Some(value_range) = &mut entry.value_range {
+                shift_range(value_range);
+            }
+        }
+    }
+}
+
+/// File pattern included by the document, e.g. `@INCLUDE inputs/*.conf`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedInclude {
+    pub pattern: String,
+    pub range: Range<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SectionIndex {
+    /// Sorted by start offset.
+    sections: Vec<IndexedSection>,
+    includes: Vec<IndexedInclude>,
+}
+
+impl SectionIndex {
+    pub fn new(tree: &Tree, rope: &Rope) -> Self {
+        let root = tree.root_node();
+        let mut cursor = root.walk();
+        let sections = root
+            .children(&mut cursor)
+            .filter_map(|node| index_section(&node, rope))
+            .collect();
+
+        Self {
+            sections,
+            includes: index_includes(tree, rope),
+        }
+    }
+
+    /// Index of sections and includes indexed from another syntax, sorted by
+    /// start offset.
+    pub(crate) fn from_parts(sections: Vec<IndexedSection>, includes: Vec<IndexedInclude>) -> Self {
+        Self { sections, includes }
+    }
+
+    pub fn sections(&self) -> &[IndexedSection] {
+        &self.sections
+    }
+
+    pub fn includes(&self) -> &[IndexedInclude] {
+        &self.includes
+    }
+
+    /// Section containing the byte offset, its end included.
+    pub fn section_at(&self, byte: usize) -> Option<&IndexedSection> {
+        self.sections
+            .iter()
+            .rev()
+            .find(|section| section.byte_range.start <= byte && byte <= section.byte_range.end)
+    }
+
+    /// Updates the index after `edit`, `tree` being the tree parsed after it
+    /// and `changed_ranges` the ones returned by [`Tree::changed_ranges`].
+    pub fn update(
+        &mut self,
+        edit: &InputEdit,
+        changed_ranges: impl IntoIterator<Item = tree_sitter::Range>,
+        tree: &Tree,
+        rope: &Rope,
+    ) {
+        // Sections touching the replaced text are gone, following ones moved.
+        let delta = edit.new_end_byte as isize - edit.old_end_byte as isize;
+        self.sections.retain_mut(|section| {
+            if overlaps(&section.byte_range, &(edit.start_byte..edit.old_end_byte)) {
+                return false;
+            }
+            if section.byte_range.start >= edit.old_end_byte {
+                section.shift(delta);
+            }
+            true
+        });
+
+        // The edited text may not change the tree's structure (e.g. renaming a
+        // key), so it's not necessarily part of the changed ranges.
+        let dirty: Vec<Range<usize>> = std::iter::once(edit.start_byte..edit.new_end_byte)
+            .chain(changed_ranges.into_iter().map(|r| r.start_byte..r.end_byte))
+            .collect();
+        self.sections.retain(|section| {
+            !dirty
+                .iter()
+                .any(|range| overlaps(&section.byte_range, range))
+        });
+
+        let root = tree.root_node();
+        let mut reindexed: Vec<IndexedSection> = Vec::new();
+        for range in &dirty {
+            let mut cursor = root.walk();
+            let mut node = cursor
+                .goto_first_child_for_byte(range.start.saturating_sub(1))
+                .map(|_| cursor.node());
+            while let Some(n) = node {
+                if n.start_byte() > range.end {
+                    break;
+                }
+                if !reindexed
+                    .iter()
+                    .any(|section| section.byte_range.start == n.start_byte())
+                {
+                    reindexed.extend(index_section(&n, rope));
+                }
+                node = n.next_sibling();
+            }
+        }
+
+        self.sections.extend(reindexed);
+        self.sections
+            .sort_by_key(|section| section.byte_range.start);
+        self.sections
+            .dedup_by_key(|section| section.byte_range.start);
+
+        // Directives are few and top-level, so they're all indexed again.
+        self.includes = index_includes(tree, rope);
+    }
+}
+
+/// Whether the ranges overlap or touch each other.
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+fn index_includes(tree: &Tree, rope: &Rope) -> Vec<IndexedInclude> {
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let includes = root
+        .children(&mut cursor)
+        .filter(|node| node.kind() == "directive")
+        .filter_map(|directive| directive.named_child(0))
+        .filter(|directive| directive.kind() == "directive_include")
+        .filter_map(|include| {
+            let pattern_node = include.child_by_field_name("pattern")?;
+            let pattern = rope.get_byte_slice(pattern_node.byte_range())?.to_string();
+            let trimmed = pattern.trim_start();
+            let start = pattern_node.start_byte() + pattern.len() - trimmed.len();
+            let pattern = trimmed.trim_end().to_string();
+
+            Some(IndexedInclude {
+                range: start..start + pattern.len(),
+                pattern,
+            })
+        })
+        .collect();
+    includes
+}
+
+/// Variables set by `@SET` directives, in the order of the document.
+pub(crate) fn index_variables(tree: &Tree, rope: &Rope) -> Vec<(String, String)> {
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let variables = root
+        .children(&mut cursor)
+        .filter(|node| node.kind() == "directive")
+        .filter_map(|directive| directive.named_child(0))
+        .filter(|directive| directive.kind() == "directive_set")
+        .filter_map(|set| {
+            let key = rope.get_byte_slice(set.child_by_field_name("key")?.byte_range())?;
+            let value = rope.get_byte_slice(set.child_by_field_name("value")?.byte_range())?;
+            Some((key.to_string(), value.to_string().trim().to_string()))
+        })
+        .collect();
+    variables
+}
+
+fn index_section(node: &Node, rope: &Rope) -> Option<IndexedSection> {
+    if node.kind() != "section" {
+        return None;
+    }
+
+    let name_node = node
+        .child_by_field_name("header")
+        .and_then(|header| header.child_by_field_name("name"))?;
+    let name = rope.get_byte_slice(name_node.byte_range())?.to_string();
+
+    let mut entries = Vec::new();
+    if let Some(body) = node.child_by_field_name("body") {
+        let mut cursor = body.walk();
+        for entry in body
+            .children(&mut cursor)
+            .filter(|entry| entry.kind() == "entry")
+        {
+            let Some(key_node) = entry.child_by_field_name("key") else {
+                continue;
+            };
+            let Some(key) = rope.get_byte_slice(key_node.byte_range()) else {
+                continue;
+            };
+            let value_node = entry.child_by_field_name("value");
+            let value = value_node
+                .and_then(|value_node| rope.get_byte_slice(value_node.byte_range()))
+                .map(|value| value.to_string().trim().to_string());
+
+            entries.push(IndexedEntry {
+                key: key.to_string(),
+                key_range: key_node.byte_range(),
+                value,
+                value_range: value_node.map(|value_node| value_node.byte_range()),
+            });
+        }
+    }
+
+    Some(IndexedSection {
+        section_type: FlbSectionType::from_str(&name).ok()?,
+        name,
+        byte_range: node.byte_range(),
+        entries,
+    })
+}
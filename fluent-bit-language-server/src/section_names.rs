@@ -0,0 +1,163 @@
+//! Diagnostics of the headers of classic sections whose name is a typo of one
+//! of fluent-bit, e.g. `[INPUTS]` or `[OUPUT]`, with the name as a fix. Such
+//! sections are completed as the intended one meanwhile.
+
+use std::str::FromStr;
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, TextEdit};
+
+use crate::{
+    document::TextDocument,
+    i18n::t,
+    lsp_ext::DiagnosticFix,
+    plugins::nearest,
+    positions::{byte_range_to_lsp_range, resolve_position, PositionEncodingKind},
+    section_index::{IndexedSection, SectionIndex},
+};
+
+/// Diagnostic code of a section header which is a typo of a section name.
+pub(crate) const UNKNOWN_SECTION: &str = "unknown-section";
+
+/// Sections of fluent-bit, the ones of upstream, plugins and stream processor
+/// files included.
+const SECTION_NAMES: &[&str] = &[
+    "SERVICE",
+    "INPUT",
+    "PARSER",
+    "MULTILINE_PARSER",
+    "FILTER",
+    "OUTPUT",
+    "CUSTOM",
+    "UPSTREAM",
+    "NODE",
+    "PLUGINS",
+    "STREAM_TASK",
+];
+
+/// Section the unknown section `name` is a typo of, in the case of `name`.
+fn intended_name(name: &str) -> Option<String> {
+    if SECTION_NAMES
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(name))
+    {
+        return None;
+    }
+
+    let suggestion = nearest(name, SECTION_NAMES.iter().copied())?;
+    match name.chars().any(char::is_uppercase) {
+        true => Some(suggestion.to_string()),
+        false => Some(suggestion.to_lowercase()),
+    }
+}
+
+/// Type of the section, or of the section it's a typo of.
+pub(crate) fn intended_section_type(section_type: FlbSectionType) -> FlbSectionType {
+    let FlbSectionType::Other(name) = &section_type else {
+        return section_type;
+    };
+
+    intended_name(name)
+        .and_then(|name| FlbSectionType::from_str(&name).ok())
+        .unwrap_or(section_type)
+}
+
+/// Byte range of the name in the header of the section.
+pub(crate) fn name_range(rope: &Rope, section: &IndexedSection) -> Option<std::ops::Range<usize>> {
+    let start = section.byte_range.start;
+    let header = rope
+        .get_byte_slice(start..section.byte_range.end)?
+        .to_string();
+    let offset = header.lines().next()?.find(&section.name)?;
+
+    Some(start + offset..start + offset + section.name.len())
+}
+
+pub(crate) fn check_section_names(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    index
+        .sections()
+        .iter()
+        .filter(|section| matches!(section.section_type, FlbSectionType::Other(_)))
+        .filter_map(|section| {
+            let suggestion = intended_name(&section.name)?;
+            Some(Diagnostic {
+                range: byte_range_to_lsp_range(rope, name_range(rope, section)?, position_encoding),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(UNKNOWN_SECTION.to_string())),
+                message: t!(
+                    UNKNOWN_SECTION,
+                    name = section.name,
+                    suggestion = suggestion
+                ),
+                ..Diagnostic::default()
+            })
+        })
+        .collect()
+}
+
+/// Replaces the name of the section with the one it's a typo of.
+pub(crate) fn unknown_section_fix(
+    document: &TextDocument,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    if diagnostic.code != Some(NumberOrString::String(UNKNOWN_SECTION.to_string())) {
+        return None;
+    }
+
+    let start =
+        resolve_position(&document.rope, &diagnostic.range.start, position_encoding).ok()?;
+    let section = document.index.section_at(start.byte_idx)?;
+    let suggestion = intended_name(&section.name)?;
+
+    Some(DiagnosticFix {
+        title: t!("fix-replace-section", suggestion = suggestion),
+        edits: vec![TextEdit {
+            range: diagnostic.range,
+            new_text: suggestion,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+
+    #[test]
+    fn typos_of_section_names() {
+        let document = TextDocument::new(
+            "[INPUTS]\n    Name cpu\n\n[ouput]\n    Name stdout\n\n\
+             [UPSTREAM]\n    name forward-balancing\n\n[SOMETHING_ELSE]\n    Key value\n",
+        );
+        let encoding = PositionEncodingKind::UTF16;
+        let diagnostics = check_section_names(&document.index, &document.rope, encoding);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Unknown section `[INPUTS]`, did you mean `[INPUT]`?",
+            "Unknown section `[ouput]`, did you mean `[output]`?",
+        ]);
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(0, 1), Position::new(0, 7))
+        );
+
+        let fix = unknown_section_fix(&document, &diagnostics[1], encoding).unwrap();
+        assert_eq!(fix.title, "Replace with `[output]`");
+        assert_eq!(fix.edits[0].new_text, "output");
+
+        let section_type = document.index.sections()[0].section_type.clone();
+        assert_eq!(intended_section_type(section_type), FlbSectionType::Input);
+        let section_type = document.index.sections()[3].section_type.clone();
+        assert!(matches!(
+            intended_section_type(section_type),
+            FlbSectionType::Other(_)
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+//! Semantic tokens (`textDocument/semanticTokens/full`) emphasizing the
+//! structure of a pipeline, on top of the grammar of the client: the headers
+//! of sections, the plugins they use, the keys routing records and the
+//! directives.
+//!
+//! Other values aren't tokens, so that clients keep highlighting them.
+
+use ropey::Rope;
+use tower_lsp::lsp_types::{
+    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensLegend,
+};
+use tree_sitter::Tree;
+
+use crate::{
+    completion::FlbData,
+    document::TextDocument,
+    lsp_ext::ConfigFormat,
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+};
+
+/// Custom modifier of the keys routing records, and of their values.
+const ROUTING: SemanticTokenModifier = SemanticTokenModifier::new("routing");
+
+/// Indexes of the types are the ones of the legend.
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::NAMESPACE,
+    SemanticTokenType::CLASS,
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::STRING,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::VARIABLE,
+];
+const NAMESPACE: u32 = 0;
+const CLASS: u32 = 1;
+const PROPERTY: u32 = 2;
+const STRING: u32 = 3;
+const MACRO: u32 = 4;
+const VARIABLE: u32 = 5;
+
+/// Bits of the modifiers are their indexes in the legend.
+const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[
+    SemanticTokenModifier::DECLARATION,
+    SemanticTokenModifier::DEFAULT_LIBRARY,
+    ROUTING,
+];
+const DECLARATION_BIT: u32 = 1 << 0;
+const DEFAULT_LIBRARY_BIT: u32 = 1 << 1;
+const ROUTING_BIT: u32 = 1 << 2;
+
+/// Keys routing the records from inputs to filters and outputs.
+const ROUTING_KEYS: &[&str] = &["Tag", "Match", "Match_Regex"];
+
+pub(crate) fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// Token of the document, not encoded yet.
+struct Token {
+    range: std::ops::Range<usize>,
+    token_type: u32,
+    modifiers: u32,
+}
+
+/// Section headers (`[INPUT]`) and directives (`@INCLUDE`, `@SET`) of a classic
+/// document, which aren't indexed.
+fn classic_tokens(tree: &Tree) -> Vec<Token> {
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let mut tokens = Vec::new();
+    for node in root.children(&mut cursor) {
+        match node.kind() {
+            "section" => {
+                let Some(name) = node
+                    .child_by_field_name("header")
+                    .and_then(|header| header.child_by_field_name("name"))
+                else {
+                    continue;
+                };
+                tokens.push(Token {
+                    range: name.byte_range(),
+                    token_type: NAMESPACE,
+                    modifiers: DECLARATION_BIT,
+                });
+            }
+            "directive" => {
+                let Some(directive) = node.named_child(0) else {
+                    continue;
+                };
+                let keyword = match directive.kind() {
+                    "directive_include" => "INCLUDE",
+                    "directive_set" => "SET",
+                    _ => continue,
+                };
+                tokens.push(Token {
+                    range: node.start_byte()..directive.start_byte() + keyword.len(),
+                    token_type: MACRO,
+                    modifiers: 0,
+                });
+                if let Some(key) = directive.child_by_field_name("key") {
+                    tokens.push(Token {
+                        range: key.byte_range(),
+                        token_type: VARIABLE,
+                        modifiers: DECLARATION_BIT,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tokens
+}
+
+/// Encodes the tokens relatively to each other, as the protocol requires.
+fn encode(
+    mut tokens: Vec<Token>,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<SemanticToken> {
+    tokens.sort_by_key(|token| token.range.start);
+
+    let mut previous = (0, 0);
+    let mut ret = Vec::new();
+    for token in tokens {
+        let range = byte_range_to_lsp_range(rope, token.range, position_encoding);
+        // tokens can't span lines
+        if range.start.line != range.end.line || range.start == range.end {
+            continue;
+        }
+
+        let (line, character) = (range.start.line, range.start.character);
+        ret.push(SemanticToken {
+            delta_line: line - previous.0,
+            delta_start: match line == previous.0 {
+                true => character - previous.1,
+                false => character,
+            },
+            length: range.end.character - character,
+            token_type: token.token_type,
+            token_modifiers_bitset: token.modifiers,
+        });
+        previous = (line, character);
+    }
+
+    ret
+}
+
+pub(crate) fn semantic_tokens(
+    data: &FlbData,
+    document: &TextDocument,
+    position_encoding: PositionEncodingKind,
+) -> Vec<SemanticToken> {
+    let TextDocument {
+        rope,
+        tree,
+        index,
+        format,
+        ..
+    } = document;
+
+    let mut tokens = match (format, tree) {
+        (ConfigFormat::Classic, Some(tree)) => classic_tokens(tree),
+        _ => Vec::new(),
+    };
+    for section in index.sections() {
+        let is_plugin = data.get_snippets(&section.section_type).is_some();
+        for entry in &section.entries {
+            let routing = ROUTING_KEYS
+                .iter()
+                .any(|key| key.eq_ignore_ascii_case(&entry.key));
+            tokens.push(Token {
+                range: entry.key_range.clone(),
+                token_type: PROPERTY,
+                modifiers: if routing { ROUTING_BIT } else { 0 },
+            });
+
+            let (Some(value), Some(value_range)) = (&entry.value, &entry.value_range) else {
+                continue;
+            };
+            let range = value_range.start..value_range.start + value.len();
+            if routing {
+                tokens.push(Token {
+                    range,
+                    token_type: STRING,
+                    modifiers: ROUTING_BIT,
+                });
+            } else if is_plugin && entry.key.eq_ignore_ascii_case("Name") {
+                let known = data.has_plugin(&section.section_type, value);
+                tokens.push(Token {
+                    range,
+                    token_type: CLASS,
+                    modifiers: if known { DEFAULT_LIBRARY_BIT } else { 0 },
+                });
+            }
+        }
+    }
+
+    encode(tokens, rope, position_encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::FLB_DATA;
+
+    #[test]
+    fn pipeline_structure() {
+        let document = TextDocument::new(
+            "@SET env=prod\n\n[INPUT]\n    Name cpu\n    Tag  cpu.local\n\n\
+             [OUTPUT]\n    Name  not_a_plugin\n    Match *\n",
+        );
+        let tokens = semantic_tokens(&FLB_DATA, &document, PositionEncodingKind::UTF16);
+        let tokens: Vec<_> = tokens
+            .iter()
+            .map(|token| {
+                (
+                    token.delta_line,
+                    token.delta_start,
+                    token.length,
+                    token.token_type,
+                    token.token_modifiers_bitset,
+                )
+            })
+            .collect();
+        assert_eq!(tokens, [
+            (0, 0, 4, MACRO, 0),
+            (0, 5, 3, VARIABLE, DECLARATION_BIT),
+            (2, 1, 5, NAMESPACE, DECLARATION_BIT),
+            (1, 4, 4, PROPERTY, 0),
+            (0, 5, 3, CLASS, DEFAULT_LIBRARY_BIT),
+            (1, 4, 3, PROPERTY, ROUTING_BIT),
+            (0, 5, 9, STRING, ROUTING_BIT),
+            (2, 1, 6, NAMESPACE, DECLARATION_BIT),
+            (1, 4, 4, PROPERTY, 0),
+            (0, 6, 12, CLASS, 0),
+            (1, 4, 5, PROPERTY, ROUTING_BIT),
+            (0, 6, 1, STRING, ROUTING_BIT),
+        ]);
+    }
+}
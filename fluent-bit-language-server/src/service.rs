@@ -0,0 +1,291 @@
+//! Keys of the `[SERVICE]` section.
+//!
+//! These are not part of the fluent-bit schema JSON, so they are maintained by
+//! hand, together with the fluent-bit version that introduced the newer ones.
+//!
+//! ref: <https://docs.fluentbit.io/manual/administration/configuring-fluent-bit/classic-mode/configuration-file#config_section>
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, TextEdit};
+
+use crate::{
+    completion::FlbData,
+    document::TextDocument,
+    i18n::t,
+    lsp_ext::DiagnosticFix,
+    positions::{byte_range_to_lsp_range, resolve_position, PositionEncodingKind},
+    section_index::SectionIndex,
+};
+
+pub(crate) struct FlbServiceParameter {
+    pub(crate) key: &'static str,
+    pub(crate) default_value: Option<&'static str>,
+    pub(crate) description: &'static str,
+    /// fluent-bit version which introduced the key, if it's a recent one.
+    pub(crate) since: Option<&'static str>,
+}
+
+macro_rules! service_parameters {
+    ($(($key:literal, $default:expr, $desc:literal $(, since = $since:literal)?)),* $(,)?) => {
+        &[
+            $(
+                FlbServiceParameter {
+                    key: $key,
+                    default_value: $default,
+                    description: $desc,
+                    since: service_parameters!(@since $($since)?),
+                },
+            )*
+        ]
+    };
+    (@since $since:literal) => { Some($since) };
+    (@since) => { None };
+}
+
+#[rustfmt::skip::macros(service_parameters)]
+pub(crate) static SERVICE_PARAMETERS: &[FlbServiceParameter] = service_parameters![
+    ("flush", Some("1"), "Set the flush time in `seconds.nanoseconds`. The engine loop uses a Flush timeout to define when is required to flush the records ingested by input plugins through the defined output plugins."),
+    ("grace", Some("5"), "Set the grace time in `seconds` as Integer value. The engine loop uses a Grace timeout to define wait time on exit."),
+    ("daemon", Some("off"), "Boolean value to set if Fluent Bit should run as a Daemon (background) or not. Allowed values are: yes, no, on and off. Note: If you are using a Systemd based unit as the one we provide in our packages, do not turn on this option."),
+    ("dns.mode", Some("UDP"), "Set the primary transport layer protocol used by the asynchronous DNS resolver which can be overridden on a per plugin basis."),
+    ("dns.resolver", None, "Select the primary DNS resolver type (`LEGACY` or `ASYNC`)."),
+    ("dns.prefer_ipv4", Some("off"), "Prioritize IPv4 DNS results when trying to establish a connection."),
+    ("log_file", None, "Absolute path for an optional log file. By default all logs are redirected to the standard error interface (stderr)."),
+    ("log_level", Some("info"), "Set the logging verbosity level. Allowed values are: `off`, `error`, `warn`, `info`, `debug` and `trace`. Values are accumulative, e.g: if `debug` is set, it will include `error`, `warning`, `info` and `debug`. Note that `trace` mode is only available if Fluent Bit was built with the `WITH_TRACE` option enabled."),
+    ("parsers_file", None, "Path for a `parsers` configuration file. Multiple Parsers_File entries can be defined within the section."),
+    ("plugins_file", None, "Path for a `plugins` configuration file. A `plugins` configuration file allows to define paths for external plugins."),
+    ("streams_file", None, "Path for the Stream Processor configuration file."),
+    ("http_server", Some("off"), "Enable built-in HTTP Server."),
+    ("http_listen", Some("0.0.0.0"), "Set listening interface for HTTP Server when it's enabled."),
+    ("http_port", Some("2020"), "Set TCP Port for the HTTP Server."),
+    ("coro_stack_size", Some("24576"), "Set the coroutines stack size in bytes. The value must be greater than the page size of the running system. Don't set too small value (say 4096), or coroutine threads can overrun the stack buffer. Do not change the default value of this parameter unless you know what you are doing."),
+    ("health_check", Some("off"), "Enable the health check endpoint of the built-in HTTP Server (`/api/v1/health`)."),
+    ("hc_errors_count", Some("5"), "Error count threshold within `hc_period` for the health check to report an unhealthy state."),
+    ("hc_retry_failure_count", Some("5"), "Retry failure count threshold within `hc_period` for the health check to report an unhealthy state."),
+    ("hc_period", Some("60"), "Time period, in seconds, used by the health check to count errors and retry failures."),
+    ("scheduler.cap", Some("2000"), "Set a maximum retry time in seconds.", since = "1.8.7"),
+    ("scheduler.base", Some("5"), "Set a base of exponential backoff.", since = "1.8.7"),
+    ("json.convert_nan_to_null", Some("false"), "If enabled, NaN is converted to null when fluent-bit converts msgpack to json.", since = "2.1.0"),
+    ("hot_reload", Some("off"), "Enable hot reloading of the configuration, triggered by `SIGHUP` or the `/api/v2/reload` endpoint of the HTTP Server.", since = "2.1.0"),
+    ("enable_chunk_trace", Some("off"), "Enable chunk tracing, managed through the HTTP Server (`/api/v1/trace`)."),
+    ("storage.path", None, "Set an optional location in the file system to store streams and chunks of data. If this parameter is not set, Input plugins can only use in-memory buffering."),
+    ("storage.sync", Some("normal"), "Configure the synchronization mode used to store the data into the file system. It can take the values `normal` or `full`."),
+    ("storage.checksum", Some("off"), "Enable the data integrity check when writing and reading data from the filesystem. The storage layer uses the CRC32 algorithm."),
+    ("storage.max_chunks_up", Some("128"), "If the input plugin has enabled `filesystem` storage type, this property sets the maximum number of chunks that can be `up` in memory."),
+    ("storage.backlog.mem_limit", Some("5M"), "If `storage.path` is set, Fluent Bit will look for data chunks that were not delivered and are still in the storage layer, these are called backlog data. This option configures a hint of maximum value of memory to use when processing these records."),
+    ("storage.metrics", Some("off"), "If `http_server` option has been enabled in the main `[SERVICE]` section, this option registers a new endpoint where internal metrics of the storage layer can be consumed."),
+    ("storage.delete_irrecoverable_chunks", Some("off"), "When enabled, irrecoverable chunks will be deleted during runtime, and any other irrecoverable chunk located in the configured storage path directory will be deleted when Fluent-Bit starts."),
+];
+
+pub(crate) fn is_service_key(key: &str) -> bool {
+    SERVICE_PARAMETERS
+        .iter()
+        .any(|param| param.key.eq_ignore_ascii_case(key))
+}
+
+/// Keys of the `[SERVICE]` section which plugin sections accept as well, to
+/// override the global value for the plugin.
+static PLUGIN_OVERRIDABLE_KEYS: &[&str] = &["log_level"];
+
+/// Diagnostic code of a `[SERVICE]` key found inside a plugin section.
+pub(crate) const MISPLACED_SERVICE_KEY: &str = "misplaced-service-key";
+
+/// Reports keys of plugin sections which only belong in `[SERVICE]`, unless
+/// the plugin has a parameter of the same name.
+pub(crate) fn check_misplaced_service_keys(
+    data: &FlbData,
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    index
+        .sections()
+        .iter()
+        .filter(|section| {
+            matches!(
+                section.section_type,
+                FlbSectionType::Input
+                    | FlbSectionType::Filter
+                    | FlbSectionType::Output
+                    | FlbSectionType::Custom
+            )
+        })
+        .flat_map(|section| {
+            section
+                .entries
+                .iter()
+                .filter(|entry| {
+                    is_service_key(&entry.key)
+                        && !PLUGIN_OVERRIDABLE_KEYS
+                            .iter()
+                            .any(|key| key.eq_ignore_ascii_case(&entry.key))
+                        && data
+                            .get_parameter_info(&section.section_type, &entry.key.to_lowercase())
+                            .is_none()
+                })
+                .map(|entry| Diagnostic {
+                    range: byte_range_to_lsp_range(
+                        rope,
+                        entry.key_range.clone(),
+                        position_encoding,
+                    ),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: Some(NumberOrString::String(MISPLACED_SERVICE_KEY.to_string())),
+                    message: t!(
+                        MISPLACED_SERVICE_KEY,
+                        key = entry.key,
+                        section = section.name
+                    ),
+                    ..Diagnostic::default()
+                })
+        })
+        .collect()
+}
+
+/// Fix of a misplaced key diagnostic, moving the entry's line to the first
+/// `[SERVICE]` section, which is created if there's none. No fix is offered if
+/// `[SERVICE]` already sets the key.
+pub(crate) fn move_to_service_fix(
+    document: &TextDocument,
+    diagnostic: &Diagnostic,
+    position_encoding: PositionEncodingKind,
+) -> Option<DiagnosticFix> {
+    if diagnostic.code != Some(NumberOrString::String(MISPLACED_SERVICE_KEY.to_string())) {
+        return None;
+    }
+
+    let rope = &document.rope;
+    let start = resolve_position(rope, &diagnostic.range.start, position_encoding).ok()?;
+    let (byte, point) = (start.byte_idx, start.point);
+    let entry = document
+        .index
+        .sections()
+        .iter()
+        .flat_map(|section| &section.entries)
+        .find(|entry| entry.key_range.start == byte)?;
+
+    let service = document
+        .index
+        .sections()
+        .iter()
+        .find(|section| section.section_type == FlbSectionType::Service);
+    if service.is_some_and(|service| service.get_value(&entry.key).is_some()) {
+        return None;
+    }
+
+    let line_ending = document.line_ending();
+    let eol = line_ending.as_str();
+    let line = rope.line(point.row).to_string();
+    let line = line.trim_end_matches(['\r', '\n']);
+
+    // the entry's line, its line ending included
+    let removed = rope.line_to_byte(point.row)..rope.line_to_byte(point.row + 1);
+    let (insert_byte, new_text) = match service {
+        Some(service) => {
+            let last_row = service.entries.last().map_or_else(
+                || rope.byte_to_line(service.byte_range.start),
+                |entry| rope.byte_to_line(entry.key_range.start),
+            );
+            let insert_byte = rope.line_to_byte(last_row + 1);
+            if insert_byte > 0 && rope.byte(insert_byte - 1) != b'\n' {
+                (insert_byte, format!("{eol}{line}"))
+            } else {
+                (insert_byte, format!("{line}{eol}"))
+            }
+        }
+        None => (0, line_ending.apply(&format!("[SERVICE]\n{line}\n\n"))),
+    };
+
+    let edits = vec![
+        TextEdit::new(
+            byte_range_to_lsp_range(rope, insert_byte..insert_byte, position_encoding),
+            new_text,
+        ),
+        TextEdit::new(
+            byte_range_to_lsp_range(rope, removed, position_encoding),
+            String::new(),
+        ),
+    ];
+
+    Some(DiagnosticFix {
+        title: t!("fix-move-to-service", key = entry.key),
+        edits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::TextDocumentContentChangeEvent;
+
+    use super::*;
+    use crate::completion::FLB_DATA;
+
+    #[test]
+    fn newer_keys_tell_their_version() {
+        for key in [
+            "hot_reload",
+            "scheduler.cap",
+            "scheduler.base",
+            "json.convert_nan_to_null",
+        ] {
+            let param = SERVICE_PARAMETERS
+                .iter()
+                .find(|param| param.key == key)
+                .unwrap();
+            assert!(param.since.is_some(), "{key}");
+        }
+    }
+
+    #[test]
+    fn misplaced_service_keys_are_moved() {
+        let text = "[SERVICE]\r\n    Flush 1\r\n\r\n\
+                    [INPUT]\r\n    Name      cpu\r\n    Log_Level debug\r\n    HTTP_Server On\r\n";
+        let mut document = TextDocument::new(text);
+
+        let diagnostics = check_misplaced_service_keys(
+            &FLB_DATA,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "`HTTP_Server` belongs in [SERVICE], not in [INPUT]."
+        ]);
+
+        let fix =
+            move_to_service_fix(&document, &diagnostics[0], PositionEncodingKind::UTF16).unwrap();
+        let mut edits = fix.edits;
+        edits.sort_by_key(|edit| edit.range.start);
+        for edit in edits.into_iter().rev() {
+            document
+                .apply_content_change(
+                    &TextDocumentContentChangeEvent {
+                        range: Some(edit.range),
+                        range_length: None,
+                        text: edit.new_text,
+                    },
+                    PositionEncodingKind::UTF16,
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            document.rope.to_string(),
+            "[SERVICE]\r\n    Flush 1\r\n    HTTP_Server On\r\n\r\n\
+             [INPUT]\r\n    Name      cpu\r\n    Log_Level debug\r\n"
+        );
+
+        // without a [SERVICE] section
+        let document = TextDocument::new("[OUTPUT]\n    Name  stdout\n    Flush 5\n");
+        let diagnostics = check_misplaced_service_keys(
+            &FLB_DATA,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let fix =
+            move_to_service_fix(&document, &diagnostics[0], PositionEncodingKind::UTF16).unwrap();
+        assert_eq!(fix.edits[0].new_text, "[SERVICE]\n    Flush 5\n\n");
+    }
+}
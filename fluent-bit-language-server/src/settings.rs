@@ -0,0 +1,313 @@
+use std::{path::PathBuf, time::Duration};
+
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    schema::Schema,
+    JsonSchema,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::level_filters::LevelFilter;
+
+use crate::{
+    debounce::DEFAULT_DEBOUNCE, document::LineEnding, markdown::DEFAULT_MAX_LENGTH,
+    scope::TemplateMode, timeouts::DEFAULT_REQUEST_TIMEOUT, variables::UndefinedVariableSeverity,
+    versions::FlbVersion,
+};
+
+/// Server settings, passed by the client as `initializationOptions`.
+///
+/// ```json
+/// {
+///     "schemaPath": "/path/to/fluent-bit-schema-3.1.5.json",
+///     "documentationMaxLength": 6000,
+///     "logRequestLatency": false,
+///     "logLevel": "warn",
+///     "lineEnding": "crlf",
+///     "locale": "ja",
+///     "exclude": ["examples/**", "charts/*/templates/**"],
+///     "parsersFiles": ["formats/*.conf"],
+///     "templates": "auto",
+///     "requestTimeoutMs": 10000,
+///     "diagnosticsDebounceMs": 300,
+///     "baselinePath": "/repo/.fluent-bit-baseline.json",
+///     "undefinedVariables": "error",
+///     "knownVariables": ["HOSTNAME", "AWS_REGION"],
+///     "fluentBitVersion": "2.2"
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+#[schemars(
+    title = "fluent-bit-language-server settings",
+    description = "Settings of the server, passed by the client as `initializationOptions`."
+)]
+pub struct Settings {
+    /// fluent-bit schema JSON (output of `fluent-bit -J`) to use instead of
+    /// the builtin one.
+    pub schema_path: Option<PathBuf>,
+
+    /// Maximum length of documentation in hovers and completions, `0` means
+    /// no limit.
+    #[schemars(default = "default_documentation_max_length")]
+    pub documentation_max_length: Option<usize>,
+
+    /// Log the time taken by each handled request and notification, at the
+    /// `info` level. A `verbose` trace also traces it to the client.
+    pub log_request_latency: bool,
+
+    /// Level (`off`, `error`, `warn`, `info`, `debug` or `trace`) of the events
+    /// logged, unless `--log-level` is given. Only warnings and errors are sent
+    /// to the client, the others being written to the `--log-file`. By default,
+    /// `info`.
+    #[schemars(schema_with = "log_level_schema")]
+    pub log_level: Option<String>,
+
+    /// Line ending (`lf` or `crlf`) documents are normalized to when
+    /// formatting. By default, the one used by most lines of the document.
+    pub line_ending: Option<LineEnding>,
+
+    /// Locale of the messages of the server, e.g. `pt-BR`. By default, the
+    /// client's one.
+    pub locale: Option<String>,
+
+    /// Glob patterns of the documents which aren't analyzed, e.g. vendored
+    /// examples. Relative patterns match at any depth.
+    pub exclude: Vec<String>,
+
+    /// Glob patterns of the parsers files whose name doesn't contain
+    /// `parsers`, which only define parsers. Relative patterns match at any
+    /// depth.
+    pub parsers_files: Vec<String>,
+
+    /// Whether documents are analyzed leniently as templates (`always` or
+    /// `never`). By default, the ones containing template markers, e.g.
+    /// `{{ .Values.port }}`, are.
+    pub templates: TemplateMode,
+
+    /// Time after which the requests walking the whole workspace, e.g.
+    /// `workspace/diagnostic`, fail, `0` means no limit.
+    #[schemars(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: Option<u64>,
+
+    /// Time the changes of a document must stop for before its diagnostics
+    /// are published, `0` means after each change.
+    #[schemars(default = "default_diagnostics_debounce_ms")]
+    pub diagnostics_debounce_ms: Option<u64>,
+
+    /// Baseline of known diagnostics which aren't reported, written by
+    /// `fluent-bit-language-server lint --write-baseline`.
+    pub baseline_path: Option<PathBuf>,
+
+    /// Severity (`information`, `warning` or `error`) of the references to
+    /// variables no file of the configuration sets, e.g. `${HOSTNAME}`.
+    pub undefined_variables: UndefinedVariableSeverity,
+
+    /// Variables set by the environment fluent-bit runs in, e.g. by CI, whose
+    /// references aren't reported.
+    pub known_variables: Vec<String>,
+
+    /// fluent-bit version the configurations target, e.g. `2.2`: plugins and
+    /// keys introduced after it aren't completed, and are reported. By
+    /// default, any version.
+    #[schemars(regex(pattern = r"^v?\d+(\.\d+){0,2}$"))]
+    pub fluent_bit_version: Option<String>,
+}
+
+impl Settings {
+    /// Settings of the `initializationOptions`, and the errors of the invalid
+    /// ones, which keep their default rather than resetting the others.
+    pub fn from_initialization_options(options: Option<Value>) -> (Self, Vec<String>) {
+        let options = match options {
+            None | Some(Value::Null) => return (Self::default(), Vec::new()),
+            Some(Value::Object(options)) => options,
+            Some(options) => {
+                let error = format!("expected an object of settings, got `{options}`");
+                return (Self::default(), vec![error]);
+            }
+        };
+
+        let mut errors = Vec::new();
+        let valid = options
+            .into_iter()
+            .filter(|(key, value)| {
+                let setting = json!({ key.as_str(): value });
+                match serde_json::from_value::<Self>(setting) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        errors.push(format!("{key}: {e}"));
+                        false
+                    }
+                }
+            })
+            .collect();
+        let settings = serde_json::from_value(Value::Object(valid)).unwrap_or_default();
+
+        (settings, errors)
+    }
+
+    pub fn documentation_max_length(&self) -> Option<usize> {
+        match self.documentation_max_length {
+            Some(0) => None,
+            Some(max_length) => Some(max_length),
+            None => Some(DEFAULT_MAX_LENGTH),
+        }
+    }
+
+    pub fn request_timeout(&self) -> Option<Duration> {
+        match self.request_timeout_ms {
+            Some(0) => None,
+            Some(timeout_ms) => Some(Duration::from_millis(timeout_ms)),
+            None => Some(DEFAULT_REQUEST_TIMEOUT),
+        }
+    }
+
+    pub(crate) fn diagnostics_debounce(&self) -> Duration {
+        self.diagnostics_debounce_ms
+            .map_or(DEFAULT_DEBOUNCE, Duration::from_millis)
+    }
+
+    /// Level of `logLevel`, `None` if it's unset or invalid.
+    pub(crate) fn log_level(&self) -> Option<LevelFilter> {
+        self.log_level.as_deref()?.parse().ok()
+    }
+
+    /// Version of `fluentBitVersion`, `None` if it's unset or invalid.
+    pub(crate) fn target_version(&self) -> Option<FlbVersion> {
+        self.fluent_bit_version.as_deref()?.parse().ok()
+    }
+
+    /// JSON Schema of the settings, for clients to declare theirs from, e.g.
+    /// the `contributes.configuration` of the VS Code extension.
+    pub fn json_schema() -> Value {
+        let schema = SchemaSettings::draft07()
+            .with(|settings| settings.inline_subschemas = true)
+            .into_generator()
+            .into_root_schema_for::<Settings>();
+        serde_json::to_value(schema).expect("schemas serialize to JSON")
+    }
+}
+
+fn default_documentation_max_length() -> Option<usize> {
+    Some(DEFAULT_MAX_LENGTH)
+}
+
+fn default_request_timeout_ms() -> Option<u64> {
+    Some(DEFAULT_REQUEST_TIMEOUT.as_millis() as u64)
+}
+
+fn default_diagnostics_debounce_ms() -> Option<u64> {
+    Some(DEFAULT_DEBOUNCE.as_millis() as u64)
+}
+
+fn log_level_schema(_: &mut SchemaGenerator) -> Schema {
+    serde_json::from_value(json!({
+        "type": ["string", "null"],
+        "enum": ["off", "error", "warn", "info", "debug", "trace", null],
+    }))
+    .expect("the schema is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_from_initialization_options() {
+        let (settings, errors) = Settings::from_initialization_options(Some(serde_json::json!({
+            "schemaPath": "/tmp/schema.json",
+            "lineEnding": "crlf",
+            "locale": "en-US",
+            "exclude": ["examples/**"],
+            "parsersFiles": ["formats/*.conf"],
+            "templates": "never",
+            "undefinedVariables": "warning",
+            "knownVariables": ["HOSTNAME"],
+            "fluentBitVersion": "2.2",
+            "logLevel": "debug",
+        })));
+        assert_eq!(
+            settings.schema_path,
+            Some(PathBuf::from("/tmp/schema.json"))
+        );
+        assert_eq!(settings.line_ending, Some(LineEnding::Crlf));
+        assert_eq!(settings.locale.as_deref(), Some("en-US"));
+        assert_eq!(settings.exclude, ["examples/**"]);
+        assert_eq!(settings.parsers_files, ["formats/*.conf"]);
+        assert_eq!(settings.templates, TemplateMode::Never);
+        assert_eq!(
+            settings.undefined_variables,
+            UndefinedVariableSeverity::Warning
+        );
+        assert_eq!(settings.known_variables, ["HOSTNAME"]);
+        assert_eq!(settings.target_version(), "2.2.0".parse().ok());
+        assert_eq!(settings.log_level(), Some(LevelFilter::DEBUG));
+        assert!(errors.is_empty());
+
+        assert_eq!(
+            Settings::from_initialization_options(None),
+            (Settings::default(), Vec::new())
+        );
+        let (settings, errors) =
+            Settings::from_initialization_options(Some(serde_json::json!("invalid")));
+        assert_eq!(settings, Settings::default());
+        assert_eq!(errors.len(), 1);
+
+        // an invalid setting keeps its default, not resetting the others
+        let (settings, errors) = Settings::from_initialization_options(Some(serde_json::json!({
+            "requestTimeoutMs": "10s",
+            "templates": "never",
+        })));
+        assert_eq!(settings.request_timeout_ms, None);
+        assert_eq!(settings.templates, TemplateMode::Never);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("requestTimeoutMs: "), "{errors:?}");
+    }
+
+    #[test]
+    fn json_schema_of_the_settings() {
+        let schema = Settings::json_schema();
+        let properties = schema["properties"].as_object().unwrap();
+
+        // every setting is in the schema, and nothing else
+        let Value::Object(settings) = serde_json::to_value(Settings::default()).unwrap() else {
+            panic!("settings should serialize to an object");
+        };
+        let mut keys: Vec<_> = settings.keys().collect();
+        let mut schema_keys: Vec<_> = properties.keys().collect();
+        keys.sort();
+        schema_keys.sort();
+        assert_eq!(keys, schema_keys);
+
+        // the defaults and the enumerated values are accepted
+        for (key, property) in properties {
+            let values = property["enum"].as_array().cloned().unwrap_or_default();
+            for value in values.into_iter().chain([property["default"].clone()]) {
+                assert!(
+                    serde_json::from_value::<Settings>(json!({ key: value })).is_ok(),
+                    "{key}: {value}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn vscode_declares_the_settings() {
+        let package = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../clients/vscode/package.json"
+        );
+        let package: Value =
+            serde_json::from_str(&std::fs::read_to_string(package).unwrap()).unwrap();
+        let configuration = package["contributes"]["configuration"]["properties"]
+            .as_object()
+            .unwrap();
+
+        for (key, property) in Settings::json_schema()["properties"].as_object().unwrap() {
+            let declared = &configuration[&format!("fluentbitLanguageServer.{key}")];
+            for field in ["type", "enum", "default", "description"] {
+                assert_eq!(declared[field], property[field], "{key}: {field}");
+            }
+        }
+    }
+}
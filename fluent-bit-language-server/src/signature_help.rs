@@ -0,0 +1,135 @@
+//! Signature help (`textDocument/signatureHelp`) while editing the value of an
+//! entry, showing it like the parameter of a function: the type its key
+//! expects, its default and the values it accepts.
+
+use tower_lsp::lsp_types::{
+    Documentation, ParameterInformation, ParameterLabel, SignatureHelp, SignatureInformation,
+};
+
+use crate::{
+    completion::FlbConfigParameterInfo,
+    document::TextDocument,
+    i18n::t,
+    lsp_ext::ConfigFormat,
+    markdown::MarkupOptions,
+    section_index::{IndexedEntry, IndexedSection},
+    validation::{hover_notes, parameter_type},
+};
+
+/// Characters separating keys from their value.
+pub(crate) const TRIGGER_CHARACTERS: &[&str] = &[" ", ":"];
+
+/// Section and entry whose value is edited at the byte offset, i.e. which key
+/// ends before it on its line.
+pub(crate) fn value_entry_at(
+    document: &TextDocument,
+    byte: usize,
+) -> Option<(&IndexedSection, &IndexedEntry)> {
+    let TextDocument { rope, index, .. } = document;
+    let line = rope.try_byte_to_line(byte).ok()?;
+    let section = index.section_at(byte)?;
+    let entry = section.entries.iter().find(|entry| {
+        entry.key_range.end < byte && rope.byte_to_line(entry.key_range.end) == line
+    })?;
+
+    Some((section, entry))
+}
+
+/// Length of the text in UTF-16 code units, which label offsets count.
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+/// Signature `key type` (`key: type` in YAML) of the entry of `section`, its
+/// parameter documenting the values of the key.
+pub(crate) fn signature_help(
+    section: &IndexedSection,
+    key: &str,
+    info: Option<&FlbConfigParameterInfo>,
+    format: ConfigFormat,
+    options: &MarkupOptions,
+) -> Option<SignatureHelp> {
+    let plugin_name = section.get_value("Name");
+    let type_ = parameter_type(info, &section.section_type, plugin_name, key);
+    let notes = hover_notes(&section.section_type, Some(section), key, info);
+    if info.is_none() && type_.is_none() && notes.is_empty() {
+        return None;
+    }
+
+    let separator = match format {
+        ConfigFormat::Classic => " ",
+        ConfigFormat::Yaml => ": ",
+    };
+    let type_name = type_.map_or_else(|| t!("signature-value"), |type_| type_.to_string());
+    let label = format!("{key}{separator}{type_name}");
+    let start = utf16_len(key) + utf16_len(separator);
+
+    let mut values = Vec::new();
+    if let Some(default_value) = info.and_then(|info| info.default_value.as_ref()) {
+        values.push(t!("doc-default", value = default_value));
+    }
+    values.extend(notes);
+    let documentation = info.map(|info| {
+        let mut value = info.description.clone();
+        if let Some(since) = &info.since {
+            value.push_str(&format!("\n\n{}", t!("doc-since", version = since)));
+        }
+        Documentation::MarkupContent(options.render(&value, None))
+    });
+
+    Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation,
+            parameters: Some(vec![ParameterInformation {
+                label: ParameterLabel::LabelOffsets([start, start + utf16_len(&type_name)]),
+                documentation: (!values.is_empty()).then(|| {
+                    Documentation::MarkupContent(options.render(&values.join("\n\n"), None))
+                }),
+            }]),
+            active_parameter: Some(0),
+        }],
+        active_signature: Some(0),
+        active_parameter: Some(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::MarkupKind;
+
+    use super::*;
+    use crate::completion::FLB_DATA;
+
+    #[test]
+    fn values_of_keys() {
+        let text = "[OUTPUT]\n    Name        stdout\n    Retry_Limit \n";
+        let document = TextDocument::new(text);
+        let options = MarkupOptions::from_client_formats(Some(&vec![MarkupKind::Markdown]), None);
+        let help = |byte: usize| {
+            let (section, entry) = value_entry_at(&document, byte)?;
+            let info = FLB_DATA.get_hover_info(&section.section_type, &entry.key);
+            signature_help(
+                section,
+                &entry.key,
+                info.as_ref(),
+                document.format,
+                &options,
+            )
+        };
+
+        // editing the key, not its value yet
+        let key_end = text.find("Retry_Limit").unwrap() + "Retry_Limit".len();
+        assert_eq!(help(key_end), None);
+
+        let help = help(key_end + 1).unwrap();
+        let signature = &help.signatures[0];
+        assert_eq!(signature.label, "Retry_Limit integer");
+        let parameter = &signature.parameters.as_ref().unwrap()[0];
+        assert_eq!(parameter.label, ParameterLabel::LabelOffsets([12, 19]));
+        let Some(Documentation::MarkupContent(values)) = &parameter.documentation else {
+            panic!("values aren't documented");
+        };
+        assert!(values.value.contains("`no_limits`"), "{}", values.value);
+    }
+}
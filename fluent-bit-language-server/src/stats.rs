@@ -0,0 +1,130 @@
+//! Statistics about the documents known to the server (`flb/workspaceStats`).
+
+use flb_schema::section::FlbSectionType;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use crate::{
+    completion::FlbData,
+    lsp_ext::{DiagnosticCounts, PluginUsage, WorkspaceStatsResult},
+    parser_index::is_parsers_file,
+    section_index::SectionIndex,
+};
+
+impl WorkspaceStatsResult {
+    pub(crate) fn new(data: &FlbData) -> Self {
+        Self {
+            fluent_bit_version: data.flb_version.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Counts the sections, plugins and diagnostics of a document.
+    pub(crate) fn add_document(
+        &mut self,
+        data: &FlbData,
+        index: &SectionIndex,
+        diagnostics: &[Diagnostic],
+    ) {
+        match is_parsers_file(index) {
+            true => self.parsers_files += 1,
+            false => self.configs += 1,
+        }
+
+        for section in index.sections() {
+            let section_type = section.section_type.to_string();
+            *self.sections.entry(section_type.clone()).or_default() += 1;
+            // named by users, they aren't plugins
+            if matches!(
+                section.section_type,
+                FlbSectionType::Parser | FlbSectionType::MultilineParser
+            ) {
+                continue;
+            }
+
+            let Some(name) = section.get_value("Name") else {
+                continue;
+            };
+            let name = name.to_lowercase();
+            match self
+                .plugins
+                .iter_mut()
+                .find(|plugin| plugin.section_type == section_type && plugin.name == name)
+            {
+                Some(plugin) => plugin.count += 1,
+                None => {
+                    let available = data.get_snippet(&section.section_type, &name).is_some();
+                    let index = self.plugins.partition_point(|plugin| {
+                        (&plugin.section_type, &plugin.name) < (&section_type, &name)
+                    });
+                    self.plugins.insert(index, PluginUsage {
+                        section_type,
+                        name,
+                        count: 1,
+                        available,
+                    });
+                }
+            }
+        }
+
+        self.diagnostics.add(diagnostics);
+    }
+}
+
+impl DiagnosticCounts {
+    pub(crate) fn add(&mut self, diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            let count = match diagnostic.severity {
+                // clients treat diagnostics without severity as errors
+                Some(DiagnosticSeverity::ERROR) | None => &mut self.error,
+                Some(DiagnosticSeverity::WARNING) => &mut self.warning,
+                Some(DiagnosticSeverity::INFORMATION) => &mut self.information,
+                Some(_) => &mut self.hint,
+            };
+            *count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{completion::FLB_DATA, document::TextDocument};
+
+    #[test]
+    fn workspace_stats() {
+        let mut stats = WorkspaceStatsResult::new(&FLB_DATA);
+        let documents = [
+            "[INPUT]\n    Name cpu\n\n[INPUT]\n    Name not_a_plugin\n\n[OUTPUT]\n    Name stdout\n",
+            "[SERVICE]\n    Flush 1\n\n[INPUT]\n    Name CPU\n",
+            "[PARSER]\n    Name   json\n    Format json\n",
+        ];
+        for text in documents {
+            let document = TextDocument::new(text);
+            let diagnostics = [Diagnostic {
+                severity: Some(DiagnosticSeverity::WARNING),
+                ..Diagnostic::default()
+            }];
+            stats.add_document(&FLB_DATA, &document.index, &diagnostics);
+        }
+
+        assert_eq!(stats.configs, 2);
+        assert_eq!(stats.parsers_files, 1);
+        assert_eq!(stats.sections.into_iter().collect::<Vec<_>>(), [
+            ("input".to_string(), 3),
+            ("output".to_string(), 1),
+            ("parser".to_string(), 1),
+            ("service".to_string(), 1)
+        ]);
+        let plugins: Vec<_> = stats
+            .plugins
+            .iter()
+            .map(|plugin| (plugin.name.as_str(), plugin.count, plugin.available))
+            .collect();
+        assert_eq!(plugins, [
+            ("cpu", 2, true),
+            ("not_a_plugin", 1, false),
+            ("stdout", 1, true)
+        ]);
+        assert_eq!(stats.diagnostics.warning, 3);
+    }
+}
@@ -0,0 +1,143 @@
+//! Outline of a document (`textDocument/documentSymbol`): its sections, named
+//! after their `Name`, with their entries as children.
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+use tower_lsp::lsp_types::{DocumentSymbol, SymbolKind};
+
+use crate::{
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    section_index::{IndexedSection, SectionIndex},
+};
+
+fn symbol_kind(section_type: &FlbSectionType) -> SymbolKind {
+    match section_type {
+        FlbSectionType::Service => SymbolKind::NAMESPACE,
+        FlbSectionType::Parser | FlbSectionType::MultilineParser => SymbolKind::FUNCTION,
+        _ => SymbolKind::MODULE,
+    }
+}
+
+#[allow(deprecated)]
+fn section_symbol(
+    section: &IndexedSection,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> DocumentSymbol {
+    let to_lsp_range = |range| byte_range_to_lsp_range(rope, range, position_encoding);
+
+    let children = section
+        .entries
+        .iter()
+        .map(|entry| {
+            let end = match (&entry.value, &entry.value_range) {
+                (Some(value), Some(value_range)) => value_range.start + value.len(),
+                _ => entry.key_range.end,
+            };
+            DocumentSymbol {
+                name: entry.key.clone(),
+                detail: entry.value.clone().filter(|value| !value.is_empty()),
+                kind: SymbolKind::PROPERTY,
+                tags: None,
+                deprecated: None,
+                range: to_lsp_range(entry.key_range.start..end),
+                selection_range: to_lsp_range(entry.key_range.clone()),
+                children: None,
+            }
+        })
+        .collect();
+
+    // sections without a name, e.g. `[SERVICE]`, are shown as written
+    let name_entry = section
+        .entries
+        .iter()
+        .find(|entry| entry.key.eq_ignore_ascii_case("Name"))
+        .filter(|entry| {
+            entry
+                .value
+                .as_deref()
+                .is_some_and(|value| !value.is_empty())
+        });
+    let (name, detail, selection_range) = match name_entry {
+        Some(entry) => {
+            let value = entry.value.clone().unwrap_or_default();
+            let start = entry
+                .value_range
+                .as_ref()
+                .map_or(entry.key_range.start, |r| r.start);
+            let selection_range = start..start + value.len();
+            (value, Some(section.name.clone()), selection_range)
+        }
+        None => (
+            section.name.clone(),
+            None,
+            section.byte_range.start..section.byte_range.start,
+        ),
+    };
+
+    DocumentSymbol {
+        name,
+        detail,
+        kind: symbol_kind(&section.section_type),
+        tags: None,
+        deprecated: None,
+        range: to_lsp_range(section.byte_range.clone()),
+        selection_range: to_lsp_range(selection_range),
+        children: Some(children),
+    }
+}
+
+pub(crate) fn document_symbols(
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<DocumentSymbol> {
+    index
+        .sections()
+        .iter()
+        .map(|section| section_symbol(section, rope, position_encoding))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+    use crate::{document::TextDocument, lsp_ext::ConfigFormat};
+
+    #[test]
+    fn sections_named_after_their_plugin() {
+        let document = TextDocument::new(
+            "[SERVICE]\n    Flush 1\n\n[INPUT]\n    Name tail\n    Path /var/log/*.log\n",
+        );
+        let symbols =
+            document_symbols(&document.index, &document.rope, PositionEncodingKind::UTF16);
+
+        let names: Vec<_> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.detail.as_deref()))
+            .collect();
+        assert_eq!(names, [("SERVICE", None), ("tail", Some("INPUT"))]);
+        assert_eq!(
+            symbols[1].selection_range,
+            Range::new(Position::new(4, 9), Position::new(4, 13))
+        );
+        let children = symbols[1].children.as_deref().unwrap_or_default();
+        assert_eq!(children[1].name, "Path");
+        assert_eq!(children[1].detail.as_deref(), Some("/var/log/*.log"));
+        assert_eq!(
+            children[1].range,
+            Range::new(Position::new(5, 4), Position::new(5, 23))
+        );
+
+        let document = TextDocument::with_format(
+            "pipeline:\n  outputs:\n    - name: stdout\n      match: '*'\n",
+            ConfigFormat::Yaml,
+        );
+        let symbols =
+            document_symbols(&document.index, &document.rope, PositionEncodingKind::UTF16);
+        assert_eq!(symbols[0].name, "stdout");
+        assert_eq!(symbols[0].children.as_ref().map(Vec::len), Some(2));
+    }
+}
@@ -0,0 +1,257 @@
+//! Timeouts and load shedding of the requests which walk the whole workspace,
+//! e.g. `workspace/diagnostic`.
+//!
+//! A pathological configuration, e.g. an `@INCLUDE` matching thousands of
+//! files, shouldn't hang the client: such requests fail with `RequestFailed`
+//! once they take longer than the `requestTimeoutMs` setting, and new ones are
+//! refused while too many are in flight. Partial results already streamed
+//! through `$/progress` are kept by the client.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tower::{Layer, Service};
+use tower_lsp::jsonrpc::{Error, ErrorCode, Id, Request, Response};
+
+/// Requests bounded by the timeout.
+const EXPENSIVE_METHODS: &[&str] = &[
+    "workspace/diagnostic",
+    "textDocument/references",
+    "flb/workspaceStats",
+];
+
+/// Requests of the same method handled at once, beyond which new ones are
+/// refused.
+const MAX_IN_FLIGHT: usize = 2;
+
+pub(crate) const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `RequestFailed` of the LSP specification.
+const REQUEST_FAILED: i64 = -32803;
+
+pub struct RequestLimits {
+    /// `0` means no timeout.
+    timeout_ms: AtomicU64,
+    in_flight: Mutex<HashMap<String, usize>>,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            timeout_ms: AtomicU64::new(DEFAULT_REQUEST_TIMEOUT.as_millis() as u64),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl RequestLimits {
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        let timeout_ms = timeout.map_or(0, |timeout| timeout.as_millis() as u64);
+        self.timeout_ms.store(timeout_ms, Ordering::Relaxed);
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        match self.timeout_ms.load(Ordering::Relaxed) {
+            0 => None,
+            timeout_ms => Some(Duration::from_millis(timeout_ms)),
+        }
+    }
+}
+
+/// Slot of a request in flight, released when it's dropped, even if the
+/// request is cancelled.
+struct InFlight {
+    limits: Arc<RequestLimits>,
+    method: String,
+}
+
+impl InFlight {
+    fn acquire(limits: &Arc<RequestLimits>, method: &str) -> Option<Self> {
+        let mut in_flight = limits.in_flight.lock().unwrap();
+        let count = in_flight.entry(method.to_string()).or_default();
+        if *count >= MAX_IN_FLIGHT {
+            return None;
+        }
+        *count += 1;
+
+        Some(Self {
+            limits: limits.clone(),
+            method: method.to_string(),
+        })
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Some(count) = self.limits.in_flight.lock().unwrap().get_mut(&self.method) {
+            *count -= 1;
+        }
+    }
+}
+
+fn request_failed(id: Option<Id>, message: String) -> Option<Response> {
+    let error = Error {
+        code: ErrorCode::ServerError(REQUEST_FAILED),
+        message: message.into(),
+        data: None,
+    };
+    id.map(|id| Response::from_error(id, error))
+}
+
+/// Bounds the expensive requests handled by the wrapped service.
+pub struct TimeoutLayer {
+    limits: Arc<RequestLimits>,
+}
+
+impl TimeoutLayer {
+    pub fn new(limits: Arc<RequestLimits>) -> Self {
+        Self { limits }
+    }
+}
+
+impl<S> Layer<S> for TimeoutLayer {
+    type Service = TimeoutService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimeoutService {
+            inner,
+            limits: self.limits.clone(),
+        }
+    }
+}
+
+pub struct TimeoutService<S> {
+    inner: S,
+    limits: Arc<RequestLimits>,
+}
+
+impl<S> Service<Request> for TimeoutService<S>
+where
+    S: Service<Request, Response = Option<Response>>,
+    S::Error: Send,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let method = req.method().to_string();
+        if !EXPENSIVE_METHODS.contains(&method.as_str()) {
+            return Box::pin(self.inner.call(req));
+        }
+
+        let id = req.id().cloned();
+        let Some(in_flight) = InFlight::acquire(&self.limits, &method) else {
+            let response = request_failed(id, format!("{method} refused: server busy"));
+            return Box::pin(async move { Ok(response) });
+        };
+        let timeout = self.limits.timeout();
+        let future = self.inner.call(req);
+
+        Box::pin(async move {
+            let _in_flight = in_flight;
+            let Some(timeout) = timeout else {
+                return future.await;
+            };
+            match tokio::time::timeout(timeout, future).await {
+                Ok(response) => response,
+                Err(_) => Ok(request_failed(
+                    id,
+                    format!("{method} timed out after {}ms", timeout.as_millis()),
+                )),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    /// Responds to requests after `delay`.
+    struct Slow {
+        delay: Duration,
+    }
+
+    impl Service<Request> for Slow {
+        type Response = Option<Response>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Option<Response>, Infallible>> + Send>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Request) -> Self::Future {
+            let delay = self.delay;
+            Box::pin(async move {
+                tokio::time::sleep(delay).await;
+                Ok(req
+                    .id()
+                    .map(|id| Response::from_ok(id.clone(), serde_json::Value::Null)))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn expensive_requests_time_out_and_are_shed() {
+        let limits = Arc::new(RequestLimits::default());
+        limits.set_timeout(Some(Duration::from_millis(20)));
+        let mut service = TimeoutLayer::new(limits.clone()).layer(Slow {
+            delay: Duration::from_millis(200),
+        });
+        let request = |method: &'static str, id: i64| Request::build(method).id(id).finish();
+        let is_failed = |response: &Option<Response>| {
+            response
+                .as_ref()
+                .and_then(|response| response.error().cloned())
+                .is_some_and(|error| error.code == ErrorCode::ServerError(REQUEST_FAILED))
+        };
+
+        let response = service
+            .call(request("workspace/diagnostic", 1))
+            .await
+            .unwrap();
+        assert!(is_failed(&response));
+        // other requests aren't bounded
+        let response = service
+            .call(request("textDocument/hover", 2))
+            .await
+            .unwrap();
+        assert!(response.is_some_and(|response| response.is_ok()));
+
+        limits.set_timeout(None);
+        let in_flight: Vec<_> = (0..MAX_IN_FLIGHT as i64)
+            .map(|id| service.call(request("workspace/diagnostic", id)))
+            .collect();
+        let refused = service
+            .call(request("workspace/diagnostic", 9))
+            .await
+            .unwrap();
+        assert!(is_failed(&refused));
+        for future in in_flight {
+            assert!(future
+                .await
+                .unwrap()
+                .is_some_and(|response| response.is_ok()));
+        }
+        // slots are released once the requests completed
+        assert_eq!(limits.in_flight.lock().unwrap()["workspace/diagnostic"], 0);
+    }
+}
@@ -0,0 +1,260 @@
+//! Transports the server talks LSP over: stdio, the first TCP connection to an
+//! address, e.g. for remote or containerized editors, or the first WebSocket
+//! connection, e.g. for editors running in a browser.
+//!
+//! WebSocket connections are bridged to the byte streams the server reads and
+//! writes: each text or binary message of the client is an LSP message without
+//! its `Content-Length` header, and the messages of the server are sent as
+//! text messages, like `vscode-ws-jsonrpc` does.
+//!
+//! As any page the browser opens could connect to a local WebSocket server,
+//! handshakes from another origin than the server's are rejected unless it's
+//! allowed by `--allow-origin`. Clients which aren't browsers send no origin.
+
+use std::io;
+
+use futures::{SinkExt, StreamExt};
+use tokio::{
+    io::{
+        AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+        BufReader,
+    },
+    net::{TcpListener, TcpStream},
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::{header, StatusCode},
+    protocol::WebSocketConfig,
+    Message,
+};
+
+/// Larger messages close the connection, not to allocate them.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+/// Size of the pipe between the bridge and the server.
+const PIPE_SIZE: usize = 64 * 1024;
+
+pub(crate) type Input = Box<dyn AsyncRead + Send + Unpin>;
+pub(crate) type Output = Box<dyn AsyncWrite + Send + Unpin>;
+
+pub(crate) enum Transport {
+    Stdio,
+    /// Address to listen on, e.g. `127.0.0.1:9257`
+    Tcp(String),
+    WebSocket {
+        addr: String,
+        /// Origins of the browsers' handshakes accepted besides the server's,
+        /// e.g. `https://vscode.dev`
+        allowed_origins: Vec<String>,
+    },
+}
+
+impl Transport {
+    /// Streams the server reads the messages of the client from and writes
+    /// its messages to, once a client connected.
+    pub(crate) async fn connect(self) -> io::Result<(Input, Output)> {
+        match self {
+            Transport::Stdio => Ok((Box::new(tokio::io::stdin()), Box::new(tokio::io::stdout()))),
+            Transport::Tcp(addr) => {
+                let (read, write) = tokio::io::split(accept(&addr).await?);
+                Ok((Box::new(read), Box::new(write)))
+            }
+            Transport::WebSocket {
+                addr,
+                allowed_origins,
+            } => bridge_websocket(accept(&addr).await?, &allowed_origins).await,
+        }
+    }
+}
+
+/// First connection to the address.
+async fn accept(addr: &str) -> io::Result<TcpStream> {
+    let context =
+        |e: io::Error| io::Error::new(e.kind(), format!("failed to listen on {addr}: {e}"));
+    let listener = TcpListener::bind(addr).await.map_err(context)?;
+    let (stream, _) = listener.accept().await.map_err(context)?;
+    Ok(stream)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Whether the handshake comes from a client which isn't a browser, from the
+/// origin of the server, or from one of the allowed origins.
+fn is_allowed_origin(request: &Request, allowed_origins: &[String]) -> bool {
+    let header = |name| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    let Some(origin) = header(header::ORIGIN) else {
+        return true;
+    };
+
+    let authority = origin
+        .split_once("://")
+        .map_or(origin, |(_, authority)| authority);
+    header(header::HOST).is_some_and(|host| host.eq_ignore_ascii_case(authority))
+        || allowed_origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+/// Reads a message the server wrote, without its headers, `None` once the
+/// server closed its output.
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let len = content_length.ok_or_else(|| invalid_data("message without Content-Length"))?;
+    let mut content = vec![0; len];
+    reader.read_exact(&mut content).await?;
+    Ok(Some(content))
+}
+
+/// Accepts the WebSocket handshake of the client, and bridges its messages to
+/// the streams returned.
+async fn bridge_websocket<S>(stream: S, allowed_origins: &[String]) -> io::Result<(Input, Output)>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let check_origin = |request: &Request, response: Response| {
+        if is_allowed_origin(request, allowed_origins) {
+            return Ok(response);
+        }
+        let mut response = ErrorResponse::new(Some("origin not allowed".to_string()));
+        *response.status_mut() = StatusCode::FORBIDDEN;
+        Err(response)
+    };
+    let config = WebSocketConfig {
+        max_message_size: Some(MAX_MESSAGE_SIZE),
+        ..WebSocketConfig::default()
+    };
+    let socket =
+        tokio_tungstenite::accept_hdr_async_with_config(stream, check_origin, Some(config))
+            .await
+            .map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("WebSocket handshake failed: {e}"),
+                )
+            })?;
+
+    let (server, bridge) = tokio::io::duplex(PIPE_SIZE);
+    let (mut sink, mut messages) = socket.split();
+    let (bridge_read, mut bridge_write) = tokio::io::split(bridge);
+
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(bridge_read);
+        while let Ok(Some(message)) = read_message(&mut reader).await {
+            let message = String::from_utf8_lossy(&message).into_owned();
+            if sink.send(Message::Text(message)).await.is_err() {
+                return;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    tokio::spawn(async move {
+        // pings are answered by the socket
+        while let Some(Ok(message)) = messages.next().await {
+            let message = match message {
+                Message::Text(text) => text.into_bytes(),
+                Message::Binary(bytes) => bytes,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            let header = format!("Content-Length: {}\r\n\r\n", message.len());
+            if bridge_write.write_all(header.as_bytes()).await.is_err()
+                || bridge_write.write_all(&message).await.is_err()
+            {
+                break;
+            }
+        }
+        // the server exits once its input ends
+        let _ = bridge_write.shutdown().await;
+    });
+
+    let (read, write) = tokio::io::split(server);
+    Ok((Box::new(read), Box::new(write)))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn websocket_messages_are_bridged() {
+        let (client, server) = tokio::io::duplex(PIPE_SIZE);
+        let bridged = tokio::spawn(bridge_websocket(server, &[]));
+        let (mut client, _) = tokio_tungstenite::client_async("ws://localhost:9257/", client)
+            .await
+            .unwrap();
+        let (mut input, mut output) = bridged.await.unwrap().unwrap();
+
+        client.send(Message::Text("{} ".to_string())).await.unwrap();
+        let mut message = vec![0; "Content-Length: 3\r\n\r\n{} ".len()];
+        input.read_exact(&mut message).await.unwrap();
+        assert_eq!(message, b"Content-Length: 3\r\n\r\n{} ");
+
+        output
+            .write_all(b"Content-Length: 2\r\n\r\n[]")
+            .await
+            .unwrap();
+        output.flush().await.unwrap();
+        assert_eq!(
+            client.next().await.unwrap().unwrap(),
+            Message::Text("[]".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn cross_origin_handshakes_are_rejected() {
+        let handshake = |origin: &str, allowed_origins: Vec<String>| {
+            let mut request = "ws://localhost:9257/".into_client_request().unwrap();
+            request
+                .headers_mut()
+                .insert(header::ORIGIN, origin.parse().unwrap());
+            async move {
+                let (client, server) = tokio::io::duplex(PIPE_SIZE);
+                let bridged =
+                    tokio::spawn(async move { bridge_websocket(server, &allowed_origins).await });
+                let connected = tokio_tungstenite::client_async(request, client).await;
+                (connected.is_ok(), bridged.await.unwrap().is_ok())
+            }
+        };
+
+        assert_eq!(
+            handshake("http://localhost:9257", vec![]).await,
+            (true, true)
+        );
+        assert_eq!(
+            handshake("https://example.com", vec![]).await,
+            (false, false)
+        );
+        assert_eq!(
+            handshake("https://example.com", vec![
+                "https://example.com/".to_string()
+            ])
+            .await,
+            (true, true)
+        );
+    }
+}
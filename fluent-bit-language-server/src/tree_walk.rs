@@ -0,0 +1,114 @@
+//! Pre-order traversal of syntax trees, for the analyses looking at every node
+//! of a document rather than at its indexed sections, e.g. its comments and
+//! its errors.
+
+use tree_sitter::{Node, TreeCursor};
+
+/// What to do after visiting a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Walk {
+    /// Visit its children too.
+    Continue,
+    /// Don't visit its children, e.g. not to report errors inside an error.
+    SkipChildren,
+}
+
+/// Moves the cursor to the node following its subtree, `false` if there's
+/// none in the node the cursor was created for.
+fn goto_next(cursor: &mut TreeCursor) -> bool {
+    while !cursor.goto_next_sibling() {
+        if !cursor.goto_parent() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Visits the node and its descendants in document order.
+pub(crate) fn walk<'tree>(node: Node<'tree>, mut visit: impl FnMut(Node<'tree>) -> Walk) {
+    let mut cursor = node.walk();
+    loop {
+        let walk = visit(cursor.node());
+        if walk == Walk::Continue && cursor.goto_first_child() {
+            continue;
+        }
+        if !goto_next(&mut cursor) {
+            return;
+        }
+    }
+}
+
+/// The node and its descendants in document order.
+pub(crate) struct Descendants<'tree> {
+    cursor: TreeCursor<'tree>,
+    done: bool,
+}
+
+pub(crate) fn descendants(node: Node<'_>) -> Descendants<'_> {
+    Descendants {
+        cursor: node.walk(),
+        done: false,
+    }
+}
+
+impl<'tree> Iterator for Descendants<'tree> {
+    type Item = Node<'tree>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let node = self.cursor.node();
+        if !self.cursor.goto_first_child() {
+            self.done = !goto_next(&mut self.cursor);
+        }
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::TextDocument;
+
+    #[test]
+    fn nodes_in_document_order() {
+        let document =
+            TextDocument::new("[INPUT]\n    Name cpu\n\n# outputs\n[OUTPUT]\n    Name stdout\n");
+        let root = document.tree.as_ref().unwrap().root_node();
+
+        let kinds: Vec<_> = descendants(root).map(|node| node.kind()).collect();
+        assert_eq!(kinds.first(), Some(&"config"));
+        assert_eq!(kinds.iter().filter(|kind| **kind == "section").count(), 2);
+        let comment = kinds.iter().position(|kind| *kind == "comment").unwrap();
+        let sections: Vec<_> = kinds
+            .iter()
+            .enumerate()
+            .filter(|(_, kind)| **kind == "section")
+            .map(|(i, _)| i)
+            .collect();
+        assert!(sections[0] < comment && comment < sections[1]);
+
+        // the same nodes are visited, unless their children are skipped
+        let mut visited = Vec::new();
+        walk(root, |node| {
+            visited.push(node.kind());
+            Walk::Continue
+        });
+        assert_eq!(visited, kinds);
+        let mut visited = Vec::new();
+        walk(root, |node| {
+            visited.push(node.kind());
+            match node.kind() {
+                "section" => Walk::SkipChildren,
+                _ => Walk::Continue,
+            }
+        });
+        assert_eq!(visited, ["config", "section", "comment", "section"]);
+
+        // a subtree doesn't go past its node
+        let section = root.named_child(0).unwrap();
+        assert!(descendants(section).all(|node| node.end_byte() <= section.end_byte()));
+    }
+}
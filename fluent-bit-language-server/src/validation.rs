@@ -0,0 +1,598 @@
+//! Validation of the values of entries against what fluent-bit accepts for
+//! their key.
+
+use flb_schema::{config::FlbPropertyType, section::FlbSectionType};
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range};
+
+use crate::{
+    completion::{FlbConfigParameterInfo, FlbData},
+    i18n::t,
+    overlay::{integer_bounds, is_repeatable, value_type},
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    regexes::match_regex_notes,
+    rules::is_filesystem_buffered,
+    section_index::{IndexedSection, SectionIndex},
+    values::{
+        group_thousands, locale_hint, parse_bool, parse_double, parse_integer, parse_size,
+        parse_time, BOOLEAN_FORMS,
+    },
+};
+
+/// Diagnostic code of a value fluent-bit doesn't accept for its key.
+pub(crate) const INVALID_VALUE: &str = "invalid-value";
+
+/// Diagnostic code of a `Tag` with characters tags can't contain.
+pub(crate) const INVALID_TAG: &str = "invalid-tag";
+
+/// Messages of the problems of a section listed in the hover of its `Name`.
+const MAX_LISTED_PROBLEMS: usize = 3;
+
+/// Whether the parameter is a boolean. Without a type, which data generated
+/// from older schemas lacks, a boolean default value tells.
+pub(crate) fn is_boolean(info: &FlbConfigParameterInfo) -> bool {
+    match &info.type_ {
+        Some(type_) => *type_ == FlbPropertyType::Boolean,
+        None => info
+            .default_value
+            .as_deref()
+            .is_some_and(|value| parse_bool(value).is_some()),
+    }
+}
+
+fn boolean_forms() -> String {
+    BOOLEAN_FORMS
+        .iter()
+        .map(|(form, _)| format!("`{form}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Values referencing environment variables are only known at runtime.
+pub(crate) fn is_env_reference(value: &str) -> bool {
+    value.contains("${")
+}
+
+/// Checks a parser's `Time_Offset`, in the format `+HHMM`, `-HH:MM` or `Z`.
+///
+/// ref: `flb_parser_tzone_offset()` in fluent-bit
+fn check_time_offset(value: &str) -> Result<(), String> {
+    if value == "Z" {
+        return Ok(());
+    }
+
+    let invalid = || t!("not-a-time-offset", value = value);
+    let digits = value
+        .strip_prefix(['+', '-'])
+        .ok_or_else(invalid)?
+        .replacen(':', "", 1);
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    if digits[2..].parse::<u8>().is_ok_and(|minutes| minutes >= 60) {
+        return Err(t!("time-offset-minutes", value = value));
+    }
+
+    Ok(())
+}
+
+/// Type of the parameter: the schema's or, without one, which data generated
+/// from older schemas lacks, the curated one, an integer if its bounds are
+/// known or a boolean if its default value is one.
+pub(crate) fn parameter_type(
+    info: Option<&FlbConfigParameterInfo>,
+    section_type: &FlbSectionType,
+    plugin_name: Option<&str>,
+    key: &str,
+) -> Option<FlbPropertyType> {
+    info.and_then(|info| info.type_.clone())
+        .or_else(|| value_type(section_type, plugin_name, key).cloned())
+        .or_else(|| {
+            integer_bounds(section_type, plugin_name, key).map(|_| FlbPropertyType::Integer)
+        })
+        .or_else(|| {
+            info.is_some_and(is_boolean)
+                .then_some(FlbPropertyType::Boolean)
+        })
+}
+
+/// Checks that `value` is of the type, as fluent-bit parses it.
+fn check_type(type_: &FlbPropertyType, value: &str) -> Result<(), String> {
+    let valid = match type_ {
+        FlbPropertyType::Boolean => parse_bool(value).is_some(),
+        FlbPropertyType::Integer => parse_integer(value).is_some(),
+        FlbPropertyType::Double => parse_double(value).is_some(),
+        FlbPropertyType::Size => parse_size(value).is_some(),
+        FlbPropertyType::Time => parse_time(value).is_some(),
+        _ => true,
+    };
+    if valid {
+        return Ok(());
+    }
+
+    Err(match (type_, locale_hint(value)) {
+        (FlbPropertyType::Integer, Some(hint)) => {
+            t!("not-an-integer-hint", value = value, hint = hint)
+        }
+        (FlbPropertyType::Integer, None) => t!("not-an-integer", value = value),
+        (FlbPropertyType::Double, Some(hint)) => {
+            t!("not-a-number-hint", value = value, hint = hint)
+        }
+        (FlbPropertyType::Double, None) => t!("not-a-number", value = value),
+        (FlbPropertyType::Size, _) => t!("not-a-size", value = value),
+        (FlbPropertyType::Time, _) => t!("not-a-time", value = value),
+        _ => t!("not-a-boolean", value = value, forms = boolean_forms()),
+    })
+}
+
+/// Checks the `Tag` of an input: letters, digits, `.`, `_` and `-`, `*` only
+/// expanded by `tail` and `<group>` only by its `Tag_Regex`.
+fn check_tag(section: &IndexedSection, tag: &str) -> Result<(), String> {
+    let plugin_name = section.get_value("Name").unwrap_or_default();
+    let tag_regex =
+        plugin_name.eq_ignore_ascii_case("tail") && section.get_value("Tag_Regex").is_some();
+
+    let invalid = tag.chars().find(|c| match c {
+        '*' => !plugin_name.eq_ignore_ascii_case("tail"),
+        '<' | '>' => !tag_regex,
+        '.' | '_' | '-' => false,
+        c => !c.is_ascii_alphanumeric(),
+    });
+    match invalid {
+        None => Ok(()),
+        Some('*') => Err(t!("tag-wildcard", tag = tag)),
+        Some(c) if c.is_whitespace() => Err(t!("tag-whitespace", tag = tag)),
+        Some(c) => Err(t!("tag-character", tag = tag, character = c)),
+    }
+}
+
+fn check_value(
+    data: &FlbData,
+    section: &IndexedSection,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    let plugin_name = section.get_value("Name");
+    if let Some(bounds) = integer_bounds(&section.section_type, plugin_name, key) {
+        return bounds.check(value);
+    }
+    if section.section_type == FlbSectionType::Parser && key.eq_ignore_ascii_case("Time_Offset") {
+        check_time_offset(value)?;
+    }
+
+    let info = data.get_hover_info(&section.section_type, key);
+    match parameter_type(info.as_ref(), &section.section_type, plugin_name, key) {
+        Some(type_) => check_type(&type_, value),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn check_values(
+    data: &FlbData,
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for section in index.sections() {
+        for entry in &section.entries {
+            let (Some(value), Some(value_range)) = (&entry.value, &entry.value_range) else {
+                continue;
+            };
+            if is_env_reference(value) {
+                continue;
+            }
+
+            if let Err(reason) = check_value(data, section, &entry.key, value) {
+                diagnostics.push(Diagnostic {
+                    range: byte_range_to_lsp_range(
+                        rope,
+                        value_range.start..value_range.start + value.len(),
+                        position_encoding,
+                    ),
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    code: Some(NumberOrString::String(INVALID_VALUE.to_string())),
+                    message: t!(INVALID_VALUE, key = entry.key, reason = reason),
+                    ..Diagnostic::default()
+                });
+            }
+
+            if section.section_type == FlbSectionType::Input
+                && entry.key.eq_ignore_ascii_case("Tag")
+            {
+                if let Err(reason) = check_tag(section, value) {
+                    diagnostics.push(Diagnostic {
+                        range: byte_range_to_lsp_range(
+                            rope,
+                            value_range.start..value_range.start + value.len(),
+                            position_encoding,
+                        ),
+                        severity: Some(DiagnosticSeverity::WARNING),
+                        code: Some(NumberOrString::String(INVALID_TAG.to_string())),
+                        message: t!(INVALID_TAG, reason = reason),
+                        ..Diagnostic::default()
+                    });
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Paragraphs about the values `key` accepts, shown in its hover. `section` is
+/// the one containing the hovered entry.
+pub(crate) fn hover_notes(
+    section_type: &FlbSectionType,
+    section: Option<&IndexedSection>,
+    key: &str,
+    info: Option<&FlbConfigParameterInfo>,
+) -> Vec<String> {
+    let mut notes = Vec::new();
+
+    let plugin_name = section.and_then(|section| section.get_value("Name"));
+    if let Some(bounds) = integer_bounds(section_type, plugin_name, key) {
+        notes.push(t!("note-valid-values", bounds = format!("{bounds:#}")));
+    }
+
+    if is_repeatable(section_type, plugin_name, key) {
+        notes.push(t!("note-repeatable"));
+    }
+
+    let value = section
+        .and_then(|section| section.get_value(key))
+        .filter(|value| !is_env_reference(value));
+    match (info.and_then(|info| info.type_.as_ref()), value) {
+        (Some(FlbPropertyType::Size), Some(value)) => notes.extend(
+            parse_size(value).map(|n| t!("note-size", value = value, bytes = group_thousands(n))),
+        ),
+        (Some(FlbPropertyType::Time), Some(value)) => notes.extend(
+            parse_time(value).map(|n| t!("note-time", value = value, seconds = group_thousands(n))),
+        ),
+        _ => {}
+    }
+
+    if info.is_some_and(is_boolean) {
+        notes.push(t!("note-boolean-forms", forms = boolean_forms()));
+        if let Some(value) = value {
+            notes.push(match parse_bool(value) {
+                Some(b) => t!("note-boolean-value", value = value, boolean = b),
+                None => t!("note-invalid-boolean", value = value),
+            });
+        }
+    }
+
+    if *section_type == FlbSectionType::Parser && key.eq_ignore_ascii_case("Time_Offset") {
+        notes.extend(time_offset_notes(section));
+    }
+
+    if matches!(
+        section_type,
+        FlbSectionType::Filter | FlbSectionType::Output
+    ) && key.eq_ignore_ascii_case("Match_Regex")
+    {
+        notes.extend(match_regex_notes(value));
+    }
+
+    if *section_type == FlbSectionType::Input && key.eq_ignore_ascii_case("Mem_Buf_Limit") {
+        notes.push(t!("note-mem-buf-limit"));
+        if section.is_some_and(is_filesystem_buffered) {
+            notes.push(t!("note-mem-buf-limit-filesystem"));
+        }
+    }
+
+    if *section_type == FlbSectionType::Output && key.eq_ignore_ascii_case("Retry_Limit") {
+        notes.extend(retry_limit_notes(value));
+    }
+
+    notes
+}
+
+/// Paragraph shown in the hover of the `Name` of a section, counting the
+/// `diagnostics` starting in `range`, the one of the section, and listing the
+/// first ones.
+pub(crate) fn problems_note(diagnostics: &[Diagnostic], range: Range) -> Option<String> {
+    let mut problems: Vec<_> = diagnostics
+        .iter()
+        .filter(|diagnostic| range.start <= diagnostic.range.start)
+        .filter(|diagnostic| diagnostic.range.start <= range.end)
+        .collect();
+    if problems.is_empty() {
+        return None;
+    }
+    problems.sort_by_key(|diagnostic| diagnostic.range.start);
+
+    let mut note = match problems.len() {
+        1 => t!("note-problems-one"),
+        count => t!("note-problems", count = count),
+    };
+    for problem in problems.iter().take(MAX_LISTED_PROBLEMS) {
+        note.push_str(&format!("\n- {}", problem.message));
+    }
+    if problems.len() > MAX_LISTED_PROBLEMS {
+        note.push_str("\n- …");
+    }
+
+    Some(note)
+}
+
+/// How the scheduler retries the chunks of an output, and what `value` of its
+/// `Retry_Limit` means.
+fn retry_limit_notes(value: Option<&str>) -> Vec<String> {
+    let mut notes = vec![t!("note-retry-scheduler")];
+
+    let is = |keyword: &str| value.is_some_and(|value| value.eq_ignore_ascii_case(keyword));
+    match value {
+        Some(value) if is("False") || is("no_limits") => {
+            notes.push(t!("note-retry-unlimited", value = value))
+        }
+        Some(value) if is("no_retries") => notes.push(t!("note-retry-none", value = value)),
+        Some(value) if parse_integer(value).is_some_and(|n| n >= 1) => {
+            notes.push(t!("note-retry-count", value = value))
+        }
+        _ => {}
+    }
+
+    notes
+}
+
+/// How a parser's `Time_Offset` interacts with its `Time_Key` and `Time_Keep`.
+fn time_offset_notes(section: Option<&IndexedSection>) -> Vec<String> {
+    let value = |key| section.and_then(|section| section.get_value(key));
+    let time_key = value("Time_Key").unwrap_or("time");
+    let time_keep = value("Time_Keep").and_then(parse_bool).unwrap_or(false);
+
+    vec![
+        t!("note-time-offset"),
+        t!("note-time-offset-key", time_key = time_key),
+        if time_keep {
+            t!("note-time-keep-on", time_key = time_key)
+        } else {
+            t!("note-time-keep-off", time_key = time_key)
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+    use crate::{completion::FLB_DATA, document::TextDocument};
+
+    #[test]
+    fn integer_values_out_of_bounds() {
+        let document = TextDocument::new(
+            "[SERVICE]\n    HTTP_Port 0\n\n\
+             [INPUT]\n    Name forward\n    Port ${FORWARD_PORT}\n\n\
+             [OUTPUT]\n    Name        forward\n    Port        70000  \n    Retry_Limit no_limits\n\n\
+             [OUTPUT]\n    Name        http\n    Port        65,535\n",
+        );
+
+        let diagnostics = check_values(
+            &FLB_DATA,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Invalid `HTTP_Port`: 0 is out of range, expected 1 to 65535.",
+            "Invalid `Port`: 70000 is out of range, expected 1 to 65535.",
+            "Invalid `Port`: `65,535` is not an integer, digits can't be grouped.",
+        ]);
+        assert_eq!(
+            diagnostics[1].range,
+            Range::new(Position::new(9, 16), Position::new(9, 21))
+        );
+
+        let section = &document.index.sections()[2];
+        assert_eq!(
+            hover_notes(&FlbSectionType::Output, Some(section), "Port", None),
+            ["Valid values: 1 to 65,535"]
+        );
+
+        let document = TextDocument::new("[INPUT]\n    Name tail\n    Buffer_Max_Size 1.5M\n");
+        let info = FlbConfigParameterInfo {
+            default_value: Some("32k".to_string()),
+            description: String::new(),
+            since: None,
+            type_: Some(FlbPropertyType::Size),
+        };
+        assert_eq!(
+            hover_notes(
+                &FlbSectionType::Input,
+                document.index.sections().first(),
+                "buffer_max_size",
+                Some(&info)
+            ),
+            ["`1.5M` is 1,500,000 bytes"]
+        );
+    }
+
+    #[test]
+    fn tags_of_inputs() {
+        let document = TextDocument::new(
+            "[INPUT]\n    Name cpu\n    Tag  cpu/host.*\n\n\
+             [INPUT]\n    Name tail\n    Tag  kube.<pod_name>\n\n\
+             [INPUT]\n    Name      tail\n    Tag       kube.<pod_name>.*\n    Tag_Regex (?<pod_name>[a-z0-9-]+)\n\n\
+             [INPUT]\n    Name mem\n    Tag  ${HOSTNAME}.mem\n",
+        );
+
+        let diagnostics = check_values(
+            &FLB_DATA,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Invalid `Tag`: `cpu/host.*` contains `/`, tags are made of letters, digits, `.`, `_` and `-`.",
+            "Invalid `Tag`: `kube.<pod_name>` contains `<`, tags are made of letters, digits, `.`, `_` and `-`.",
+        ]);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+
+        let section = &document.index.sections()[0];
+        assert_eq!(
+            check_tag(section, "cpu.*"),
+            Err("`*` in `cpu.*` is only expanded by `tail`, to the path of each file".to_string())
+        );
+    }
+
+    #[test]
+    fn boolean_values() {
+        let document = TextDocument::new(
+            "[SERVICE]\n    Daemon          Yes\n    dns.prefer_ipv4 maybe\n    HTTP_Server     ${HTTP}\n",
+        );
+
+        let diagnostics = check_values(
+            &FLB_DATA,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Invalid `dns.prefer_ipv4`: `maybe` is not a boolean, expected one of `on`, `off`, `true`, `false`, `yes`, `no`."
+        ]);
+
+        let section = &document.index.sections()[0];
+        let info = FLB_DATA.get_hover_info(&FlbSectionType::Service, "Daemon");
+        assert_eq!(
+            hover_notes(
+                &FlbSectionType::Service,
+                Some(section),
+                "Daemon",
+                info.as_ref()
+            ),
+            [
+                "Accepted values (case insensitive): `on`, `off`, `true`, `false`, `yes`, `no`",
+                "`Yes` is parsed as `true`",
+            ]
+        );
+    }
+
+    #[test]
+    fn values_of_the_wrong_type() {
+        let document = TextDocument::new(
+            "[SERVICE]\n    Flush 1,5\n\n\
+             [INPUT]\n    Name          tail\n    Mem_Buf_Limit notasize\n    Buffer_Max_Size 1.5M\n\n\
+             [OUTPUT]\n    Name    stdout\n    Workers abc\n",
+        );
+
+        let diagnostics = check_values(
+            &FLB_DATA,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Invalid `Flush`: `1,5` is not a number, the decimal separator is `.`.",
+            "Invalid `Mem_Buf_Limit`: `notasize` is not a size, expected bytes with an optional `K`, `M` or `G` unit, e.g. `5M`.",
+            "Invalid `Workers`: `abc` is not an integer, expected 0 or more.",
+        ]);
+
+        assert_eq!(check_type(&FlbPropertyType::Time, "5m"), Ok(()));
+        assert_eq!(
+            check_type(&FlbPropertyType::Time, "5 minutes"),
+            Err("`5 minutes` is not a time, expected seconds with an optional `m`, `h` or `d` unit, e.g. `30m`".to_string())
+        );
+        assert_eq!(
+            check_type(&FlbPropertyType::Integer, "1,000"),
+            Err("`1,000` is not an integer, digits can't be grouped".to_string())
+        );
+    }
+
+    #[test]
+    fn parser_time_offset() {
+        let document = TextDocument::new(
+            "[PARSER]\n    Name        a\n    Time_Offset +0900\n\n\
+             [PARSER]\n    Name        b\n    Time_Offset -07:30\n\n\
+             [PARSER]\n    Name        c\n    Time_Offset 0900\n\n\
+             [PARSER]\n    Name        d\n    Time_Offset +0975\n    Time_Key    ts\n    Time_Keep   on\n",
+        );
+
+        let diagnostics = check_values(
+            &FLB_DATA,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Invalid `Time_Offset`: `0900` is not a time zone offset, expected `+HHMM`, `-HHMM`, `+HH:MM` or `Z`.",
+            "Invalid `Time_Offset`: `+0975` has more than 59 minutes, expected `+HHMM`, `-HHMM`, `+HH:MM` or `Z`.",
+        ]);
+
+        let section = &document.index.sections()[3];
+        let notes = hover_notes(&FlbSectionType::Parser, Some(section), "time_offset", None);
+        assert_eq!(notes[1..], [
+            "The offset is applied to the time read from the `ts` field (`Time_Key`) to set the timestamp of the record.",
+            "`Time_Keep` is on: the `ts` field is kept as is, without the offset.",
+        ]);
+    }
+
+    #[test]
+    fn retry_limit_semantics() {
+        let document = TextDocument::new(
+            "[OUTPUT]\n    Name        stdout\n    Retry_Limit 0\n\n\
+             [OUTPUT]\n    Name        stdout\n    Retry_Limit no_limits\n\n\
+             [OUTPUT]\n    Name        stdout\n    Retry_Limit 3\n",
+        );
+
+        let diagnostics = check_values(
+            &FLB_DATA,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Invalid `Retry_Limit`: 0 is out of range, expected 1 or more, `False`, `no_limits`, `no_retries`.",
+        ]);
+
+        let notes = |i: usize| {
+            let section = &document.index.sections()[i];
+            hover_notes(&FlbSectionType::Output, Some(section), "retry_limit", None)
+        };
+        assert_eq!(notes(0).len(), 2);
+        assert!(notes(0)[1].starts_with("When an output fails to flush a chunk"));
+        assert!(notes(1)
+            .last()
+            .unwrap()
+            .starts_with("`no_limits`: chunks are retried until"));
+        assert_eq!(
+            notes(2).last().unwrap(),
+            "`3`: a chunk is retried up to 3 times, then discarded."
+        );
+    }
+
+    #[test]
+    fn problems_of_sections() {
+        let diagnostic = |line, message: &str| Diagnostic {
+            range: Range::new(Position::new(line, 4), Position::new(line, 8)),
+            message: message.to_string(),
+            ..Diagnostic::default()
+        };
+        let diagnostics = [
+            diagnostic(9, "elsewhere"),
+            diagnostic(3, "second"),
+            diagnostic(1, "first"),
+            diagnostic(4, "third"),
+            diagnostic(5, "fourth"),
+        ];
+        let section = |start, end| Range::new(Position::new(start, 0), Position::new(end, 0));
+
+        assert_eq!(
+            problems_note(&diagnostics, section(0, 6)).unwrap(),
+            "4 problems in this section:\n- first\n- second\n- third\n- …"
+        );
+        assert_eq!(
+            problems_note(&diagnostics, section(8, 10)).unwrap(),
+            "1 problem in this section:\n- elsewhere"
+        );
+        assert_eq!(problems_note(&diagnostics, section(6, 8)), None);
+    }
+}
@@ -0,0 +1,243 @@
+//! Parsing of the values fluent-bit reads as booleans, numbers, sizes or times,
+//! and display of the numbers they stand for.
+//!
+//! fluent-bit parses numbers in the C locale whatever the locale of the host,
+//! so they're parsed the same way here: `.` is the only decimal separator and
+//! digits are never grouped. Numbers shown to users are grouped by thousands
+//! with `,`, e.g. `65,535`.
+
+use crate::i18n::t;
+
+/// Forms of booleans fluent-bit accepts (case insensitive).
+///
+/// ref: `flb_utils_bool()` in fluent-bit
+pub(crate) const BOOLEAN_FORMS: &[(&str, bool)] = &[
+    ("on", true),
+    ("off", false),
+    ("true", true),
+    ("false", false),
+    ("yes", true),
+    ("no", false),
+];
+
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    BOOLEAN_FORMS
+        .iter()
+        .find(|(form, _)| form.eq_ignore_ascii_case(value))
+        .map(|(_, b)| *b)
+}
+
+/// Multipliers of the units of sizes (case insensitive), in bytes.
+///
+/// ref: `flb_utils_size_to_bytes()` in fluent-bit
+const SIZE_UNITS: &[(&str, i64)] = &[
+    ("", 1),
+    ("K", 1_000),
+    ("KB", 1_000),
+    ("M", 1_000_000),
+    ("MB", 1_000_000),
+    ("G", 1_000_000_000),
+    ("GB", 1_000_000_000),
+];
+
+/// Multipliers of the units of times (case insensitive), in seconds.
+///
+/// ref: `flb_utils_time_to_seconds()` in fluent-bit
+const TIME_UNITS: &[(&str, i64)] = &[("", 1), ("S", 1), ("M", 60), ("H", 3_600), ("D", 86_400)];
+
+/// Whether `value` is only ASCII digits, optionally signed.
+fn is_integer(value: &str) -> bool {
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+pub(crate) fn parse_integer(value: &str) -> Option<i64> {
+    is_integer(value).then(|| value.parse().ok()).flatten()
+}
+
+/// Parses a floating point number as `strtod()` does in the C locale, e.g.
+/// `1.5`, `.5` or `2e3`, but not `1,5`.
+pub(crate) fn parse_double(value: &str) -> Option<f64> {
+    let unsigned = value.strip_prefix(['+', '-']).unwrap_or(value);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let valid = !(integer.is_empty() && fraction.is_empty())
+        && all_digits(integer)
+        && all_digits(fraction)
+        && exponent.map_or(true, is_integer);
+    valid.then(|| value.parse().ok()).flatten()
+}
+
+/// Splits `value` in a number and a unit among `units`.
+fn parse_with_unit(value: &str, units: &[(&str, i64)]) -> Option<f64> {
+    let split = value
+        .rfind(|c: char| c.is_ascii_digit() || c == '.')
+        .map_or(0, |i| i + 1);
+    let (number, unit) = value.split_at(split);
+    let (_, multiplier) = units
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(unit))?;
+
+    parse_double(number)
+        .map(|n| n * *multiplier as f64)
+        .filter(|n| n.abs() <= i64::MAX as f64)
+}
+
+/// Parses a size in bytes, e.g. `512`, `32k`, `1.5MB` or `false` (0).
+pub(crate) fn parse_size(value: &str) -> Option<i64> {
+    if value.eq_ignore_ascii_case("false") {
+        return Some(0);
+    }
+
+    parse_with_unit(value, SIZE_UNITS).map(|n| n as i64)
+}
+
+/// Parses a time in seconds, e.g. `30`, `5m` or `1d`.
+pub(crate) fn parse_time(value: &str) -> Option<i64> {
+    let split = value.len()
+        - value
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+            .len();
+    if !is_integer(&value[..value.len() - split]) {
+        return None;
+    }
+
+    parse_with_unit(value, TIME_UNITS).map(|n| n as i64)
+}
+
+/// Why `value` isn't a number, if it's written as in a locale fluent-bit
+/// doesn't use, e.g. `65,535` or `1,5`.
+pub(crate) fn locale_hint(value: &str) -> Option<String> {
+    if parse_double(value).is_some() {
+        return None;
+    }
+
+    // `1,500` is more likely grouped than a decimal
+    if let Some((integer, fraction)) = value.split_once(',') {
+        if fraction.len() != 3 && parse_double(&format!("{integer}.{fraction}")).is_some() {
+            return Some(t!("hint-decimal-separator"));
+        }
+    }
+    let separators = [',', '\'', '_', ' '];
+    (value.contains(separators) && parse_double(&value.replace(separators, "")).is_some())
+        .then(|| t!("hint-grouped-digits"))
+}
+
+/// Formats a size in bytes in the largest unit it reaches, e.g. `1.5M`.
+pub(crate) fn format_size(bytes: i64) -> String {
+    let (unit, multiplier) = [("G", 1_000_000_000), ("M", 1_000_000), ("K", 1_000)]
+        .into_iter()
+        .find(|(_, multiplier)| bytes.abs() >= *multiplier)
+        .unwrap_or(("", 1));
+    let n = format!("{:.1}", bytes as f64 / multiplier as f64);
+
+    format!("{}{unit}", n.trim_end_matches(".0"))
+}
+
+/// Formats `n` with its digits grouped by thousands, e.g. `-1,234,567`.
+pub(crate) fn group_thousands(n: i64) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut ret = String::from(if n < 0 { "-" } else { "" });
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            ret.push(',');
+        }
+        ret.push(digit);
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn booleans_and_numbers() {
+        assert_eq!(parse_bool("Yes"), Some(true));
+        assert_eq!(parse_bool("OFF"), Some(false));
+        assert_eq!(parse_bool("1"), None);
+
+        assert_eq!(parse_integer("42"), Some(42));
+        assert_eq!(parse_integer("+42"), Some(42));
+        assert_eq!(parse_integer("-7"), Some(-7));
+        for value in ["", "-", "4.2", "1,000", "1_000", "1 000", "0x10", "١٢"] {
+            assert_eq!(parse_integer(value), None, "{value}");
+        }
+        assert_eq!(parse_integer("99999999999999999999"), None);
+
+        assert_eq!(parse_double("1.5"), Some(1.5));
+        assert_eq!(parse_double(".5"), Some(0.5));
+        assert_eq!(parse_double("5."), Some(5.0));
+        assert_eq!(parse_double("-2e3"), Some(-2000.0));
+        assert_eq!(parse_double("2E-1"), Some(0.2));
+        for value in ["", ".", "1,5", "1.2.3", "inf", "NaN", "1e", "e5", "1_5"] {
+            assert_eq!(parse_double(value), None, "{value}");
+        }
+
+        assert_eq!(
+            locale_hint("65,535"),
+            Some("digits can't be grouped".to_string())
+        );
+        assert_eq!(
+            locale_hint("1.234,5"),
+            Some("digits can't be grouped".to_string())
+        );
+        assert_eq!(
+            locale_hint("1 000"),
+            Some("digits can't be grouped".to_string())
+        );
+        assert_eq!(
+            locale_hint("1,5"),
+            Some("the decimal separator is `.`".to_string())
+        );
+        assert_eq!(
+            locale_hint("0,25"),
+            Some("the decimal separator is `.`".to_string())
+        );
+        assert_eq!(locale_hint("1500"), None);
+        assert_eq!(locale_hint("a,b"), None);
+        assert_eq!(locale_hint("1, 2, x"), None);
+    }
+
+    #[test]
+    fn sizes_and_times() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("32k"), Some(32_000));
+        assert_eq!(parse_size("32KB"), Some(32_000));
+        assert_eq!(parse_size("1.5M"), Some(1_500_000));
+        assert_eq!(parse_size("2gb"), Some(2_000_000_000));
+        assert_eq!(parse_size("false"), Some(0));
+        for value in ["", "k", "1,5M", "10 MB", "5T", "5KiB", "5BK", "1e30G"] {
+            assert_eq!(parse_size(value), None, "{value}");
+        }
+
+        assert_eq!(parse_time("30"), Some(30));
+        assert_eq!(parse_time("30s"), Some(30));
+        assert_eq!(parse_time("5m"), Some(300));
+        assert_eq!(parse_time("2H"), Some(7_200));
+        assert_eq!(parse_time("1d"), Some(86_400));
+        for value in ["", "m", "1.5h", "1,5h", "10 s", "1w", "5ms"] {
+            assert_eq!(parse_time(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn numbers_grouped_by_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(1_000), "1,000");
+        assert_eq!(group_thousands(65_535), "65,535");
+        assert_eq!(group_thousands(-1_234_567), "-1,234,567");
+        assert_eq!(group_thousands(i64::MIN), "-9,223,372,036,854,775,808");
+
+        assert_eq!(format_size(512), "512");
+        assert_eq!(format_size(1_500_000), "1.5M");
+        assert_eq!(format_size(2_000_000_000), "2G");
+    }
+}
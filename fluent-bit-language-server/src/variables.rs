@@ -0,0 +1,166 @@
+//! Variables of a configuration, set by `@SET` directives (`env` in YAML) and
+//! referenced as `${NAME}` in values: their completion, their value in hovers
+//! and the references to variables no file of the configuration sets, which
+//! fluent-bit reads from its environment.
+
+use std::{collections::HashMap, ops::Range};
+
+use ropey::Rope;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, DiagnosticSeverity};
+
+use crate::{i18n::t, section_index::SectionIndex};
+
+/// Diagnostic code of a reference to a variable the configuration doesn't set.
+pub(crate) const UNDEFINED_VARIABLE: &str = "undefined-variable";
+
+/// Severity of the references to variables no file of the configuration sets,
+/// e.g. `error` where every variable must be known.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UndefinedVariableSeverity {
+    #[default]
+    Information,
+    Warning,
+    Error,
+}
+
+impl From<UndefinedVariableSeverity> for DiagnosticSeverity {
+    fn from(severity: UndefinedVariableSeverity) -> Self {
+        match severity {
+            UndefinedVariableSeverity::Information => DiagnosticSeverity::INFORMATION,
+            UndefinedVariableSeverity::Warning => DiagnosticSeverity::WARNING,
+            UndefinedVariableSeverity::Error => DiagnosticSeverity::ERROR,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct VariableReference {
+    pub(crate) name: String,
+    /// Of `${NAME}`, the braces included.
+    pub(crate) range: Range<usize>,
+}
+
+/// `${NAME}` references of the text, by byte range in it.
+fn references_in(text: &str) -> Vec<(Range<usize>, &str)> {
+    let mut references = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("${").map(|start| offset + start) {
+        let Some(len) = text[start + 2..].find('}') else {
+            break;
+        };
+        let end = start + 3 + len;
+        references.push((start..end, &text[start + 2..end - 1]));
+        offset = end;
+    }
+
+    references
+}
+
+/// References of the values of the entries.
+pub(crate) fn references(index: &SectionIndex) -> Vec<VariableReference> {
+    index
+        .sections()
+        .iter()
+        .flat_map(|section| &section.entries)
+        .filter_map(|entry| Some((entry.value.as_ref()?, entry.value_range.as_ref()?)))
+        .flat_map(|(value, value_range)| {
+            references_in(value)
+                .into_iter()
+                .map(|(range, name)| VariableReference {
+                    name: name.to_string(),
+                    range: value_range.start + range.start..value_range.start + range.end,
+                })
+        })
+        .collect()
+}
+
+/// Reference containing the byte offset, its end included.
+pub(crate) fn reference_at(index: &SectionIndex, byte: usize) -> Option<VariableReference> {
+    references(index)
+        .into_iter()
+        .find(|reference| reference.range.start <= byte && byte <= reference.range.end)
+}
+
+/// Whether a reference is being typed before the byte offset, i.e. `${` isn't
+/// closed on its line, and whether a `}` closes it already.
+pub(crate) fn typed_reference(rope: &Rope, byte: usize) -> Option<bool> {
+    let line = rope.try_byte_to_line(byte).ok()?;
+    let head = rope
+        .get_byte_slice(rope.line_to_byte(line)..byte)?
+        .to_string();
+    let (_, name) = head.rsplit_once("${")?;
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    Some(rope.get_byte_slice(byte..)?.chars().next() == Some('}'))
+}
+
+/// Completions of the variables the document sets, closing the reference
+/// unless it's closed already.
+pub(crate) fn completion_items(
+    variables: &HashMap<String, String>,
+    closed: bool,
+) -> Vec<CompletionItem> {
+    let mut items: Vec<CompletionItem> = variables
+        .iter()
+        .map(|(name, value)| CompletionItem {
+            label: name.clone(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some(value.clone()),
+            insert_text: (!closed).then(|| format!("{name}}}")),
+            ..CompletionItem::default()
+        })
+        .collect();
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+
+    items
+}
+
+/// Hover of a reference: the value the document sets, or where fluent-bit
+/// reads it from otherwise.
+pub(crate) fn hover(reference: &VariableReference, variables: &HashMap<String, String>) -> String {
+    let text = format!("${{{}}}", reference.name);
+    match variables.get(&reference.name) {
+        Some(value) => t!("note-variable", reference = text, value = value),
+        None => t!("note-environment-variable", reference = text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{document::TextDocument, effective};
+
+    #[test]
+    fn references_and_completions() {
+        let text = "@SET level=debug\n\n[INPUT]\n    Name      cpu\n    Tag       ${HOST}.${level}\n    Log_Level ${le\n";
+        let document = TextDocument::new(text);
+        let names: Vec<_> = references(&document.index)
+            .into_iter()
+            .map(|reference| (reference.name, &text[reference.range]))
+            .collect();
+        assert_eq!(names, [
+            ("HOST".to_string(), "${HOST}"),
+            ("level".to_string(), "${level}")
+        ]);
+
+        let variables = effective::variables(&document);
+        let reference = reference_at(&document.index, text.find("level}").unwrap()).unwrap();
+        assert_eq!(
+            hover(&reference, &variables),
+            "`${level}` is set to `debug` by the configuration."
+        );
+
+        assert_eq!(typed_reference(&document.rope, text.len() - 1), Some(false));
+        assert_eq!(
+            typed_reference(&document.rope, text.find("cpu").unwrap()),
+            None
+        );
+        let items = completion_items(&variables, false);
+        assert_eq!(items[0].insert_text.as_deref(), Some("level}"));
+    }
+}
@@ -0,0 +1,152 @@
+//! fluent-bit version a configuration targets, set by the `fluentBitVersion`
+//! setting: plugins and keys introduced after it aren't completed, and they're
+//! reported where they're used.
+//!
+//! Versions come from the `since` of the parameters, and from the versions
+//! introducing the plugins, generated by `cargo xtask generate` from the
+//! bundled schemas.
+
+use flb_schema::section::FlbSectionType;
+pub(crate) use flb_schema::versions::FlbVersion;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::{
+    completion::FlbData,
+    i18n::t,
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    section_index::SectionIndex,
+};
+
+/// Diagnostic code of a plugin or key introduced after the targeted version.
+pub(crate) const UNSUPPORTED_BY_VERSION: &str = "unsupported-by-version";
+
+include!("plugin_versions.generated.rs");
+
+/// Version which introduced the plugin, if it's a recent one.
+fn plugin_since(section_type: &FlbSectionType, plugin_name: &str) -> Option<FlbVersion> {
+    PLUGIN_VERSIONS
+        .iter()
+        .find(|(type_, name, _)| type_ == section_type && name.eq_ignore_ascii_case(plugin_name))
+        .and_then(|(_, _, since)| since.parse().ok())
+}
+
+/// Whether something introduced in `since` is available in `version`, any
+/// version when none is targeted.
+pub(crate) fn is_available(since: Option<&str>, version: Option<FlbVersion>) -> bool {
+    let since = since.and_then(|since| since.parse::<FlbVersion>().ok());
+    match (since, version) {
+        (Some(since), Some(version)) => since <= version,
+        _ => true,
+    }
+}
+
+/// Whether the plugin is available in `version`.
+pub(crate) fn is_plugin_available(
+    section_type: &FlbSectionType,
+    plugin_name: &str,
+    version: Option<FlbVersion>,
+) -> bool {
+    match (plugin_since(section_type, plugin_name), version) {
+        (Some(since), Some(version)) => since <= version,
+        _ => true,
+    }
+}
+
+/// Reports the plugins and the keys the targeted version doesn't have yet.
+pub(crate) fn check_versions(
+    data: &FlbData,
+    index: &SectionIndex,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+    version: FlbVersion,
+) -> Vec<Diagnostic> {
+    let diagnostic = |range, message| Diagnostic {
+        range: byte_range_to_lsp_range(rope, range, position_encoding),
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String(UNSUPPORTED_BY_VERSION.to_string())),
+        message,
+        ..Diagnostic::default()
+    };
+    let mut diagnostics = Vec::new();
+
+    for section in index.sections() {
+        for entry in &section.entries {
+            if entry.key.eq_ignore_ascii_case("Name") {
+                let (Some(name), Some(value_range)) = (&entry.value, &entry.value_range) else {
+                    continue;
+                };
+                let Some(since) = plugin_since(&section.section_type, name) else {
+                    continue;
+                };
+                if since > version {
+                    let message = t!(
+                        "unsupported-plugin-by-version",
+                        name = name,
+                        section_type = section.section_type,
+                        since = since,
+                        version = version,
+                    );
+                    diagnostics.push(diagnostic(value_range.clone(), message));
+                }
+                continue;
+            }
+
+            let Some(info) = data.get_hover_info(&section.section_type, &entry.key) else {
+                continue;
+            };
+            let Some(since) = info
+                .since
+                .and_then(|since| since.parse::<FlbVersion>().ok())
+            else {
+                continue;
+            };
+            if since > version {
+                let message = t!(
+                    UNSUPPORTED_BY_VERSION,
+                    key = entry.key,
+                    since = since,
+                    version = version,
+                );
+                diagnostics.push(diagnostic(entry.key_range.clone(), message));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{completion::FLB_DATA, document::TextDocument};
+
+    #[test]
+    fn plugins_and_keys_newer_than_the_version() {
+        let document = TextDocument::new(
+            "[SERVICE]\n    Hot_Reload    on\n    scheduler.cap 300\n\n\
+             [INPUT]\n    Name opentelemetry\n\n[OUTPUT]\n    Name  opentelemetry\n    Match *\n",
+        );
+        let version = FlbVersion(1, 9, 10);
+        let diagnostics = check_versions(
+            &FLB_DATA,
+            &document.index,
+            &document.rope,
+            PositionEncodingKind::UTF16,
+            version,
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "`Hot_Reload` was introduced in fluent-bit 2.1.0, after the targeted 1.9.10.",
+            "The `opentelemetry` input plugin was introduced in fluent-bit 2.0.0, after the targeted 1.9.10.",
+        ]);
+
+        assert!(!is_available(Some("2.1.0"), Some(version)));
+        assert!(is_available(Some("2.1.0"), None));
+        assert!(is_plugin_available(
+            &FlbSectionType::Output,
+            "opentelemetry",
+            Some(version)
+        ));
+    }
+}
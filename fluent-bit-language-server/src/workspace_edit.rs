@@ -0,0 +1,158 @@
+//! Building of workspace edits.
+//!
+//! Edits can be grouped under change annotations, e.g. the fixes of a fix-all
+//! action. Annotations needing confirmation let clients show the changes for
+//! review before applying them. They're dropped for clients which don't
+//! support them, the edit being the same otherwise.
+
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{
+    AnnotatedTextEdit, ChangeAnnotation, ClientCapabilities, DocumentChanges, OneOf,
+    OptionalVersionedTextDocumentIdentifier, TextDocumentEdit, TextEdit, Url, WorkspaceEdit,
+};
+
+/// Whether the client supports change annotations, which requires
+/// `documentChanges` as well.
+pub(crate) fn supports_change_annotations(capabilities: Option<&ClientCapabilities>) -> bool {
+    capabilities
+        .and_then(|c| c.workspace.as_ref()?.workspace_edit.as_ref())
+        .is_some_and(|workspace_edit| {
+            workspace_edit.document_changes == Some(true)
+                && workspace_edit.change_annotation_support.is_some()
+        })
+}
+
+pub(crate) struct WorkspaceEditBuilder {
+    change_annotations: bool,
+    /// In insertion order, so that edits of a document keep their order.
+    edits: Vec<(Url, TextEdit, Option<String>)>,
+    annotations: HashMap<String, ChangeAnnotation>,
+}
+
+impl WorkspaceEditBuilder {
+    pub(crate) fn new(change_annotations: bool) -> Self {
+        Self {
+            change_annotations,
+            edits: Vec::new(),
+            annotations: HashMap::new(),
+        }
+    }
+
+    /// Declares an annotation, returning its identifier.
+    pub(crate) fn annotation(
+        &mut self,
+        label: &str,
+        description: Option<String>,
+        needs_confirmation: bool,
+    ) -> String {
+        let id = format!("flb-{}", self.annotations.len());
+        self.annotations.insert(id.clone(), ChangeAnnotation {
+            label: label.to_string(),
+            needs_confirmation: Some(needs_confirmation),
+            description,
+        });
+        id
+    }
+
+    pub(crate) fn edit(&mut self, uri: &Url, edit: TextEdit) -> &mut Self {
+        self.edits.push((uri.clone(), edit, None));
+        self
+    }
+
+    pub(crate) fn annotated_edit(
+        &mut self,
+        uri: &Url,
+        edit: TextEdit,
+        annotation: &str,
+    ) -> &mut Self {
+        self.edits
+            .push((uri.clone(), edit, Some(annotation.to_string())));
+        self
+    }
+
+    pub(crate) fn build(self) -> WorkspaceEdit {
+        if !self.change_annotations || self.annotations.is_empty() {
+            let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+            for (uri, edit, _) in self.edits {
+                changes.entry(uri).or_default().push(edit);
+            }
+            return WorkspaceEdit {
+                changes: Some(changes),
+                ..WorkspaceEdit::default()
+            };
+        }
+
+        let mut document_edits: Vec<TextDocumentEdit> = Vec::new();
+        for (uri, text_edit, annotation) in self.edits {
+            let edit = match annotation {
+                Some(annotation_id) => OneOf::Right(AnnotatedTextEdit {
+                    text_edit,
+                    annotation_id,
+                }),
+                None => OneOf::Left(text_edit),
+            };
+            match document_edits
+                .iter_mut()
+                .find(|document_edit| document_edit.text_document.uri == uri)
+            {
+                Some(document_edit) => document_edit.edits.push(edit),
+                None => document_edits.push(TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                    edits: vec![edit],
+                }),
+            }
+        }
+
+        WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Edits(document_edits)),
+            change_annotations: Some(self.annotations),
+            ..WorkspaceEdit::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::*;
+
+    #[test]
+    fn annotations_are_dropped_unless_supported() {
+        let uri: Url = "file:///fluent-bit.conf".parse().unwrap();
+        let text_edit = TextEdit::new(
+            Range::new(Position::new(0, 0), Position::new(0, 1)),
+            String::new(),
+        );
+        let build = |change_annotations| {
+            let mut builder = WorkspaceEditBuilder::new(change_annotations);
+            let annotation = builder.annotation("Fix all", None, true);
+            builder
+                .annotated_edit(&uri, text_edit.clone(), &annotation)
+                .edit(&uri, text_edit.clone());
+            builder.build()
+        };
+
+        let edit = build(false);
+        assert_eq!(edit.changes.unwrap()[&uri].len(), 2);
+        assert_eq!(edit.change_annotations, None);
+
+        let edit = build(true);
+        let Some(DocumentChanges::Edits(document_edits)) = edit.document_changes else {
+            panic!("expected document edits");
+        };
+        assert_eq!(document_edits.len(), 1);
+        assert_eq!(document_edits[0].edits, [
+            OneOf::Right(AnnotatedTextEdit {
+                text_edit: text_edit.clone(),
+                annotation_id: "flb-0".to_string(),
+            }),
+            OneOf::Left(text_edit),
+        ]);
+        assert_eq!(
+            edit.change_annotations.unwrap()["flb-0"].needs_confirmation,
+            Some(true)
+        );
+    }
+}
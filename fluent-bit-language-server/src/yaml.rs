@@ -0,0 +1,567 @@
+//! YAML configurations, indexed into the same sections as classic ones.
+//!
+//! ```yaml
+//! service:
+//!   flush: 1
+//! pipeline:
+//!   inputs:
+//!     - name: tail
+//!       path: /var/log/*.log
+//! ```
+//!
+//! ref: https://docs.fluentbit.io/manual/administration/configuring-fluent-bit/yaml
+
+use std::{borrow::Cow, ops::Range};
+
+use flb_schema::section::FlbSectionType;
+use ropey::Rope;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+use tree_sitter::{Node, Tree};
+
+use crate::{
+    i18n::t,
+    positions::{to_lsp_range, PositionEncodingKind},
+    section_index::{IndexedEntry, IndexedInclude, IndexedSection, SectionIndex},
+    tree_walk::{walk, Walk},
+};
+
+/// Diagnostic code of invalid YAML syntax.
+pub(crate) const YAML_SYNTAX: &str = "yaml-syntax";
+
+/// Key of the plugins processing the records of an input or output, which
+/// aren't sections themselves.
+const PROCESSORS_KEY: &str = "processors";
+
+/// Section type of the value of `key`, `parent` being the key of the mapping
+/// containing it (`None` at the top level).
+fn section_type_of(parent: Option<&str>, key: &str) -> Option<FlbSectionType> {
+    let key = key.to_ascii_lowercase();
+    match (parent.map(str::to_ascii_lowercase).as_deref(), key.as_str()) {
+        (None, "service") => Some(FlbSectionType::Service),
+        (None, "parsers") => Some(FlbSectionType::Parser),
+        (None, "multiline_parsers") => Some(FlbSectionType::MultilineParser),
+        (None, "customs") => Some(FlbSectionType::Custom),
+        (Some("pipeline"), "inputs") => Some(FlbSectionType::Input),
+        (Some("pipeline"), "filters") => Some(FlbSectionType::Filter),
+        (Some("pipeline"), "outputs") => Some(FlbSectionType::Output),
+        _ => None,
+    }
+}
+
+fn is_pair(node: &Node) -> bool {
+    matches!(node.kind(), "block_mapping_pair" | "flow_pair")
+}
+
+fn is_mapping(node: &Node) -> bool {
+    matches!(node.kind(), "block_mapping" | "flow_mapping")
+}
+
+/// Mapping, sequence or scalar of a `block_node` or `flow_node`.
+fn content(node: Node) -> Option<Node> {
+    if !matches!(node.kind(), "block_node" | "flow_node") {
+        return Some(node);
+    }
+
+    let mut cursor = node.walk();
+    let content = node
+        .named_children(&mut cursor)
+        .find(|child| !matches!(child.kind(), "anchor" | "tag" | "comment"));
+    content
+}
+
+/// Scalar of a node without its quotes, and its range.
+fn scalar(node: Node, rope: &Rope) -> Option<(String, Range<usize>)> {
+    let node = content(node)?;
+    let range = match node.kind() {
+        "plain_scalar" => node.byte_range(),
+        "double_quote_scalar" | "single_quote_scalar" if node.byte_range().len() >= 2 => {
+            node.start_byte() + 1..node.end_byte() - 1
+        }
+        _ => return None,
+    };
+    let text = rope.get_byte_slice(range.clone())?.to_string();
+
+    Some((text.trim().to_string(), range))
+}
+
+fn pair_key(pair: Node, rope: &Rope) -> Option<String> {
+    scalar(pair.child_by_field_name("key")?, rope).map(|(key, _)| key)
+}
+
+/// Pairs of a mapping, and the keys without a value yet being parsed as
+/// errors.
+fn pairs(node: Node) -> Vec<Node> {
+    let Some(mapping) = content(node).filter(is_mapping) else {
+        return Vec::new();
+    };
+
+    let mut cursor = mapping.walk();
+    let pairs = mapping
+        .named_children(&mut cursor)
+        .filter(|child| is_pair(child) || child.kind() == "ERROR")
+        .collect();
+    pairs
+}
+
+/// Items of a sequence.
+fn items(node: Node) -> Vec<Node> {
+    let Some(sequence) = content(node) else {
+        return Vec::new();
+    };
+
+    let mut cursor = sequence.walk();
+    let items = match sequence.kind() {
+        "block_sequence" => sequence
+            .named_children(&mut cursor)
+            .filter(|child| child.kind() == "block_sequence_item")
+            .filter_map(|item| item.named_child(0))
+            .collect(),
+        "flow_sequence" => sequence
+            .named_children(&mut cursor)
+            .filter(|child| child.kind() == "flow_node")
+            .collect(),
+        _ => Vec::new(),
+    };
+    items
+}
+
+/// Top-level node of the first document.
+fn document_content(tree: &Tree) -> Option<Node> {
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let document = root
+        .named_children(&mut cursor)
+        .find(|child| child.kind() == "document")?;
+
+    let mut cursor = document.walk();
+    let content = document
+        .named_children(&mut cursor)
+        .find(|child| matches!(child.kind(), "block_node" | "flow_node"));
+    content
+}
+
+fn index_section(
+    name: &str,
+    section_type: FlbSectionType,
+    node: Node,
+    rope: &Rope,
+) -> Option<IndexedSection> {
+    let mapping = content(node).filter(is_mapping)?;
+
+    let mut entries = Vec::new();
+    for pair in pairs(mapping) {
+        let key_node = match pair.kind() {
+            "ERROR" => pair.named_child(0),
+            _ => pair.child_by_field_name("key"),
+        };
+        let Some((key, key_range)) = key_node.and_then(|key_node| scalar(key_node, rope)) else {
+            continue;
+        };
+        let value = pair
+            .child_by_field_name("value")
+            .and_then(|value_node| scalar(value_node, rope));
+
+        entries.push(IndexedEntry {
+            key,
+            key_range,
+            value: value.as_ref().map(|(value, _)| value.clone()),
+            value_range: value.map(|(_, range)| range),
+        });
+    }
+
+    Some(IndexedSection {
+        name: name.to_string(),
+        section_type,
+        byte_range: mapping.byte_range(),
+        entries,
+    })
+}
+
+fn index_pairs(
+    mapping: Node,
+    parent: Option<&str>,
+    rope: &Rope,
+    sections: &mut Vec<IndexedSection>,
+) {
+    for pair in pairs(mapping).into_iter().filter(is_pair) {
+        let (Some(key), Some(value)) = (pair_key(pair, rope), pair.child_by_field_name("value"))
+        else {
+            continue;
+        };
+
+        match section_type_of(parent, &key) {
+            Some(FlbSectionType::Service) => {
+                sections.extend(index_section(&key, FlbSectionType::Service, value, rope));
+            }
+            Some(section_type) => {
+                for item in items(value) {
+                    sections.extend(index_section(&key, section_type.clone(), item, rope));
+                }
+            }
+            None if parent.is_none() && key.eq_ignore_ascii_case("pipeline") => {
+                index_pairs(value, Some(&key), rope, sections);
+            }
+            None => {}
+        }
+    }
+}
+
+/// Sections of a YAML configuration, in the order of the document.
+pub(crate) fn index_sections(tree: &Tree, rope: &Rope) -> Vec<IndexedSection> {
+    let mut sections = Vec::new();
+    if let Some(content) = document_content(tree) {
+        index_pairs(content, None, rope, &mut sections);
+    }
+    sections.sort_by_key(|section| section.byte_range.start);
+
+    sections
+}
+
+/// Files included by a YAML configuration, listed by its top-level `includes`.
+pub(crate) fn index_includes(tree: &Tree, rope: &Rope) -> Vec<IndexedInclude> {
+    let Some(content) = document_content(tree) else {
+        return Vec::new();
+    };
+
+    pairs(content)
+        .into_iter()
+        .filter(is_pair)
+        .filter(|pair| {
+            pair_key(*pair, rope).is_some_and(|key| key.eq_ignore_ascii_case("includes"))
+        })
+        .filter_map(|pair| pair.child_by_field_name("value"))
+        .flat_map(items)
+        .filter_map(|item| scalar(item, rope))
+        .map(|(pattern, range)| IndexedInclude { pattern, range })
+        .collect()
+}
+
+/// Pairs of the top-level `env` of a YAML configuration.
+fn env_pairs<'tree>(tree: &'tree Tree, rope: &Rope) -> Vec<Node<'tree>> {
+    let Some(content) = document_content(tree) else {
+        return Vec::new();
+    };
+
+    pairs(content)
+        .into_iter()
+        .filter(is_pair)
+        .filter(|pair| pair_key(*pair, rope).is_some_and(|key| key.eq_ignore_ascii_case("env")))
+        .filter_map(|pair| pair.child_by_field_name("value"))
+        .flat_map(pairs)
+        .filter(is_pair)
+        .collect()
+}
+
+/// Variables set by the top-level `env` of a YAML configuration.
+pub(crate) fn index_variables(tree: &Tree, rope: &Rope) -> Vec<(String, String)> {
+    env_pairs(tree, rope)
+        .into_iter()
+        .filter_map(|pair| {
+            let key = pair_key(pair, rope)?;
+            let (value, _) = scalar(pair.child_by_field_name("value")?, rope)?;
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Names of the variables set by the top-level `env`, and the ranges of their
+/// keys.
+pub(crate) fn env_keys(tree: &Tree, rope: &Rope) -> Vec<(String, Range<usize>)> {
+    env_pairs(tree, rope)
+        .into_iter()
+        .filter_map(|pair| scalar(pair.child_by_field_name("key")?, rope))
+        .collect()
+}
+
+/// Whether a key of the top-level `env` is being typed before the byte offset:
+/// the line is indented, without a `:` yet, and the first line indented less
+/// before it is `env:`. Keys being typed aren't pairs of the tree yet.
+pub(crate) fn env_key_at(rope: &Rope, byte: usize) -> bool {
+    let Ok(line) = rope.try_byte_to_line(byte) else {
+        return false;
+    };
+    let Some(head) = rope.get_byte_slice(rope.line_to_byte(line)..byte) else {
+        return false;
+    };
+    let head = head.to_string();
+    let indent = head.len() - head.trim_start().len();
+    if indent == 0 || head.contains(':') {
+        return false;
+    }
+
+    let parent = (0..line)
+        .rev()
+        .map(|line| rope.line(line).to_string())
+        .find(|text| {
+            let trimmed = text.trim_start();
+            !trimmed.is_empty() && !trimmed.starts_with('#') && text.len() - trimmed.len() < indent
+        });
+    parent.is_some_and(|text| text.trim_end().eq_ignore_ascii_case("env:"))
+}
+
+/// Block scalars (`|` or `>`) of the document which are the contents of files,
+/// with the names of the files: their key, e.g. `fluent-bit.conf` in the
+/// `data` of a ConfigMap, or the `path` next to their `content` key, as in the
+/// `write_files` of cloud-init.
+pub(crate) fn file_contents(tree: &Tree, rope: &Rope) -> Vec<(String, Range<usize>)> {
+    let mut ret = Vec::new();
+    let mut nodes = vec![tree.root_node()];
+    while let Some(node) = nodes.pop() {
+        let mut cursor = node.walk();
+        nodes.extend(node.named_children(&mut cursor));
+        if node.kind() != "block_mapping_pair" {
+            continue;
+        }
+
+        let Some(value) = node
+            .child_by_field_name("value")
+            .and_then(content)
+            .filter(|value| value.kind() == "block_scalar")
+        else {
+            continue;
+        };
+        let Some(key) = pair_key(node, rope) else {
+            continue;
+        };
+        let name = match key.eq_ignore_ascii_case("content") {
+            true => node.parent().and_then(|mapping| {
+                pairs(mapping)
+                    .into_iter()
+                    .filter(is_pair)
+                    .find(|pair| pair_key(*pair, rope).is_some_and(|key| key == "path"))
+                    .and_then(|pair| scalar(pair.child_by_field_name("value")?, rope))
+                    .map(|(path, _)| path)
+            }),
+            false => Some(key),
+        };
+        ret.extend(name.map(|name| (name, value.byte_range())));
+    }
+    ret.sort_by_key(|(_, range)| range.start);
+
+    ret
+}
+
+/// Section type at a position, and the start of the section's mapping when
+/// there's one already.
+///
+/// The position may be on a blank line, e.g. a new line being indented. As in
+/// YAML, it's then part of the pairs and sequence items indented less, and of
+/// the mappings indented as much.
+pub(crate) fn section_at(
+    tree: &Tree,
+    rope: &Rope,
+    byte: usize,
+    column: usize,
+) -> Option<(FlbSectionType, Option<usize>)> {
+    // the node ending before the position, whitespace skipped
+    let lookup = (0..byte.min(rope.len_bytes()))
+        .rev()
+        .find(|&i| !rope.byte(i).is_ascii_whitespace())?;
+    let mut node = tree.root_node().descendant_for_byte_range(lookup, lookup);
+    // nodes span the blank lines following them, their indentation tells
+    let on_blank_line = rope
+        .get_byte_slice(lookup..byte)
+        .is_some_and(|slice| slice.chars().any(|c| c == '\n'));
+
+    let mut mapping_start = None;
+    while let Some(n) = node {
+        node = n.parent();
+        let indent = n.start_position().column;
+        let contains = !on_blank_line;
+        if is_mapping(&n) && (contains || indent <= column) {
+            mapping_start = Some(n.start_byte());
+        }
+        if !is_pair(&n) || !(contains || indent < column) {
+            continue;
+        }
+
+        let key = pair_key(n, rope)?;
+        if key.eq_ignore_ascii_case(PROCESSORS_KEY) {
+            return None;
+        }
+        let parent = std::iter::successors(n.parent(), Node::parent).find(is_pair);
+        let parent_key = parent.and_then(|parent| pair_key(parent, rope));
+        if let Some(section_type) = section_type_of(parent_key.as_deref(), &key) {
+            // the pair's value is the mapping of the service, not an item
+            let mapping_start = match section_type {
+                FlbSectionType::Service => n
+                    .child_by_field_name("value")
+                    .and_then(content)
+                    .filter(is_mapping)
+                    .map(|mapping| mapping.start_byte()),
+                _ => mapping_start,
+            };
+            return Some((section_type, mapping_start));
+        }
+    }
+
+    None
+}
+
+/// Entry whose key or value contains the byte offset, their end included.
+pub(crate) fn entry_at(index: &SectionIndex, byte: usize) -> Option<&IndexedEntry> {
+    let contains = |range: &Range<usize>| range.start <= byte && byte <= range.end;
+    index.section_at(byte)?.entries.iter().find(|entry| {
+        contains(&entry.key_range) || entry.value_range.as_ref().is_some_and(contains)
+    })
+}
+
+/// Errors of the YAML syntax.
+pub(crate) fn syntax_errors(
+    tree: &Tree,
+    rope: &Rope,
+    position_encoding: PositionEncodingKind,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    walk(tree.root_node(), |node| {
+        let error = match () {
+            _ if node.is_error() => Some(t!(YAML_SYNTAX)),
+            _ if node.is_missing() => Some(t!("yaml-missing-node", kind = node.kind())),
+            _ => None,
+        };
+        let is_error = error.is_some();
+        if let Some(message) = error {
+            diagnostics.push(Diagnostic {
+                range: to_lsp_range(rope, node.range(), position_encoding),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(YAML_SYNTAX.to_string())),
+                message,
+                ..Diagnostic::default()
+            });
+        }
+
+        // errors aren't reported twice for the nodes inside them
+        match is_error {
+            true => Walk::SkipChildren,
+            false => Walk::Continue,
+        }
+    });
+
+    diagnostics
+}
+
+/// Quotes a scalar which YAML would parse as something else, e.g. `*` as an
+/// alias.
+pub(crate) fn quote(value: &str) -> Cow<'_, str> {
+    let special_start = value.starts_with([
+        '*', '&', '!', '{', '}', '[', ']', '|', '>', '%', '@', '`', '#', '\'', '"', ',',
+    ]);
+    // indicators unless followed by a space, e.g. `- a` but not `-1`
+    let indicator = ["-", "?", ":"]
+        .iter()
+        .any(|indicator| value == *indicator || value.starts_with(&format!("{indicator} ")));
+    if value.is_empty()
+        || special_start
+        || indicator
+        || value.contains(": ")
+        || value.contains(" #")
+        || value.trim() != value
+    {
+        return Cow::Owned(format!("'{}'", value.replace('\'', "''")));
+    }
+
+    Cow::Borrowed(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{document::TextDocument, lsp_ext::ConfigFormat};
+
+    #[test]
+    fn index_yaml_sections() {
+        let text = "\
+service:
+  flush: 1
+  log_level: \"info\"
+pipeline:
+  inputs:
+    - name: tail
+      path: /var/log/*.log
+      processors:
+        logs:
+          - name: content_modifier
+    - {name: cpu, tag: 'cpu.local'}
+  outputs:
+    - name: stdout
+      match
+parsers:
+  - name: json
+    format: json
+";
+        let document = TextDocument::with_format(text, ConfigFormat::Yaml);
+        let sections: Vec<_> = document
+            .index
+            .sections()
+            .iter()
+            .map(|section| {
+                let entries: Vec<_> = section
+                    .entries
+                    .iter()
+                    .map(|entry| (entry.key.as_str(), entry.value.as_deref()))
+                    .collect();
+                (section.section_type.clone(), entries)
+            })
+            .collect();
+        assert_eq!(sections, [
+            (FlbSectionType::Service, vec![
+                ("flush", Some("1")),
+                ("log_level", Some("info"))
+            ]),
+            (FlbSectionType::Input, vec![
+                ("name", Some("tail")),
+                ("path", Some("/var/log/*.log")),
+                ("processors", None),
+            ]),
+            (FlbSectionType::Input, vec![
+                ("name", Some("cpu")),
+                ("tag", Some("cpu.local"))
+            ]),
+            (FlbSectionType::Output, vec![
+                ("name", Some("stdout")),
+                ("match", None)
+            ]),
+            (FlbSectionType::Parser, vec![
+                ("name", Some("json")),
+                ("format", Some("json"))
+            ]),
+        ]);
+        // quotes aren't part of the value
+        let tag = &document.index.sections()[2].entries[1];
+        assert_eq!(&text[tag.value_range.clone().unwrap()], "cpu.local");
+
+        let tree = document.tree.as_ref().unwrap();
+        let at = |line: usize, column: usize| {
+            let byte = document.rope.line_to_byte(line) + column;
+            section_at(tree, &document.rope, byte, column).map(|(section_type, _)| section_type)
+        };
+        assert_eq!(at(1, 4), Some(FlbSectionType::Service));
+        assert_eq!(at(6, 8), Some(FlbSectionType::Input));
+        assert_eq!(at(9, 16), None);
+        assert_eq!(at(13, 6), Some(FlbSectionType::Output));
+        // a new line at the end of the outputs, indented as their keys, or not
+        let document = TextDocument::with_format(
+            "pipeline:\n  outputs:\n    - name: stdout\n      \n  \n",
+            ConfigFormat::Yaml,
+        );
+        let tree = document.tree.as_ref().unwrap();
+        let at = |line: usize, column: usize| {
+            let byte = document.rope.line_to_byte(line) + column;
+            section_at(tree, &document.rope, byte, column)
+        };
+        assert_eq!(
+            at(3, 6),
+            Some((
+                FlbSectionType::Output,
+                Some(document.index.sections()[0].byte_range.start)
+            ))
+        );
+        assert_eq!(at(4, 2), None);
+
+        assert_eq!(quote("*"), "'*'");
+        assert_eq!(quote("it's: here"), "'it''s: here'");
+        assert_eq!(quote("kube.*"), "kube.*");
+        assert_eq!(quote("-1"), "-1");
+    }
+}
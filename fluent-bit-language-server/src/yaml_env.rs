@@ -0,0 +1,179 @@
+//! The top-level `env` of YAML configurations, setting the variables their
+//! values reference as `${NAME}`: the variables the configuration references
+//! without setting them are completed as its keys, the hover of a variable
+//! tells where it's referenced, and the variables nothing references are
+//! reported.
+//!
+//! ```yaml
+//! env:
+//!   LOG_DIR: /var/log
+//! pipeline:
+//!   inputs:
+//!     - name: tail
+//!       path: ${LOG_DIR}/*.log
+//! ```
+
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Range, Url};
+
+use crate::{
+    document::TextDocument,
+    i18n::t,
+    lsp_ext::ConfigFormat,
+    overrides::{location_label, location_link},
+    positions::{byte_range_to_lsp_range, PositionEncodingKind},
+    yaml,
+};
+
+/// Diagnostic code of a variable of `env` no value of the configuration
+/// references.
+pub(crate) const UNUSED_VARIABLE: &str = "unused-variable";
+
+/// Variable set by the `env` of a YAML document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct EnvVariable {
+    pub(crate) name: String,
+    /// Of its key.
+    pub(crate) range: Range,
+}
+
+/// Variables of the `env` of the document, none if it isn't YAML.
+pub(crate) fn env_variables(
+    document: &TextDocument,
+    position_encoding: PositionEncodingKind,
+) -> Vec<EnvVariable> {
+    let Some(tree) = document
+        .tree
+        .as_ref()
+        .filter(|_| document.format == ConfigFormat::Yaml)
+    else {
+        return Vec::new();
+    };
+
+    yaml::env_keys(tree, &document.rope)
+        .into_iter()
+        .map(|(name, range)| EnvVariable {
+            name,
+            range: byte_range_to_lsp_range(&document.rope, range, position_encoding),
+        })
+        .collect()
+}
+
+/// Completions of the variables referenced but set nowhere, as keys of `env`,
+/// with their first reference.
+pub(crate) fn completion_items(undefined: &[(String, &Url, Range)]) -> Vec<CompletionItem> {
+    undefined
+        .iter()
+        .map(|(name, uri, range)| CompletionItem {
+            label: name.clone(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some(t!(
+                "detail-referenced-variable",
+                location = location_label(uri, range)
+            )),
+            insert_text: Some(format!("{name}: ")),
+            ..CompletionItem::default()
+        })
+        .collect()
+}
+
+/// Hover note of a variable of `env`, with the references to it.
+pub(crate) fn hover_note(name: &str, references: &[(&Url, Range)]) -> String {
+    let reference = format!("${{{name}}}");
+    if references.is_empty() {
+        return t!("note-unused-variable", reference = reference);
+    }
+
+    let locations: Vec<_> = references
+        .iter()
+        .map(|(uri, range)| location_link(uri, range))
+        .collect();
+    t!(
+        "note-variable-references",
+        reference = reference,
+        locations = locations.join(", "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tower_lsp::lsp_types::Position;
+
+    use super::*;
+    use crate::{
+        disk_cache::DiskCache,
+        include_graph::{DocumentLinks, IncludeGraph},
+    };
+
+    #[tokio::test]
+    async fn env_variables_across_the_configuration() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        fs::write(
+            dir.join("inputs.yaml"),
+            "pipeline:\n  inputs:\n    - name: tail\n      path: ${LOG_DIR}/*.log\n      tag: ${HOSTNAME}\n",
+        )
+        .unwrap();
+
+        let encoding = PositionEncodingKind::UTF16;
+        let uri = Url::from_file_path(dir.join("fluent-bit.yaml")).unwrap();
+        let text = "env:\n  LOG_DIR: /var/log\n  UNUSED: 1\n  \nincludes:\n  - inputs.yaml\n";
+        let document = TextDocument::with_format(text, ConfigFormat::Yaml);
+        let variables = env_variables(&document, encoding);
+        let names: Vec<_> = variables.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["LOG_DIR", "UNUSED"]);
+        assert!(yaml::env_key_at(
+            &document.rope,
+            text.find("  \n").unwrap() + 2
+        ));
+        assert!(!yaml::env_key_at(
+            &document.rope,
+            text.find("/var").unwrap()
+        ));
+        assert!(!yaml::env_key_at(
+            &document.rope,
+            text.find("- inputs").unwrap()
+        ));
+
+        let links = DocumentLinks::new(&uri, &document, encoding);
+        let graph = IncludeGraph::load(
+            vec![(uri.clone(), links)],
+            Vec::new(),
+            &[],
+            &DiskCache::default(),
+            &[],
+            encoding,
+        )
+        .await;
+        let included = Url::from_file_path(dir.join("inputs.yaml")).unwrap();
+
+        let unused = graph.unused_variables();
+        let messages: Vec<_> = unused[&uri].iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "`${UNUSED}` is set by `env`, but no value of the configuration references it."
+        ]);
+
+        assert_eq!(
+            graph.env_variable_note(&uri, Position::new(1, 4)).unwrap(),
+            format!("`${{LOG_DIR}}` is referenced in [`inputs.yaml:4`]({included}#L4).")
+        );
+        assert_eq!(
+            graph.env_variable_note(&uri, Position::new(2, 4)).unwrap(),
+            "`${UNUSED}` isn't referenced by the configuration."
+        );
+
+        let undefined = graph.undefined_variable_references(&uri, &[]);
+        let items = completion_items(&undefined);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].label, "HOSTNAME");
+        assert_eq!(items[0].insert_text.as_deref(), Some("HOSTNAME: "));
+        assert_eq!(
+            items[0].detail.as_deref(),
+            Some("Referenced in inputs.yaml:5")
+        );
+        assert!(graph
+            .undefined_variable_references(&uri, &["HOSTNAME".to_string()])
+            .is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+//! Cross-reference the bundled plugin docs with schema.generated.rs
+//!
+//! Docs of plugins listed in `plugin_info.csv` but missing from the schema
+//! (e.g. Windows-only plugins) aren't considered orphaned.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use anyhow::bail;
+
+use crate::{project_root, schema::known_doc_paths};
+
+const GENERATED_PATH: &str = "fluent-bit-language-server/src/schema.generated.rs";
+const DOCS_DIR: &str = "fluent-bit-language-server/src/assets/docs";
+
+/// Plugin as found in an `add_snippet!` invocation of the generated file.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct GeneratedPlugin {
+    label: String,
+    /// e.g. `input/cpu-metrics`
+    doc_path: String,
+    /// (key, default, description)
+    params: Vec<(String, Option<String>, String)>,
+}
+
+/// Fails when a generated plugin has no doc, or when a doc isn't used by any
+/// known plugin. With `write_stubs`, stub docs are created for the missing ones
+/// instead.
+pub fn check_assets(write_stubs: bool) -> anyhow::Result<()> {
+    let root = project_root();
+    let generated = fs::read_to_string(root.join(GENERATED_PATH))?;
+    let plugins = parse_generated(&generated);
+
+    let docs_dir = root.join(DOCS_DIR);
+    let mut docs = BTreeSet::new();
+    collect_docs(&docs_dir, "", &mut docs)?;
+
+    let missing: Vec<&GeneratedPlugin> = plugins
+        .iter()
+        .filter(|plugin| !docs.contains(&plugin.doc_path))
+        .collect();
+    let known: BTreeSet<String> = plugins
+        .iter()
+        .map(|plugin| plugin.doc_path.clone())
+        .chain(known_doc_paths())
+        .collect();
+    let orphaned: Vec<&String> = docs.difference(&known).collect();
+
+    for plugin in &missing {
+        if write_stubs {
+            let path = docs_dir.join(format!("{}.md", plugin.doc_path));
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, stub_doc(plugin))?;
+            println!("Created stub doc: {}", path.display());
+        } else {
+            println!("Missing doc: {DOCS_DIR}/{}.md", plugin.doc_path);
+        }
+    }
+    for doc in &orphaned {
+        println!("Orphaned doc: {DOCS_DIR}/{doc}.md");
+    }
+
+    if orphaned.is_empty() && (missing.is_empty() || write_stubs) {
+        return Ok(());
+    }
+    bail!(
+        "{} plugin(s) without doc, {} orphaned doc(s)",
+        if write_stubs { 0 } else { missing.len() },
+        orphaned.len()
+    )
+}
+
+/// Collects `<section>/<name>` of every markdown file under `dir`.
+fn collect_docs(dir: &Path, prefix: &str, docs: &mut BTreeSet<String>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        if path.is_dir() {
+            collect_docs(&path, &format!("{prefix}{file_name}/"), docs)?;
+        } else if let Some(name) = file_name.strip_suffix(".md") {
+            docs.insert(format!("{prefix}{name}"));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_generated(generated: &str) -> Vec<GeneratedPlugin> {
+    let mut plugins: Vec<GeneratedPlugin> = Vec::new();
+
+    for line in generated.lines().map(str::trim) {
+        if line.starts_with("add_snippet!(") {
+            // add_snippet!(data, FlbSectionType::Input, "CPU Log Based Metrics", "cpu", "input/cpu-metrics", [
+            let strings: Vec<&str> = line.split('"').skip(1).step_by(2).collect();
+            if let (Some(label), Some(doc_path)) = (strings.first(), strings.last()) {
+                plugins.push(GeneratedPlugin {
+                    label: label.to_string(),
+                    doc_path: doc_path.to_string(),
+                    params: Vec::new(),
+                });
+            }
+        } else if let Some(param) = line.strip_prefix("(\"") {
+            // ("pid", Some(r#"-1"#), r#"Configure a single process"#),
+            let Some(plugin) = plugins.last_mut() else {
+                continue;
+            };
+            let Some((key, rest)) = param.split_once("\", ") else {
+                continue;
+            };
+            let (default, rest) = match rest.strip_prefix("Some(r#\"") {
+                Some(rest) => match rest.split_once("\"#), ") {
+                    Some((default, rest)) => (Some(default.to_string()), rest),
+                    None => continue,
+                },
+                None => (None, rest.strip_prefix("None, ").unwrap_or(rest)),
+            };
+            let description = rest
+                .strip_prefix("r#\"")
+                .and_then(|rest| rest.split_once("\"#"))
+                .map_or("", |(description, _)| description);
+
+            plugin
+                .params
+                .push((key.to_string(), default, description.to_string()));
+        }
+    }
+
+    plugins
+}
+
+fn stub_doc(plugin: &GeneratedPlugin) -> String {
+    let section = plugin.doc_path.split('/').next().unwrap_or_default();
+    let mut doc = format!(
+        "# {label}\n\n{label} {section} plugin\n\n\
+         [//]: # (This is a stub generated by `cargo xtask check-assets`, there's no documentation for this plugin yet)\n\n\
+         ## Configuration Parameters\n\n\
+         | Key | Description | Default |\n\
+         |:----|:------------|:--------|\n",
+        label = plugin.label,
+    );
+    for (key, default, description) in &plugin.params {
+        doc.push_str(&format!(
+            "| {key} | {} | {} |\n",
+            description.replace('|', "\\|"),
+            default.as_deref().unwrap_or_default().replace('|', "\\|"),
+        ));
+    }
+
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_generated_plugins() {
+        let generated = r###"
+    //// Input
+    add_snippet!(data, FlbSectionType::Input, "CPU Log Based Metrics", "cpu", "input/cpu-metrics", [
+        ("pid", Some(r#"-1"#), r#"Configure a single process to measure usage via their PID"#),
+        ("interval_sec", None, r#""#, FlbPropertyType::Integer),
+    ]);
+"###;
+
+        assert_eq!(parse_generated(generated), [GeneratedPlugin {
+            label: "CPU Log Based Metrics".to_string(),
+            doc_path: "input/cpu-metrics".to_string(),
+            params: vec![
+                (
+                    "pid".to_string(),
+                    Some("-1".to_string()),
+                    "Configure a single process to measure usage via their PID".to_string()
+                ),
+                ("interval_sec".to_string(), None, "".to_string()),
+            ],
+        }]);
+    }
+}
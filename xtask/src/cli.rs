@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
@@ -17,7 +19,53 @@ pub enum Commands {
 
     #[command(about = "Generate the schema")]
     Generate {
+        /// Published schema to download, or source to build with `--from-source`
+        /// (default: latest commit)
         #[arg(long)]
         flb_version: Option<String>,
+
+        /// Read the schema from this JSON file, e.g.
+        /// `fluent-bit-schema-3.1.5.json`, instead of downloading it
+        #[arg(long, conflicts_with_all = ["from_binary", "from_source"])]
+        from_json: Option<PathBuf>,
+
+        /// Dump the schema with this fluent-bit binary (`fluent-bit -J`) instead
+        /// of downloading it
+        #[arg(long, conflicts_with = "from_source")]
+        from_binary: Option<PathBuf>,
+
+        /// Build fluent-bit from source and dump its schema, for versions
+        /// without a published one
+        #[arg(long)]
+        from_source: bool,
+
+        /// Also write the normalized schema JSON, e.g. for the `schemaPath`
+        /// setting of the language server
+        #[arg(long)]
+        output_json: Option<PathBuf>,
+
+        /// Also bundle the schemas of these versions in `flb-schema`, keyed by
+        /// minor version, or JSON files of them
+        #[arg(long, value_delimiter = ',', default_values = ["3.0.2", "3.1.9", "3.2.2"])]
+        bundle: Vec<String>,
+
+        /// Fail instead of writing generated files which aren't up-to-date,
+        /// e.g. edited by hand
+        #[arg(long)]
+        check: bool,
+    },
+
+    #[command(about = "Check that every generated plugin has a doc, and every doc a plugin")]
+    CheckAssets {
+        /// Create stub docs for plugins without one
+        #[arg(long)]
+        write_stubs: bool,
+    },
+
+    #[command(about = "Rewrite GitBook-specific syntax of the plugin docs to plain markdown")]
+    NormalizeDocs {
+        /// Fail instead of rewriting docs which aren't normalized
+        #[arg(long)]
+        check: bool,
     },
 }
@@ -0,0 +1,325 @@
+//! Normalization of the bundled plugin docs
+//!
+//! The docs are scraped from the fluent-bit documentation, written for GitBook.
+//! GitBook-specific syntax renders poorly in editors, so it is rewritten to
+//! plain markdown:
+//! - relative links are made absolute <https://docs.fluentbit.io> URLs
+//! - hint blocks become blockquotes, tabs become bold titles
+//! - tracking pixels (standalone `<img>` lines) are removed
+
+use std::{fs, path::Path};
+
+use crate::{codegen::ensure_file_contents, project_root};
+
+const DOCS_DIR: &str = "fluent-bit-language-server/src/assets/docs";
+const MANUAL_URL: &str = "https://docs.fluentbit.io/manual";
+
+/// Top-level directories of the manual, used by links relative to its root.
+const MANUAL_ROOTS: &[&str] = &[
+    "about/",
+    "administration/",
+    "concepts/",
+    "development/",
+    "installation/",
+    "local-testing/",
+    "pipeline/",
+    "stream-processing/",
+];
+
+const CODE_FENCE: &str = "```";
+
+pub fn normalize_docs(check: bool) -> anyhow::Result<()> {
+    let docs_dir = project_root().join(DOCS_DIR);
+    normalize_dir(&docs_dir, &docs_dir, check)
+}
+
+fn normalize_dir(docs_dir: &Path, dir: &Path, check: bool) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            normalize_dir(docs_dir, &path, check)?;
+            continue;
+        }
+        if path.extension().is_some_and(|ext| ext == "md") {
+            // e.g. `input/tail`
+            let doc_path = path.strip_prefix(docs_dir)?.with_extension("");
+            let doc_path = doc_path.to_string_lossy().replace('\\', "/");
+            let markdown = fs::read_to_string(&path)?;
+            ensure_file_contents(&path, &normalize(&doc_path, &markdown), check);
+        }
+    }
+
+    Ok(())
+}
+
+/// URL of the directory containing the page of `doc_path` in the manual.
+fn page_dir_url(doc_path: &str) -> Option<String> {
+    let pipeline = match doc_path.split_once('/')?.0 {
+        "input" => "inputs",
+        "filter" => "filters",
+        "output" => "outputs",
+        _ => return None,
+    };
+
+    Some(format!("{MANUAL_URL}/pipeline/{pipeline}/"))
+}
+
+fn normalize(doc_path: &str, markdown: &str) -> String {
+    let page_dir = page_dir_url(doc_path);
+    let page_url = page_dir.as_ref().and_then(|dir| {
+        let name = doc_path.rsplit('/').next()?;
+        Some(format!("{dir}{name}"))
+    });
+
+    let mut ret = String::with_capacity(markdown.len());
+    let mut in_code_block = false;
+    let mut in_hint = false;
+    // set when removing a line, so that it doesn't leave two blank lines
+    let mut skip_blank = false;
+
+    for line in markdown.lines() {
+        if line.trim_start().starts_with(CODE_FENCE) {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || line.trim_start().starts_with(CODE_FENCE) {
+            push_line(&mut ret, line, in_hint);
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if std::mem::take(&mut skip_blank) && trimmed.is_empty() {
+            continue;
+        }
+        if let Some(style) = tag_attribute(trimmed, "hint", "style") {
+            in_hint = true;
+            let title = match style.as_str() {
+                "warning" => "Warning",
+                "danger" => "Danger",
+                "success" => "Tip",
+                _ => "Note",
+            };
+            ret.push_str(&format!("> **{title}**\n>\n"));
+            continue;
+        }
+        if trimmed == "{% endhint %}" {
+            in_hint = false;
+            // drop the blank lines closing the blockquote
+            while ret.ends_with("\n>\n") {
+                ret.truncate(ret.len() - 2);
+            }
+            continue;
+        }
+        if let Some(title) = tag_attribute(trimmed, "tab", "title") {
+            push_line(&mut ret, &format!("**{title}**"), in_hint);
+            push_line(&mut ret, "", in_hint);
+            skip_blank = true;
+            continue;
+        }
+        if matches!(trimmed, "{% tabs %}" | "{% endtabs %}" | "{% endtab %}") {
+            continue;
+        }
+        if trimmed.starts_with("<img") && trimmed.ends_with("/>") {
+            skip_blank = true;
+            continue;
+        }
+        if in_hint && trimmed.is_empty() && ret.ends_with("\n>\n") {
+            continue;
+        }
+
+        let line = rewrite_links(line, page_dir.as_deref(), page_url.as_deref());
+        push_line(&mut ret, &line, in_hint);
+    }
+
+    ret
+}
+
+fn push_line(ret: &mut String, line: &str, in_hint: bool) {
+    if in_hint {
+        ret.push('>');
+        if !line.is_empty() {
+            ret.push(' ');
+        }
+    }
+    ret.push_str(line);
+    ret.push('\n');
+}
+
+/// Value of `attribute` if `line` is the GitBook tag `{% name attribute="value" %}`.
+fn tag_attribute(line: &str, name: &str, attribute: &str) -> Option<String> {
+    let rest = line
+        .strip_prefix("{%")?
+        .strip_suffix("%}")?
+        .trim()
+        .strip_prefix(name)?
+        .trim_start();
+    let value = rest
+        .strip_prefix(attribute)?
+        .strip_prefix("=\"")?
+        .split_once('"')?
+        .0;
+
+    Some(value.to_string())
+}
+
+/// Makes the targets of the links of `line` absolute, code spans excepted.
+fn rewrite_links(line: &str, page_dir: Option<&str>, page_url: Option<&str>) -> String {
+    let mut ret = String::with_capacity(line.len());
+    let mut in_code_span = false;
+    let mut rest = line;
+
+    while let Some(i) = rest.find(['`', ']']) {
+        let (head, tail) = rest.split_at(i);
+        ret.push_str(head);
+
+        if let Some(after) = tail.strip_prefix('`') {
+            in_code_span = !in_code_span;
+            ret.push('`');
+            rest = after;
+            continue;
+        }
+
+        // `](target)`
+        let target_end = tail
+            .find(')')
+            .filter(|_| !in_code_span && tail.starts_with("]("));
+        match target_end.and_then(|end| {
+            resolve_link(&tail[2..end], page_dir, page_url).map(|target| (end, target))
+        }) {
+            Some((end, target)) => {
+                ret.push_str(&format!("]({target})"));
+                rest = &tail[end + 1..];
+            }
+            None => {
+                ret.push(']');
+                rest = &tail[1..];
+            }
+        }
+    }
+    ret.push_str(rest);
+
+    ret
+}
+
+/// Absolute URL of a relative link target, `None` if it's absolute already or
+/// isn't a link to the manual.
+fn resolve_link(target: &str, page_dir: Option<&str>, page_url: Option<&str>) -> Option<String> {
+    if target.contains("://") || target.starts_with("mailto:") || target.contains(' ') {
+        return None;
+    }
+    if let Some(anchor) = target.strip_prefix('#') {
+        return page_url.map(|url| format!("{url}#{anchor}"));
+    }
+
+    let (path, anchor) = match target.split_once('#') {
+        Some((path, anchor)) => (path, Some(anchor)),
+        None => (target, None),
+    };
+    if !(path.ends_with(".md") || path.contains(".gitbook/")) {
+        return None;
+    }
+
+    let path = path.strip_suffix(".md").unwrap_or(path);
+    let path = path.strip_suffix("/README").unwrap_or(path);
+    let mut url = if MANUAL_ROOTS.iter().any(|root| path.starts_with(root)) {
+        format!("{MANUAL_URL}/{path}")
+    } else {
+        resolve_relative(page_dir?, path)?
+    };
+    if let Some(anchor) = anchor {
+        url.push('#');
+        url.push_str(anchor);
+    }
+
+    Some(url)
+}
+
+/// Joins `path` to `dir_url` (ending with `/`), resolving `.` and `..`.
+fn resolve_relative(dir_url: &str, path: &str) -> Option<String> {
+    let root_len = MANUAL_URL.len();
+    let mut segments: Vec<&str> = dir_url[root_len..]
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    Some(format!("{MANUAL_URL}/{}", segments.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use super::*;
+
+    #[test]
+    fn normalize_gitbook_syntax() {
+        let markdown = r#"# Multiline
+
+<img referrerpolicy="no-referrer-when-downgrade" src="https://static.scarf.sh/a.png" />
+
+See [TLS](../../administration/transport-security.md), [tail](../inputs/tail.md#config),
+[rewrite tag](pipeline/filters/rewrite-tag.md), [below](#example) and [grep](grep.md).
+Keep [this](https://fluentbit.io) and `[not](a-link.md)` as is.
+
+{% hint style="warning" %}
+
+Buffering is required.
+
+Really.
+{% endhint %}
+
+{% tabs %}
+{% tab title="fluent-bit.conf" %}
+```
+[FILTER]
+    Name multiline
+```
+{% endtab %}
+{% endtabs %}
+"#;
+
+        expect![[r#"
+            # Multiline
+
+            See [TLS](https://docs.fluentbit.io/manual/administration/transport-security), [tail](https://docs.fluentbit.io/manual/pipeline/inputs/tail#config),
+            [rewrite tag](https://docs.fluentbit.io/manual/pipeline/filters/rewrite-tag), [below](https://docs.fluentbit.io/manual/pipeline/filters/multiline-stacktrace#example) and [grep](https://docs.fluentbit.io/manual/pipeline/filters/grep).
+            Keep [this](https://fluentbit.io) and `[not](a-link.md)` as is.
+
+            > **Warning**
+            >
+            > Buffering is required.
+            >
+            > Really.
+
+            **fluent-bit.conf**
+
+            ```
+            [FILTER]
+                Name multiline
+            ```
+        "#]]
+        .assert_eq(&normalize("filter/multiline-stacktrace", markdown));
+    }
+
+    #[test]
+    fn normalize_is_idempotent() {
+        let markdown =
+            "> **Note**\n>\n> [tail](https://docs.fluentbit.io/manual/pipeline/inputs/tail)\n";
+
+        expect![[r#"
+            > **Note**
+            >
+            > [tail](https://docs.fluentbit.io/manual/pipeline/inputs/tail)
+        "#]]
+        .assert_eq(&normalize("input/cpu-metrics", markdown));
+    }
+}
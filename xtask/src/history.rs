@@ -0,0 +1,395 @@
+//! Generate migrations.generated.rs and plugin_versions.generated.rs
+//!
+//! The keys fluent-bit versions renamed or removed, for the migration report
+//! and the diagnostics of the language server. Removed keys are the ones of a
+//! plugin which a bundled schema has and the schema of the next bundled minor
+//! version doesn't.
+//!
+//! Schemas can't tell a rename from a removal, so renames are listed in
+//! `assets/renamed_parameters.csv`, including the ones of versions older than
+//! the bundled schemas.
+//!
+//! Plugins are introduced by the first minor version whose bundled schema has
+//! them. The ones introduced before the oldest bundled schema, which the
+//! schemas can't tell, are listed in `assets/plugin_versions.csv`.
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use convert_case::{Case, Casing};
+use csv::ReaderBuilder;
+use flb_schema::{
+    config::{FlbConfigSchema, FlbPlugin},
+    section::FlbSectionType,
+    versions::FlbVersion,
+};
+use serde::Deserialize;
+use xshell::Shell;
+
+use crate::{codegen::ensure_file_contents, project_root, schema::BUNDLED_SCHEMAS_DIR};
+
+#[derive(Deserialize)]
+struct RenamedParameterRow {
+    version: String,
+    section_type: String,
+    plugin_name: String,
+    key: String,
+    renamed_to: String,
+}
+
+#[derive(Deserialize)]
+struct PluginVersionRow {
+    section_type: String,
+    plugin_name: String,
+    since: String,
+}
+
+/// Plugin, and the version which introduced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PluginVersion {
+    section_type: FlbSectionType,
+    plugin_name: String,
+    since: FlbVersion,
+}
+
+/// Key of the sections of a plugin a version renamed or removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Migration {
+    version: FlbVersion,
+    section_type: FlbSectionType,
+    plugin_name: String,
+    key: String,
+    /// Key replacing it, `None` if it was removed.
+    renamed_to: Option<String>,
+}
+
+/// Renames of `renamed_parameters.csv`.
+fn renamed_parameters() -> anyhow::Result<Vec<Migration>> {
+    let contents = include_str!("assets/renamed_parameters.csv");
+
+    let mut reader = ReaderBuilder::new().from_reader(contents.as_bytes());
+    reader
+        .deserialize::<RenamedParameterRow>()
+        .map(|result| {
+            let record = result?;
+            Ok(Migration {
+                version: record.version.parse()?,
+                section_type: record.section_type.parse()?,
+                plugin_name: record.plugin_name,
+                key: record.key,
+                renamed_to: Some(record.renamed_to),
+            })
+        })
+        .collect()
+}
+
+/// Plugins of `plugin_versions.csv`.
+fn listed_plugin_versions() -> anyhow::Result<Vec<PluginVersion>> {
+    let contents = include_str!("assets/plugin_versions.csv");
+
+    let mut reader = ReaderBuilder::new().from_reader(contents.as_bytes());
+    reader
+        .deserialize::<PluginVersionRow>()
+        .map(|result| {
+            let record = result?;
+            Ok(PluginVersion {
+                section_type: record.section_type.parse()?,
+                plugin_name: record.plugin_name,
+                since: record.since.parse()?,
+            })
+        })
+        .collect()
+}
+
+/// Schemas bundled in `flb-schema`, oldest first.
+fn bundled_schemas(sh: &Shell, dir: &Path) -> anyhow::Result<Vec<(FlbVersion, FlbConfigSchema)>> {
+    if !sh.path_exists(dir) {
+        return Ok(Vec::new());
+    }
+
+    let mut schemas = Vec::new();
+    for path in sh.read_dir(dir)? {
+        if path
+            .extension()
+            .map_or(true, |extension| extension != "json")
+        {
+            continue;
+        }
+        let schema: FlbConfigSchema = serde_json::from_str(&sh.read_file(&path)?)?;
+        let version = schema
+            .fluent_bit
+            .version
+            .parse()
+            .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        schemas.push((version, schema));
+    }
+    schemas.sort_by_key(|(version, _)| *version);
+
+    Ok(schemas)
+}
+
+fn plugins(schema: &FlbConfigSchema) -> impl Iterator<Item = &FlbPlugin> {
+    schema
+        .customs
+        .iter()
+        .chain(&schema.inputs)
+        .chain(&schema.filters)
+        .chain(&schema.outputs)
+}
+
+fn has_key(plugin: &FlbPlugin, key: &str) -> bool {
+    plugin
+        .properties
+        .iter()
+        .any(|prop| prop.name.eq_ignore_ascii_case(key))
+}
+
+/// Renames, and the keys removed between consecutive schemas, by version. A
+/// key removed by a minor version is dated to its first release.
+fn migrations(
+    schemas: &[(FlbVersion, FlbConfigSchema)],
+    renames: Vec<Migration>,
+) -> Vec<Migration> {
+    let mut migrations = renames;
+    for pair in schemas.windows(2) {
+        let [(_, before), (version, after)] = pair else {
+            unreachable!("windows of 2 schemas");
+        };
+        let version = FlbVersion(version.0, version.1, 0);
+        for plugin in plugins(after) {
+            let Some(previous) = plugins(before)
+                .find(|previous| previous.type_ == plugin.type_ && previous.name == plugin.name)
+            else {
+                continue;
+            };
+            for prop in &previous.properties {
+                let renamed = migrations.iter().any(|migration| {
+                    migration.section_type == plugin.type_
+                        && migration.plugin_name == plugin.name
+                        && migration.key.eq_ignore_ascii_case(&prop.name)
+                });
+                if renamed || has_key(plugin, &prop.name) {
+                    continue;
+                }
+                migrations.push(Migration {
+                    version,
+                    section_type: plugin.type_.clone(),
+                    plugin_name: plugin.name.clone(),
+                    key: prop.name.clone(),
+                    renamed_to: None,
+                });
+            }
+        }
+    }
+
+    migrations.sort_by_key(|migration| {
+        (
+            migration.version,
+            migration.section_type.to_string(),
+            migration.plugin_name.clone(),
+        )
+    });
+    migrations
+}
+
+/// Plugins listed, and the ones a schema has and the previous one doesn't,
+/// dated to the first release of the minor version, the earliest version
+/// winning.
+fn plugin_versions(
+    schemas: &[(FlbVersion, FlbConfigSchema)],
+    listed: Vec<PluginVersion>,
+) -> Vec<PluginVersion> {
+    let mut plugin_versions = listed;
+    for pair in schemas.windows(2) {
+        let [(_, before), (version, after)] = pair else {
+            unreachable!("windows of 2 schemas");
+        };
+        let since = FlbVersion(version.0, version.1, 0);
+        for plugin in plugins(after) {
+            let is_new = !plugins(before)
+                .any(|previous| previous.type_ == plugin.type_ && previous.name == plugin.name);
+            let is_listed = plugin_versions.iter().any(|plugin_version| {
+                plugin_version.section_type == plugin.type_
+                    && plugin_version.plugin_name == plugin.name
+            });
+            if is_new && !is_listed {
+                plugin_versions.push(PluginVersion {
+                    section_type: plugin.type_.clone(),
+                    plugin_name: plugin.name.clone(),
+                    since,
+                });
+            }
+        }
+    }
+
+    plugin_versions.sort_by_key(|plugin_version| {
+        (
+            plugin_version.section_type.to_string(),
+            plugin_version.plugin_name.clone(),
+        )
+    });
+    plugin_versions
+}
+
+fn generate_plugin_versions(plugin_versions: &[PluginVersion]) -> String {
+    let mut res = String::from(
+        r#"/// Generated by `cargo xtask generate`
+/// Don't modify this file manually.
+
+const PLUGIN_VERSIONS: &[(FlbSectionType, &str, &str)] = &[
+"#,
+    );
+    for plugin_version in plugin_versions {
+        res.push_str(&format!(
+            "    (FlbSectionType::{section_type}, {plugin_name:?}, \"{since}\"),\n",
+            section_type = plugin_version
+                .section_type
+                .to_string()
+                .to_case(Case::Pascal),
+            plugin_name = plugin_version.plugin_name,
+            since = plugin_version.since,
+        ));
+    }
+    res.push_str("];\n");
+
+    res
+}
+
+fn generate_migrations(migrations: &[Migration]) -> String {
+    let mut res = String::from(
+        r#"/// Generated by `cargo xtask generate`
+/// Don't modify this file manually.
+
+const MIGRATIONS: &[Migration] = &[
+"#,
+    );
+    for migration in migrations {
+        let renamed_to = match &migration.renamed_to {
+            Some(key) => format!("Some({key:?})"),
+            None => "None".to_owned(),
+        };
+        res.push_str(&format!(
+            r#"    Migration {{
+        version: "{version}",
+        section_type: FlbSectionType::{section_type},
+        plugin_name: {plugin_name:?},
+        key: {key:?},
+        renamed_to: {renamed_to},
+    }},
+"#,
+            version = migration.version,
+            section_type = migration.section_type.to_string().to_case(Case::Pascal),
+            plugin_name = migration.plugin_name,
+            key = migration.key,
+        ));
+    }
+    res.push_str("];\n");
+
+    res
+}
+
+/// Generates the migrations and the versions of the plugins from the bundled
+/// schemas and the assets.
+pub(crate) fn generate(sh: &Shell, check: bool) -> anyhow::Result<()> {
+    let schemas = bundled_schemas(sh, &project_root().join(BUNDLED_SCHEMAS_DIR))?;
+    let migrations = migrations(&schemas, renamed_parameters()?);
+    let plugin_versions = plugin_versions(&schemas, listed_plugin_versions()?);
+
+    let dir = project_root().join("fluent-bit-language-server/src");
+    ensure_file_contents(
+        &dir.join("migrations.generated.rs"),
+        &generate_migrations(&migrations),
+        check,
+    );
+    ensure_file_contents(
+        &dir.join("plugin_versions.generated.rs"),
+        &generate_plugin_versions(&plugin_versions),
+        check,
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema(version: &str, keys: &[&str]) -> (FlbVersion, FlbConfigSchema) {
+        with_inputs(version, &["tail"], keys)
+    }
+
+    /// Schema of the inputs, which have the keys.
+    fn with_inputs(version: &str, inputs: &[&str], keys: &[&str]) -> (FlbVersion, FlbConfigSchema) {
+        let options: Vec<_> = keys
+            .iter()
+            .map(|key| json!({ "name": key, "description": "", "type": "string" }))
+            .collect();
+        let inputs: Vec<_> = inputs
+            .iter()
+            .map(|name| json!({ "type": "input", "name": name, "description": "", "properties": { "options": options } }))
+            .collect();
+        let schema: FlbConfigSchema = serde_json::from_value(json!({
+            "fluent-bit": { "version": version, "schema_version": "1", "os": "linux" },
+            "customs": [],
+            "inputs": inputs,
+            "filters": [],
+            "outputs": [],
+        }))
+        .unwrap();
+        (version.parse().unwrap(), schema)
+    }
+
+    #[test]
+    fn keys_renamed_and_removed() {
+        assert!(!renamed_parameters().unwrap().is_empty());
+
+        let schemas = [
+            schema("3.0.2", &["path", "db.journal", "old_key"]),
+            schema("3.1.9", &["path", "new_key"]),
+        ];
+        let renames = vec![Migration {
+            version: FlbVersion(3, 1, 0),
+            section_type: FlbSectionType::Input,
+            plugin_name: "tail".to_string(),
+            key: "old_key".to_string(),
+            renamed_to: Some("new_key".to_string()),
+        }];
+        let migrations = migrations(&schemas, renames);
+        let keys: Vec<_> = migrations
+            .iter()
+            .map(|migration| (migration.key.as_str(), migration.renamed_to.as_deref()))
+            .collect();
+        assert_eq!(keys, [("old_key", Some("new_key")), ("db.journal", None)]);
+
+        let generated = generate_migrations(&migrations);
+        assert!(generated.contains(
+            r#"    Migration {
+        version: "3.1.0",
+        section_type: FlbSectionType::Input,
+        plugin_name: "tail",
+        key: "db.journal",
+        renamed_to: None,
+    },"#
+        ));
+    }
+
+    #[test]
+    fn plugins_introduced_by_versions() {
+        let listed = listed_plugin_versions().unwrap();
+        assert!(listed
+            .iter()
+            .all(|plugin_version| plugin_version.since < FlbVersion(3, 0, 0)));
+
+        let schemas = [
+            with_inputs("3.0.2", &["tail", "opentelemetry"], &[]),
+            with_inputs("3.1.9", &["tail", "opentelemetry", "ebpf"], &[]),
+        ];
+        let plugin_versions = plugin_versions(&schemas, listed);
+        let generated = generate_plugin_versions(&plugin_versions);
+        assert!(generated.contains(r#"    (FlbSectionType::Input, "ebpf", "3.1.0"),"#));
+        assert!(generated.contains(r#"    (FlbSectionType::Input, "opentelemetry", "2.0.0"),"#));
+        assert!(!generated.contains(r#""tail""#));
+    }
+}
@@ -5,9 +5,12 @@ use xshell::Shell;
 
 use crate::cli::{Cli, Commands};
 
+mod check_assets;
 mod cli;
 mod codegen;
 mod dist;
+mod docs;
+mod history;
 mod schema;
 
 fn main() -> anyhow::Result<()> {
@@ -20,7 +23,25 @@ fn main() -> anyhow::Result<()> {
         Commands::Dist {
             client_patch_version,
         } => dist::run_dist(sh, client_patch_version),
-        Commands::Generate { flb_version } => schema::generate(sh, flb_version),
+        Commands::Generate {
+            flb_version,
+            from_json,
+            from_binary,
+            from_source,
+            output_json,
+            bundle,
+            check,
+        } => {
+            let source = match (from_json, from_binary, from_source) {
+                (Some(path), _, _) => schema::SchemaSource::File(path),
+                (None, Some(binary), _) => schema::SchemaSource::Binary(binary),
+                (None, None, true) => schema::SchemaSource::Source,
+                (None, None, false) => schema::SchemaSource::Published,
+            };
+            schema::generate(sh, flb_version, source, output_json, &bundle, check)
+        }
+        Commands::CheckAssets { write_stubs } => check_assets::check_assets(write_stubs),
+        Commands::NormalizeDocs { check } => docs::normalize_docs(check),
     }
 }
 
@@ -1,19 +1,45 @@
 //! Generate schema.generated.rs
-
-use std::collections::HashMap;
-
+//!
+//! The schema is the one published for a release, or for versions without one
+//! (e.g. nightly builds), dumped by a fluent-bit binary, built from source if
+//! needed. Every plugin of the schema is generated, stub docs being written for
+//! the ones without a doc.
+//!
+//! Parameters plugins can't do without, which the schema doesn't tell, are
+//! listed in `assets/required_parameters.csv`, for the snippets to insert them
+//! only.
+//!
+//! Schemas of other versions can be bundled in `flb-schema` as well, keyed by
+//! their minor version, for the language server to switch to at runtime.
+//!
+//! With `--check`, e.g. in CI, generated files which aren't up-to-date, e.g.
+//! modified by hand, fail the generation instead of being written.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
 use convert_case::{Case, Casing};
 use csv::ReaderBuilder;
-use flb_schema::config::{FlbConfigSchema, FlbPlugin};
+use flb_schema::{
+    config::{FlbConfigSchema, FlbPlugin},
+    versions::FlbVersion,
+};
 use once_cell::sync::Lazy;
 use serde::Deserialize;
+use serde_json::{json, Value};
 use xshell::{cmd, Shell};
 
-use crate::{codegen::ensure_file_contents, project_root};
+use crate::{check_assets::check_assets, codegen::ensure_file_contents, history, project_root};
 
 const FLB_SCHEMA_DEFAULT_VERSION: &str = "3.1.5";
 const FLB_SCHEMA_URL_TEMPLATE: &str =
     "https://packages.fluentbit.io/{version}/fluent-bit-schema-{version}.json";
+const FLB_REPOSITORY_URL: &str = "https://github.com/fluent/fluent-bit";
+/// Bundled schemas, by minor version, e.g. `3.1.json`
+pub(crate) const BUNDLED_SCHEMAS_DIR: &str = "flb-schema/schemas";
 
 #[derive(Debug, Hash, PartialEq, Eq)]
 struct PluginKey {
@@ -101,24 +127,225 @@ static PLUGIN_INFO_MAP: Lazy<HashMap<PluginKey, PluginInfo>> = Lazy::new(|| {
     map
 });
 
-pub fn generate(sh: &Shell, flb_version: Option<String>) -> anyhow::Result<()> {
-    let flb_version = flb_version.unwrap_or_else(|| FLB_SCHEMA_DEFAULT_VERSION.to_owned());
-    let url = FLB_SCHEMA_URL_TEMPLATE.replace("{version}", &flb_version);
+#[derive(Deserialize)]
+struct RequiredParameterRow {
+    section_type: String,
+    plugin_name: String,
+    key: String,
+}
 
-    // TODO; maybe cache this?
-    let schema_json = cmd!(sh, "curl {url}").read()?;
-    let parsed: FlbConfigSchema = serde_json::from_str(&schema_json)?;
+/// <(section_type, plugin_name), lowercase keys>
+/// ex. <("input", "tail"), ["path"]>
+static REQUIRED_PARAMETERS: Lazy<HashMap<PluginKey, Vec<String>>> = Lazy::new(|| {
+    let contents = include_str!("assets/required_parameters.csv");
+
+    let mut map: HashMap<PluginKey, Vec<String>> = HashMap::new();
+    let mut reader = ReaderBuilder::new().from_reader(contents.as_bytes());
+    for result in reader.deserialize::<RequiredParameterRow>() {
+        let record = result.unwrap();
+        map.entry(PluginKey::new(record.section_type, record.plugin_name))
+            .or_default()
+            .push(record.key.to_lowercase());
+    }
+
+    map
+});
+
+/// Doc paths of every plugin in `plugin_info.csv`, including the ones missing
+/// from the schema (e.g. Windows-only plugins), e.g. `input/cpu-metrics`.
+pub(crate) fn known_doc_paths() -> impl Iterator<Item = String> {
+    PLUGIN_INFO_MAP
+        .iter()
+        .map(|(key, info)| format!("{}/{}", key.section_type, info.doc_path))
+}
+
+/// Where the schema comes from.
+pub enum SchemaSource {
+    /// The JSON published for releases
+    Published,
+    /// A JSON file, e.g. a published one downloaded before
+    File(PathBuf),
+    /// The dump of a fluent-bit binary
+    Binary(PathBuf),
+    /// The dump of fluent-bit built from source
+    Source,
+}
+
+pub fn generate(
+    sh: &Shell,
+    flb_version: Option<String>,
+    source: SchemaSource,
+    output_json: Option<PathBuf>,
+    bundle: &[String],
+    check: bool,
+) -> anyhow::Result<()> {
+    let schema_json = match source {
+        SchemaSource::Published => download_schema(
+            sh,
+            flb_version.as_deref().unwrap_or(FLB_SCHEMA_DEFAULT_VERSION),
+        )?,
+        SchemaSource::File(path) => sh.read_file(path)?,
+        SchemaSource::Binary(binary) => dump_schema(sh, &binary)?,
+        SchemaSource::Source => {
+            let binary = build_fluent_bit(sh, flb_version.as_deref())?;
+            dump_schema(sh, &binary)?
+        }
+    };
+    let schema_json = normalize_schema(&schema_json)?;
+    let parsed: FlbConfigSchema = serde_json::from_value(schema_json.clone())?;
+
+    if let Some(output_json) = output_json {
+        sh.write_file(output_json, serde_json::to_string_pretty(&schema_json)?)?;
+    }
 
     let generated = generate0(&parsed);
 
     let path = project_root().join("fluent-bit-language-server/src/schema.generated.rs");
-    let generated = add_preamble(&flb_version, generated);
+    let generated = add_preamble(&parsed.fluent_bit.version, generated);
+
+    ensure_file_contents(path.as_path(), &generated, check);
+    if !bundle.is_empty() {
+        bundle_schemas(sh, bundle, check)?;
+    }
+    history::generate(sh, check)?;
+
+    // plugins new to the schema have no doc yet
+    check_assets(!check)
+}
 
-    ensure_file_contents(path.as_path(), &generated, false);
+fn download_schema(sh: &Shell, flb_version: &str) -> anyhow::Result<String> {
+    let url = FLB_SCHEMA_URL_TEMPLATE.replace("{version}", flb_version);
+
+    // TODO; maybe cache this?
+    Ok(cmd!(sh, "curl {url}").read()?)
+}
+
+/// Bundles the schemas of `versions` in `flb-schema`, each being a published
+/// version, e.g. `3.0.2`, or a JSON file. Schemas bundled before are kept, a
+/// version replacing the one bundled for its minor version.
+fn bundle_schemas(sh: &Shell, versions: &[String], check: bool) -> anyhow::Result<()> {
+    let dir = project_root().join(BUNDLED_SCHEMAS_DIR);
+    for version in versions {
+        let schema_json = match version.ends_with(".json") {
+            true => sh.read_file(version)?,
+            false => download_schema(sh, version)?,
+        };
+        let schema_json = normalize_schema(&schema_json)?;
+        let parsed: FlbConfigSchema = serde_json::from_value(schema_json.clone())?;
+        let minor = parsed.fluent_bit.version.parse::<FlbVersion>()?.minor();
+        println!(
+            "Bundling fluent-bit {} as {minor}",
+            parsed.fluent_bit.version
+        );
+        ensure_file_contents(
+            &dir.join(format!("{minor}.json")),
+            &serde_json::to_string_pretty(&schema_json)?,
+            check,
+        );
+    }
+
+    let minors = sh
+        .read_dir(&dir)?
+        .into_iter()
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+        .collect();
+    let path = project_root().join("flb-schema/src/versions.generated.rs");
+    ensure_file_contents(path.as_path(), &bundled_schemas(minors), check);
 
     Ok(())
 }
 
+/// Table of the bundled schemas, oldest minor version first.
+fn bundled_schemas(mut minors: Vec<String>) -> String {
+    minors.sort_by_key(|minor| {
+        minor
+            .split('.')
+            .map(|part| part.parse::<u32>().unwrap_or_default())
+            .collect::<Vec<_>>()
+    });
+
+    let mut res = String::from(
+        r#"/// Generated by `cargo xtask generate --bundle`
+/// Don't modify this file manually.
+
+const BUNDLED_SCHEMAS: &[(&str, &str)] = &[
+"#,
+    );
+    for minor in minors {
+        res.push_str(&format!(
+            "    (\"{minor}\", include_str!(\"../schemas/{minor}.json\")),\n"
+        ));
+    }
+    res.push_str("];\n");
+
+    res
+}
+
+/// Clones and builds fluent-bit `v{flb_version}`, or its latest commit,
+/// returning the path to the binary. Sources are kept in `target/`, so that
+/// building again is incremental.
+fn build_fluent_bit(sh: &Shell, flb_version: Option<&str>) -> anyhow::Result<PathBuf> {
+    let git_ref = flb_version.map_or_else(|| "master".to_owned(), |v| format!("v{v}"));
+    let src = project_root().join(format!("target/fluent-bit-{git_ref}"));
+    let build = src.join("build");
+
+    if !sh.path_exists(&src) {
+        cmd!(
+            sh,
+            "git clone --depth 1 --branch {git_ref} {FLB_REPOSITORY_URL} {src}"
+        )
+        .run()?;
+    }
+    cmd!(sh, "cmake -S {src} -B {build} -DFLB_RELEASE=On").run()?;
+    cmd!(sh, "cmake --build {build} --parallel").run()?;
+
+    Ok(build.join("bin/fluent-bit"))
+}
+
+fn dump_schema(sh: &Shell, binary: &Path) -> anyhow::Result<String> {
+    Ok(cmd!(sh, "{binary} --help-json").read()?)
+}
+
+/// Normalizes a schema dumped by fluent-bit into the shape of the published
+/// ones: output preceding the JSON (e.g. the banner of some versions) is
+/// skipped, sections older versions lack are added, and plugins are sorted by
+/// name so that dumps of different builds compare equal.
+fn normalize_schema(schema_json: &str) -> anyhow::Result<Value> {
+    let start = schema_json
+        .find('{')
+        .ok_or_else(|| anyhow!("no schema in the output of fluent-bit"))?;
+    let mut schema: Value = serde_json::Deserializer::from_str(&schema_json[start..])
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("no schema in the output of fluent-bit"))??;
+    let schema_object = schema
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("the schema is not an object"))?;
+
+    let info = schema_object
+        .entry("fluent-bit")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("`fluent-bit` is not an object"))?;
+    info.entry("version").or_insert_with(|| json!("unknown"));
+    info.entry("schema_version").or_insert_with(|| json!("1"));
+    info.entry("os")
+        .or_insert_with(|| json!(std::env::consts::OS));
+
+    for section in ["customs", "inputs", "filters", "outputs"] {
+        let plugins = schema_object.entry(section).or_insert_with(|| json!([]));
+        if let Some(plugins) = plugins.as_array_mut() {
+            plugins.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        }
+    }
+
+    Ok(schema)
+}
+
 fn add_preamble(flb_version: &str, mut text: String) -> String {
     let preamble = format!(
         r#"/// Generated by `cargo xtask schema` (fluent-bit version: {flb_version})
@@ -129,76 +356,37 @@ fn add_preamble(flb_version: &str, mut text: String) -> String {
     text
 }
 
-// TODO: something better than this?
+/// Plugins for fluent-bit's own tests and for programs embedding it, which
+/// configurations don't use.
+const INTERNAL_PLUGINS: &[&str] = &["alter_size", "event_type", "exit", "lib"];
+
 fn generate0(schema: &FlbConfigSchema) -> String {
     let mut res = String::new();
 
-    res.push_str(
+    res.push_str(&format!(
         r#"
 #[rustfmt::skip::macros(add_snippet)]
-pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
-    let mut data = FlbData::new();
+pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {{
+    let mut data = FlbData::new("{flb_version}");
 "#,
-    );
+        flb_version = schema.fluent_bit.version,
+    ));
 
-    // TODO: add docs for undocumented ones?
-    let ignored = [
-        "event_type",
-        "lib",
-        // filter
-        "alter_size",
-        // output
-        "exit",
-        "plot",
-        "udp",
+    let sections = [
+        ("Customs", &schema.customs),
+        ("Input", &schema.inputs),
+        ("Filter", &schema.filters),
+        ("Output", &schema.outputs),
     ];
-
-    //customs
-    res.push_str("\n//// Customs\n");
-    for plugin in schema.customs.iter() {
-        if ignored.contains(&plugin.name.as_str()) {
-            println!("Ignoring custom plugin: {}", plugin.name);
-            continue;
-        }
-        res.push_str(
-            &plugin_stub(plugin).unwrap_or_else(|| panic!("Plugin stub not found: {:?}", plugin)),
-        );
-    }
-
-    res.push_str("\n//// Input\n");
-    for plugin in schema.inputs.iter() {
-        // res.push_str(&plugin_stub(plugin));
-        if ignored.contains(&plugin.name.as_str()) {
-            println!("Ignoring input plugin: {}", plugin.name);
-            continue;
+    for (title, plugins) in sections {
+        res.push_str(&format!("\n//// {title}\n"));
+        for plugin in plugins {
+            if INTERNAL_PLUGINS.contains(&plugin.name.as_str()) {
+                println!("Ignoring internal {} plugin: {}", plugin.type_, plugin.name);
+                continue;
+            }
+            res.push_str(&plugin_stub(plugin));
         }
-        res.push_str(
-            &plugin_stub(plugin).unwrap_or_else(|| panic!("Plugin stub not found: {:?}", plugin)),
-        );
-    }
-
-    res.push_str("\n//// Filter\n");
-    for plugin in schema.filters.iter() {
-        // res.push_str(&plugin_stub(plugin));
-        if ignored.contains(&plugin.name.as_str()) {
-            println!("Ignoring filter plugin: {}", plugin.name);
-            continue;
-        }
-        res.push_str(
-            &plugin_stub(plugin).unwrap_or_else(|| panic!("Plugin stub not found: {:?}", plugin)),
-        );
-    }
-
-    res.push_str("\n//// Output\n");
-    for plugin in schema.outputs.iter() {
-        // res.push_str(&plugin_stub(plugin));
-        if ignored.contains(&plugin.name.as_str()) {
-            println!("Ignoring output plugin: {}", plugin.name);
-            continue;
-        }
-        res.push_str(
-            &plugin_stub(plugin).unwrap_or_else(|| panic!("Plugin stub not found: {:?}", plugin)),
-        );
     }
 
     res.push_str(
@@ -210,13 +398,29 @@ pub static FLB_DATA: Lazy<FlbData> = Lazy::new(|| {
     res
 }
 
-fn plugin_stub(plugin: &FlbPlugin) -> Option<String> {
+/// Label and doc path of the plugin, from `plugin_info.csv`. Plugins missing
+/// from it, e.g. new ones, are labeled after their name and documented by the
+/// stub `check-assets` writes.
+fn plugin_info(plugin: &FlbPlugin) -> PluginInfo {
+    let key = PluginKey::new(plugin.type_.to_string(), plugin.name.clone());
+    PLUGIN_INFO_MAP.get(&key).cloned().unwrap_or_else(|| {
+        println!(
+            "No {} plugin `{}` in plugin_info.csv, labeling it after its name",
+            plugin.type_, plugin.name
+        );
+        PluginInfo {
+            label_in_docs: plugin.name.to_case(Case::Title),
+            doc_path: plugin.name.to_case(Case::Kebab),
+        }
+    })
+}
+
+fn plugin_stub(plugin: &FlbPlugin) -> String {
     let section_type = plugin.type_.to_string();
     let PluginInfo {
         label_in_docs,
         doc_path,
-    } = PLUGIN_INFO_MAP.get(&PluginKey::new(section_type.clone(), plugin.name.clone()))?;
-    // .unwrap_or_else(|| panic!("Plugin info not found for plugin: {:?}", plugin));
+    } = plugin_info(plugin);
 
     let mut res = String::new();
 
@@ -231,9 +435,10 @@ fn plugin_stub(plugin: &FlbPlugin) -> Option<String> {
 
     for prop in plugin.properties.iter() {
         res.push_str(&format!(
-            r##"        ("{key}", {default}, r#"{desc}"#),
+            r##"        ("{key}", {default}, r#"{desc}"#, FlbPropertyType::{type_:?}),
 "##,
             key = prop.name,
+            type_ = prop.type_,
             default = prop
                 .default
                 .as_ref()
@@ -247,10 +452,128 @@ fn plugin_stub(plugin: &FlbPlugin) -> Option<String> {
     // - networking
     // - network_tls
 
-    res.push_str(
-        r#"    ]);
-"#,
-    );
+    let required = required_parameters(plugin);
+    match required.is_empty() {
+        true => res.push_str("    ]);\n"),
+        false => res.push_str(&format!("    ], required: [{}]);\n", required.join(", "))),
+    }
+
+    res
+}
+
+/// Keys of `required_parameters.csv` of the plugin, quoted as the schema
+/// spells them, in its order.
+fn required_parameters(plugin: &FlbPlugin) -> Vec<String> {
+    let key = PluginKey::new(plugin.type_.to_string(), plugin.name.clone());
+    let Some(keys) = REQUIRED_PARAMETERS.get(&key) else {
+        return Vec::new();
+    };
+    for key in keys {
+        if !plugin
+            .properties
+            .iter()
+            .any(|prop| prop.name.eq_ignore_ascii_case(key))
+        {
+            println!(
+                "No key `{key}` of the {} plugin `{}` in the schema, ignoring it",
+                plugin.type_, plugin.name
+            );
+        }
+    }
+
+    plugin
+        .properties
+        .iter()
+        .filter(|prop| keys.contains(&prop.name.to_lowercase()))
+        .map(|prop| format!("{:?}", prop.name))
+        .collect()
+}
 
-    Some(res)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_dumped_schema() {
+        let dump = r#"Fluent Bit v3.2.0
+* Copyright (C) 2015-2024 The Fluent Bit Authors
+{"fluent-bit": {"version": "3.2.0", "schema_version": "1"},
+ "inputs": [
+  {"type": "input", "name": "tail", "description": "Tail files", "properties": {"options": []}},
+  {"type": "input", "name": "cpu", "description": "CPU metrics", "properties": {"options": []}}],
+ "filters": [], "outputs": []}
+"#;
+
+        let schema: FlbConfigSchema =
+            serde_json::from_value(normalize_schema(dump).unwrap()).unwrap();
+        assert_eq!(schema.fluent_bit.version, "3.2.0");
+        assert_eq!(schema.fluent_bit.os, std::env::consts::OS);
+        assert!(schema.customs.is_empty());
+        let inputs: Vec<_> = schema.inputs.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(inputs, ["cpu", "tail"]);
+
+        assert!(normalize_schema("fluent-bit: unrecognized option").is_err());
+    }
+
+    #[test]
+    fn bundled_schemas_by_minor_version() {
+        let generated = bundled_schemas(vec!["3.10".into(), "3.0".into(), "3.2".into()]);
+        let entries: Vec<_> = generated
+            .lines()
+            .filter(|line| line.contains("include_str!"))
+            .collect();
+        assert_eq!(entries, [
+            r#"    ("3.0", include_str!("../schemas/3.0.json")),"#,
+            r#"    ("3.2", include_str!("../schemas/3.2.json")),"#,
+            r#"    ("3.10", include_str!("../schemas/3.10.json")),"#,
+        ]);
+        assert!(bundled_schemas(Vec::new()).ends_with("&[\n];\n"));
+    }
+
+    #[test]
+    fn minor_versions_bundled_by_default() {
+        use clap::Parser;
+
+        use crate::cli::{Cli, Commands};
+
+        let Commands::Generate { bundle, .. } = Cli::parse_from(["xtask", "generate"]).command
+        else {
+            panic!("expected the generate command");
+        };
+        let minors: Vec<_> = bundle
+            .iter()
+            .map(|version| version.parse::<FlbVersion>().unwrap().minor())
+            .collect();
+        assert_eq!(minors, ["3.0", "3.1", "3.2"]);
+    }
+
+    #[test]
+    fn every_plugin_is_generated() {
+        let schema: FlbConfigSchema = serde_json::from_value(json!({
+            "fluent-bit": { "version": "3.2.0", "schema_version": "1", "os": "linux" },
+            "customs": [],
+            "inputs": [
+                { "type": "input", "name": "tail", "description": "Tail files", "properties": { "options": [
+                    { "name": "path", "description": "pattern specifying log files", "type": "string" },
+                ] } },
+                { "type": "input", "name": "new_thing", "description": "New input", "properties": { "options": [] } },
+                { "type": "input", "name": "lib", "description": "Library mode input", "properties": { "options": [] } },
+            ],
+            "filters": [],
+            "outputs": [],
+        }))
+        .unwrap();
+        let generated = generate0(&schema);
+
+        assert!(generated.contains(
+            r#"add_snippet!(data, FlbSectionType::Input, "Tail", "tail", "input/tail", ["#
+        ));
+        assert!(generated.contains(
+            r##"("path", None, r#"pattern specifying log files"#, FlbPropertyType::String),"##
+        ));
+        assert!(generated.contains(r#"    ], required: ["path"]);"#));
+        // missing from plugin_info.csv
+        assert!(generated.contains(r#"add_snippet!(data, FlbSectionType::Input, "New Thing", "new_thing", "input/new-thing", ["#));
+        assert!(!generated.contains(r#""lib""#));
+    }
 }